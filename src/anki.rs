@@ -0,0 +1,113 @@
+//! Flashcards for Anki from a file of positions
+//!
+//! Each FEN or EPD line becomes a card with a diagram of the position on
+//! the front and the FEN and any `bm` solution on the back. The deck is a
+//! CSV file for Anki's importer; the diagrams are written to a media
+//! folder beside it, to be copied into the profile's `collection.media`.
+
+use crate::batch;
+use crate::diagram::{self, DiagramOptions, Palette, Quality};
+use crate::epd;
+use crate::fonts::PieceFont;
+use crate::png;
+use crate::position::Color;
+use std::io::BufRead;
+use std::path::Path;
+
+fn csv_field(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Write a card for every position in `path` to the CSV file `deck`.
+pub fn run(
+    path: &str,
+    deck: &str,
+    pieces: &PieceFont,
+    palette: Palette,
+    quality: Quality,
+) -> Result<(), String> {
+    let deck_path = Path::new(deck);
+    match deck_path.extension().and_then(|e| e.to_str()) {
+        Some("csv") | Some("txt") => {}
+        Some("apkg") => {
+            return Err(
+                "Writing .apkg packages is not supported; give a .csv deck and import it into Anki"
+                    .to_string(),
+            )
+        }
+        _ => return Err(format!("Expected a .csv deck, found {}", deck)),
+    }
+    let stem = deck_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("deck")
+        .to_string();
+    let media = deck_path.with_extension("media");
+    std::fs::create_dir_all(&media)
+        .map_err(|e| format!("Could not create {}: {}", media.display(), e))?;
+
+    let mut out = String::from("#separator:comma\n#html:true\n#columns:Front,Back\n");
+    let mut cards = 0;
+    for (n, line) in batch::open(path)?.lines().enumerate() {
+        let line = line.map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let record = match epd::parse_line(line) {
+            Ok(record) => record,
+            Err(e) => {
+                eprintln!("line {}: Error: {}", n + 1, e);
+                continue;
+            }
+        };
+        cards += 1;
+        // image names are shared by the whole collection, so carry the deck's name
+        let image = format!("{}-{:03}.png", stem, cards);
+        let opts = DiagramOptions {
+            palette,
+            shadows: quality == Quality::High,
+            ..DiagramOptions::default()
+        };
+        let diagram = diagram::board(&record.pos, &opts);
+        png::save(
+            &diagram,
+            &media.join(&image).to_string_lossy(),
+            pieces,
+            quality,
+        )?;
+
+        let to_move = match record.pos.side {
+            Color::White => "White to move",
+            Color::Black => "Black to move",
+        };
+        let mut front = format!("<img src=\"{}\"><br>{}", image, to_move);
+        if let Some(id) = record.op("id") {
+            front.push_str(&format!("<br><i>{}</i>", html_escape(id)));
+        }
+        let mut back = String::new();
+        if let Some(bm) = record.op("bm") {
+            back.push_str(&format!("<b>{}</b><br>", html_escape(bm)));
+        }
+        if let Some(comment) = record.op("c0") {
+            back.push_str(&format!("{}<br>", html_escape(comment)));
+        }
+        back.push_str(&format!("<code>{}</code>", record.pos.to_fen()));
+        out.push_str(&format!("{},{}\n", csv_field(&front), csv_field(&back)));
+    }
+    std::fs::write(deck, out).map_err(|e| format!("Could not write {}: {}", deck, e))?;
+    println!(
+        "Wrote {} card{} to {}; copy the images in {} into Anki's collection.media folder",
+        cards,
+        if cards == 1 { "" } else { "s" },
+        deck,
+        media.display()
+    );
+    Ok(())
+}
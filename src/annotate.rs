@@ -0,0 +1,79 @@
+//! Engine annotations drawn as arrows on the board
+
+use crate::diagram::{Palette, Rgba};
+use crate::movegen::Move;
+use crate::position::{Color, Position, Square};
+use crate::san::to_san;
+use crate::uci::{Engine, Score};
+
+/// a played move losing at least this many centipawns is a blunder
+pub const BLUNDER: i32 = 300;
+
+/// best score the engine finds from `fen`, optionally restricted to `only`
+fn search(
+    engine: &mut Engine,
+    fen: &str,
+    only: Option<Move>,
+    movetime: u64,
+) -> Result<Option<(Move, Score)>, String> {
+    let mut go = format!("movetime {}", movetime);
+    if let Some(mv) = only {
+        go.push_str(&format!(" searchmoves {}", mv.to_uci()));
+    }
+    let analysis = engine.analyse(fen, &[], &go)?;
+    let best = analysis.best.as_deref().and_then(Move::from_uci);
+    let score = analysis.lines.first().map(|l| l.score);
+    Ok(best.zip(score))
+}
+
+/// Arrows for the engine's best move from `before` and, if it differs,
+/// for the `played` move: red when it is a blunder against the best
+/// move, blue otherwise (in the default palette). With `verbose` the
+/// comparison is printed.
+pub fn best_move_arrows(
+    engine: &mut Engine,
+    before: &Position,
+    played: Option<Move>,
+    movetime: u64,
+    verbose: bool,
+    palette: &Palette,
+) -> Result<Vec<(Square, Square, Rgba)>, String> {
+    let fen = before.to_fen();
+    // engines score for the side to move; print from White's point of view
+    let white = |score: Score| match before.side {
+        Color::White => score,
+        Color::Black => score.negate(),
+    };
+    let (best, best_score) = match search(engine, &fen, None, movetime)? {
+        Some(found) => found,
+        None => return Ok(Vec::new()),
+    };
+    if verbose {
+        println!(
+            "Best move: {} ({})",
+            to_san(before, best),
+            white(best_score)
+        );
+    }
+    let mut arrows = vec![(best.from, best.to, palette.best)];
+    if let Some(mv) = played.filter(|&mv| mv != best) {
+        if let Some((_, score)) = search(engine, &fen, Some(mv), movetime)? {
+            let loss = best_score.centipawns() - score.centipawns();
+            let color = if loss >= BLUNDER {
+                palette.blunder
+            } else {
+                palette.played
+            };
+            if verbose {
+                println!(
+                    "Played move: {} ({}){}",
+                    to_san(before, mv),
+                    white(score),
+                    if loss >= BLUNDER { ", a blunder" } else { "" }
+                );
+            }
+            arrows.push((mv.from, mv.to, color));
+        }
+    }
+    Ok(arrows)
+}
@@ -0,0 +1,130 @@
+//! Annotation layers kept in a sidecar file next to the positions
+//!
+//! `--annotations FILE` reads arrows, highlighted squares and a caption for
+//! each position from JSON, so a teaching diagram can be drawn again
+//! exactly as it was:
+//!
+//! ```text
+//! { "positions": [
+//!   { "fen": "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3",
+//!     "caption": "The Italian Game: f7 is the target",
+//!     "arrows": [ { "from": "c4", "to": "f7" },
+//!                 { "from": "f3", "to": "g5", "color": "played" } ],
+//!     "highlights": [ "f7" ] } ] }
+//! ```
+//!
+//! A layer applies to the position whose FEN matches its `fen` in the
+//! placement, side to move, castling and en-passant fields, so the move
+//! counters do not matter; a layer without a `fen` applies to every
+//! position. An arrow's `color` is one of the palette's `best` (the
+//! default), `played`, `blunder` or `highlight`, or `#rrggbb` or
+//! `#rrggbbaa`.
+
+use crate::diagram::{Palette, Rgba};
+use crate::json::Json;
+use crate::position::{parse_square, Position, Square};
+
+/// what a layer draws over one position
+#[derive(Default)]
+pub struct Layer {
+    pub arrows: Vec<(Square, Square, Rgba)>,
+    pub highlights: Vec<Square>,
+    pub caption: Option<String>,
+}
+
+// the fields of a FEN that say which position it is
+fn key(fen: &str) -> Result<String, String> {
+    let fen = Position::from_fen(fen)?.to_fen();
+    Ok(fen.split(' ').take(4).collect::<Vec<_>>().join(" "))
+}
+
+fn colour(text: &str, palette: &Palette) -> Result<Rgba, String> {
+    let named = match text {
+        "best" => Some(palette.best),
+        "played" => Some(palette.played),
+        "blunder" => Some(palette.blunder),
+        "highlight" => Some(palette.highlight),
+        _ => None,
+    };
+    if let Some(colour) = named {
+        return Ok(colour);
+    }
+    let hex = text
+        .strip_prefix('#')
+        .filter(|hex| (hex.len() == 6 || hex.len() == 8) && hex.is_ascii())
+        .ok_or_else(|| format!("Unknown colour {}", text))?;
+    let mut colour = [0, 0, 0, 255];
+    for (i, part) in colour.iter_mut().enumerate().take(hex.len() / 2) {
+        *part = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|_| format!("Unknown colour {}", text))?;
+    }
+    Ok(colour)
+}
+
+fn square(value: &Json) -> Result<Square, String> {
+    let name = value.as_str().ok_or("A square must be a name such as e4")?;
+    parse_square(name).ok_or_else(|| format!("{} is not a square", name))
+}
+
+/// The arrows, highlights and caption of one entry.
+pub fn layer(entry: &Json, palette: &Palette) -> Result<Layer, String> {
+    let list = |key: &str| entry.get(key).and_then(Json::as_array).unwrap_or_default();
+    let arrows = list("arrows")
+        .iter()
+        .map(|arrow| {
+            let end = |key: &str| {
+                arrow
+                    .get(key)
+                    .ok_or_else(|| format!("An arrow has no \"{}\" square", key))
+                    .and_then(square)
+            };
+            let colour = match arrow.get("color").and_then(Json::as_str) {
+                Some(text) => colour(text, palette)?,
+                None => palette.best,
+            };
+            Ok((end("from")?, end("to")?, colour))
+        })
+        .collect::<Result<_, String>>()?;
+    let highlights = list("highlights")
+        .iter()
+        .map(square)
+        .collect::<Result<_, _>>()?;
+    Ok(Layer {
+        arrows,
+        highlights,
+        caption: entry
+            .get("caption")
+            .and_then(Json::as_str)
+            .map(String::from),
+    })
+}
+
+/// The layers in `path` that apply to `fen`, combined, with named colours
+/// taken from `palette`.
+pub fn load(path: &str, fen: &str, palette: &Palette) -> Result<Layer, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    let json = Json::parse(&text).map_err(|e| format!("{}: {}", path, e))?;
+    let entries = json
+        .get("positions")
+        .and_then(Json::as_array)
+        .ok_or_else(|| format!("{} has no \"positions\" list", path))?;
+    let wanted = key(fen)?;
+    let mut combined = Layer::default();
+    for (i, entry) in entries.iter().enumerate() {
+        let applies = match entry.get("fen").and_then(Json::as_str) {
+            Some(fen) => {
+                key(fen).map_err(|e| format!("{}: position {}: {}", path, i + 1, e))? == wanted
+            }
+            None => true,
+        };
+        if applies {
+            let found = layer(entry, palette)
+                .map_err(|e| format!("{}: position {}: {}", path, i + 1, e))?;
+            combined.arrows.extend(found.arrows);
+            combined.highlights.extend(found.highlights);
+            combined.caption = found.caption.or(combined.caption);
+        }
+    }
+    Ok(combined)
+}
@@ -0,0 +1,72 @@
+//! Piece sprites packed into one texture for the window
+//!
+//! The twelve pieces, drawn as in the exported diagrams, and a white mask
+//! of each shape for tinting shadows, are rasterised once at startup into a
+//! single image, a square cell each. The window then draws every piece as a
+//! quad cut from that one texture instead of laying out glyphs each frame,
+//! which keeps grids of boards and animations cheap.
+
+use crate::diagram::{self, Diagram, Quality};
+use crate::fonts::PieceFont;
+use crate::png;
+use crate::position::Color;
+use image::RgbaImage;
+
+/// side of a sprite's cell in pixels, as large as a square is likely to be
+/// drawn; smaller squares are sampled from the mipmaps
+pub const CELL: u32 = 128;
+
+const LETTERS: [char; 6] = ['P', 'N', 'B', 'R', 'Q', 'K'];
+
+/// the sprite of a glyph from `board_glyphs`: White's pieces from pawn to
+/// king, then Black's
+pub fn sprite(glyph: &str) -> Option<usize> {
+    [Color::White, Color::Black]
+        .into_iter()
+        .flat_map(|color| LETTERS.map(move |letter| diagram::glyph(letter, color)))
+        .position(|g| g == glyph)
+}
+
+/// the white mask with the shape of `sprite`
+pub fn mask(sprite: usize) -> usize {
+    12 + sprite % 6
+}
+
+/// the rectangle of `sprite` in the atlas, as `[x, y, w, h]`
+pub fn source(sprite: usize) -> [f64; 4] {
+    let cell = CELL as f64;
+    [
+        (sprite % 6) as f64 * cell,
+        (sprite / 6) as f64 * cell,
+        cell,
+        cell,
+    ]
+}
+
+/// Rasterise the sprites with `pieces`: rows of White's pieces, Black's
+/// pieces and the masks, six cells each.
+pub fn build(pieces: &PieceFont) -> RgbaImage {
+    let size = 0.8 * CELL as f64;
+    let mut shapes = Vec::new();
+    for i in 0..12 {
+        let [x, y, _, _] = source(i);
+        let (x, y) = (x + 0.15 * CELL as f64, y + 0.8 * CELL as f64);
+        let letter = LETTERS[i % 6];
+        let color = [Color::White, Color::Black][i / 6];
+        shapes.extend(diagram::piece_shapes(letter, color, x, y, size));
+    }
+    let sprites = Diagram {
+        width: 6.0 * CELL as f64,
+        height: 3.0 * CELL as f64,
+        shapes,
+    };
+    let mut image = png::render(&sprites, pieces, Quality::Low);
+    // the masks take the shape of Black's pieces, which are solid
+    for y in CELL..2 * CELL {
+        for x in 0..6 * CELL {
+            let alpha = image.get_pixel(x, y)[3];
+            image.put_pixel(x, y + CELL, image::Rgba([255, 255, 255, alpha]));
+        }
+    }
+    image
+}
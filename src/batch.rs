@@ -0,0 +1,68 @@
+//! Validate many FENs at once, one per line
+
+use crate::position::Position;
+use crate::retro;
+use crate::stats::Stats;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+/// open `path` for reading line by line, with `-` meaning standard input
+pub fn open(path: &str) -> Result<Box<dyn BufRead>, String> {
+    if path == "-" {
+        Ok(Box::new(BufReader::new(io::stdin())))
+    } else {
+        File::open(path)
+            .map(|f| Box::new(BufReader::new(f)) as Box<dyn BufRead>)
+            .map_err(|e| format!("Could not read {}: {}", path, e))
+    }
+}
+
+/// open `path` for writing, or standard output when it is `None`
+pub fn create(path: Option<&str>) -> Result<Box<dyn Write>, String> {
+    Ok(match path {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|e| format!("Could not write {}: {}", path, e))?,
+        )),
+        None => Box::new(BufWriter::new(io::stdout())),
+    })
+}
+
+/// Check every FEN in `path`, reporting invalid lines on stderr. Blank
+/// lines and `#` comments are skipped. With `retro` positions that cannot
+/// arise in a legal game count as invalid too. With `stats` an aggregate
+/// report is printed, as JSON when `json` is set.
+pub fn run(path: &str, stats: bool, json: bool, retro: bool) -> Result<(), String> {
+    let mut summary = Stats::default();
+    for (n, line) in open(path)?.lines().enumerate() {
+        let line = line.map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match Position::from_fen(line) {
+            Ok(pos) if retro && !retro::problems(&pos).is_empty() => {
+                summary.invalid += 1;
+                for problem in retro::problems(&pos) {
+                    eprintln!("line {}: Illegal: {}", n + 1, problem);
+                }
+            }
+            Ok(pos) => summary.add(&pos),
+            Err(e) => {
+                summary.invalid += 1;
+                eprintln!("line {}: Error: {}", n + 1, e);
+            }
+        }
+    }
+    if stats && json {
+        println!("{}", summary.to_json());
+    } else if stats {
+        print!("{}", summary.to_text());
+    } else {
+        println!(
+            "{} of {} positions are valid",
+            summary.positions,
+            summary.positions + summary.invalid
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,153 @@
+//! Benchmarks over a built-in corpus of positions
+//!
+//! Each benchmark runs over the corpus until about a second has passed and
+//! reports positions per second and heap allocations per position, so
+//! that releases can be compared on the same machine. Allocations are
+//! counted by `Counting`, which wraps the system allocator; they are only
+//! seen when the program installs it as its global allocator, as the `fen`
+//! tool does, and only while a benchmark is being measured.
+
+use crate::diagram::{self, DiagramOptions};
+use crate::position::Position;
+use crate::svg;
+use clap::ValueEnum;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// the system allocator, counting each allocation while `COUNTING` is set
+pub struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+// off outside `measure`, so that other commands pay one relaxed load per
+// allocation and nothing more
+static COUNTING: AtomicBool = AtomicBool::new(false);
+
+fn count() {
+    if COUNTING.load(Ordering::Relaxed) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Bench {
+    /// reading FENs
+    Parse,
+    /// generating the legal moves
+    Movegen,
+    /// drawing diagrams and writing them as SVG
+    RenderSvg,
+}
+
+/// openings, middlegames and endgames, with castling, en passant,
+/// promotions and checks among them
+const CORPUS: [&str; 16] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3",
+    "rnbqkb1r/1p2pppp/p2p1n2/8/3NP3/2N5/PPP2PPP/R1BQKB1R w KQkq - 0 6",
+    "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11",
+    "r1b2rk1/pp1nqppp/2pb1n2/3p4/3P4/2NBPN2/PPQ2PPP/R1B2RK1 w - - 0 9",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+    "8/8/1k6/8/8/8/6P1/6K1 w - - 0 1",
+    "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1",
+    "8/8/8/2k5/2P5/2K5/8/8 w - - 0 1",
+    "4k3/8/8/8/8/8/4p3/4K3 b - - 0 1",
+];
+
+const BUDGET: Duration = Duration::from_secs(1);
+
+struct Measurement {
+    positions: u64,
+    allocations: u64,
+    elapsed: Duration,
+}
+
+// run `step` over the corpus again and again until the budget is spent
+fn measure(positions: &[Position], mut step: impl FnMut(&str, &Position)) -> Measurement {
+    let mut count = 0;
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    COUNTING.store(true, Ordering::Relaxed);
+    let start = Instant::now();
+    while start.elapsed() < BUDGET {
+        for (fen, pos) in CORPUS.iter().zip(positions) {
+            step(fen, pos);
+        }
+        count += CORPUS.len() as u64;
+    }
+    COUNTING.store(false, Ordering::Relaxed);
+    Measurement {
+        positions: count,
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - before,
+        elapsed: start.elapsed(),
+    }
+}
+
+fn run_one(bench: Bench, positions: &[Position]) -> Measurement {
+    match bench {
+        Bench::Parse => measure(positions, |fen, _| {
+            let _ = black_box(Position::from_fen(black_box(fen)));
+        }),
+        Bench::Movegen => measure(positions, |_, pos| {
+            black_box(black_box(pos).legal_moves());
+        }),
+        Bench::RenderSvg => {
+            let opts = DiagramOptions::default();
+            measure(positions, |_, pos| {
+                black_box(svg::to_svg(&diagram::board(black_box(pos), &opts)));
+            })
+        }
+    }
+}
+
+/// Run `only` or every benchmark, printing a line for each.
+pub fn run(only: Option<Bench>) -> Result<(), String> {
+    let positions = CORPUS
+        .iter()
+        .map(|fen| Position::from_fen(fen))
+        .collect::<Result<Vec<_>, _>>()?;
+    let benches = match only {
+        Some(bench) => vec![bench],
+        None => Bench::value_variants().to_vec(),
+    };
+    println!(
+        "{:<11} {:>14} {:>18}",
+        "Benchmark", "Positions/s", "Allocations/pos"
+    );
+    for bench in benches {
+        let result = run_one(bench, &positions);
+        let name = bench
+            .to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_default();
+        println!(
+            "{:<11} {:>14.0} {:>18.1}",
+            name,
+            result.positions as f64 / result.elapsed.as_secs_f64(),
+            result.allocations as f64 / result.positions as f64
+        );
+    }
+    Ok(())
+}
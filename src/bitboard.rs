@@ -0,0 +1,113 @@
+//! Bitboards shown as marked squares
+//!
+//! Bit 0 is a1 and bit 63 is h8, counting along the ranks (the
+//! little-endian rank-file order most engines use), the same numbering as
+//! `--index-overlay 0-63`.
+
+use crate::position::{parse_square, square_name, Color, Piece, PieceKind, Position, Square};
+use crate::terminal::{board_glyphs, print_board, print_strip, TerminalOptions};
+
+/// Read a mask written in hex (`0x`), binary (`0b`) or decimal, with any
+/// `_` separators.
+pub fn parse(text: &str) -> Result<u64, String> {
+    let digits = text.replace('_', "").to_ascii_lowercase();
+    let parsed = if let Some(hex) = digits.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        u64::from_str_radix(binary, 2)
+    } else {
+        digits.parse()
+    };
+    parsed.map_err(|e| format!("Cannot read bitboard {}: {}", text, e))
+}
+
+/// The attack set of `piece` (a name such as `knight` or a FEN letter) on
+/// `square`, stopping at the pieces of the `on` position if given. Lowercase
+/// letters are Black's pieces unless `color` says otherwise.
+pub fn attacks(
+    piece: &str,
+    square: &str,
+    color: Option<&str>,
+    on: Option<&str>,
+) -> Result<u64, String> {
+    let kind = match piece.to_ascii_lowercase().as_str() {
+        "pawn" => Some(PieceKind::Pawn),
+        "knight" => Some(PieceKind::Knight),
+        "bishop" => Some(PieceKind::Bishop),
+        "rook" => Some(PieceKind::Rook),
+        "queen" => Some(PieceKind::Queen),
+        "king" => Some(PieceKind::King),
+        _ if piece.chars().count() == 1 => piece.chars().next().and_then(PieceKind::from_letter),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        format!(
+            "Unknown piece {}, expected a name such as knight or a letter",
+            piece
+        )
+    })?;
+    let color = match color {
+        Some("black") => Color::Black,
+        Some(_) => Color::White,
+        None if piece.chars().count() == 1 && piece.chars().all(|c| c.is_ascii_lowercase()) => {
+            Color::Black
+        }
+        None => Color::White,
+    };
+    let from = parse_square(square).ok_or_else(|| format!("Invalid square {}", square))?;
+    let board = match on {
+        Some(fen) => Position::from_fen(fen)?,
+        None => Position::from_placement([None; 64], Color::White),
+    };
+    Ok(board.attacks(Piece::new(color, kind), from))
+}
+
+/// the squares whose bits are set in `mask`, from a1
+pub fn squares(mask: u64) -> Vec<Square> {
+    (0..64u8).filter(|&sq| mask >> sq & 1 == 1).collect()
+}
+
+/// an empty board with the set squares marked, as terminal glyphs
+fn marked(mask: u64) -> Vec<Vec<String>> {
+    (0..8u8)
+        .rev()
+        .map(|rank| {
+            (0..8u8)
+                .map(|file| {
+                    if mask >> (rank * 8 + file) & 1 == 1 {
+                        "■".to_string()
+                    } else {
+                        "·".to_string()
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Print `mask` as a board of marked squares, beside `pos` when given,
+/// followed by the mask in hex and the squares it holds.
+pub fn print(mask: u64, pos: Option<&Position>, term: &TerminalOptions) {
+    let hex = format!("0x{:016x}", mask);
+    match pos {
+        Some(pos) => print_strip(
+            &[
+                ("mask".to_string(), marked(mask)),
+                ("position".to_string(), board_glyphs(&pos.placement())),
+            ],
+            term,
+        ),
+        None => print_board(&marked(mask), term),
+    }
+    let set = squares(mask);
+    let names: Vec<String> = set.iter().map(|&sq| square_name(sq)).collect();
+    println!(
+        "{}: {} square{}",
+        hex,
+        set.len(),
+        if set.len() == 1 { "" } else { "s" }
+    );
+    if !names.is_empty() {
+        println!("{}", names.join(" "));
+    }
+}
@@ -0,0 +1,245 @@
+//! Polyglot opening books built from PGN databases
+//!
+//! Every move played from a position becomes an entry keyed by the
+//! position's Zobrist hash. Its weight is what the move scored for the
+//! side playing it, two for a win and one for a draw, as Polyglot's own
+//! `make-book` does. Entries are written as 16 big-endian bytes, sorted by
+//! key so that probers, such as [`probe`] here, can binary search the
+//! file.
+
+use crate::movegen::Move;
+use crate::pgn::Game;
+use crate::position::{file_of, rank_of, square, Color, PieceKind, Position};
+use crate::zobrist;
+use std::collections::HashMap;
+
+/// games and points of one move from one position
+#[derive(Clone, Copy, Default)]
+struct Tally {
+    games: usize,
+    points: u64,
+}
+
+/// one entry of a `.bin` book
+pub struct Entry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+}
+
+impl Entry {
+    fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        // the last four bytes are Polyglot's unused learning field
+        bytes
+    }
+}
+
+/// A move in Polyglot's encoding: the squares as file and rank triplets,
+/// the promotion piece above them, and castling as the king taking its
+/// own rook.
+fn encode(pos: &Position, mv: Move) -> u16 {
+    let mut to = mv.to;
+    if pos.is_castle(mv) {
+        let rook_file = if file_of(mv.to) > file_of(mv.from) {
+            7
+        } else {
+            0
+        };
+        to = square(rook_file, rank_of(mv.from));
+    }
+    let promotion = match mv.promotion {
+        Some(PieceKind::Knight) => 1,
+        Some(PieceKind::Bishop) => 2,
+        Some(PieceKind::Rook) => 3,
+        Some(PieceKind::Queen) => 4,
+        _ => 0,
+    };
+    file_of(to) as u16
+        | (rank_of(to) as u16) << 3
+        | (file_of(mv.from) as u16) << 6
+        | (rank_of(mv.from) as u16) << 9
+        | promotion << 12
+}
+
+/// The legal move of `pos` that `mv` encodes, if there is one.
+pub fn decode(pos: &Position, mv: u16) -> Option<Move> {
+    pos.legal_moves()
+        .into_iter()
+        .find(|&m| encode(pos, m) == mv)
+}
+
+/// Read the entries of a `.bin` book.
+pub fn read(bytes: &[u8]) -> Result<Vec<Entry>, String> {
+    if !bytes.len().is_multiple_of(16) {
+        return Err(format!(
+            "A Polyglot book is a whole number of 16-byte entries, not {} bytes",
+            bytes.len()
+        ));
+    }
+    Ok(bytes
+        .chunks(16)
+        .map(|e| Entry {
+            key: u64::from_be_bytes(e[0..8].try_into().expect("8 bytes")),
+            mv: u16::from_be_bytes([e[8], e[9]]),
+            weight: u16::from_be_bytes([e[10], e[11]]),
+        })
+        .collect())
+}
+
+/// The moves `entries` (sorted by key, as books are) give for `pos`, with
+/// their weights, heaviest first.
+pub fn probe(entries: &[Entry], pos: &Position) -> Vec<(Move, u16)> {
+    let key = zobrist::hash(pos);
+    let first = entries.partition_point(|e| e.key < key);
+    entries[first..]
+        .iter()
+        .take_while(|e| e.key == key)
+        .filter_map(|e| Some((decode(pos, e.mv)?, e.weight)))
+        .collect()
+}
+
+/// Collect the book entries of `games`, keeping the moves played in at
+/// least `min_games` of them that scored any points. Weights are
+/// scaled down if needed to fit in 16 bits.
+pub fn build(games: &[Game], min_games: usize) -> Vec<Entry> {
+    let mut tallies: HashMap<(u64, u16), Tally> = HashMap::new();
+    for game in games {
+        let mut pos = game.start.clone();
+        for &mv in &game.moves {
+            let tally = tallies
+                .entry((zobrist::hash(&pos), encode(&pos, mv)))
+                .or_default();
+            tally.games += 1;
+            tally.points += match (game.result.as_str(), pos.side) {
+                ("1-0", Color::White) | ("0-1", Color::Black) => 2,
+                ("1/2-1/2", _) => 1,
+                _ => 0,
+            };
+            pos = pos.make_move(mv);
+        }
+    }
+    let kept: Vec<_> = tallies
+        .into_iter()
+        .filter(|(_, tally)| tally.games >= min_games && tally.points > 0)
+        .collect();
+    let most = kept.iter().map(|(_, t)| t.points).max().unwrap_or(0);
+    let scale = (most as f64 / u16::MAX as f64).max(1.0);
+    let mut entries: Vec<Entry> = kept
+        .into_iter()
+        .map(|((key, mv), tally)| Entry {
+            key,
+            mv,
+            weight: ((tally.points as f64 / scale).round() as u16).max(1),
+        })
+        .collect();
+    // heaviest first within a position, as probers expect
+    entries.sort_by(|a, b| a.key.cmp(&b.key).then(b.weight.cmp(&a.weight)));
+    entries
+}
+
+/// Build a book from the games in `pgn` and write it to `out`.
+pub fn run(pgn: &str, out: &str, min_games: usize) -> Result<(), String> {
+    let games = crate::pgn::read_games(pgn)?;
+    let entries = build(&games, min_games);
+    let bytes: Vec<u8> = entries.iter().flat_map(|e| e.to_bytes()).collect();
+    std::fs::write(out, bytes).map_err(|e| format!("Could not write {}: {}", out, e))?;
+    let mut positions: Vec<u64> = entries.iter().map(|e| e.key).collect();
+    positions.dedup();
+    println!(
+        "Wrote {} moves from {} positions of {} games to {}",
+        entries.len(),
+        positions.len(),
+        games.len(),
+        out
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::parse_games;
+
+    fn polyglot(fen: &str, uci: &str) -> u16 {
+        let pos = Position::from_fen(fen).unwrap();
+        encode(&pos, Move::from_uci(uci).unwrap())
+    }
+
+    // the squares of a Polyglot move, as from and to in UCI form
+    fn squares(mv: u16) -> String {
+        let name = |sq: u16| crate::position::square_name((sq & 0x3f) as u8);
+        format!("{}{}", name(mv >> 6), name(mv))
+    }
+
+    #[test]
+    fn castling_is_king_takes_rook() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(squares(polyglot(fen, "e1g1")), "e1h1");
+        assert_eq!(squares(polyglot(fen, "e1c1")), "e1a1");
+        let black = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1";
+        assert_eq!(squares(polyglot(black, "e8g8")), "e8h8");
+        assert_eq!(squares(polyglot(black, "e8c8")), "e8a8");
+        // an ordinary king move is left alone
+        assert_eq!(squares(polyglot(fen, "e1f1")), "e1f1");
+    }
+
+    #[test]
+    fn moves_and_promotions() {
+        let start = crate::position::STARTING_FEN;
+        // e2e4: to e4 = 4 | 3 << 3, from e2 = 4 << 6 | 1 << 9
+        assert_eq!(polyglot(start, "e2e4"), 4 | 3 << 3 | 4 << 6 | 1 << 9);
+        let promoting = "8/4P3/8/8/8/8/k7/4K3 w - - 0 1";
+        assert_eq!(polyglot(promoting, "e7e8q") >> 12, 4);
+        assert_eq!(polyglot(promoting, "e7e8n") >> 12, 1);
+    }
+
+    #[test]
+    fn entries_are_sorted_by_key_then_weight() {
+        let games = parse_games(
+            "[Result \"1-0\"]\n\n1. e4 e5 1-0\n\n\
+             [Result \"1-0\"]\n\n1. e4 c5 1-0\n\n\
+             [Result \"1/2-1/2\"]\n\n1. d4 d5 1/2-1/2\n\n\
+             [Result \"0-1\"]\n\n1. e4 e5 0-1\n",
+        )
+        .unwrap();
+        let entries = build(&games, 1);
+        assert!(entries
+            .windows(2)
+            .all(|w| w[0].key < w[1].key || (w[0].key == w[1].key && w[0].weight >= w[1].weight)));
+        // 1. e4 won twice and lost once, 1. d4 drew
+        let start = Position::from_fen(crate::position::STARTING_FEN).unwrap();
+        let book: Vec<u8> = entries.iter().flat_map(|e| e.to_bytes()).collect();
+        let moves = probe(&read(&book).unwrap(), &start);
+        let e4 = Move::from_uci("e2e4").unwrap();
+        let d4 = Move::from_uci("d2d4").unwrap();
+        assert_eq!(moves, vec![(e4, 4), (d4, 1)]);
+    }
+
+    #[test]
+    fn books_read_back() {
+        let games =
+            parse_games("[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. O-O 1-0\n").unwrap();
+        let entries = build(&games, 1);
+        let bytes: Vec<u8> = entries.iter().flat_map(|e| e.to_bytes()).collect();
+        assert_eq!(bytes.len(), 16 * entries.len());
+        let read = read(&bytes).unwrap();
+        for (a, b) in entries.iter().zip(&read) {
+            assert_eq!((a.key, a.mv, a.weight), (b.key, b.mv, b.weight));
+        }
+        let mut pos = games[0].start.clone();
+        for &mv in &games[0].moves {
+            let expected = if pos.side == Color::White {
+                vec![(mv, 2)]
+            } else {
+                vec![]
+            };
+            assert_eq!(probe(&read, &pos), expected, "{}", pos.to_fen());
+            pos = pos.make_move(mv);
+        }
+        assert!(super::read(&bytes[..15]).is_err());
+    }
+}
@@ -0,0 +1,79 @@
+//! Legal moves grouped and ordered for study
+
+use crate::movegen::Move;
+use crate::position::{file_of, rank_of, square, Position, Square};
+use crate::san::to_san;
+use crate::stats::value;
+
+/// The legal moves of `pos` as checks, captures and quiet moves, in that
+/// order. Captures are ordered most valuable victim first, then least
+/// valuable attacker.
+pub fn grouped(pos: &Position) -> Vec<(&'static str, Vec<Move>)> {
+    let mut checks = Vec::new();
+    let mut captures = Vec::new();
+    let mut quiet = Vec::new();
+    for mv in pos.legal_moves() {
+        if pos.make_move(mv).in_check() {
+            checks.push(mv);
+        } else if pos.is_capture(mv) {
+            captures.push(mv);
+        } else {
+            quiet.push(mv);
+        }
+    }
+    captures.sort_by_key(|&mv| {
+        let victim = if pos.is_en_passant(mv) {
+            pos.piece_at(square(file_of(mv.to), rank_of(mv.from)))
+        } else {
+            pos.piece_at(mv.to)
+        };
+        let attacker = pos.piece_at(mv.from);
+        (
+            -victim.map_or(0, |p| value(p.kind)),
+            attacker.map_or(0, |p| value(p.kind)),
+        )
+    });
+    vec![("Checks", checks), ("Captures", captures), ("Quiet", quiet)]
+}
+
+/// print the groups with each move numbered, counting on across groups,
+/// in figurine notation when `figurine`
+pub fn print(pos: &Position, figurine: bool) {
+    let mut n = 0;
+    for (name, moves) in grouped(pos) {
+        let listed: Vec<String> = moves
+            .iter()
+            .map(|&mv| {
+                n += 1;
+                let san = to_san(pos, mv);
+                if figurine {
+                    format!("{} {}", n, crate::san::figurine(&san))
+                } else {
+                    format!("{} {}", n, san)
+                }
+            })
+            .collect();
+        if listed.is_empty() {
+            println!("{:<9} -", name);
+        } else {
+            println!("{:<9} {}", name, listed.join("  "));
+        }
+    }
+}
+
+/// target squares labelled with the numbers `print` gives their moves
+pub fn target_labels(pos: &Position) -> Vec<(Square, String)> {
+    let mut labels: Vec<(Square, String)> = Vec::new();
+    let moves = grouped(pos).into_iter().flat_map(|(_, moves)| moves);
+    for (i, mv) in moves.enumerate() {
+        let n = (i + 1).to_string();
+        match labels.iter_mut().find(|(sq, _)| *sq == mv.to) {
+            Some((_, text)) => {
+                text.push(',');
+                text.push_str(&n);
+            }
+            None => labels.push((mv.to, n)),
+        }
+    }
+    labels
+}
@@ -0,0 +1,98 @@
+//! What the terminal can show, guessed from the environment
+//!
+//! The probe reads the variables terminals set rather than querying the
+//! terminal itself, so it works the same when output is piped: `NO_COLOR`
+//! and `TERM=dumb` turn colours off, `COLORTERM` announces 24-bit colour
+//! and a `TERM` ending in `256color` the 256-colour palette. Kitty, iTerm2
+//! and WezTerm are recognised for inline images, except inside tmux, which
+//! would swallow them. Each guess can be overridden by its own flag.
+
+use crate::terminal::Charset;
+use clap::ValueEnum;
+
+/// how many colours the terminal draws
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum ColorDepth {
+    /// no colours at all
+    None,
+    /// the 16 standard ANSI colours
+    #[value(name = "16")]
+    Ansi16,
+    /// the xterm 256-colour palette
+    #[value(name = "256")]
+    Ansi256,
+    /// 24-bit colour
+    Truecolor,
+}
+
+/// how the terminal takes inline images
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ImageProtocol {
+    None,
+    /// the kitty graphics protocol
+    Kitty,
+    /// iTerm2's inline images, also read by WezTerm
+    Iterm,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Capabilities {
+    pub color: ColorDepth,
+    /// chess glyphs rather than letters
+    pub unicode: bool,
+    pub images: ImageProtocol,
+}
+
+fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+fn color_depth() -> ColorDepth {
+    let term = var("TERM").unwrap_or_default();
+    if var("NO_COLOR").is_some() || term == "dumb" {
+        return ColorDepth::None;
+    }
+    let colorterm = var("COLORTERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        return ColorDepth::Truecolor;
+    }
+    let program = var("TERM_PROGRAM").unwrap_or_default();
+    if ["iTerm.app", "WezTerm", "vscode"].contains(&program.as_str())
+        || var("KITTY_WINDOW_ID").is_some()
+        || var("WT_SESSION").is_some()
+    {
+        return ColorDepth::Truecolor;
+    }
+    if term.ends_with("256color") {
+        return ColorDepth::Ansi256;
+    }
+    ColorDepth::Ansi16
+}
+
+fn image_protocol() -> ImageProtocol {
+    if var("TMUX").is_some() {
+        return ImageProtocol::None;
+    }
+    let program = var("TERM_PROGRAM").unwrap_or_default();
+    if var("KITTY_WINDOW_ID").is_some() || var("TERM").as_deref() == Some("xterm-kitty") {
+        ImageProtocol::Kitty
+    } else if program == "iTerm.app"
+        || program == "WezTerm"
+        || var("LC_TERMINAL").as_deref() == Some("iTerm2")
+    {
+        ImageProtocol::Iterm
+    } else {
+        ImageProtocol::None
+    }
+}
+
+/// Guess what the terminal can do.
+pub fn probe() -> Capabilities {
+    let caps = Capabilities {
+        color: color_depth(),
+        unicode: Charset::detect() != Charset::Ascii,
+        images: image_protocol(),
+    };
+    tracing::debug!(target: "fen::terminal", ?caps, "probed the terminal");
+    caps
+}
@@ -0,0 +1,136 @@
+//! Lecture slides from a study file
+//!
+//! A study is a YAML file listing the positions of a talk in order, each
+//! with the caption and annotations to draw on it, in the form
+//! `--annotations` reads:
+//!
+//! ```text
+//! title: The Italian Game
+//! slides:
+//!   - fen: rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1
+//!     caption: 1. e4
+//!   - fen: r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3
+//!     caption: The bishop eyes f7
+//!     arrows:
+//!       - {from: c4, to: f7}
+//!     highlights: [f7]
+//! ```
+//!
+//! Each slide becomes a numbered image, `slide-01.png` and on, in the
+//! output directory, so the files sort in the order of the talk, or a page
+//! of a single PDF.
+
+use crate::annotations;
+use crate::diagram::{self, DiagramOptions, Quality};
+use crate::fonts::PieceFont;
+use crate::json::Json;
+use crate::pdf::{self, Layout};
+use crate::png;
+use crate::position::Position;
+use crate::svg;
+use crate::yaml;
+use clap::ValueEnum;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Format {
+    Png,
+    Svg,
+    /// one document, written to the --out file (.pdf added if missing)
+    Pdf,
+}
+
+/// how the slides are drawn
+pub struct Style<'a> {
+    pub pieces: &'a PieceFont,
+    /// square size, colours and the rest, which the slides add to
+    pub diagram: DiagramOptions,
+    pub quality: Quality,
+    /// compact SVG markup
+    pub optimize: bool,
+    /// how PDF slides are put on pages
+    pub layout: Layout,
+}
+
+/// Write a slide for every position in the study at `path` to the
+/// directory `out`.
+pub fn run(path: &str, out: &str, format: Format, style: &Style) -> Result<(), String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    let study = yaml::parse(&text).map_err(|e| format!("{}: {}", path, e))?;
+    let slides = study
+        .get("slides")
+        .and_then(Json::as_array)
+        .ok_or_else(|| format!("{} has no \"slides\" list", path))?;
+    if slides.is_empty() {
+        return Err(format!("{} has no slides", path));
+    }
+    if format != Format::Pdf {
+        std::fs::create_dir_all(out).map_err(|e| format!("Could not create {}: {}", out, e))?;
+    }
+
+    let digits = slides.len().to_string().len().max(2);
+    let extension = match format {
+        Format::Png => "png",
+        Format::Svg => "svg",
+        Format::Pdf => "pdf",
+    };
+    let mut pages = Vec::new();
+    for (i, slide) in slides.iter().enumerate() {
+        let context = |e: String| format!("{}: slide {}: {}", path, i + 1, e);
+        let fen = slide
+            .get("fen")
+            .and_then(Json::as_str)
+            .ok_or_else(|| context("No fen given".to_string()))?;
+        let pos = Position::from_fen(fen).map_err(context)?;
+        let layer = annotations::layer(slide, &style.diagram.palette).map_err(context)?;
+        let mut diagram = diagram::board(
+            &pos,
+            &DiagramOptions {
+                arrows: layer.arrows,
+                highlights: layer.highlights,
+                ..style.diagram.clone()
+            },
+        );
+        if let Some(caption) = &layer.caption {
+            diagram::add_caption(&mut diagram, caption);
+        }
+        let file = Path::new(out).join(format!("slide-{:0digits$}.{}", i + 1, extension));
+        let file = file.to_string_lossy();
+        match format {
+            Format::Pdf => pages.push(diagram),
+            Format::Png => png::save(&diagram, &file, style.pieces, style.quality)?,
+            Format::Svg => {
+                let markup = if style.optimize {
+                    svg::to_optimized_svg(&diagram)
+                } else {
+                    svg::to_svg(&diagram)
+                };
+                std::fs::write(&*file, markup)
+                    .map_err(|e| format!("Could not write {}: {}", file, e))?
+            }
+        }
+    }
+    let out = match format {
+        Format::Pdf => {
+            let file = Path::new(out).with_extension("pdf");
+            let file = file.to_string_lossy();
+            pdf::save(&pages, &file, style.pieces, style.layout)?;
+            file.into_owned()
+        }
+        _ => out.to_string(),
+    };
+    let title = study
+        .get("title")
+        .and_then(Json::as_str)
+        .map(|title| format!(" of {}", title))
+        .unwrap_or_default();
+    println!(
+        "Wrote {} slide{}{} to {}",
+        slides.len(),
+        if slides.len() == 1 { "" } else { "s" },
+        title,
+        out
+    );
+    Ok(())
+}
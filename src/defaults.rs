@@ -0,0 +1,270 @@
+//! Default flags and position from the environment and a config file
+//!
+//! `FEN_OPTS` holds flags to use on every run, written as on a command line
+//! (quotes group words), and `FEN_DEFAULT_POSITION` the position to show
+//! when none is given, so wrappers and aliases can set both. The file
+//! `config` in the configuration directory (see `store::config_dir`) can
+//! set them as well, as `opts = ...` and `position = ...` lines:
+//!
+//! ```text
+//! # a slide-sized window on the muted palette
+//! opts = --square-px 100 --palette muted
+//! position = rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1
+//! ```
+//!
+//! The config file overrides the environment and the command line
+//! overrides both: flags are put in front of the command line's own, so a
+//! later one with a value takes its place, and the default position is
+//! only used when the command line gives none. A subcommand only gets the
+//! flags every command takes, such as `--palette`, after its name.
+
+use crate::store;
+use clap::Command;
+use std::ffi::OsString;
+
+#[derive(Clone, Debug, Default)]
+pub struct Defaults {
+    /// flags put before those of the command line
+    pub opts: Vec<String>,
+    pub position: Option<String>,
+}
+
+/// `text` split into words as a shell would: at spaces, except inside
+/// single or double quotes, with a backslash escaping the next character
+/// outside single quotes.
+pub fn words(text: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                let escaped = chars.next().ok_or("Nothing to escape after \\")?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (_, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if let Some(q) = quote {
+        return Err(format!("{} is never closed", q));
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+// the defaults set by the environment
+fn from_env() -> Result<Defaults, String> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    let opts = match var("FEN_OPTS") {
+        Some(text) => words(&text).map_err(|e| format!("FEN_OPTS: {}", e))?,
+        None => Vec::new(),
+    };
+    Ok(Defaults {
+        opts,
+        position: var("FEN_DEFAULT_POSITION").map(|p| p.trim().to_string()),
+    })
+}
+
+// the defaults set by the config file, none if there is no file
+fn from_config() -> Result<Defaults, String> {
+    let Ok(dir) = store::config_dir() else {
+        return Ok(Defaults::default());
+    };
+    let path = dir.join("config");
+    let Ok(text) = std::fs::read_to_string(&path) else {
+        return Ok(Defaults::default());
+    };
+    parse_config(&text, &path.display().to_string())
+}
+
+// the settings in the text of the config file at `path`
+fn parse_config(text: &str, path: &str) -> Result<Defaults, String> {
+    let mut defaults = Defaults::default();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .map(|(k, v)| (k.trim(), v.trim()))
+            .ok_or_else(|| format!("{} line {}: Expected key = value", path, i + 1))?;
+        match key {
+            "opts" => {
+                defaults.opts =
+                    words(value).map_err(|e| format!("{} line {}: {}", path, i + 1, e))?
+            }
+            "position" => defaults.position = Some(value.to_string()),
+            _ => return Err(format!("{} line {}: Unknown setting {}", path, i + 1, key)),
+        }
+    }
+    Ok(defaults)
+}
+
+/// The defaults of the environment with the config file's laid over them:
+/// its flags after the environment's, and its position instead.
+pub fn load() -> Result<Defaults, String> {
+    Ok(layered(from_env()?, from_config()?))
+}
+
+fn layered(env: Defaults, config: Defaults) -> Defaults {
+    Defaults {
+        opts: env.opts.into_iter().chain(config.opts).collect(),
+        position: config.position.or(env.position),
+    }
+}
+
+// the flags of `opts` that every subcommand takes, each with its value
+fn global_opts(cmd: &Command, opts: &[String]) -> Vec<String> {
+    let mut kept = Vec::new();
+    let mut opts = opts.iter();
+    while let Some(opt) = opts.next() {
+        let name = opt.split('=').next().unwrap_or_default();
+        let arg = cmd.get_arguments().find(|a| match name.strip_prefix("--") {
+            Some(long) => a.get_long() == Some(long),
+            None => {
+                let mut short = name.chars().skip(1);
+                name.starts_with('-') && short.next() == a.get_short() && short.next().is_none()
+            }
+        });
+        let takes_value = arg.is_some_and(|a| a.get_action().takes_values()) && !opt.contains('=');
+        let value = if takes_value { opts.next() } else { None };
+        if arg.is_some_and(|a| a.is_global_set()) {
+            kept.push(opt.clone());
+            kept.extend(value.cloned());
+        }
+    }
+    kept
+}
+
+impl Defaults {
+    /// The command line `args` (the program name first) of `cmd` with the
+    /// default flags put in front of its own, or after the name of a
+    /// subcommand.
+    pub fn command_line(
+        &self,
+        cmd: &Command,
+        args: impl IntoIterator<Item = OsString>,
+    ) -> Vec<OsString> {
+        let mut args: Vec<OsString> = args.into_iter().collect();
+        let subcommand = args
+            .get(1)
+            .and_then(|a| a.to_str())
+            .is_some_and(|name| cmd.find_subcommand(name).is_some());
+        let (at, opts) = if subcommand {
+            (2, global_opts(cmd, &self.opts))
+        } else {
+            (1, self.opts.clone())
+        };
+        let at = at.min(args.len());
+        args.splice(at..at, opts.into_iter().map(OsString::from));
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Arg, ArgAction};
+
+    #[test]
+    fn words_are_split_as_by_a_shell() {
+        assert_eq!(
+            words(r#"  --palette muted --title "Move 12"  --note 'a\b' c\ d "\"q\"" '' "#),
+            Ok(vec![
+                "--palette".to_string(),
+                "muted".to_string(),
+                "--title".to_string(),
+                "Move 12".to_string(),
+                "--note".to_string(),
+                // a backslash is only an escape outside single quotes
+                "a\\b".to_string(),
+                "c d".to_string(),
+                "\"q\"".to_string(),
+                String::new(),
+            ])
+        );
+        assert_eq!(words("a'b c'd"), Ok(vec!["ab cd".to_string()]));
+        assert!(words("--title \"Move 12").is_err());
+        assert!(words("trailing\\").is_err());
+    }
+
+    fn command() -> Command {
+        Command::new("fen")
+            .args_override_self(true)
+            .arg(Arg::new("palette").long("palette").global(true))
+            .arg(Arg::new("square-px").long("square-px"))
+            .arg(Arg::new("info").short('i').action(ArgAction::SetTrue))
+            .arg(Arg::new("fen"))
+            .subcommand(Command::new("tree"))
+    }
+
+    fn os(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn the_command_line_wins_over_config_and_config_over_environment() {
+        let env = Defaults {
+            opts: words("--palette env --square-px 60").unwrap(),
+            position: Some("env".to_string()),
+        };
+        let config = parse_config(
+            "# comment\n\nopts = --palette config\nposition = config\n",
+            "config",
+        )
+        .unwrap();
+        let defaults = layered(env, config);
+        assert_eq!(defaults.position.as_deref(), Some("config"));
+
+        let palette = |args: &[&str]| {
+            let line = defaults.command_line(&command(), os(args));
+            let matches = command().try_get_matches_from(line).unwrap();
+            let square_px = matches.get_one::<String>("square-px").cloned();
+            (
+                matches.get_one::<String>("palette").cloned().unwrap(),
+                square_px,
+            )
+        };
+        assert_eq!(
+            palette(&["fen"]),
+            ("config".to_string(), Some("60".to_string()))
+        );
+        assert_eq!(
+            palette(&["fen", "--palette", "cli"]),
+            ("cli".to_string(), Some("60".to_string()))
+        );
+    }
+
+    #[test]
+    fn a_subcommand_only_gets_the_global_flags() {
+        let defaults = Defaults {
+            opts: words("--square-px 60 -i --palette=muted").unwrap(),
+            position: None,
+        };
+        assert_eq!(
+            defaults.command_line(&command(), os(&["fen", "tree", "x"])),
+            os(&["fen", "tree", "--palette=muted", "x"])
+        );
+        assert_eq!(
+            defaults.command_line(&command(), os(&["fen"])),
+            os(&["fen", "--square-px", "60", "-i", "--palette=muted"])
+        );
+    }
+
+    #[test]
+    fn config_errors_name_the_line() {
+        assert_eq!(
+            parse_config("opts = -i\ncolour = red", "config").unwrap_err(),
+            "config line 2: Unknown setting colour"
+        );
+        assert!(parse_config("opts", "config").is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! DGT electronic board input over a serial port
+//!
+//! The board is asked for a full dump and then for field updates, which it
+//! sends whenever a piece is lifted or put down. The port is set to the
+//! board's 9600 baud, 8N1 with `stty`.
+
+use crate::position::{square, Color, Piece, PieceKind, Position};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::process::Command;
+
+const SEND_RESET: u8 = 0x40;
+const SEND_BOARD: u8 = 0x42;
+const SEND_UPDATES: u8 = 0x44;
+const MSG_BOARD_DUMP: u8 = 0x86;
+const MSG_FIELD_UPDATE: u8 = 0x8e;
+
+// DGT piece codes, from 1 (white pawn) to 12 (black queen)
+fn piece(code: u8) -> Option<Piece> {
+    use PieceKind::*;
+    let kinds = [Pawn, Rook, Knight, Bishop, King, Queen];
+    match code {
+        1..=6 => Some(Piece::new(Color::White, kinds[code as usize - 1])),
+        7..=12 => Some(Piece::new(Color::Black, kinds[code as usize - 7])),
+        _ => None,
+    }
+}
+
+// DGT fields run from a8 (0) along each rank to h1 (63)
+fn field_square(field: u8) -> usize {
+    square(field % 8, 7 - field / 8) as usize
+}
+
+fn configure(port: &str) -> Result<(), String> {
+    let flag = if cfg!(target_os = "macos") {
+        "-f"
+    } else {
+        "-F"
+    };
+    let status = Command::new("stty")
+        .args([
+            flag, port, "9600", "cs8", "-cstopb", "-parenb", "raw", "-echo",
+        ])
+        .status()
+        .map_err(|e| format!("Could not run stty to configure {}: {}", port, e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("Could not configure serial port {}", port))
+    }
+}
+
+// the next message from the board, as its id and payload
+fn read_message(port: &mut File) -> Result<(u8, Vec<u8>), String> {
+    let mut byte = [0; 1];
+    let read_err = |e: std::io::Error| format!("Could not read from the DGT board: {}", e);
+    // skip anything up to the start of a message, which has the top bit set
+    loop {
+        port.read_exact(&mut byte).map_err(read_err)?;
+        if byte[0] & 0x80 != 0 {
+            break;
+        }
+    }
+    let id = byte[0];
+    let mut size = [0; 2];
+    port.read_exact(&mut size).map_err(read_err)?;
+    let len = ((size[0] as usize) << 7 | size[1] as usize).saturating_sub(3);
+    let mut payload = vec![0; len];
+    port.read_exact(&mut payload).map_err(read_err)?;
+    Ok((id, payload))
+}
+
+/// Follow the board on `port`, calling `on_change` with the position every
+/// time a piece is lifted or placed. The side to move is taken to be the
+/// opponent of the side whose piece last arrived on a square. Only returns
+/// on an error.
+pub fn follow(port: &str, mut on_change: impl FnMut(&Position)) -> Result<(), String> {
+    configure(port)?;
+    let mut port_file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(port)
+        .map_err(|e| format!("Could not open {}: {}", port, e))?;
+    port_file
+        .write_all(&[SEND_RESET, SEND_BOARD, SEND_UPDATES])
+        .map_err(|e| format!("Could not write to the DGT board: {}", e))?;
+
+    let mut board = [None; 64];
+    let mut side = Color::White;
+    let mut last = None;
+    loop {
+        match read_message(&mut port_file)? {
+            (MSG_BOARD_DUMP, payload) if payload.len() == 64 => {
+                for (field, &code) in payload.iter().enumerate() {
+                    board[field_square(field as u8)] = piece(code);
+                }
+            }
+            (MSG_FIELD_UPDATE, payload) if payload.len() == 2 && payload[0] < 64 => {
+                let arrived = piece(payload[1]);
+                board[field_square(payload[0])] = arrived;
+                if let Some(p) = arrived {
+                    side = p.color.opponent();
+                }
+            }
+            _ => continue,
+        }
+        let pos = Position::from_placement(board, side);
+        if last.as_ref() != Some(&pos) {
+            on_change(&pos);
+            last = Some(pos);
+        }
+    }
+}
@@ -0,0 +1,870 @@
+//! Backend-independent board diagrams
+//!
+//! A diagram is a flat list of shapes in pixel coordinates which the
+//! SVG and PNG exporters draw in order.
+
+use crate::position::{
+    file_of, parse_square, rank_of, square, square_name, Color, Position, Square,
+};
+use crate::qr::QrCode;
+use clap::ValueEnum;
+
+pub type Rgba = [u8; 4];
+
+pub const WHITE: Rgba = [255, 255, 255, 255];
+pub const BLACK: Rgba = [0, 0, 0, 255];
+const BAR_WHITE: Rgba = [240, 240, 240, 255];
+const BAR_BLACK: Rgba = [40, 40, 40, 255];
+const LABEL: Rgba = [200, 30, 30, 255];
+pub const SHADOW: Rgba = [0, 0, 0, 70];
+pub const FRAME: Rgba = [60, 60, 60, 255];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Quality {
+    /// draw at the output size
+    #[default]
+    Low,
+    /// smoother edges (multisampling in the window, supersampling in PNGs) and piece shadows
+    High,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum PaletteName {
+    /// muted green board
+    Default,
+    /// blue and sand board, blue and orange marks, for red-green (green-weak) colour blindness
+    Deuteranopia,
+    /// slate and cream board, blue and yellow marks, for red-green (red-weak) colour blindness
+    Protanopia,
+    /// pale grey board and soft marks, for diagrams set in text
+    Muted,
+}
+
+/// how squares are named by `--index-overlay`
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum SquareIndex {
+    /// 0 for a1 up to 63 for h8, the bit of the square in a bitboard
+    #[value(name = "0-63")]
+    Number,
+    /// the square's name, a1 to h8
+    #[value(name = "a1h8")]
+    Name,
+}
+
+impl SquareIndex {
+    pub fn label(self, sq: Square) -> String {
+        match self {
+            SquareIndex::Number => sq.to_string(),
+            SquareIndex::Name => square_name(sq),
+        }
+    }
+}
+
+/// Colours of the squares and of everything drawn over them, shared by the
+/// window, the terminal and the exported diagrams
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    pub light: Rgba,
+    pub dark: Rgba,
+    /// squares picked out with `highlight`
+    pub highlight: Rgba,
+    /// arrow for an engine's best move
+    pub best: Rgba,
+    /// arrow for a move played instead of the best one
+    pub played: Rgba,
+    /// arrow for a blunder, also used to mark a square that needs attention
+    pub blunder: Rgba,
+}
+
+impl Palette {
+    // https://colorswall.com/palette/166635
+    pub const DEFAULT: Palette = Palette {
+        light: [180, 188, 170, 255],
+        dark: [67, 74, 58, 255],
+        highlight: [230, 204, 26, 128],
+        best: [21, 120, 27, 200],
+        played: [30, 90, 200, 200],
+        blunder: [200, 30, 30, 200],
+    };
+
+    // marks from the Okabe-Ito palette, which stays distinct under each kind of
+    // colour blindness
+    const DEUTERANOPIA: Palette = Palette {
+        light: [232, 220, 190, 255],
+        dark: [74, 112, 158, 255],
+        highlight: [86, 180, 233, 140],
+        best: [0, 114, 178, 210],
+        played: [204, 121, 167, 210],
+        blunder: [230, 159, 0, 210],
+    };
+
+    const PROTANOPIA: Palette = Palette {
+        light: [240, 232, 200, 255],
+        dark: [96, 108, 128, 255],
+        highlight: [240, 228, 66, 150],
+        best: [0, 114, 178, 210],
+        played: [86, 180, 233, 210],
+        blunder: [230, 159, 0, 210],
+    };
+
+    const MUTED: Palette = Palette {
+        light: [238, 236, 228, 255],
+        dark: [184, 186, 178, 255],
+        highlight: [238, 214, 110, 120],
+        best: [86, 128, 92, 190],
+        played: [92, 118, 164, 190],
+        blunder: [176, 84, 72, 190],
+    };
+
+    /// The named palette, with `high_contrast` pushing the squares towards
+    /// white and grey and drawing the marks opaque.
+    pub fn new(name: PaletteName, high_contrast: bool) -> Palette {
+        let palette = match name {
+            PaletteName::Default => Palette::DEFAULT,
+            PaletteName::Deuteranopia => Palette::DEUTERANOPIA,
+            PaletteName::Protanopia => Palette::PROTANOPIA,
+            PaletteName::Muted => Palette::MUTED,
+        };
+        if !high_contrast {
+            return palette;
+        }
+        // blend the squares towards white and a mid grey; black pieces still
+        // stand out on the dark squares
+        let mix = |c: Rgba, target: u8| c.map(|v| ((v as u16 + 3 * target as u16) / 4) as u8);
+        let opaque = |[r, g, b, _]: Rgba| [r, g, b, 255];
+        Palette {
+            light: opaque(mix(palette.light, 255)),
+            dark: opaque(mix(palette.dark, 110)),
+            highlight: [
+                palette.highlight[0],
+                palette.highlight[1],
+                palette.highlight[2],
+                200,
+            ],
+            best: opaque(palette.best),
+            played: opaque(palette.played),
+            blunder: opaque(palette.blunder),
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::DEFAULT
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Font {
+    /// coordinates and captions
+    Text,
+    /// chess glyphs and annotation symbols
+    Pieces,
+}
+
+#[derive(Clone, Debug)]
+pub enum Shape {
+    Rect {
+        x: f64,
+        y: f64,
+        w: f64,
+        h: f64,
+        color: Rgba,
+    },
+    /// text drawn with its baseline starting at (x, y)
+    Text {
+        x: f64,
+        y: f64,
+        size: f64,
+        text: String,
+        color: Rgba,
+        font: Font,
+    },
+    /// filled polygon
+    Polygon {
+        points: Vec<(f64, f64)>,
+        color: Rgba,
+    },
+}
+
+pub struct Diagram {
+    pub width: f64,
+    pub height: f64,
+    pub shapes: Vec<Shape>,
+}
+
+/// A rectangle of squares, inclusive of both corners
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    pub files: (u8, u8),
+    pub ranks: (u8, u8),
+}
+
+impl Region {
+    pub const FULL: Region = Region {
+        files: (0, 7),
+        ranks: (0, 7),
+    };
+
+    /// parse two opposite corners such as `a1-d4` (or `d4:a1`)
+    pub fn parse(text: &str) -> Option<Region> {
+        let (a, b) = text.split_once(['-', ':'])?;
+        let (a, b) = (parse_square(a.trim())?, parse_square(b.trim())?);
+        Some(Region {
+            files: (file_of(a).min(file_of(b)), file_of(a).max(file_of(b))),
+            ranks: (rank_of(a).min(rank_of(b)), rank_of(a).max(rank_of(b))),
+        })
+    }
+
+    pub fn width(&self) -> u8 {
+        self.files.1 - self.files.0 + 1
+    }
+
+    pub fn height(&self) -> u8 {
+        self.ranks.1 - self.ranks.0 + 1
+    }
+
+    pub fn contains(&self, sq: Square) -> bool {
+        (self.files.0..=self.files.1).contains(&file_of(sq))
+            && (self.ranks.0..=self.ranks.1).contains(&rank_of(sq))
+    }
+}
+
+/// The sizes of a drawn board, in pixels, for diagrams and the window alike
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+    /// edge length of a square
+    pub square: f64,
+    /// empty space around the frame
+    pub margin: f64,
+    /// width of a frame around the board, none when 0
+    pub border: f64,
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout {
+            square: 75.0,
+            margin: 0.0,
+            border: 0.0,
+        }
+    }
+}
+
+impl Layout {
+    /// the space between the edge of the drawing and the board
+    pub fn inset(self) -> f64 {
+        self.margin + self.border
+    }
+
+    /// the edge length of a whole board with its frame and margin
+    pub fn side(self) -> f64 {
+        8.0 * self.square + 2.0 * self.inset()
+    }
+}
+
+#[derive(Clone)]
+pub struct DiagramOptions {
+    pub layout: Layout,
+    /// the file letters and rank numbers along the edge squares
+    pub coordinates: bool,
+    /// White's share of the evaluation bar (0..=1); no bar when `None`
+    pub eval: Option<f64>,
+    /// annotation symbol (e.g. `!!` or `±`) shown as a badge in the top-right corner
+    pub badge: Option<String>,
+    /// draw the pieces; off for blindfold diagrams
+    pub pieces: bool,
+    /// the part of the board to draw
+    pub region: Region,
+    /// short labels (e.g. candidate move numbers) in the top-right of squares
+    pub labels: Vec<(Square, String)>,
+    /// arrows from one square to another, such as an engine's best move
+    pub arrows: Vec<(Square, Square, Rgba)>,
+    pub palette: Palette,
+    /// soft shadows under the pieces
+    pub shadows: bool,
+    /// each square's index or name in its bottom-left corner
+    pub index: Option<SquareIndex>,
+    /// squares tinted with the palette's highlight
+    pub highlights: Vec<Square>,
+}
+
+impl Default for DiagramOptions {
+    fn default() -> DiagramOptions {
+        DiagramOptions {
+            layout: Layout::default(),
+            coordinates: true,
+            eval: None,
+            badge: None,
+            pieces: true,
+            region: Region::FULL,
+            labels: Vec::new(),
+            arrows: Vec::new(),
+            palette: Palette::DEFAULT,
+            shadows: false,
+            index: None,
+            highlights: Vec::new(),
+        }
+    }
+}
+
+/// outline glyphs for white, solid glyphs for black
+pub fn glyph(kind_letter: char, color: Color) -> &'static str {
+    match (color, kind_letter) {
+        (Color::White, 'P') => "♙",
+        (Color::White, 'N') => "♘",
+        (Color::White, 'B') => "♗",
+        (Color::White, 'R') => "♖",
+        (Color::White, 'Q') => "♕",
+        (Color::White, 'K') => "♔",
+        (_, 'P') => "♟",
+        (_, 'N') => "♞",
+        (_, 'B') => "♝",
+        (_, 'R') => "♜",
+        (_, 'Q') => "♛",
+        (_, 'K') => "♚",
+        _ => "",
+    }
+}
+
+/// Lay out `pos` as seen from White, with file and rank labels drawn
+/// inside the edge squares as in the graphical window. Only the squares
+/// of `opts.region` are drawn, labelled along the region's own edges, and
+/// only the arrows with both ends inside it.
+pub fn board(pos: &Position, opts: &DiagramOptions) -> Diagram {
+    let sq = opts.layout.square;
+    let region = opts.region;
+    let mut shapes = Vec::new();
+    let files = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+
+    for rank in (region.ranks.0..=region.ranks.1).rev() {
+        for file in region.files.0..=region.files.1 {
+            let x = (file - region.files.0) as f64 * sq;
+            let y = (region.ranks.1 - rank) as f64 * sq;
+            let light = (file + rank) % 2 == 1;
+            let (color, other) = if light {
+                (opts.palette.light, opts.palette.dark)
+            } else {
+                (opts.palette.dark, opts.palette.light)
+            };
+            shapes.push(Shape::Rect {
+                x,
+                y,
+                w: sq,
+                h: sq,
+                color,
+            });
+            if opts.highlights.contains(&square(file, rank)) {
+                shapes.push(Shape::Rect {
+                    x,
+                    y,
+                    w: sq,
+                    h: sq,
+                    color: opts.palette.highlight,
+                });
+            }
+            if rank == region.ranks.0 && opts.coordinates {
+                shapes.push(Shape::Text {
+                    x: x + 0.8 * sq,
+                    y: y + 0.9 * sq,
+                    size: 0.21 * sq,
+                    text: files[file as usize].to_string(),
+                    color: other,
+                    font: Font::Text,
+                });
+            }
+            if file == region.files.0 && opts.coordinates {
+                shapes.push(Shape::Text {
+                    x: x + 0.05 * sq,
+                    y: y + 0.25 * sq,
+                    size: 0.21 * sq,
+                    text: (rank + 1).to_string(),
+                    color: other,
+                    font: Font::Text,
+                });
+            }
+            if let Some(index) = opts.index {
+                shapes.push(Shape::Text {
+                    x: x + 0.05 * sq,
+                    y: y + 0.95 * sq,
+                    size: 0.17 * sq,
+                    text: index.label(square(file, rank)),
+                    color: other,
+                    font: Font::Text,
+                });
+            }
+            if let Some(piece) = pos.piece_at(square(file, rank)).filter(|_| opts.pieces) {
+                if opts.shadows {
+                    shapes.push(shadow(
+                        piece.kind.letter(),
+                        x + 0.15 * sq,
+                        y + 0.8 * sq,
+                        0.8 * sq,
+                    ));
+                }
+                shapes.extend(piece_shapes(
+                    piece.kind.letter(),
+                    piece.color,
+                    x + 0.15 * sq,
+                    y + 0.8 * sq,
+                    0.8 * sq,
+                ));
+            }
+            if let Some((_, text)) = opts.labels.iter().find(|(s, _)| *s == square(file, rank)) {
+                label(&mut shapes, text, x + sq, y, sq);
+            }
+        }
+    }
+
+    let inside = opts
+        .arrows
+        .iter()
+        .filter(|(from, to, _)| region.contains(*from) && region.contains(*to));
+    for &(from, to, color) in inside {
+        let centre = |s: Square| {
+            (
+                (file_of(s) as f64 - region.files.0 as f64 + 0.5) * sq,
+                (region.ranks.1 as f64 - rank_of(s) as f64 + 0.5) * sq,
+            )
+        };
+        for points in arrow(centre(from), centre(to), sq) {
+            shapes.push(Shape::Polygon { points, color });
+        }
+    }
+
+    let mut width = region.width() as f64 * sq;
+    let height = region.height() as f64 * sq;
+    if let Some(text) = &opts.badge {
+        badge(&mut shapes, text, width, sq);
+    }
+    if let Some(share) = opts.eval {
+        width += eval_bar(&mut shapes, share, width, height, sq);
+    }
+    let diagram = Diagram {
+        width,
+        height,
+        shapes,
+    };
+    let diagram = if opts.layout.border > 0.0 {
+        framed(&diagram, opts.layout.border)
+    } else {
+        diagram
+    };
+    if opts.layout.margin > 0.0 {
+        margined(&diagram, opts.layout.margin)
+    } else {
+        diagram
+    }
+}
+
+/// `diagram` inside a frame `border` pixels wide.
+fn framed(diagram: &Diagram, border: f64) -> Diagram {
+    let mut shapes = vec![Shape::Rect {
+        x: 0.0,
+        y: 0.0,
+        w: diagram.width + 2.0 * border,
+        h: diagram.height + 2.0 * border,
+        color: FRAME,
+    }];
+    shapes.extend(diagram.shapes.iter().map(|s| shifted(s, border, border)));
+    Diagram {
+        width: diagram.width + 2.0 * border,
+        height: diagram.height + 2.0 * border,
+        shapes,
+    }
+}
+
+/// `diagram` with `margin` pixels of empty space on every side.
+fn margined(diagram: &Diagram, margin: f64) -> Diagram {
+    Diagram {
+        width: diagram.width + 2.0 * margin,
+        height: diagram.height + 2.0 * margin,
+        shapes: diagram
+            .shapes
+            .iter()
+            .map(|s| shifted(s, margin, margin))
+            .collect(),
+    }
+}
+
+/// A piece glyph at baseline (x, y). White pieces are a white-filled solid
+/// glyph under a black outline glyph so they read on both square colours.
+pub fn piece_shapes(letter: char, color: Color, x: f64, y: f64, size: f64) -> Vec<Shape> {
+    let solid = glyph(letter, Color::Black).to_string();
+    match color {
+        Color::Black => vec![Shape::Text {
+            x,
+            y,
+            size,
+            text: solid,
+            color: BLACK,
+            font: Font::Pieces,
+        }],
+        Color::White => vec![
+            Shape::Text {
+                x,
+                y,
+                size,
+                text: solid,
+                color: WHITE,
+                font: Font::Pieces,
+            },
+            Shape::Text {
+                x,
+                y,
+                size,
+                text: glyph(letter, Color::White).to_string(),
+                color: BLACK,
+                font: Font::Pieces,
+            },
+        ],
+    }
+}
+
+/// The shadow cast down and to the right by a piece glyph at baseline
+/// (x, y), drawn before the piece itself.
+pub fn shadow(letter: char, x: f64, y: f64, size: f64) -> Shape {
+    let offset = 0.04 * size;
+    Shape::Text {
+        x: x + offset,
+        y: y + offset,
+        size,
+        text: glyph(letter, Color::Black).to_string(),
+        color: SHADOW,
+        font: Font::Pieces,
+    }
+}
+
+impl Diagram {
+    /// the same diagram drawn `factor` times larger
+    pub fn scaled(&self, factor: f64) -> Diagram {
+        let shapes = self
+            .shapes
+            .iter()
+            .map(|shape| match shape {
+                Shape::Rect { x, y, w, h, color } => Shape::Rect {
+                    x: x * factor,
+                    y: y * factor,
+                    w: w * factor,
+                    h: h * factor,
+                    color: *color,
+                },
+                Shape::Text {
+                    x,
+                    y,
+                    size,
+                    text,
+                    color,
+                    font,
+                } => Shape::Text {
+                    x: x * factor,
+                    y: y * factor,
+                    size: size * factor,
+                    text: text.clone(),
+                    color: *color,
+                    font: *font,
+                },
+                Shape::Polygon { points, color } => Shape::Polygon {
+                    points: points
+                        .iter()
+                        .map(|&(x, y)| (x * factor, y * factor))
+                        .collect(),
+                    color: *color,
+                },
+            })
+            .collect();
+        Diagram {
+            width: self.width * factor,
+            height: self.height * factor,
+            shapes,
+        }
+    }
+}
+
+/// boards side by side in a row of the image filmstrip
+const STRIP_COLUMNS: usize = 4;
+
+/// `shape` moved `dx` right and `dy` down
+fn shifted(shape: &Shape, dx: f64, dy: f64) -> Shape {
+    match shape {
+        Shape::Rect { x, y, w, h, color } => Shape::Rect {
+            x: x + dx,
+            y: y + dy,
+            w: *w,
+            h: *h,
+            color: *color,
+        },
+        Shape::Text {
+            x,
+            y,
+            size,
+            text,
+            color,
+            font,
+        } => Shape::Text {
+            x: x + dx,
+            y: y + dy,
+            size: *size,
+            text: text.clone(),
+            color: *color,
+            font: *font,
+        },
+        Shape::Polygon { points, color } => Shape::Polygon {
+            points: points.iter().map(|&(x, y)| (x + dx, y + dy)).collect(),
+            color: *color,
+        },
+    }
+}
+
+/// Lay `frames` out a few to a row on a white sheet, each diagram under
+/// its caption (such as the move that led to it).
+pub fn filmstrip(frames: &[(String, Diagram)]) -> Diagram {
+    let cell_width = frames.iter().map(|(_, d)| d.width).fold(0.0, f64::max);
+    let board_height = frames.iter().map(|(_, d)| d.height).fold(0.0, f64::max);
+    let gap = board_height / 16.0;
+    let caption = board_height / 10.0;
+    let columns = frames.len().clamp(1, STRIP_COLUMNS);
+    let rows = frames.len().div_ceil(STRIP_COLUMNS).max(1);
+    let width = columns as f64 * (cell_width + gap) + gap;
+    let height = rows as f64 * (caption + board_height + gap) + gap;
+
+    let mut shapes = vec![Shape::Rect {
+        x: 0.0,
+        y: 0.0,
+        w: width,
+        h: height,
+        color: WHITE,
+    }];
+    for (i, (text, diagram)) in frames.iter().enumerate() {
+        let x = gap + (i % STRIP_COLUMNS) as f64 * (cell_width + gap);
+        let y = gap + (i / STRIP_COLUMNS) as f64 * (caption + board_height + gap);
+        shapes.push(Shape::Text {
+            x,
+            y: y + 0.75 * caption,
+            size: 0.7 * caption,
+            text: text.clone(),
+            color: BLACK,
+            font: Font::Text,
+        });
+        shapes.extend(diagram.shapes.iter().map(|s| shifted(s, x, y + caption)));
+    }
+    Diagram {
+        width,
+        height,
+        shapes,
+    }
+}
+
+/// Widen `diagram` with a square panel on its right holding `code`,
+/// with a quiet zone around it so it scans when printed.
+pub fn add_qr(diagram: &mut Diagram, code: &QrCode) {
+    let left = diagram.width;
+    let side = diagram.height;
+    let module = side / (code.size + 8) as f64;
+    diagram.shapes.push(Shape::Rect {
+        x: left,
+        y: 0.0,
+        w: side,
+        h: side,
+        color: WHITE,
+    });
+    for y in 0..code.size {
+        for x in 0..code.size {
+            if code.is_dark(x, y) {
+                diagram.shapes.push(Shape::Rect {
+                    x: left + (x + 4) as f64 * module,
+                    y: (y + 4) as f64 * module,
+                    w: module,
+                    h: module,
+                    color: BLACK,
+                });
+            }
+        }
+    }
+    diagram.width += side;
+}
+
+/// Lengthen `diagram` with a strip below it holding `text`, as the
+/// captions of a filmstrip are drawn.
+pub fn add_caption(diagram: &mut Diagram, text: &str) {
+    let strip = diagram.height / 10.0;
+    diagram.shapes.push(Shape::Rect {
+        x: 0.0,
+        y: diagram.height,
+        w: diagram.width,
+        h: strip,
+        color: WHITE,
+    });
+    diagram.shapes.push(Shape::Text {
+        x: 0.2 * strip,
+        y: diagram.height + 0.75 * strip,
+        size: 0.7 * strip,
+        text: text.to_string(),
+        color: BLACK,
+        font: Font::Text,
+    });
+    diagram.height += strip;
+}
+
+/// An arrow between two square centres as its shaft and head, both convex
+/// so any polygon filler can draw them.
+pub fn arrow(from: (f64, f64), to: (f64, f64), sq: f64) -> [Vec<(f64, f64)>; 2] {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let len = (dx * dx + dy * dy).sqrt().max(1.0);
+    let (ux, uy) = (dx / len, dy / len);
+    // unit normal, then half the shaft and head widths
+    let (nx, ny) = (-uy, ux);
+    let (shaft, head, head_len) = (0.08 * sq, 0.22 * sq, 0.35 * sq);
+    let base = (to.0 - ux * head_len, to.1 - uy * head_len);
+    let at = |p: (f64, f64), w: f64| (p.0 + nx * w, p.1 + ny * w);
+    [
+        vec![
+            at(from, shaft),
+            at(base, shaft),
+            at(base, -shaft),
+            at(from, -shaft),
+        ],
+        vec![at(base, head), to, at(base, -head)],
+    ]
+}
+
+// vertical bar to the right of the board, White's share filling from the bottom;
+// returns the width used
+fn eval_bar(shapes: &mut Vec<Shape>, share: f64, left: f64, height: f64, sq: f64) -> f64 {
+    let width = 0.3 * sq;
+    let white = height * share.clamp(0.0, 1.0);
+    shapes.push(Shape::Rect {
+        x: left,
+        y: 0.0,
+        w: width,
+        h: height - white,
+        color: BAR_BLACK,
+    });
+    shapes.push(Shape::Rect {
+        x: left,
+        y: height - white,
+        w: width,
+        h: white,
+        color: BAR_WHITE,
+    });
+    width
+}
+
+// square label tucked into the top-right corner of the square ending at `right`
+fn label(shapes: &mut Vec<Shape>, text: &str, right: f64, top: f64, sq: f64) {
+    let size = 0.2 * sq;
+    let w = size * (0.3 + 0.55 * text.chars().count() as f64);
+    shapes.push(Shape::Rect {
+        x: right - w,
+        y: top,
+        w,
+        h: 1.2 * size,
+        color: [255, 255, 255, 200],
+    });
+    shapes.push(Shape::Text {
+        x: right - w + 0.15 * size,
+        y: top + size,
+        size,
+        text: text.to_string(),
+        color: LABEL,
+        font: Font::Text,
+    });
+}
+
+// annotation badge tucked into the top-right corner
+fn badge(shapes: &mut Vec<Shape>, text: &str, right: f64, sq: f64) {
+    let size = 0.3 * sq;
+    let w = size * (0.4 + 0.55 * text.chars().count() as f64);
+    shapes.push(Shape::Rect {
+        x: right - w,
+        y: 0.0,
+        w,
+        h: 1.3 * size,
+        color: [255, 255, 255, 230],
+    });
+    shapes.push(Shape::Text {
+        x: right - w + 0.2 * size,
+        y: size,
+        size,
+        text: text.to_string(),
+        color: BLACK,
+        font: Font::Pieces,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARROW: Rgba = [1, 2, 3, 255];
+
+    fn arrow_polygons(diagram: &Diagram) -> Vec<&Vec<(f64, f64)>> {
+        diagram
+            .shapes
+            .iter()
+            .filter_map(|shape| match shape {
+                Shape::Polygon { points, color } if *color == ARROW => Some(points),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cropped_boards_only_draw_arrows_inside_the_crop() {
+        let pos = Position::from_fen("8/8/8/4k3/8/2K5/1P6/8 w - - 0 1").unwrap();
+        let sq = |name| parse_square(name).unwrap();
+        let region = Region::parse("a1-d4").unwrap();
+        let inside = DiagramOptions {
+            region,
+            arrows: vec![(sq("b2"), sq("b4"), ARROW)],
+            ..DiagramOptions::default()
+        };
+        let expected = arrow_polygons(&board(&pos, &inside)).len();
+        assert!(expected > 0);
+
+        let mixed = DiagramOptions {
+            arrows: vec![
+                (sq("b2"), sq("b4"), ARROW),
+                // out to e5, in from h8, and wholly outside
+                (sq("c3"), sq("e5"), ARROW),
+                (sq("h8"), sq("a1"), ARROW),
+                (sq("e2"), sq("e4"), ARROW),
+            ],
+            ..inside
+        };
+        let diagram = board(&pos, &mixed);
+        let polygons = arrow_polygons(&diagram);
+        assert_eq!(polygons.len(), expected);
+        for &(x, y) in polygons.iter().flat_map(|points| points.iter()) {
+            assert!((0.0..=diagram.width).contains(&x), "{}", x);
+            assert!((0.0..=diagram.height).contains(&y), "{}", y);
+        }
+
+        // uncropped, all four are drawn
+        let full = DiagramOptions {
+            region: Region::FULL,
+            ..mixed
+        };
+        assert_eq!(arrow_polygons(&board(&pos, &full)).len(), 4 * expected);
+    }
+
+    #[test]
+    fn regions_contain_their_corners() {
+        let region = Region::parse("d4:a1").unwrap();
+        assert_eq!((region.width(), region.height()), (4, 4));
+        for (name, inside) in [
+            ("a1", true),
+            ("d4", true),
+            ("b3", true),
+            ("e4", false),
+            ("d5", false),
+        ] {
+            assert_eq!(
+                region.contains(parse_square(name).unwrap()),
+                inside,
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! Puzzle drill with spaced repetition
+//!
+//! Puzzles come from an EPD file, their solutions from `bm`. Each answer
+//! is graded and the puzzle rescheduled with the SM-2 algorithm; the
+//! schedule is kept in `drill.json` beside the position store.
+
+use crate::epd::{self, Record};
+use crate::json::Json;
+use crate::position::Color;
+use crate::san::{parse_move, promotions, to_san};
+use crate::store::{data_dir, format_time, now, write_atomic};
+use crate::terminal::{ask_promotion, board_glyphs, print_board, TerminalOptions};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+const DAY: u64 = 86_400;
+
+/// SM-2 state of one puzzle
+struct Card {
+    /// the puzzle's position as a FEN
+    key: String,
+    easiness: f64,
+    repetitions: u32,
+    interval_days: u64,
+    due: u64,
+}
+
+impl Card {
+    fn new(key: String) -> Card {
+        Card {
+            key,
+            easiness: 2.5,
+            repetitions: 0,
+            interval_days: 0,
+            due: 0,
+        }
+    }
+
+    /// Reschedule after an answer of `quality`, from 0 (forgotten) to 5
+    /// (perfect recall).
+    fn review(&mut self, quality: u32) {
+        let q = quality.min(5) as f64;
+        if quality >= 3 {
+            self.interval_days = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval_days as f64 * self.easiness).round() as u64,
+            };
+            self.repetitions += 1;
+        } else {
+            self.repetitions = 0;
+            self.interval_days = 1;
+        }
+        self.easiness = (self.easiness + 0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)).max(1.3);
+        self.due = now() + self.interval_days * DAY;
+    }
+}
+
+fn schedule_path() -> Result<PathBuf, String> {
+    Ok(data_dir()?.join("drill.json"))
+}
+
+fn load_schedule() -> Result<Vec<Card>, String> {
+    let path = schedule_path()?;
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Could not read {}: {}", path.display(), e)),
+    };
+    let json = Json::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let num = |card: &Json, key: &str| card.get(key).and_then(Json::as_f64).unwrap_or(0.0);
+    Ok(json
+        .as_array()
+        .unwrap_or(&[])
+        .iter()
+        .filter_map(|card| {
+            Some(Card {
+                key: card.get("fen")?.as_str()?.to_string(),
+                easiness: num(card, "easiness").max(1.3),
+                repetitions: num(card, "repetitions") as u32,
+                interval_days: num(card, "interval") as u64,
+                due: num(card, "due") as u64,
+            })
+        })
+        .collect())
+}
+
+fn save_schedule(cards: &[Card]) -> Result<(), String> {
+    let path = schedule_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+    }
+    let json = Json::Arr(
+        cards
+            .iter()
+            .map(|c| {
+                Json::obj(vec![
+                    ("fen", Json::Str(c.key.clone())),
+                    (
+                        "easiness",
+                        Json::Num((c.easiness * 1000.0).round() / 1000.0),
+                    ),
+                    ("repetitions", Json::Num(c.repetitions as f64)),
+                    ("interval", Json::Num(c.interval_days as f64)),
+                    ("due", Json::Num(c.due as f64)),
+                ])
+            })
+            .collect(),
+    );
+    write_atomic(&path, &format!("{}\n", json))
+}
+
+/// the puzzles in `path` that have a `bm` solution
+fn puzzles(path: &str) -> Result<Vec<Record>, String> {
+    let mut out = Vec::new();
+    for (n, line) in crate::batch::open(path)?.lines().enumerate() {
+        let line = line.map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match epd::parse_line(line) {
+            Ok(record) if record.op("bm").is_some() => out.push(record),
+            Ok(_) => eprintln!("line {}: Warning: no bm solution, skipped", n + 1),
+            Err(e) => eprintln!("line {}: Error: {}", n + 1, e),
+        }
+    }
+    Ok(out)
+}
+
+// SM-2 quality of an answer, a correct one graded by how long it took
+fn quality(correct: bool, secs: f64) -> u32 {
+    match (correct, secs) {
+        (false, _) => 1,
+        (true, s) if s < 10.0 => 5,
+        (true, s) if s < 30.0 => 4,
+        (true, _) => 3,
+    }
+}
+
+/// Present the due puzzles of `path`, at most `limit` of them, reading
+/// moves from the terminal. An empty answer gives up on a puzzle and `q`
+/// ends the session; the schedule is saved after every answer.
+pub fn run(path: &str, limit: usize, term: &TerminalOptions) -> Result<(), String> {
+    let puzzles = puzzles(path)?;
+    let mut schedule = load_schedule()?;
+    let time = now();
+    let due: Vec<&Record> = puzzles
+        .iter()
+        .filter(|p| {
+            let key = p.pos.to_fen();
+            schedule
+                .iter()
+                .find(|c| c.key == key)
+                .is_none_or(|c| c.due <= time)
+        })
+        .take(limit)
+        .collect();
+    if due.is_empty() {
+        let next = puzzles
+            .iter()
+            .filter_map(|p| schedule.iter().find(|c| c.key == p.pos.to_fen()))
+            .map(|c| c.due)
+            .min();
+        match next {
+            Some(t) => println!("No puzzles due; the next is due at {} UTC", format_time(t)),
+            None => println!("No puzzles with a bm solution in {}", path),
+        }
+        return Ok(());
+    }
+
+    let stdin = io::stdin();
+    let (mut correct, mut total) = (0, 0);
+    for puzzle in due {
+        let pos = &puzzle.pos;
+        let solutions: Vec<_> = puzzle
+            .op("bm")
+            .unwrap_or_default()
+            .split_whitespace()
+            .filter_map(|san| parse_move(pos, san).ok())
+            .collect();
+        if solutions.is_empty() {
+            eprintln!(
+                "Warning: the bm of {} is not a legal move, skipped",
+                pos.to_fen()
+            );
+            continue;
+        }
+        println!();
+        print_board(&board_glyphs(&pos.placement()), term);
+        let side = match pos.side {
+            Color::White => "White",
+            Color::Black => "Black",
+        };
+        match puzzle.op("id") {
+            Some(id) => println!("{} to move ({})", side, id),
+            None => println!("{} to move", side),
+        }
+
+        let start = Instant::now();
+        let answer = loop {
+            print!("Move? ");
+            let _ = io::stdout().flush();
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 || line.trim() == "q" {
+                break None;
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                break Some(None);
+            }
+            let parsed =
+                parse_move(pos, line).or_else(|e| ask_promotion(&promotions(pos, line)).ok_or(e));
+            match parsed {
+                Ok(mv) => break Some(Some(mv)),
+                Err(e) => println!("{}, try again", e),
+            }
+        };
+        let answer = match answer {
+            Some(answer) => answer,
+            None => break,
+        };
+
+        let secs = start.elapsed().as_secs_f64();
+        let right = answer.is_some_and(|mv| solutions.contains(&mv));
+        let best: Vec<String> = solutions.iter().map(|&mv| to_san(pos, mv)).collect();
+        if right {
+            correct += 1;
+            println!("Correct ({:.1}s)", secs);
+        } else {
+            println!("The answer was {}", best.join(" or "));
+        }
+        total += 1;
+
+        let key = pos.to_fen();
+        let index = match schedule.iter().position(|c| c.key == key) {
+            Some(i) => i,
+            None => {
+                schedule.push(Card::new(key));
+                schedule.len() - 1
+            }
+        };
+        let card = &mut schedule[index];
+        card.review(if answer.is_none() {
+            0
+        } else {
+            quality(right, secs)
+        });
+        println!(
+            "Next review in {} day{}",
+            card.interval_days,
+            if card.interval_days == 1 { "" } else { "s" }
+        );
+        save_schedule(&schedule)?;
+    }
+    if total > 0 {
+        println!(
+            "\nScore: {}/{} ({:.0}%)",
+            correct,
+            total,
+            100.0 * correct as f64 / total as f64
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn reviews_follow_sm2() {
+        let mut card = Card::new("fen".to_string());
+        card.review(5);
+        assert_eq!((card.repetitions, card.interval_days), (1, 1));
+        assert!(close(card.easiness, 2.6), "{}", card.easiness);
+        card.review(5);
+        assert_eq!((card.repetitions, card.interval_days), (2, 6));
+        assert!(close(card.easiness, 2.7), "{}", card.easiness);
+        // a hard recall still lengthens the interval, by the old easiness,
+        // but lowers the easiness
+        card.review(3);
+        assert_eq!((card.repetitions, card.interval_days), (3, 16));
+        assert!(close(card.easiness, 2.56), "{}", card.easiness);
+        let before = now();
+        card.review(0);
+        assert_eq!((card.repetitions, card.interval_days), (0, 1));
+        assert!(close(card.easiness, 1.76), "{}", card.easiness);
+        assert!((before + DAY..=now() + DAY).contains(&card.due));
+        // the easiness never drops below 1.3
+        card.review(0);
+        card.review(0);
+        assert!(close(card.easiness, 1.3), "{}", card.easiness);
+        card.review(3);
+        assert_eq!((card.repetitions, card.interval_days), (1, 1));
+    }
+
+    #[test]
+    fn answers_are_graded_by_time() {
+        assert_eq!(quality(false, 1.0), 1);
+        assert_eq!(quality(true, 5.0), 5);
+        assert_eq!(quality(true, 20.0), 4);
+        assert_eq!(quality(true, 60.0), 3);
+    }
+}
@@ -0,0 +1,220 @@
+//! The input FEN reprinted field by field, with any error pinpointed
+//!
+//! Each field is coloured and listed under its name. Validation is done
+//! character by character, so the first character that makes the FEN
+//! invalid can be underlined (and marked with a caret) along with the
+//! reason.
+
+use std::io::IsTerminal;
+
+const FIELDS: [(&str, &str); 6] = [
+    ("placement", "36"),
+    ("side to move", "33"),
+    ("castling", "32"),
+    ("en passant", "35"),
+    ("halfmove clock", "34"),
+    ("fullmove number", "34"),
+];
+
+/// the first invalid character and why, as a byte offset into the FEN
+struct Problem {
+    at: usize,
+    message: String,
+}
+
+fn problem(at: usize, message: impl Into<String>) -> Option<Problem> {
+    Some(Problem {
+        at,
+        message: message.into(),
+    })
+}
+
+fn check_placement(text: &str, start: usize) -> Option<Problem> {
+    let mut ranks = 1;
+    let mut squares = 0;
+    for (i, c) in text.char_indices() {
+        let at = start + i;
+        match c {
+            '/' => {
+                if squares != 8 {
+                    return problem(
+                        at,
+                        format!("rank {} describes {} squares, not 8", 9 - ranks, squares),
+                    );
+                }
+                ranks += 1;
+                if ranks > 8 {
+                    return problem(at, "more than 8 ranks");
+                }
+                squares = 0;
+            }
+            '1'..='8' => squares += c as u32 - '0' as u32,
+            'K' | 'Q' | 'B' | 'N' | 'R' | 'P' | 'k' | 'q' | 'b' | 'n' | 'r' | 'p' => squares += 1,
+            _ => return problem(at, format!("unexpected symbol '{}'", c)),
+        }
+        if squares > 8 {
+            return problem(at, format!("rank {} has more than 8 squares", 9 - ranks));
+        }
+    }
+    let end = start + text.len();
+    if squares != 8 {
+        return problem(
+            end,
+            format!("rank {} describes {} squares, not 8", 9 - ranks, squares),
+        );
+    }
+    if ranks < 8 {
+        return problem(end, format!("only {} ranks", ranks));
+    }
+    None
+}
+
+fn check_castling(text: &str, start: usize) -> Option<Problem> {
+    if text == "-" {
+        return None;
+    }
+    let mut seen = String::new();
+    for (i, c) in text.char_indices() {
+        if !"KQkq".contains(c) {
+            return problem(start + i, format!("'{}' is not one of KQkq or -", c));
+        }
+        if seen.contains(c) {
+            return problem(start + i, format!("'{}' is repeated", c));
+        }
+        seen.push(c);
+    }
+    None
+}
+
+fn check_ep(text: &str, start: usize) -> Option<Problem> {
+    if text == "-" {
+        return None;
+    }
+    let mut chars = text.char_indices();
+    match chars.next() {
+        Some((_, 'a'..='h')) => {}
+        Some((i, c)) => return problem(start + i, format!("'{}' is not a file a-h", c)),
+        None => return None,
+    }
+    match chars.next() {
+        Some((_, '3' | '6')) => {}
+        Some((i, c)) => return problem(start + i, format!("'{}' is not rank 3 or 6", c)),
+        None => return problem(start + text.len(), "the square needs a rank"),
+    }
+    chars
+        .next()
+        .and_then(|(i, _)| problem(start + i, "the square is too long"))
+}
+
+fn check_number(text: &str, start: usize) -> Option<Problem> {
+    text.char_indices()
+        .find(|(_, c)| !c.is_ascii_digit())
+        .and_then(|(i, c)| problem(start + i, format!("'{}' is not a digit", c)))
+}
+
+/// the fields of `fen` with their byte offsets
+fn fields(fen: &str) -> Vec<(usize, &str)> {
+    let mut out = Vec::new();
+    let mut start = None;
+    for (i, c) in fen.char_indices() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                out.push((s, &fen[s..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        out.push((s, &fen[s..]));
+    }
+    out
+}
+
+// field `n` (counting from 0), which starts at `start` in the FEN
+fn check_field(n: usize, text: &str, start: usize) -> Option<Problem> {
+    match n {
+        0 => check_placement(text, start),
+        1 if text != "w" && text != "b" => problem(
+            start + (text.starts_with(['w', 'b']) as usize),
+            "expected w or b",
+        ),
+        2 => check_castling(text, start),
+        3 => check_ep(text, start),
+        4 | 5 => check_number(text, start),
+        6.. => problem(start, "a FEN has at most 6 fields"),
+        _ => None,
+    }
+}
+
+/// why `text` is not a valid field `n` of a FEN (counting from 0 for the
+/// placement), if it is not
+pub fn field_error(n: usize, text: &str) -> Option<String> {
+    if text.is_empty() {
+        return Some("the field is empty".to_string());
+    }
+    check_field(n, text, 0).map(|p| p.message)
+}
+
+fn check(fen: &str, fields: &[(usize, &str)]) -> Option<Problem> {
+    for (n, &(start, text)) in fields.iter().enumerate() {
+        let found = check_field(n, text, start);
+        if found.is_some() {
+            return found;
+        }
+    }
+    if fields.len() < 4 {
+        let missing = FIELDS[fields.len()].0;
+        return problem(fen.trim_end().len(), format!("no {} field", missing));
+    }
+    None
+}
+
+/// Format `fen` coloured by field (when `colour` is set) with a line per
+/// field below it, and the first error underlined and explained.
+pub fn format(fen: &str, colour: bool) -> String {
+    let fields = fields(fen);
+    let trouble = check(fen, &fields);
+    let paint = |text: &str, code: &str| {
+        if colour {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    };
+
+    let mut line = String::new();
+    for (i, c) in fen.char_indices() {
+        let field = fields.iter().rposition(|&(start, _)| start <= i);
+        let code = field.map_or("0", |n| FIELDS.get(n).map_or("37", |f| f.1));
+        let bad = trouble.as_ref().is_some_and(|p| p.at == i);
+        if bad {
+            line.push_str(&paint(&c.to_string(), "4;1;31"));
+        } else if c.is_whitespace() {
+            line.push(c);
+        } else {
+            line.push_str(&paint(&c.to_string(), code));
+        }
+    }
+    if trouble.as_ref().is_some_and(|p| p.at >= fen.len()) {
+        // the problem is something missing at the end
+        line.push_str(&paint("_", "4;1;31"));
+    }
+    let mut out = line;
+    out.push('\n');
+    if let Some(p) = &trouble {
+        let column = fen[..p.at.min(fen.len())].chars().count();
+        out.push_str(&format!("{}^ {}\n", " ".repeat(column), p.message));
+    }
+    for (n, &(_, text)) in fields.iter().enumerate().take(FIELDS.len()) {
+        let (name, code) = FIELDS[n];
+        out.push_str(&format!("  {:<16} {}\n", name, paint(text, code)));
+    }
+    out
+}
+
+/// Print `fen` as `format` does, in colour on a terminal.
+pub fn print(fen: &str) {
+    print!("{}", format(fen, std::io::stdout().is_terminal()));
+}
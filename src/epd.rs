@@ -0,0 +1,96 @@
+//! Extended Position Description (EPD) records
+//!
+//! An EPD line is the first four FEN fields followed by `opcode operand;`
+//! operations such as `bm Qxf7#; id "mate 1";`. Plain FENs are accepted
+//! too, with no operations.
+
+use crate::position::Position;
+
+pub struct Record {
+    pub pos: Position,
+    /// operations in file order, operands with any quotes removed
+    pub ops: Vec<(String, String)>,
+}
+
+impl Record {
+    /// the operand of `opcode`, if the record has it
+    pub fn op(&self, opcode: &str) -> Option<&str> {
+        self.ops
+            .iter()
+            .find(|(op, _)| op == opcode)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+// split operations at semicolons outside quoted strings
+fn operations(text: &str) -> Vec<(String, String)> {
+    let mut ops = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                ops.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    ops.push(current);
+    ops.iter()
+        .filter_map(|op| {
+            let op = op.trim();
+            let (code, operand) = op.split_once(char::is_whitespace).unwrap_or((op, ""));
+            (!code.is_empty()).then(|| {
+                let operand = operand.trim();
+                let operand = operand
+                    .strip_prefix('"')
+                    .and_then(|o| o.strip_suffix('"'))
+                    .unwrap_or(operand);
+                (code.to_string(), operand.to_string())
+            })
+        })
+        .collect()
+}
+
+/// Parse one line of an EPD file, or a plain FEN.
+pub fn parse_line(line: &str) -> Result<Record, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let counters = fields.len() == 6 && fields[4..].iter().all(|f| f.parse::<u32>().is_ok());
+    if counters {
+        return Ok(Record {
+            pos: Position::from_fen(line)?,
+            ops: Vec::new(),
+        });
+    }
+    if fields.len() < 4 {
+        return Err(format!(
+            "EPD should start with 4 FEN fields, found {}",
+            fields.len()
+        ));
+    }
+    // the operations start after the fourth field
+    let mut rest = line.trim_start();
+    for _ in 0..4 {
+        rest = rest.trim_start();
+        rest = &rest[rest.find(char::is_whitespace).unwrap_or(rest.len())..];
+    }
+    let ops = operations(rest);
+    let counter = |op: &str, default: &str| {
+        ops.iter()
+            .find(|(code, _)| code == op)
+            .map_or(default.to_string(), |(_, v)| v.clone())
+    };
+    let fen = format!(
+        "{} {} {}",
+        fields[..4].join(" "),
+        counter("hmvc", "0"),
+        counter("fmvn", "1")
+    );
+    Ok(Record {
+        pos: Position::from_fen(&fen)?,
+        ops,
+    })
+}
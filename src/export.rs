@@ -0,0 +1,171 @@
+//! Text exports of the position for other chess software and hardware
+//!
+//! `livechess` is an XML record in the style of DGT LiveChess board
+//! setups: the FEN, the 64 squares from a8 to h1 in the DGT board's own
+//! field order, and the other FEN fields spelled out. `setup` is the older
+//! board string some relay and broadcast boards take, each rank from the
+//! 8th as eight characters with `-` for an empty square, then the side to
+//! move. `cb-html` is diagram markup for chess publishing: a table of
+//! class `DiagramChess` with one cell a square, the pieces as figurines
+//! for a diagram font such as ChessBase's DiagramTTFritz (falling back to
+//! any font with the Unicode chess symbols), and the FEN as its caption.
+//! For screen readers each cell is labelled with its square and piece, and
+//! a live region above the table lists the pieces, so a page that swaps in
+//! a new diagram has it read out.
+
+use crate::position::{square, square_name, Color, Piece, PieceKind, Position};
+use crate::terminal::glyph;
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Format {
+    /// XML for DGT LiveChess
+    Livechess,
+    /// ranks as `rnbqkbnr pppppppp -------- ...` and the side to move
+    Setup,
+    /// HTML diagram table with figurines, for ChessBase-style publishing
+    #[value(name = "cb-html")]
+    CbHtml,
+}
+
+fn side(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+// each rank from the 8th, with `empty` for an empty square
+fn ranks(pos: &Position, empty: char) -> Vec<String> {
+    (0..8u8)
+        .rev()
+        .map(|rank| {
+            (0..8u8)
+                .map(|file| {
+                    pos.piece_at(square(file, rank))
+                        .map_or(empty, |p| p.to_char())
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn livechess(pos: &Position) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<setup>\n");
+    out.push_str(&format!("  <fen>{}</fen>\n", pos.to_fen()));
+    out.push_str(&format!("  <board>{}</board>\n", ranks(pos, '.').concat()));
+    out.push_str(&format!("  <sideToMove>{}</sideToMove>\n", side(pos.side)));
+    out.push_str(&format!("  <castling>{}</castling>\n", pos.castling));
+    out.push_str(&format!(
+        "  <enPassant>{}</enPassant>\n",
+        pos.ep.map(square_name).unwrap_or_else(|| "-".to_string())
+    ));
+    out.push_str(&format!(
+        "  <halfmoveClock>{}</halfmoveClock>\n",
+        pos.halfmove
+    ));
+    out.push_str(&format!("  <moveNumber>{}</moveNumber>\n", pos.fullmove));
+    out.push_str("</setup>\n");
+    out
+}
+
+fn setup(pos: &Position) -> String {
+    let to_move = match pos.side {
+        Color::White => "W",
+        Color::Black => "B",
+    };
+    format!("{} {}\n", ranks(pos, '-').join(" "), to_move)
+}
+
+fn kind_name(kind: PieceKind) -> &'static str {
+    match kind {
+        PieceKind::Pawn => "pawn",
+        PieceKind::Knight => "knight",
+        PieceKind::Bishop => "bishop",
+        PieceKind::Rook => "rook",
+        PieceKind::Queen => "queen",
+        PieceKind::King => "king",
+    }
+}
+
+// the side to move and each side's pieces, king first, for the live region
+fn description(pos: &Position, to_move: &str) -> String {
+    let kinds = [
+        PieceKind::King,
+        PieceKind::Queen,
+        PieceKind::Rook,
+        PieceKind::Bishop,
+        PieceKind::Knight,
+        PieceKind::Pawn,
+    ];
+    let mut out = format!("{}.", to_move);
+    for (color, name) in [(Color::White, "White"), (Color::Black, "Black")] {
+        let mut pieces = Vec::new();
+        for kind in kinds {
+            for sq in (0..64).filter(|&sq| pos.piece_at(sq) == Some(Piece::new(color, kind))) {
+                pieces.push(format!("{} {}", kind_name(kind), square_name(sq)));
+            }
+        }
+        out.push_str(&format!(" {}: {}.", name, pieces.join(", ")));
+    }
+    out
+}
+
+const CB_STYLE: &str = "table.DiagramChess { border-collapse: collapse; border: 2px solid #000; font-family: 'DiagramTTFritz', 'Chess Merida', serif; }
+table.DiagramChess td { width: 1.4em; height: 1.4em; padding: 0; text-align: center; vertical-align: middle; font-size: 2em; line-height: 1; }
+table.DiagramChess td.DiagramLight { background: #fff; }
+table.DiagramChess td.DiagramDark { background: #bbb; }
+table.DiagramChess caption { caption-side: bottom; font: 0.8em sans-serif; padding-top: 0.3em; }
+.DiagramStatus { position: absolute; width: 1px; height: 1px; overflow: hidden; clip: rect(0 0 0 0); white-space: nowrap; }";
+
+fn cb_html(pos: &Position) -> String {
+    let to_move = match pos.side {
+        Color::White => "White to move",
+        Color::Black => "Black to move",
+    };
+    // the caption comes first in the table, and CB_STYLE puts it underneath
+    // the live region is read out, but not shown
+    let mut out = format!(
+        "<style>\n{}\n</style>\n<div class=\"DiagramStatus\" role=\"status\" aria-live=\"polite\">{}</div>\n<table class=\"DiagramChess\" aria-label=\"Chess diagram\">\n<caption class=\"DiagramCaption\">{}<br><span class=\"DiagramFen\">{}</span></caption>\n",
+        CB_STYLE,
+        description(pos, to_move),
+        to_move,
+        pos.to_fen()
+    );
+    for rank in (0..8u8).rev() {
+        out.push_str("<tr>");
+        for file in 0..8u8 {
+            let sq = square(file, rank);
+            let shade = if (file + rank) % 2 == 1 {
+                "DiagramLight"
+            } else {
+                "DiagramDark"
+            };
+            let piece = pos.piece_at(sq);
+            let figurine = piece.map(glyph).unwrap_or_default();
+            let contents = piece.map_or("empty".to_string(), |p| {
+                format!("{} {}", side(p.color), kind_name(p.kind))
+            });
+            out.push_str(&format!(
+                "<td class=\"{}\" title=\"{}\" aria-label=\"{}, {}\">{}</td>",
+                shade,
+                square_name(sq),
+                square_name(sq),
+                contents,
+                figurine
+            ));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n");
+    out
+}
+
+/// `pos` written out in `format`
+pub fn export(pos: &Position, format: Format) -> String {
+    match format {
+        Format::Livechess => livechess(pos),
+        Format::Setup => setup(pos),
+        Format::CbHtml => cb_html(pos),
+    }
+}
@@ -0,0 +1,327 @@
+//! Neural-network input features of positions
+//!
+//! `planes` gives 18 planes of 8x8 bits, ranks from the first and files
+//! from a: the twelve pieces (White's PNBRQK then Black's), a plane set
+//! when White is to move, one per castling right (KQkq) and one marking
+//! the en-passant square. `halfkp` gives the active feature indices of
+//! the HalfKP encoding used by NNUE networks, side to move first: for each
+//! king, `1 + square + 64 * piece + 641 * king square`, with the squares
+//! turned round for Black and pieces counted from that side's point of
+//! view (own pawn 0, their pawn 1, own knight 2 and so on).
+//!
+//! NumPy output is a single `.npy` array: `uint8` of shape (N, 18, 8, 8)
+//! for planes, or `int32` of shape (N, 2, 30) for HalfKP with unused
+//! entries set to -1 (positions with more than 30 pieces besides the kings
+//! are skipped). CSV output has a header and one row per position.
+
+use crate::batch;
+use crate::epd;
+use crate::pack;
+use crate::position::{square_name, Color, PieceKind, Position};
+use clap::ValueEnum;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Seek, SeekFrom, Write};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Format {
+    /// a NumPy .npy array
+    Numpy,
+    /// comma-separated values with a header
+    Csv,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Encoding {
+    /// 18 planes of 8x8 bits
+    Planes,
+    /// HalfKP feature indices, as used by NNUE
+    Halfkp,
+}
+
+pub const PLANES: usize = 18;
+/// most pieces besides the kings, so the longest HalfKP feature list
+const HALFKP_LEN: usize = 30;
+
+const KINDS: [PieceKind; 6] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+    PieceKind::King,
+];
+
+fn kind_index(kind: PieceKind) -> usize {
+    KINDS.iter().position(|&k| k == kind).unwrap_or(0)
+}
+
+/// the 18 input planes of `pos`, plane by plane from a1 to h8
+pub fn planes(pos: &Position) -> Vec<u8> {
+    let mut out = vec![0; PLANES * 64];
+    for (sq, piece) in pos.board.iter().enumerate() {
+        if let Some(piece) = piece {
+            let plane = kind_index(piece.kind) + 6 * (piece.color == Color::Black) as usize;
+            out[64 * plane + sq] = 1;
+        }
+    }
+    let filled = [
+        pos.side == Color::White,
+        pos.castling.white_kingside,
+        pos.castling.white_queenside,
+        pos.castling.black_kingside,
+        pos.castling.black_queenside,
+    ];
+    for (i, set) in filled.into_iter().enumerate() {
+        if set {
+            out[64 * (12 + i)..64 * (13 + i)].fill(1);
+        }
+    }
+    if let Some(ep) = pos.ep {
+        out[64 * 17 + ep as usize] = 1;
+    }
+    out
+}
+
+/// The HalfKP features of `pos` from the side to move's king, then the
+/// other. Both kings must be on the board.
+pub fn halfkp(pos: &Position) -> Result<[Vec<i32>; 2], String> {
+    let features = |perspective: Color| -> Result<Vec<i32>, String> {
+        let orient = |sq: u8| match perspective {
+            Color::White => sq as i32,
+            Color::Black => (sq ^ 63) as i32,
+        };
+        let king = pos
+            .king_square(perspective)
+            .ok_or("HalfKP features need both kings on the board")?;
+        let mut out = Vec::new();
+        for (sq, piece) in pos.board.iter().enumerate() {
+            match piece {
+                Some(piece) if piece.kind != PieceKind::King => {
+                    let index = 2 * kind_index(piece.kind) + (piece.color != perspective) as usize;
+                    out.push(1 + orient(sq as u8) + 64 * index as i32 + 641 * orient(king));
+                }
+                _ => {}
+            }
+        }
+        Ok(out)
+    };
+    Ok([features(pos.side)?, features(pos.side.opponent())?])
+}
+
+// both HalfKP lists as one `.npy` row, padded with -1; a list too long for
+// the row (only possible with more than 32 pieces) is an error rather than
+// one cut short
+fn halfkp_row(lists: &[Vec<i32>; 2]) -> Result<Vec<u8>, String> {
+    if let Some(list) = lists.iter().find(|list| list.len() > HALFKP_LEN) {
+        return Err(format!(
+            "{} HalfKP features do not fit in the {} of a NumPy row",
+            list.len(),
+            HALFKP_LEN
+        ));
+    }
+    Ok(lists
+        .iter()
+        .flat_map(|list| (0..HALFKP_LEN).map(|i| list.get(i).copied().unwrap_or(-1)))
+        .flat_map(i32::to_le_bytes)
+        .collect())
+}
+
+/// A header for a `.npy` file of `rows` items of `shape`, padded to a fixed
+/// length so that it can be rewritten once the number of rows is known.
+fn npy_header(descr: &str, rows: usize, shape: &[usize]) -> Vec<u8> {
+    let dims: Vec<String> = std::iter::once(rows)
+        .chain(shape.iter().copied())
+        .map(|d| d.to_string())
+        .collect();
+    let dict = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': ({}), }}",
+        descr,
+        dims.join(", ")
+    );
+    const LEN: usize = 128;
+    let mut out = b"\x93NUMPY\x01\x00".to_vec();
+    out.extend(((LEN - 10) as u16).to_le_bytes());
+    out.extend(dict.bytes());
+    out.resize(LEN - 1, b' ');
+    out.push(b'\n');
+    out
+}
+
+/// the positions of a packed file or of a file of FENs or EPD records,
+/// reporting and skipping lines that cannot be read
+fn positions(input: &str) -> Result<Box<dyn Iterator<Item = Position>>, String> {
+    if input != "-" && pack::is_packed(input) {
+        let dataset = pack::Dataset::open(input)?;
+        return Ok(Box::new((0..dataset.len()).filter_map(move |i| {
+            dataset
+                .get(i)
+                .map_err(|e| eprintln!("Error: {}, skipped", e))
+                .ok()
+        })));
+    }
+    let path = input.to_string();
+    let lines = batch::open(input)?.lines().enumerate();
+    Ok(Box::new(lines.filter_map(move |(n, line)| {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Could not read {}: {}", path, e);
+                return None;
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        epd::parse_line(line)
+            .map(|record| record.pos)
+            .map_err(|e| eprintln!("line {}: Error: {}, skipped", n + 1, e))
+            .ok()
+    })))
+}
+
+fn csv_header(encoding: Encoding) -> String {
+    match encoding {
+        Encoding::Planes => {
+            let names = [
+                "P", "N", "B", "R", "Q", "K", "p", "n", "b", "r", "q", "k", "white", "castle_K",
+                "castle_Q", "castle_k", "castle_q", "ep",
+            ];
+            let columns: Vec<String> = names
+                .iter()
+                .flat_map(|name| (0..64).map(move |sq| format!("{}_{}", name, square_name(sq))))
+                .collect();
+            columns.join(",")
+        }
+        Encoding::Halfkp => "side_to_move,other".to_string(),
+    }
+}
+
+/// Write the features of every position in `input` in `format` to `out`,
+/// or to standard output for CSV. NumPy output needs a file, as its header
+/// is completed once the positions have been counted.
+pub fn run(
+    input: &str,
+    format: Format,
+    encoding: Encoding,
+    out: Option<&str>,
+) -> Result<(), String> {
+    let write_error = |e: io::Error| format!("Could not write {}: {}", out.unwrap_or("output"), e);
+    let (descr, shape): (&str, &[usize]) = match encoding {
+        Encoding::Planes => ("|u1", &[PLANES, 8, 8]),
+        Encoding::Halfkp => ("<i4", &[2, HALFKP_LEN]),
+    };
+    let mut rows = 0;
+    match format {
+        Format::Numpy => {
+            let path = out.ok_or("NumPy output needs a file to write to, given with --out")?;
+            let file =
+                File::create(path).map_err(|e| format!("Could not write {}: {}", path, e))?;
+            let mut writer = BufWriter::new(file);
+            writer
+                .write_all(&npy_header(descr, 0, shape))
+                .map_err(write_error)?;
+            for pos in positions(input)? {
+                let bytes = match encoding {
+                    Encoding::Planes => planes(&pos),
+                    Encoding::Halfkp => match halfkp(&pos).and_then(|lists| halfkp_row(&lists)) {
+                        Ok(row) => row,
+                        Err(e) => {
+                            eprintln!("Error: {} ({}), skipped", e, pos.to_fen());
+                            continue;
+                        }
+                    },
+                };
+                writer.write_all(&bytes).map_err(write_error)?;
+                rows += 1;
+            }
+            let mut file = writer
+                .into_inner()
+                .map_err(|e| write_error(e.into_error()))?;
+            file.seek(SeekFrom::Start(0))
+                .and_then(|_| file.write_all(&npy_header(descr, rows, shape)))
+                .map_err(write_error)?;
+        }
+        Format::Csv => {
+            let mut writer = batch::create(out)?;
+            writeln!(writer, "{}", csv_header(encoding)).map_err(write_error)?;
+            for pos in positions(input)? {
+                let row = match encoding {
+                    Encoding::Planes => {
+                        let bits: Vec<String> =
+                            planes(&pos).iter().map(|b| b.to_string()).collect();
+                        bits.join(",")
+                    }
+                    Encoding::Halfkp => match halfkp(&pos) {
+                        Ok(lists) => lists
+                            .iter()
+                            .map(|list| {
+                                let indices: Vec<String> =
+                                    list.iter().map(|i| i.to_string()).collect();
+                                indices.join(" ")
+                            })
+                            .collect::<Vec<_>>()
+                            .join(","),
+                        Err(e) => {
+                            eprintln!("Error: {} ({}), skipped", e, pos.to_fen());
+                            continue;
+                        }
+                    },
+                };
+                writeln!(writer, "{}", row).map_err(write_error)?;
+                rows += 1;
+            }
+            writer.flush().map_err(write_error)?;
+        }
+    }
+    eprintln!("Wrote the features of {} positions", rows);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn npy_header_is_128_bytes() {
+        let header = npy_header("<i4", 12, &[2, HALFKP_LEN]);
+        assert_eq!(header.len(), 128);
+        assert!(header.starts_with(b"\x93NUMPY\x01\x00"));
+        assert_eq!(u16::from_le_bytes([header[8], header[9]]), 118);
+        let dict = String::from_utf8_lossy(&header[10..]);
+        assert!(
+            dict.starts_with("{'descr': '<i4', 'fortran_order': False, 'shape': (12, 2, 30), }")
+        );
+        assert!(dict.ends_with(" \n"));
+    }
+
+    #[test]
+    fn halfkp_indices() {
+        let pos = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        // White: own pawn on e2 (12) with the king on e1 (4)
+        // Black: their pawn on e2 turned round (51), king on e8 turned round (3)
+        assert_eq!(
+            halfkp(&pos).unwrap(),
+            [vec![1 + 12 + 641 * 4], vec![1 + 51 + 64 + 641 * 3]]
+        );
+        let no_king = Position::from_fen("8/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        assert!(halfkp(&no_king).is_err());
+    }
+
+    #[test]
+    fn halfkp_rows_are_never_cut_short() {
+        let row = halfkp_row(&[vec![7], vec![]]).unwrap();
+        assert_eq!(row.len(), 4 * 2 * HALFKP_LEN);
+        assert_eq!(row[..8], [7, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]);
+        assert!(halfkp_row(&[vec![0; HALFKP_LEN + 1], vec![]]).is_err());
+    }
+
+    #[test]
+    fn start_planes() {
+        let pos = Position::from_fen(crate::position::STARTING_FEN).unwrap();
+        let planes = planes(&pos);
+        // white pawns fill the second rank, and White is to move
+        assert!(planes[8..16].iter().all(|&b| b == 1));
+        assert_eq!(planes.iter().filter(|&&b| b == 1).count(), 32 + 5 * 64);
+    }
+}
@@ -0,0 +1,175 @@
+//! Fonts for text and chess glyphs, with fallbacks
+//!
+//! Piece glyphs come from the configured font when it has all of them,
+//! then from the bundled FreeSerif, and failing both are drawn as simple
+//! vector silhouettes so the board is never left blank.
+
+use crate::pieceset::{self, PieceSet};
+use rusttype::Font;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+pub const TEXT_FONT: &[u8] = include_bytes!("../assets/FiraSans-Regular.ttf");
+pub const PIECE_FONT: &[u8] = include_bytes!("../assets/FreeSerif-4aeK.ttf");
+
+const GLYPHS: [char; 12] = ['♔', '♕', '♖', '♗', '♘', '♙', '♚', '♛', '♜', '♝', '♞', '♟'];
+
+/// how chess glyphs are drawn
+#[derive(Clone)]
+pub enum PieceFont {
+    Font(Font<'static>),
+    Shapes,
+    /// pictures from an installed piece set, in the window and PNGs
+    Set(Arc<PieceSet>),
+}
+
+impl Default for PieceFont {
+    fn default() -> PieceFont {
+        bundled().map_or(PieceFont::Shapes, PieceFont::Font)
+    }
+}
+
+/// the bundled text font
+pub fn text_font() -> Font<'static> {
+    Font::try_from_bytes(TEXT_FONT).expect("bundled text font is valid")
+}
+
+fn bundled() -> Option<Font<'static>> {
+    Font::try_from_bytes(PIECE_FONT).filter(has_chess_glyphs)
+}
+
+/// whether `font` can draw all twelve chess pieces
+pub fn has_chess_glyphs(font: &Font) -> bool {
+    GLYPHS.iter().all(|&c| font.glyph(c).id().0 != 0)
+}
+
+/// Resolve the piece font from `configured`, a font file or `shapes`,
+/// falling back (with a warning) to the bundled font and then to shapes.
+pub fn resolve(configured: Option<&str>) -> PieceFont {
+    match configured {
+        None => PieceFont::default(),
+        Some("shapes") => PieceFont::Shapes,
+        Some(path) => {
+            let font = std::fs::read(path)
+                .map_err(|e| format!("Could not read {}: {}", path, e))
+                .and_then(|bytes| {
+                    Font::try_from_vec(bytes).ok_or_else(|| format!("{} is not a font", path))
+                })
+                .and_then(|font| match has_chess_glyphs(&font) {
+                    true => Ok(font),
+                    false => Err(format!("{} has no chess glyphs", path)),
+                });
+            match font {
+                Ok(font) => PieceFont::Font(font),
+                Err(e) => {
+                    eprintln!("Warning: {}, using the bundled piece font", e);
+                    PieceFont::default()
+                }
+            }
+        }
+    }
+}
+
+/// The installed piece set `name`, falling back (with a warning) to the
+/// bundled font when it cannot be loaded.
+pub fn piece_set(name: &str) -> PieceFont {
+    match pieceset::load(name) {
+        Ok(set) => PieceFont::Set(Arc::new(set)),
+        Err(e) => {
+            eprintln!("Warning: {}, using the bundled piece font", e);
+            PieceFont::default()
+        }
+    }
+}
+
+/// Convex polygons whose union is the silhouette of a chess glyph, in a
+/// unit box with y pointing down, and whether the glyph is solid (filled)
+/// or an outline.
+pub fn silhouette(glyph: char) -> Option<(Vec<Vec<[f64; 2]>>, bool)> {
+    let index = GLYPHS.iter().position(|&c| c == glyph)?;
+    let mut parts = vec![rect(0.18, 0.88, 0.82, 1.0)];
+    match index % 6 {
+        // king
+        0 => parts.extend([
+            vec![
+                [0.22, 0.88],
+                [0.78, 0.88],
+                [0.84, 0.52],
+                [0.5, 0.42],
+                [0.16, 0.52],
+            ],
+            rect(0.46, 0.06, 0.54, 0.46),
+            rect(0.36, 0.14, 0.64, 0.22),
+        ]),
+        // queen
+        1 => {
+            parts.push(vec![[0.2, 0.88], [0.8, 0.88], [0.86, 0.45], [0.14, 0.45]]);
+            for (tip, left, right) in [
+                ([0.12, 0.28], 0.14, 0.34),
+                ([0.31, 0.18], 0.26, 0.44),
+                ([0.5, 0.14], 0.4, 0.6),
+                ([0.69, 0.18], 0.56, 0.74),
+                ([0.88, 0.28], 0.66, 0.86),
+            ] {
+                parts.push(vec![tip, [right, 0.5], [left, 0.5]]);
+                parts.push(circle(tip[0], tip[1], 0.055));
+            }
+        }
+        // rook
+        2 => parts.extend([
+            rect(0.26, 0.35, 0.74, 0.88),
+            rect(0.18, 0.24, 0.82, 0.35),
+            rect(0.18, 0.12, 0.32, 0.24),
+            rect(0.43, 0.12, 0.57, 0.24),
+            rect(0.68, 0.12, 0.82, 0.24),
+        ]),
+        // bishop
+        3 => parts.extend([
+            vec![[0.3, 0.88], [0.7, 0.88], [0.5, 0.6]],
+            ellipse(0.5, 0.45, 0.17, 0.22),
+            circle(0.5, 0.16, 0.06),
+        ]),
+        // knight
+        4 => parts.extend([
+            vec![[0.3, 0.88], [0.78, 0.88], [0.72, 0.4], [0.42, 0.4]],
+            vec![
+                [0.42, 0.2],
+                [0.62, 0.16],
+                [0.74, 0.42],
+                [0.5, 0.5],
+                [0.16, 0.56],
+                [0.12, 0.48],
+            ],
+            vec![[0.48, 0.06], [0.56, 0.17], [0.44, 0.2]],
+        ]),
+        // pawn
+        _ => parts.extend([
+            vec![[0.28, 0.88], [0.72, 0.88], [0.58, 0.45], [0.42, 0.45]],
+            circle(0.5, 0.32, 0.16),
+        ]),
+    }
+    Some((parts, index >= 6))
+}
+
+/// The square `(left, top, edge)` a silhouette fills in place of a glyph
+/// of `size` whose baseline starts at (x, y).
+pub fn silhouette_box(x: f64, y: f64, size: f64) -> (f64, f64, f64) {
+    (x, y - 0.8125 * size, 0.875 * size)
+}
+
+fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Vec<[f64; 2]> {
+    vec![[x0, y0], [x1, y0], [x1, y1], [x0, y1]]
+}
+
+fn ellipse(cx: f64, cy: f64, rx: f64, ry: f64) -> Vec<[f64; 2]> {
+    (0..16)
+        .map(|i| {
+            let a = i as f64 * PI / 8.0;
+            [cx + rx * a.cos(), cy + ry * a.sin()]
+        })
+        .collect()
+}
+
+fn circle(cx: f64, cy: f64, r: f64) -> Vec<[f64; 2]> {
+    ellipse(cx, cy, r, r)
+}
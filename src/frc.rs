@@ -0,0 +1,102 @@
+//! Chess960 starting positions by their standard number
+//!
+//! The numbering (0 to 959, 518 being the usual start) places the pieces
+//! of the back rank in turn: the light-squared bishop, the dark-squared
+//! bishop, the queen on one of the six empty squares, then the knights on
+//! two of the remaining five, leaving rook, king and rook. Castling rights
+//! are written `KQkq`, which is unambiguous with two rooks a side.
+
+use crate::position::Position;
+
+// the files of the two knights on the five squares left, for each number
+const KNIGHTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+/// White's back rank of position `n`, from the a-file, in FEN letters
+fn back_rank(n: u32) -> [char; 8] {
+    let mut rank = [' '; 8];
+    let n = n as usize;
+    rank[2 * (n % 4) + 1] = 'B';
+    rank[2 * (n / 4 % 4)] = 'B';
+    let empty = |rank: &[char; 8]| -> Vec<usize> { (0..8).filter(|&f| rank[f] == ' ').collect() };
+    let queen = empty(&rank)[n / 16 % 6];
+    rank[queen] = 'Q';
+    let (a, b) = KNIGHTS[n / 96];
+    let left = empty(&rank);
+    rank[left[a]] = 'N';
+    rank[left[b]] = 'N';
+    for (f, piece) in empty(&rank).into_iter().zip(['R', 'K', 'R']) {
+        rank[f] = piece;
+    }
+    rank
+}
+
+/// The FEN of Chess960 starting position `n`.
+pub fn fen(n: u32) -> Result<String, String> {
+    if n >= 960 {
+        return Err(format!(
+            "There is no Chess960 position {}, they are numbered 0 to 959",
+            n
+        ));
+    }
+    let white: String = back_rank(n).iter().collect();
+    Ok(format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+        white.to_lowercase(),
+        white
+    ))
+}
+
+/// The number of the Chess960 starting position `fen` sets up.
+pub fn index(fen: &str) -> Result<u32, String> {
+    let placement = Position::from_fen(fen)?.placement();
+    (0..960)
+        .find(|&n| {
+            self::fen(n).is_ok_and(|start| start.split(' ').next() == Some(placement.as_str()))
+        })
+        .ok_or_else(|| format!("{} is not a Chess960 starting position", placement))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_numbers() {
+        assert_eq!(fen(518).unwrap(), crate::position::STARTING_FEN);
+        assert!(fen(0).unwrap().starts_with("bbqnnrkr/"));
+        assert!(fen(0).unwrap().contains("/BBQNNRKR "));
+        assert!(fen(959).unwrap().starts_with("rkrnnqbb/"));
+        assert!(fen(960).is_err());
+    }
+
+    #[test]
+    fn every_number_round_trips() {
+        let mut seen = std::collections::HashSet::new();
+        for n in 0..960 {
+            let start = fen(n).unwrap();
+            assert_eq!(index(&start), Ok(n), "{}", start);
+            assert!(seen.insert(start));
+        }
+        assert!(index("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").is_err());
+    }
+
+    #[test]
+    fn kings_sit_between_the_rooks() {
+        for n in 0..960 {
+            let rank: String = back_rank(n).iter().collect();
+            let (rook, king) = (rank.find('R').unwrap(), rank.find('K').unwrap());
+            assert!(rook < king && king < rank.rfind('R').unwrap(), "{}", rank);
+        }
+    }
+}
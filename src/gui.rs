@@ -0,0 +1,705 @@
+//! Interactive board in the window
+//!
+//! Click a piece of the side to move and then its destination to play a
+//! legal move, or play by keyboard alone: Tab shows an outlined focus,
+//! moved with the arrow keys or by typing a square such as `e4`, and Enter
+//! acts on the focused square as a click would. `c` (or a right click)
+//! prints the current position's FEN and copies it to the clipboard, so a
+//! session in the window can be taken back to the command line; the moves
+//! themselves can be undone, redone and saved as PGN. With an engine, `h`
+//! shows its best move as a hint.
+
+use crate::movegen::Move;
+use crate::pgn::Game;
+use crate::play::{self, Conduct, Gesture};
+use crate::position::{file_of, rank_of, square, Color, Piece, PieceKind, Position, Square};
+use crate::san::move_rows;
+use crate::terminal::{board_glyphs, glyph};
+use crate::uci::Engine;
+use crate::window::{self, MoveList, WindowOptions};
+use piston::input::{
+    Button, Key, MouseButton, MouseCursorEvent, MouseScrollEvent, PressEvent, ReleaseEvent,
+    RenderEvent,
+};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// background of the promotion picker's squares, grey so that pieces of
+/// either colour stand out
+const PICKER: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+
+/// tint of a premove's squares
+const PREMOVE: [f32; 4] = [0.25, 0.45, 0.9, 0.5];
+
+/// Copy `text` to the system clipboard with whichever clipboard tool is
+/// installed.
+fn copy_to_clipboard(text: &str) -> Result<(), String> {
+    let tools: &[(&str, &[&str])] = if cfg!(target_os = "windows") {
+        &[("clip", &[])]
+    } else if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else {
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    };
+    for (tool, args) in tools {
+        let mut child = match Command::new(tool).args(*args).stdin(Stdio::piped()).spawn() {
+            Ok(child) => child,
+            Err(_) => continue,
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(text.as_bytes());
+        }
+        if child.wait().is_ok_and(|status| status.success()) {
+            return Ok(());
+        }
+    }
+    Err("no clipboard tool found".to_string())
+}
+
+/// Print the FEN of `pos` and put it on the clipboard.
+fn export(pos: &Position) {
+    let fen = pos.to_fen();
+    println!("{}", fen);
+    if let Err(e) = copy_to_clipboard(&fen) {
+        eprintln!("Warning: FEN not copied: {}", e);
+    }
+}
+
+/// the legal moves from `from` to `to`: one, or one for each promotion
+fn legal_moves(pos: &Position, from: Square, to: Square) -> Vec<Move> {
+    pos.legal_moves()
+        .into_iter()
+        .filter(|mv| mv.from == from && mv.to == to)
+        .collect()
+}
+
+/// the squares the piece on `from` can move to, each marked as a capture
+/// or not
+fn hints(pos: &Position, from: Option<Square>) -> Vec<(Square, bool)> {
+    let mut hints: Vec<(Square, bool)> = pos
+        .legal_moves()
+        .into_iter()
+        .filter(|mv| Some(mv.from) == from)
+        .map(|mv| (mv.to, pos.is_capture(mv)))
+        .collect();
+    // one hint for the four promotions to a square
+    hints.dedup();
+    hints
+}
+
+/// The squares of a promotion picker over the file of `promotions[0].to`,
+/// queen on the promotion square and then rook, bishop and knight towards
+/// the middle of the board, each with its move.
+fn picker(promotions: &[Move]) -> Vec<(Square, Move)> {
+    let to = promotions[0].to;
+    [
+        PieceKind::Queen,
+        PieceKind::Rook,
+        PieceKind::Bishop,
+        PieceKind::Knight,
+    ]
+    .into_iter()
+    .enumerate()
+    .filter_map(|(i, kind)| {
+        let mv = *promotions.iter().find(|mv| mv.promotion == Some(kind))?;
+        let rank = if rank_of(to) == 7 {
+            7 - i as u8
+        } else {
+            i as u8
+        };
+        Some((square(file_of(to), rank), mv))
+    })
+    .collect()
+}
+
+/// The moves played in the window and the ply being shown. Undone moves
+/// stay until a different move is played, so they can be redone.
+struct History {
+    game: Game,
+    /// the position after each ply, the start first
+    positions: Vec<Position>,
+    ply: usize,
+}
+
+impl History {
+    fn new(game: Game) -> History {
+        let positions = game.positions();
+        let ply = game.moves.len();
+        History {
+            game,
+            positions,
+            ply,
+        }
+    }
+
+    fn current(&self) -> &Position {
+        &self.positions[self.ply]
+    }
+
+    /// the move that led to the position shown
+    fn last_move(&self) -> Option<Move> {
+        self.ply.checked_sub(1).map(|i| self.game.moves[i])
+    }
+
+    /// Play `mv` from the position shown, dropping any undone moves.
+    fn play(&mut self, mv: Move) {
+        let next = self.current().make_move(mv);
+        self.game.moves.truncate(self.ply);
+        self.positions.truncate(self.ply + 1);
+        self.game.moves.push(mv);
+        self.positions.push(next);
+        self.ply += 1;
+    }
+
+    /// Show ply `ply`, if the history reaches it.
+    fn go_to(&mut self, ply: usize) -> bool {
+        if ply >= self.positions.len() || ply == self.ply {
+            return false;
+        }
+        self.ply = ply;
+        true
+    }
+
+    /// Take back the last `plies` plies played, which cannot be redone.
+    fn take_back(&mut self, plies: usize) {
+        let kept = self.ply.saturating_sub(plies);
+        self.game.moves.truncate(kept);
+        self.positions.truncate(kept + 1);
+        self.ply = kept;
+    }
+
+    /// the game up to the position shown, as PGN, with `result` if the
+    /// whole game is shown and it ended so
+    fn to_pgn(&self, result: Option<&str>) -> String {
+        let mut game = Game::new(self.game.start.clone());
+        game.tags = self.game.tags.clone();
+        game.moves = self.game.moves[..self.ply].to_vec();
+        game.conclude();
+        if let Some(result) = result.filter(|_| self.ply == self.game.moves.len()) {
+            game.result = result.to_string();
+        }
+        game.to_pgn()
+    }
+}
+
+/// a search asked of the engine: a move to play, or a hint for the player
+type Job = (String, Vec<String>, bool);
+
+/// the best move a search found, and its score in centipawns
+type Search = (Option<String>, Option<i32>);
+
+/// A UCI engine playing one side in the window, or with no side only
+/// giving hints. It thinks on a thread of its own so that the window stays
+/// responsive, and premoves can be queued, while it does.
+pub struct Opponent {
+    pub side: Option<Color>,
+    pub name: String,
+    requests: Sender<Job>,
+    /// each best move found and its score for the side to move, marked
+    /// true when it answers a hint
+    replies: Receiver<(bool, Result<Search, String>)>,
+}
+
+impl Opponent {
+    /// Let `engine` play `side`, if any, searching `movetime` milliseconds
+    /// a move.
+    pub fn spawn(
+        mut engine: Engine,
+        side: Option<Color>,
+        movetime: u64,
+    ) -> Result<Opponent, String> {
+        engine.new_game()?;
+        let name = engine.name.clone();
+        let (requests, jobs) = mpsc::channel::<Job>();
+        let (results, replies) = mpsc::channel();
+        let go = format!("movetime {}", movetime);
+        thread::spawn(move || {
+            for (fen, moves, hint) in jobs {
+                let found = engine.analyse(&fen, &moves, &go).map(|analysis| {
+                    let score = analysis.lines.first().map(|l| l.score.centipawns());
+                    (analysis.best, score)
+                });
+                if results.send((hint, found)).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Opponent {
+            side,
+            name,
+            requests,
+            replies,
+        })
+    }
+
+    /// Start thinking about the position shown in `history`, for a move
+    /// of its own or, with `hint`, one to suggest.
+    fn ask(&self, history: &History, hint: bool) {
+        let moves = history.game.moves[..history.ply]
+            .iter()
+            .map(|mv| mv.to_uci())
+            .collect();
+        let _ = self
+            .requests
+            .send((history.game.start.to_fen(), moves, hint));
+    }
+}
+
+// the window's title for `pos`, with a count of the hints taken
+fn hint_title(pos: &Position, hints: usize) -> String {
+    match hints {
+        0 => window::summary(pos),
+        1 => format!("{}, 1 hint", window::summary(pos)),
+        n => format!("{}, {} hints", window::summary(pos), n),
+    }
+}
+
+/// Open the window on the final position of `game` and let moves be played
+/// on it by mouse, the selected piece's legal moves marked with dots (or
+/// rings for captures) and a pawn reaching the last rank offering a choice of
+/// piece over its file. Moves can be played by keyboard too, with Tab
+/// toggling the focus that the arrows and typed squares move (while it is
+/// shown the arrows and letters act on it only) and Enter picking its
+/// square. Ctrl+Z and Ctrl+Y (or the left and right arrows) undo
+/// and redo moves, Home and End go to the start and end, and `p` prints
+/// the game so far as PGN. With `save_pgn` the game is also written there
+/// when the window closes. A game that came with moves gets a slider under
+/// the board, dragged to show any ply, and a list of its moves at the
+/// right, where clicking a move shows the position after it.
+///
+/// With an `opponent` playing a side, it replies to each move made at the
+/// end of the game. A move entered while it thinks is kept as a premove and
+/// played, if it is legal then, as soon as the reply arrives. With any
+/// opponent, `h` asks it for the best move in the position shown, drawn
+/// as an arrow until the board changes; the window's title counts the
+/// hints taken.
+///
+/// Against an opponent `t` asks to take back the last move, `d` offers a
+/// draw (or takes one the engine has offered) and `r` resigns, the engine
+/// answering as `play::Conduct` has it; it may resign or offer a draw
+/// itself. The result of a game ended so goes into its PGN.
+pub fn run(
+    game: Game,
+    save_pgn: Option<&str>,
+    mut opponent: Option<Opponent>,
+    opts: WindowOptions,
+) {
+    let blindfold = opts.blindfold;
+    let mut revealed = false;
+    let mut ctrl = false;
+    let mut history = History::new(game);
+    let mut selected: Option<Square> = None;
+    let mut promoting: Vec<(Square, Move)> = Vec::new();
+    // the position the opponent is thinking about
+    let mut thinking: Option<Position> = None;
+    // the position a hint was asked for, and the hints given so far
+    let mut hinting: Option<Position> = None;
+    let mut hints_used = 0;
+    let mut conduct = Conduct::default();
+    // a draw offered by the engine with its last move
+    let mut offered = false;
+    // the result of a game ended by a resignation or agreed draw, and how
+    let mut over: Option<(String, String)> = None;
+    let mut premove: Option<(Square, Square)> = None;
+    let mut size = [0.0; 2];
+    let mut cursor = [0.0; 2];
+    // the square keyboard moves act on, and a file letter typed towards it
+    let mut focus: Option<Square> = None;
+    let mut file_typed: Option<u8> = None;
+    // a game that came with moves can be dragged through on the slider,
+    // and its moves picked from a list
+    let slider = !history.game.moves.is_empty();
+    let mut dragging = false;
+    let mut list = MoveList {
+        rows: move_rows(&history.game.start, &history.game.moves),
+        ply: history.ply,
+        scroll: 0,
+    };
+    // the rows of the list in view, which change with the window's size
+    let mut shown = 0;
+    let highlight = window::gl_colour(opts.palette.highlight);
+    let pieces = board_glyphs(&history.current().placement());
+    let opts = WindowOptions {
+        title: window::summary(history.current()),
+        slider,
+        move_list: slider,
+        ..opts
+    };
+    window::run(pieces, opts, |board, event| {
+        if let Some(args) = event.render_args() {
+            size = args.window_size;
+        }
+        if let Some(at) = event.mouse_cursor_args() {
+            cursor = at;
+        }
+        let mut moved = false;
+        let was_over = over.is_some();
+        let at_end = history.ply == history.game.moves.len();
+        let engine_to_move = opponent
+            .as_ref()
+            .is_some_and(|o| o.side == Some(history.current().side));
+
+        if let Some(opp) = &opponent {
+            if engine_to_move
+                && at_end
+                && over.is_none()
+                && thinking.is_none()
+                && !history.current().legal_moves().is_empty()
+            {
+                opp.ask(&history, false);
+                thinking = Some(history.current().clone());
+            }
+        }
+        let reply = opponent.as_ref().and_then(|o| o.replies.try_recv().ok());
+        // a hint is drawn only if the position it was asked for is still shown
+        let reply = match reply {
+            Some((true, hint)) => {
+                let asked = hinting.take();
+                let pos = history.current();
+                match hint {
+                    Ok((Some(uci), _)) if asked.as_ref() == Some(pos) => {
+                        if let Some(mv) = Move::from_uci(&uci).filter(|&mv| pos.is_legal(mv)) {
+                            board.arrows = vec![(mv.from, mv.to, board.palette.best)];
+                            hints_used += 1;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Error: {}", e),
+                }
+                None
+            }
+            Some((false, reply)) => Some(reply),
+            None => None,
+        };
+        if let Some(reply) = reply {
+            // a reply to a position no longer at the end of the game is dropped
+            let asked = thinking.take();
+            let current = at_end && asked.as_ref() == Some(history.current());
+            match reply {
+                Ok((Some(uci), score)) if current => {
+                    let pos = history.current();
+                    let name = opponent
+                        .as_ref()
+                        .map(|o| o.name.clone())
+                        .unwrap_or_default();
+                    let gesture = conduct.searched(score, pos.fullmove);
+                    if gesture == Some(Gesture::OfferDraw) {
+                        println!("{} offers a draw (press d to accept)", name);
+                        offered = true;
+                    }
+                    let mv = Move::from_uci(&uci).filter(|&mv| pos.is_legal(mv));
+                    match mv.filter(|_| gesture != Some(Gesture::Resign)) {
+                        Some(mv) => {
+                            history.play(mv);
+                            moved = true;
+                            let queued = premove.take().and_then(|(from, to)| {
+                                let moves = legal_moves(history.current(), from, to);
+                                moves.iter().copied().find(|mv| {
+                                    mv.promotion.is_none() || mv.promotion == Some(PieceKind::Queen)
+                                })
+                            });
+                            if let Some(mv) = queued {
+                                history.play(mv);
+                            }
+                        }
+                        None if gesture == Some(Gesture::Resign) => {
+                            println!("{} resigns", name);
+                            let side = play::name(pos.side);
+                            over = Some((
+                                play::win_for(pos.side.opponent()).to_string(),
+                                format!("{} resigned", side),
+                            ));
+                        }
+                        None => {
+                            opponent = None;
+                            eprintln!("Error: Engine {} played illegal move {}", name, uci);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    opponent = None;
+                }
+            }
+        }
+
+        let pressed = event.press_args();
+        if pressed == Some(Button::Mouse(MouseButton::Left)) {
+            dragging = board.slider_at(size, cursor, false).is_some();
+        }
+        if event.release_args() == Some(Button::Mouse(MouseButton::Left)) {
+            dragging = false;
+        }
+        // the board follows the knob while it is held
+        if dragging {
+            let ply = board.slider_at(size, cursor, true);
+            if ply.is_some_and(|ply| history.go_to(ply)) {
+                moved = true;
+                premove = None;
+            }
+        }
+        if let Some([_, dy]) = event.mouse_scroll_args().filter(|_| slider) {
+            list.scroll_by(if dy > 0.0 { -1 } else { 1 }, board.rows_shown(size));
+        }
+        let picked = match pressed {
+            Some(Button::Mouse(MouseButton::Left)) => board.move_at(size, cursor),
+            _ => None,
+        };
+        if picked.is_some_and(|ply| history.go_to(ply)) {
+            moved = true;
+            premove = None;
+        }
+        // a left click, or Enter on the focused square, picks a square
+        let chosen = match pressed {
+            Some(Button::Mouse(MouseButton::Left)) if dragging || picked.is_some() => None,
+            Some(Button::Mouse(MouseButton::Left)) => {
+                let clicked = board.square_at(size, cursor);
+                if focus.is_some() && clicked.is_some() {
+                    focus = clicked;
+                }
+                Some(clicked)
+            }
+            Some(Button::Keyboard(Key::Return | Key::NumPadEnter)) if focus.is_some() => {
+                Some(focus)
+            }
+            _ => None,
+        };
+        // the side the opponent plays, if the game against it goes on
+        let against = opponent
+            .as_ref()
+            .and_then(|o| o.side)
+            .filter(|_| over.is_none() && at_end);
+        match (chosen, pressed) {
+            // a pick on the picker promotes, anywhere else takes the move back
+            (Some(clicked), _) if !promoting.is_empty() => {
+                if let Some(&(_, mv)) = promoting.iter().find(|(sq, _)| Some(*sq) == clicked) {
+                    history.play(mv);
+                }
+                moved = true;
+            }
+            // a game that was resigned or drawn takes no more moves
+            (Some(_), _) if over.is_some() => {}
+            // while the opponent thinks, a move of one's own pieces is a premove
+            (Some(clicked), _) if engine_to_move => {
+                let pos = history.current();
+                let own = |sq: Square| pos.piece_at(sq).is_some_and(|p| p.color != pos.side);
+                match selected.zip(clicked) {
+                    Some((from, to)) if at_end && to != from && !own(to) => {
+                        premove = Some((from, to));
+                        selected = None;
+                    }
+                    _ => {
+                        premove = None;
+                        selected = clicked.filter(|&sq| at_end && own(sq));
+                    }
+                }
+            }
+            (Some(clicked), _) => {
+                let pos = history.current();
+                let played = selected
+                    .zip(clicked)
+                    .map(|(from, to)| legal_moves(pos, from, to))
+                    .unwrap_or_default();
+                if played.len() > 1 {
+                    board.hints.clear();
+                    promoting = picker(&played);
+                    if focus.is_some() {
+                        focus = Some(promoting[0].0);
+                    }
+                    for &(sq, mv) in &promoting {
+                        let piece = Piece::new(pos.side, mv.promotion.unwrap_or(PieceKind::Queen));
+                        board.pieces[7 - rank_of(sq) as usize][file_of(sq) as usize] = glyph(piece);
+                    }
+                } else if let Some(&mv) = played.first() {
+                    history.play(mv);
+                    moved = true;
+                } else {
+                    selected =
+                        clicked.filter(|&sq| pos.piece_at(sq).is_some_and(|p| p.color == pos.side));
+                    board.hints = hints(pos, selected);
+                }
+            }
+            (_, Some(Button::Keyboard(Key::Tab))) => {
+                focus = match focus {
+                    Some(_) => None,
+                    None => Some(
+                        selected
+                            .or(history.last_move().map(|mv| mv.to))
+                            .unwrap_or(square(4, 1)),
+                    ),
+                };
+                file_typed = None;
+            }
+            (_, Some(Button::Mouse(MouseButton::Right))) => export(history.current()),
+            (_, Some(Button::Keyboard(Key::C))) if focus.is_none() => export(history.current()),
+            (_, Some(Button::Keyboard(Key::P))) => {
+                print!("{}", history.to_pgn(over.as_ref().map(|(r, _)| r.as_str())))
+            }
+            // the player's last move and the engine's reply
+            (_, Some(Button::Keyboard(Key::T))) if against.is_some() && !engine_to_move => {
+                let name = opponent
+                    .as_ref()
+                    .map(|o| o.name.as_str())
+                    .unwrap_or_default();
+                if history.ply < 2 {
+                    println!("There is no move of yours to take back");
+                } else if !conduct.grants_takeback() {
+                    println!("{} declines the takeback", name);
+                } else {
+                    println!("{} agrees to the takeback", name);
+                    history.take_back(2);
+                    conduct.taken_back();
+                    offered = false;
+                    premove = None;
+                    moved = true;
+                }
+            }
+            (_, Some(Button::Keyboard(Key::D))) if against.is_some() && focus.is_none() => {
+                let name = opponent
+                    .as_ref()
+                    .map(|o| o.name.as_str())
+                    .unwrap_or_default();
+                if offered || conduct.accepts_draw() {
+                    println!("{} accepts the draw", name);
+                    over = Some(("1/2-1/2".to_string(), "draw agreed".to_string()));
+                } else {
+                    println!("{} declines the draw", name);
+                }
+            }
+            (_, Some(Button::Keyboard(Key::R))) if against.is_some() => {
+                if let Some(side) = against {
+                    let human = side.opponent();
+                    over = Some((
+                        play::win_for(side).to_string(),
+                        format!("{} resigned", play::name(human)),
+                    ));
+                }
+            }
+            // a hint on the player's turn, one at a time
+            (_, Some(Button::Keyboard(Key::H))) if focus.is_none() && !engine_to_move => {
+                if let Some(opp) = opponent.as_ref().filter(|_| hinting.is_none()) {
+                    if !history.current().legal_moves().is_empty() {
+                        opp.ask(&history, true);
+                        hinting = Some(history.current().clone());
+                    }
+                }
+            }
+            (_, Some(Button::Keyboard(key))) => {
+                // with the focus shown the arrows move it across the screen,
+                // whichever way the board is turned
+                let dir = if board.flipped { -1 } else { 1 };
+                let step = |df: i8, dr: i8| {
+                    focus.map(|sq| {
+                        let file = (file_of(sq) as i8 + dir * df).clamp(0, 7);
+                        let rank = (rank_of(sq) as i8 + dir * dr).clamp(0, 7);
+                        square(file as u8, rank as u8)
+                    })
+                };
+                let target = match key {
+                    Key::LCtrl | Key::RCtrl | Key::LGui | Key::RGui => {
+                        ctrl = true;
+                        None
+                    }
+                    Key::Space => {
+                        revealed = true;
+                        None
+                    }
+                    Key::Z if ctrl => history.ply.checked_sub(1),
+                    Key::Y if ctrl => Some(history.ply + 1),
+                    Key::Left | Key::Right | Key::Up | Key::Down if focus.is_some() => {
+                        focus = match key {
+                            Key::Left => step(-1, 0),
+                            Key::Right => step(1, 0),
+                            Key::Up => step(0, 1),
+                            _ => step(0, -1),
+                        };
+                        None
+                    }
+                    Key::Left => history.ply.checked_sub(1),
+                    Key::Right => Some(history.ply + 1),
+                    Key::Home => Some(0),
+                    Key::End => Some(history.positions.len() - 1),
+                    // a file letter and then a rank digit move the focus there
+                    _ if focus.is_some() => {
+                        match char::from_u32(key as u32) {
+                            Some(c @ 'a'..='h') => file_typed = Some(c as u8 - b'a'),
+                            Some(c @ '1'..='8') => {
+                                if let Some(file) = file_typed.take() {
+                                    focus = Some(square(file, c as u8 - b'1'));
+                                }
+                            }
+                            _ => file_typed = None,
+                        }
+                        None
+                    }
+                    _ => None,
+                };
+                if target.is_some_and(|ply| history.go_to(ply)) {
+                    moved = true;
+                    premove = None;
+                }
+            }
+            _ => {}
+        }
+        match event.release_args() {
+            Some(Button::Keyboard(Key::Space)) => revealed = false,
+            Some(Button::Keyboard(Key::LCtrl | Key::RCtrl | Key::LGui | Key::RGui)) => ctrl = false,
+            _ => {}
+        }
+        if moved {
+            board.pieces = board_glyphs(&history.current().placement());
+            // arrows were drawn for the position the window opened on, or a
+            // hint for the last one
+            board.arrows.clear();
+            selected = None;
+            promoting.clear();
+            board.hints.clear();
+        }
+        board.highlights = if promoting.is_empty() {
+            let last = history.last_move().map(|mv| [mv.from, mv.to]);
+            let queued = premove.map(|(from, to)| [from, to]);
+            last.into_iter()
+                .flatten()
+                .chain(selected)
+                .map(|sq| (sq, highlight))
+                .chain(queued.into_iter().flatten().map(|sq| (sq, PREMOVE)))
+                .collect()
+        } else {
+            promoting.iter().map(|&(sq, _)| (sq, PICKER)).collect()
+        };
+        board.hidden = blindfold && !revealed;
+        board.focus = focus;
+        if let Some((result, how)) = over.as_ref().filter(|_| !was_over) {
+            println!("{} ({})", result, how);
+        }
+        board.title = match &over {
+            Some((result, how)) => format!("{}, {}", result, how),
+            None => hint_title(history.current(), hints_used),
+        };
+        if slider {
+            board.slider = Some((history.ply, history.positions.len() - 1));
+            // the list keeps the move shown in view, however it was reached
+            if moved || board.rows_shown(size) != shown {
+                shown = board.rows_shown(size);
+                list.rows = move_rows(&history.game.start, &history.game.moves);
+                list.ply = history.ply;
+                list.follow(shown);
+            }
+            board.moves = Some(list.clone());
+        }
+        true
+    });
+    if let Some(path) = save_pgn {
+        if let Err(e) = std::fs::write(path, history.to_pgn(over.as_ref().map(|(r, _)| r.as_str())))
+        {
+            eprintln!("Error: Could not write {}: {}", path, e);
+        }
+    }
+}
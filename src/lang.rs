@@ -0,0 +1,300 @@
+//! Translations of the `--info` descriptions and of SAN piece letters
+//!
+//! Moves are written and read with the piece letters of the chosen
+//! language, such as German `S` (Springer) for the knight, so `--play
+//! "e4 e5 Sf3"` works with `--lang de`. Pawns have no letter in any of
+//! them, and castling is `O-O` everywhere.
+//!
+//! The placement field of a FEN can also be read with such letters (the
+//! German `BSLTDK` for pawn to king, uppercase for White), except in
+//! Russian, whose king takes two letters.
+
+use crate::position::{Color, PieceKind};
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Lang {
+    /// English
+    #[default]
+    En,
+    /// German
+    De,
+    /// Spanish
+    Es,
+    /// French
+    Fr,
+    /// Russian
+    Ru,
+}
+
+impl Lang {
+    /// the letter (or letters) for `kind` in SAN, `None` for a pawn
+    pub fn piece_letter(self, kind: PieceKind) -> Option<&'static str> {
+        use PieceKind::*;
+        let letters = match self {
+            Lang::En => ["N", "B", "R", "Q", "K"],
+            Lang::De => ["S", "L", "T", "D", "K"],
+            Lang::Es => ["C", "A", "T", "D", "R"],
+            Lang::Fr => ["C", "F", "T", "D", "R"],
+            Lang::Ru => ["К", "С", "Л", "Ф", "Кр"],
+        };
+        match kind {
+            Pawn => None,
+            Knight => Some(letters[0]),
+            Bishop => Some(letters[1]),
+            Rook => Some(letters[2]),
+            Queen => Some(letters[3]),
+            King => Some(letters[4]),
+        }
+    }
+
+    /// White's letters for pawn, knight, bishop, rook, queen and king in a
+    /// placement string, `None` where one takes more than a letter
+    fn placement_letters(self) -> Option<[char; 6]> {
+        match self {
+            Lang::En => Some(['P', 'N', 'B', 'R', 'Q', 'K']),
+            Lang::De => Some(['B', 'S', 'L', 'T', 'D', 'K']),
+            Lang::Es => Some(['P', 'C', 'A', 'T', 'D', 'R']),
+            Lang::Fr => Some(['P', 'C', 'F', 'T', 'D', 'R']),
+            Lang::Ru => None,
+        }
+    }
+
+    /// e.g. "White to move"
+    pub fn to_move(self, color: Color) -> &'static str {
+        match (self, color) {
+            (Lang::En, Color::White) => "White to move",
+            (Lang::En, Color::Black) => "Black to move",
+            (Lang::De, Color::White) => "Weiß am Zug",
+            (Lang::De, Color::Black) => "Schwarz am Zug",
+            (Lang::Es, Color::White) => "Juegan las blancas",
+            (Lang::Es, Color::Black) => "Juegan las negras",
+            (Lang::Fr, Color::White) => "Les blancs jouent",
+            (Lang::Fr, Color::Black) => "Les noirs jouent",
+            (Lang::Ru, Color::White) => "Ход белых",
+            (Lang::Ru, Color::Black) => "Ход чёрных",
+        }
+    }
+
+    pub fn no_castling(self) -> &'static str {
+        match self {
+            Lang::En => "Neither side can castle",
+            Lang::De => "Keine Seite kann rochieren",
+            Lang::Es => "Ningún bando puede enrocar",
+            Lang::Fr => "Aucun camp ne peut roquer",
+            Lang::Ru => "Ни одна сторона не может рокироваться",
+        }
+    }
+
+    /// that `color` holds the right to castle on one side; `now` is whether
+    /// the board allows it, `Ok(true)` when it can castle this move and
+    /// `Ok(false)` when it could once it is its turn, with `Err` giving the
+    /// reason it cannot, and `None` when the position could not be read to
+    /// tell
+    pub fn castling(
+        self,
+        color: Color,
+        kingside: bool,
+        now: Option<Result<bool, String>>,
+    ) -> String {
+        let side = match (self, color) {
+            (Lang::En, Color::White) => "White",
+            (Lang::En, Color::Black) => "Black",
+            (Lang::De, Color::White) => "Weiß",
+            (Lang::De, Color::Black) => "Schwarz",
+            (Lang::Es, Color::White) => "Las blancas",
+            (Lang::Es, Color::Black) => "Las negras",
+            (Lang::Fr, Color::White) => "Les blancs",
+            (Lang::Fr, Color::Black) => "Les noirs",
+            (Lang::Ru, Color::White) => "Белые",
+            (Lang::Ru, Color::Black) => "Чёрные",
+        };
+        let wing = match (self, kingside) {
+            (Lang::En, true) => "kingside",
+            (Lang::En, false) => "queenside",
+            (Lang::De, true) => "kurz",
+            (Lang::De, false) => "lang",
+            (Lang::Es, true) => "corto",
+            (Lang::Es, false) => "largo",
+            (Lang::Fr, true) => "petit",
+            (Lang::Fr, false) => "grand",
+            (Lang::Ru, true) => "в короткую сторону",
+            (Lang::Ru, false) => "в длинную сторону",
+        };
+        let right = match self {
+            Lang::En => format!("{} has the right to castle {}", side, wing),
+            Lang::De => format!("{} darf {} rochieren", side, wing),
+            Lang::Es => format!("{} tienen derecho al enroque {}", side, wing),
+            Lang::Fr => format!("{} ont le droit au {} roque", side, wing),
+            Lang::Ru => format!("{} имеют право рокироваться {}", side, wing),
+        };
+        match (self, now) {
+            (Lang::En, None) => format!("{} can castle {}", side, wing),
+            (Lang::En, Some(Ok(true))) => format!("{}, and can do so now", right),
+            (Lang::En, Some(Ok(false))) => {
+                format!("{}, and could do so once it is their turn", right)
+            }
+            (Lang::En, Some(Err(reason))) => format!("{}, but cannot now: {}", right, reason),
+            (Lang::De, None) => right,
+            (Lang::De, Some(Ok(true))) => format!("{}, und kann es jetzt", right),
+            (Lang::De, Some(Ok(false))) => format!("{}, und könnte es am Zug", right),
+            (Lang::De, Some(Err(reason))) => format!("{}, aber jetzt nicht: {}", right, reason),
+            (Lang::Es, None) => right,
+            (Lang::Es, Some(Ok(true))) => format!("{}, y pueden hacerlo ya", right),
+            (Lang::Es, Some(Ok(false))) => format!("{}, y podrían hacerlo en su turno", right),
+            (Lang::Es, Some(Err(reason))) => format!("{}, pero ahora no: {}", right, reason),
+            (Lang::Fr, None) => right,
+            (Lang::Fr, Some(Ok(true))) => format!("{}, et peuvent le faire maintenant", right),
+            (Lang::Fr, Some(Ok(false))) => format!("{}, et pourraient le faire à leur tour", right),
+            (Lang::Fr, Some(Err(reason))) => {
+                format!("{}, mais pas maintenant : {}", right, reason)
+            }
+            (Lang::Ru, None) => right,
+            (Lang::Ru, Some(Ok(true))) => format!("{}, и могут сделать это сейчас", right),
+            (Lang::Ru, Some(Ok(false))) => format!("{}, и смогут сделать это в свой ход", right),
+            (Lang::Ru, Some(Err(reason))) => format!("{}, но сейчас не могут: {}", right, reason),
+        }
+    }
+
+    /// the en-passant target square, if there is one
+    pub fn en_passant(self, target: Option<&str>) -> String {
+        match (self, target) {
+            (Lang::En, None) => "No en-passant target square is available".to_string(),
+            (Lang::En, Some(sq)) => format!("En-passant target square is {}", sq),
+            (Lang::De, None) => "Kein En-passant-Zielfeld".to_string(),
+            (Lang::De, Some(sq)) => format!("En-passant-Zielfeld ist {}", sq),
+            (Lang::Es, None) => "No hay casilla de captura al paso".to_string(),
+            (Lang::Es, Some(sq)) => format!("La casilla de captura al paso es {}", sq),
+            (Lang::Fr, None) => "Aucune case de prise en passant".to_string(),
+            (Lang::Fr, Some(sq)) => format!("La case de prise en passant est {}", sq),
+            (Lang::Ru, None) => "Поля для взятия на проходе нет".to_string(),
+            (Lang::Ru, Some(sq)) => format!("Поле для взятия на проходе: {}", sq),
+        }
+    }
+}
+
+const KINDS: [PieceKind; 5] = [
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+    PieceKind::King,
+];
+
+/// `text` with the English piece letters of its SAN moves (at the start of
+/// a move or after `=`) replaced by `letter` for their kind
+pub fn replace_pieces(text: &str, letter: impl Fn(PieceKind) -> String) -> String {
+    let mut out = String::new();
+    let mut previous = ' ';
+    for c in text.chars() {
+        let kind = KINDS.into_iter().find(|&k| k.letter() == c);
+        match kind {
+            Some(kind) if previous.is_whitespace() || previous == '=' => {
+                out.push_str(&letter(kind))
+            }
+            _ => out.push(c),
+        }
+        previous = c;
+    }
+    out
+}
+
+/// SAN moves written with the piece letters of `lang`
+pub fn localize(text: &str, lang: Lang) -> String {
+    if lang == Lang::En {
+        return text.to_string();
+    }
+    replace_pieces(text, |kind| {
+        lang.piece_letter(kind).unwrap_or_default().to_string()
+    })
+}
+
+/// a move written with the piece letters of `lang`, in English letters
+pub fn delocalize(text: &str, lang: Lang) -> String {
+    if lang == Lang::En {
+        return text.to_string();
+    }
+    // longest letters first, so that Russian Кр is not read as К
+    let mut letters: Vec<(&str, char)> = KINDS
+        .into_iter()
+        .filter_map(|k| lang.piece_letter(k).map(|l| (l, k.letter())))
+        .collect();
+    letters.sort_by_key(|(l, _)| std::cmp::Reverse(l.chars().count()));
+    let translate = |part: &str| -> String {
+        match letters.iter().find(|(l, _)| part.starts_with(l)) {
+            Some((l, english)) => format!("{}{}", english, &part[l.len()..]),
+            None => part.to_string(),
+        }
+    };
+    match text.split_once('=') {
+        Some((before, after)) => format!("{}={}", translate(before), translate(after)),
+        None => translate(text),
+    }
+}
+
+/// `fen` with the piece letters of `lang` in its placement field replaced
+/// by the standard ones; the other fields are left as they are.
+pub fn standard_fen(fen: &str, lang: Lang) -> Result<String, String> {
+    let letters = lang
+        .placement_letters()
+        .ok_or("Russian piece letters cannot be read in a FEN, as the king takes two letters")?;
+    let standard = Lang::En.placement_letters().unwrap_or_default();
+    let (placement, rest) = fen.trim().split_once(' ').unwrap_or((fen.trim(), ""));
+    let mut out = String::new();
+    for c in placement.chars() {
+        let upper = c.to_uppercase().next().unwrap_or(c);
+        match letters.iter().position(|&l| l == upper) {
+            Some(i) if c.is_uppercase() => out.push(standard[i]),
+            Some(i) => out.push(standard[i].to_ascii_lowercase()),
+            None => out.push(c),
+        }
+    }
+    if !rest.is_empty() {
+        out.push(' ');
+        out.push_str(rest);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn localized_moves_read_back() {
+        assert_eq!(delocalize("Sf3", Lang::De), "Nf3");
+        assert_eq!(delocalize("e8=D+", Lang::De), "e8=Q+");
+        assert_eq!(delocalize("Cxe5", Lang::Fr), "Nxe5");
+        assert_eq!(delocalize("Fb5", Lang::Fr), "Bb5");
+        assert_eq!(delocalize("Rg1", Lang::Es), "Kg1");
+        // the two-letter Russian king is not taken for a knight
+        assert_eq!(delocalize("Крe2", Lang::Ru), "Ke2");
+        assert_eq!(delocalize("Кf3", Lang::Ru), "Nf3");
+        assert_eq!(delocalize("O-O", Lang::De), "O-O");
+        for lang in [Lang::De, Lang::Es, Lang::Fr, Lang::Ru] {
+            let line = "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. O-O Qe7 5. Re1 Kd8";
+            let back: Vec<String> = localize(line, lang)
+                .split(' ')
+                .map(|w| delocalize(w, lang))
+                .collect();
+            assert_eq!(back.join(" "), line, "{:?}", lang);
+        }
+    }
+
+    #[test]
+    fn localized_fens_read_back() {
+        // German: Bauer, Springer, Läufer, Turm, Dame, König
+        assert_eq!(
+            standard_fen(
+                "tsldklst/bbbbbbbb/8/8/8/8/BBBBBBBB/TSLDKLST w KQkq - 0 1",
+                Lang::De
+            ),
+            Ok(crate::position::STARTING_FEN.to_string())
+        );
+        assert_eq!(
+            standard_fen("4r3/8/8/8/8/8/3P4/4R3 b - - 0 1", Lang::Fr),
+            Ok("4k3/8/8/8/8/8/3P4/4K3 b - - 0 1".to_string())
+        );
+        assert!(standard_fen(crate::position::STARTING_FEN, Lang::Ru).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! Corrections for common FEN typos
+//!
+//! Hand-typed and pasted FENs often have backslashes for slashes, castling
+//! written the way moves are (`O-O`, `0-0-0`), a side to move spelt out,
+//! clocks run together or missing, and the moves of the game trailing
+//! behind. Each is put right here and described, so the user can fix the
+//! source.
+
+/// Castling written as moves, e.g. `O-O` or `0-0,0-0-0`, as the rights of
+/// both sides; `None` if `field` is not of that form.
+fn castling_as_moves(field: &str) -> Option<String> {
+    let mut kingside = false;
+    let mut queenside = false;
+    for part in field.split([',', '/', '+']) {
+        match part.replace('0', "O").as_str() {
+            "O-O" => kingside = true,
+            "O-O-O" => queenside = true,
+            _ => return None,
+        }
+    }
+    let mut rights = String::new();
+    if kingside {
+        rights.push('K');
+    }
+    if queenside {
+        rights.push('Q');
+    }
+    if kingside {
+        rights.push('k');
+    }
+    if queenside {
+        rights.push('q');
+    }
+    Some(rights)
+}
+
+/// Put right the typos in `input`, returning the corrected FEN and a
+/// description of each correction.
+pub fn repair(input: &str) -> (String, Vec<String>) {
+    let mut notes = Vec::new();
+    let mut text = input.trim().to_string();
+
+    let unquoted = text.trim_matches(|c| c == '"' || c == '\'');
+    if unquoted.len() != text.len() {
+        notes.push("removed the quotes around the FEN".to_string());
+        text = unquoted.trim().to_string();
+    }
+    if text.contains('\\') {
+        notes.push("replaced backslashes with slashes".to_string());
+        text = text.replace('\\', "/");
+    }
+
+    let mut fields: Vec<String> = text.split_whitespace().map(str::to_string).collect();
+    if fields.is_empty() {
+        return (text, notes);
+    }
+
+    if fields[0].ends_with('/') && fields[0].matches('/').count() == 8 {
+        notes.push("removed the slash after the last rank".to_string());
+        fields[0].pop();
+    }
+
+    if let Some(side) = fields.get_mut(1) {
+        let fixed = match side.to_ascii_lowercase().as_str() {
+            "w" | "white" => "w",
+            "b" | "black" => "b",
+            _ => "",
+        };
+        if !fixed.is_empty() && side != fixed {
+            notes.push(format!("read side to move {} as {}", side, fixed));
+            *side = fixed.to_string();
+        }
+    }
+
+    if let Some(castling) = fields.get_mut(2) {
+        let fixed = match castling.as_str() {
+            "--" | "none" | "0" => Some("-".to_string()),
+            field => castling_as_moves(field),
+        };
+        if let Some(fixed) = fixed.filter(|f| f != castling) {
+            notes.push(format!("read castling {} as {}", castling, fixed));
+            *castling = fixed;
+        }
+    }
+
+    // after the en-passant square come at most two clocks, perhaps run
+    // together, and then possibly the moves of the game
+    if fields.len() > 4 {
+        let mut clocks: Vec<String> = Vec::new();
+        let mut rest = fields.split_off(4).into_iter();
+        for field in rest.by_ref() {
+            let parts: Vec<&str> = field.split(['-', ',', '/', '+', ':']).collect();
+            if parts
+                .iter()
+                .all(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()))
+                && clocks.len() + parts.len() <= 2
+            {
+                if parts.len() > 1 {
+                    notes.push(format!("split the clocks {} in two", field));
+                }
+                clocks.extend(parts.iter().map(|p| p.to_string()));
+                if clocks.len() == 2 {
+                    break;
+                }
+            } else {
+                let trailing: Vec<String> = std::iter::once(field).chain(rest.by_ref()).collect();
+                notes.push(format!(
+                    "dropped the text after the FEN: {}",
+                    trailing.join(" ")
+                ));
+                break;
+            }
+        }
+        let trailing: Vec<String> = rest.collect();
+        if !trailing.is_empty() {
+            notes.push(format!(
+                "dropped the text after the FEN: {}",
+                trailing.join(" ")
+            ));
+        }
+        // a clock has no leading zero, so `012` is a halfmove clock of 0
+        // run into a fullmove number of 12
+        if clocks.len() == 1 && clocks[0].len() > 1 && clocks[0].starts_with('0') {
+            notes.push(format!("split the clocks {} in two", clocks[0]));
+            let fullmove = clocks[0][1..].to_string();
+            clocks = vec!["0".to_string(), fullmove];
+        }
+        fields.extend(clocks);
+    }
+    if fields.len() == 4 {
+        notes.push("added the missing clocks 0 1".to_string());
+        fields.extend(["0".to_string(), "1".to_string()]);
+    } else if fields.len() == 5 {
+        notes.push("added the missing fullmove number 1".to_string());
+        fields.push("1".to_string());
+    }
+
+    (fields.join(" "), notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::{Position, STARTING_FEN};
+
+    #[test]
+    fn a_correct_fen_is_left_alone() {
+        assert_eq!(repair(STARTING_FEN), (STARTING_FEN.to_string(), vec![]));
+    }
+
+    #[test]
+    fn missing_fields_are_added() {
+        let (fen, notes) = repair("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -");
+        assert_eq!(fen, STARTING_FEN);
+        assert_eq!(notes, ["added the missing clocks 0 1"]);
+        let (fen, notes) = repair("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0");
+        assert_eq!(fen, STARTING_FEN);
+        assert_eq!(notes, ["added the missing fullmove number 1"]);
+    }
+
+    #[test]
+    fn whitespace_and_quotes_are_trimmed() {
+        let (fen, notes) =
+            repair("  \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR   w  KQkq\t-  0 1\" \n");
+        assert_eq!(fen, STARTING_FEN);
+        assert_eq!(notes, ["removed the quotes around the FEN"]);
+    }
+
+    #[test]
+    fn ranks_and_fields_are_put_right() {
+        let (fen, notes) = repair(
+            "rnbqkbnr\\pppppppp\\8\\8\\4P3\\8\\PPPP1PPP\\RNBQKBNR/ B O-O,0-0-0 e3 01 1... e5",
+        );
+        assert_eq!(
+            fen,
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        assert_eq!(
+            notes,
+            [
+                "replaced backslashes with slashes",
+                "removed the slash after the last rank",
+                "read side to move B as b",
+                "read castling O-O,0-0-0 as KQkq",
+                "dropped the text after the FEN: 1... e5",
+                "split the clocks 01 in two",
+            ]
+        );
+        assert!(Position::from_fen(&fen).is_ok());
+        let (fen, _) = repair("8/8/8/8/8/8/8/K6k w - - 12-40");
+        assert_eq!(fen, "8/8/8/8/8/8/8/K6k w - - 12 40");
+    }
+
+    #[test]
+    fn a_rank_of_the_wrong_length_is_not_guessed_at() {
+        let input = "rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let (fen, notes) = repair(input);
+        assert_eq!(fen, input);
+        assert!(notes.is_empty());
+        assert!(Position::from_fen(&fen).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+//! Reading, checking and drawing chess positions given as FENs
+//!
+//! This is the library behind the `fen` command-line tool. Besides what
+//! the tool uses, it offers `png::render_to_rgba` for turning a position
+//! into raw pixels, e.g. for bots and site generators.
+
+pub mod anki;
+pub mod annotate;
+pub mod annotations;
+pub mod atlas;
+pub mod batch;
+pub mod bench;
+pub mod bitboard;
+pub mod book;
+pub mod candidates;
+pub mod capabilities;
+pub mod deck;
+pub mod defaults;
+pub mod dgt;
+pub mod diagram;
+pub mod drill;
+pub mod echo;
+pub mod epd;
+pub mod export;
+pub mod features;
+pub mod fonts;
+pub mod frc;
+pub mod gui;
+pub mod json;
+pub mod lang;
+pub mod lenient;
+pub mod listen;
+pub mod mate;
+pub mod movegen;
+pub mod nag;
+pub mod pack;
+pub mod passers;
+pub mod path;
+pub mod pdf;
+pub mod pgn;
+pub mod pieceset;
+pub mod play;
+pub mod png;
+pub mod position;
+pub mod puzzles;
+pub mod qr;
+pub mod recognize;
+pub mod relay;
+pub mod results;
+pub mod retro;
+pub mod rng;
+pub mod san;
+pub mod scan;
+#[cfg(feature = "engine")]
+pub mod search;
+pub mod selfplay;
+pub mod stats;
+pub mod store;
+pub mod style;
+pub mod svg;
+pub mod template;
+pub mod terminal;
+pub mod texture;
+pub mod tournament;
+pub mod train;
+pub mod transpositions;
+pub mod tree;
+pub mod uci;
+#[cfg(feature = "self-update")]
+pub mod update;
+pub mod url;
+pub mod version;
+pub mod window;
+pub mod wizard;
+pub mod xray;
+pub mod yaml;
+pub mod zobrist;
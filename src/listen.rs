@@ -0,0 +1,161 @@
+//! Local socket for pushing positions to an open window
+//!
+//! Each line a client writes is a FEN (or analysis link) to show, or one of
+//! the commands `flip` and `highlight <square>...`; `highlight` alone clears
+//! the highlights. Every line is answered with `ok` or `error: <reason>`.
+
+use crate::position::{parse_square, Position};
+use crate::url;
+use crate::window::Update;
+use std::sync::mpsc::Sender;
+
+/// the window update asked for by one line from a client
+pub fn parse(line: &str) -> Result<Update, String> {
+    let mut words = line.split_whitespace();
+    match words.next() {
+        Some("flip") => Ok(Update::Flip),
+        Some("highlight") => words
+            .map(|w| parse_square(w).ok_or_else(|| format!("Expected a square, found {}", w)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Update::Highlight),
+        Some(_) => {
+            let fen =
+                url::fen_from_url(line.trim()).unwrap_or_else(|| Ok(line.trim().to_string()))?;
+            Ok(Update::Position(Position::from_fen(&fen)?))
+        }
+        None => Err("Empty line".to_string()),
+    }
+}
+
+/// The socket `spawn` listens on, removed again when this is dropped
+/// (once the window has closed) so that sockets do not pile up.
+pub struct Socket {
+    path: String,
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        let _ = remove_socket(&self.path);
+    }
+}
+
+// Remove the socket at `path`, if there is one; anything else there is
+// left alone and is an error.
+#[cfg(unix)]
+fn remove_socket(path: &str) -> Result<(), String> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path)
+            .map_err(|e| format!("Could not remove the old socket {}: {}", path, e)),
+        Ok(_) => Err(format!("{} exists and is not a socket", path)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Could not check {}: {}", path, e)),
+    }
+}
+
+#[cfg(not(unix))]
+fn remove_socket(_path: &str) -> Result<(), String> {
+    Ok(())
+}
+
+/// Listen on the Unix socket at `path` in the background, sending each
+/// client's requests to the window on `updates`, for as long as the
+/// returned [`Socket`] is kept. A stale socket left from an earlier run is
+/// replaced, but any other file at `path` is an error.
+#[cfg(unix)]
+pub fn spawn(path: &str, updates: Sender<Update>) -> Result<Socket, String> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixListener;
+
+    remove_socket(path)?;
+    let listener =
+        UnixListener::bind(path).map_err(|e| format!("Could not listen on {}: {}", path, e))?;
+    let socket = Socket {
+        path: path.to_string(),
+    };
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let updates = updates.clone();
+            std::thread::spawn(move || {
+                let mut reply = match stream.try_clone() {
+                    Ok(reply) => reply,
+                    Err(_) => return,
+                };
+                for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let answer = match parse(&line) {
+                        Ok(update) => match updates.send(update) {
+                            Ok(()) => "ok".to_string(),
+                            // the window has closed
+                            Err(_) => return,
+                        },
+                        Err(e) => format!("error: {}", e),
+                    };
+                    if writeln!(reply, "{}", answer).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    Ok(socket)
+}
+
+#[cfg(not(unix))]
+pub fn spawn(path: &str, _updates: Sender<Update>) -> Result<Socket, String> {
+    Err(format!(
+        "Could not listen on {}: sockets are only supported on Unix-like systems",
+        path
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn scratch(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("fen-listen-{}-{}", std::process::id(), name));
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn other_files_are_left_alone() {
+        let path = scratch("notes.txt");
+        std::fs::write(&path, "keep me").unwrap();
+        let (sender, _receiver) = mpsc::channel();
+        let error = spawn(&path, sender).err().unwrap();
+        assert!(error.contains("is not a socket"), "{}", error);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn sockets_are_replaced_and_removed() {
+        let path = scratch("sock");
+        let (sender, _receiver) = mpsc::channel();
+        let stale = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        drop(stale);
+        let socket = spawn(&path, sender).unwrap();
+        assert!(std::path::Path::new(&path).exists());
+        drop(socket);
+        assert!(!std::path::Path::new(&path).exists());
+    }
+
+    #[test]
+    fn lines_become_updates() {
+        assert!(matches!(parse("flip"), Ok(Update::Flip)));
+        assert!(
+            matches!(parse("highlight e4 d5"), Ok(Update::Highlight(squares)) if squares.len() == 2)
+        );
+        assert!(matches!(
+            parse(crate::position::STARTING_FEN),
+            Ok(Update::Position(_))
+        ));
+        assert!(parse("highlight z9").is_err());
+        assert!(parse("").is_err());
+    }
+}
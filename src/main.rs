@@ -8,183 +8,1382 @@
 //! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11"
 //! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" -i
 //! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" -i -w
+//! cargo -q run -- "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" --play "e4 e5 Nf3" --save-pgn game.pgn
+//! cargo -q run -- "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" --pv "e4 e5 Nf3 Nc6 Bb5" --filmstrip line.png
+//! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" --qr board.png --qr-url
+//! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" --url chesscom --open
+//! cargo -q run -- "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1" --format setup
+//! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" --palette protanopia --high-contrast -w
+//! cargo -q run -- "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3" --style minimal --svg inline.svg
+//! cargo -q run -- "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3" --square-px 50 --border-width 2 --margin 12 --svg figure.svg
+//! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" -w --board-style wood
+//! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" --quality high --png board.png
+//! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" --piece-set merida --png board.png
+//! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" --index-overlay 0-63 --svg board.svg
+//! cargo -q run -- "r1b2rk1/pp1nqppp/2pb1n2/3p4/3P4/2NBPN2/PPQ2PPP/R1B2RK1 w - - 0 9" --xrays --xray-arrows --png xrays.png
+//! cargo -q run -- "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3" --annotations italian.json --png italian.png
+//! cargo -q run -- "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 2 3" --candidates --figurine
+//! cargo -q run -- "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" --play "e4 e5 Sf3" -i --lang de
+//! cargo -q run -- "8/8/1k6/8/8/8/6P1/6K1 w - - 0 1" --passers --png square.png
+//! cargo -q run -- "8/8/8/2k5/2P5/2K5/8/8 w - - 0 1" --engine stockfish --null-move-compare
+//! cargo -q run -- --from-image board.png -i
+//! cargo -q run -- "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e9 0 1" --echo
+//! cargo -q run -- "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1" --trace fen::parse=debug --png board.png
+//! cargo -q run -- 'rnbqkbnr\pppppppp\8\8\4P3\8\PPPP1PPP\RNBQKBNR B O-O,O-O-O e3 0-1 1... e5' --lenient
+//! cargo -q run -- "tsldklst/bbbbbbbb/8/8/4B3/8/BBBB1BBB/TSLDKLST b KQkq e3 0 1" --piece-letters de
+//! cargo -q run --features engine -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" --analyze --depth 6
+//! cargo -q run -- version --json
+//! cargo -q run --features self-update -- self-update --check
+//! cargo -q run -- uci "position startpos moves e2e4 e7e5 g1f3" -i
+//! cargo -q run -- wizard
+//! cargo -q run -- attacks bishop c4 --on "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/8/PPPP1PPP/RNBQK1NR w KQkq - 2 3"
+//! cargo -q run -- "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3" --svg board.svg --svg-optimize
+//! cargo -q run -- "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3" --pdf board.pdf --page-size letter
+//! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" --template board.svg.hbs
+//! cargo -q run -- bitboard 0x0000001818000000 --on "r1bqkbnr/pppp1ppp/2n5/4p3/3PP3/5N2/PPP2PPP/RNBQKB1R b KQkq d3 0 3" --png mask.png
+//! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" --engine stockfish --svg board.svg
+//! cargo -q run -- "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" --listen /tmp/fen.sock
+//! cargo -q run -- --dgt /dev/ttyUSB0 -w --kiosk --on-top
+//! cargo -q run -- --dgt /dev/ttyUSB0 -w --fps 30 --vsync
+//! cargo -q run -- anki puzzles.epd --deck puzzles.csv
+//! cargo -q run -- deck study.yaml --out slides
+//! cargo -q run -- drill puzzles.epd --limit 10
+//! cargo -q run -- train-coords --rounds 10 -w
+//! cargo -q run -- tour --from a1 -w
+//! cargo -q run -- eight-queens --all
+//! cargo -q run -- batch positions.txt --stats
+//! cargo -q run -- batch composed.txt --retro
+//! cargo -q run -- pack positions.txt --out positions.bin
+//! cargo -q run -- sample positions.bin --count 1000
+//! cargo -q run -- features positions.bin --format numpy --encoding halfkp --out features.npy
+//! cargo -q run -- scan game.pgn --engine stockfish --threshold 150 --out mistakes
+//! cargo -q run -- positions game.pgn --after-move 10
+//! cargo -q run -- relay https://lichess.org/broadcast/<tour>/<round>/<id> --board 2
+//! cargo -q run -- relay https://lichess.org/broadcast/<tour>/<round>/<id> --engine stockfish --multipv 3
+//! FEN_OPTS="--palette muted --charset ascii" FEN_DEFAULT_POSITION="8/8/8/4k3/8/8/8/4K3 w - - 0 1" cargo -q run -- -i
+//! cargo -q run -- save najdorf "rnbqkb1r/1p2pppp/p2p1n2/8/3NP3/2N5/PPP2PPP/R1BQKB1R w KQkq - 0 6"
+//! cargo -q run -- save lucena "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1" --tag endgame,rook-endgame
+//! cargo -q run -- search --tag rook-endgame --material "RP vs R"
+//! cargo -q run -- load najdorf -i
+//! cargo -q run -- history
+//! cargo -q run -- tree games.pgn --depth 8
+//! cargo -q run -- build-book games.pgn --out book.bin --min-games 5
+//! cargo -q run -- transpositions game1.pgn game2.pgn
+//! cargo -q run -- convert-move "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" Nf3 d5
+//! cargo -q run -- convert-move "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" g1f3 --to-san
+//! cargo -q run -- frc 518
+//! cargo -q run -- frc random --seed 2024
+//! cargo -q run -- frc "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+//! cargo -q run -- path "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" Ng1 f6 --png route.png
+//! cargo -q run --release -- bench movegen
+//! cargo -q run -- mate "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4" --max 2
+//! cargo -q run -- boards --follow -w < games.txt
+//! cargo -q run -- "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3" "r1bqkbnr/pppp1ppp/2n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3" -w
+//! cargo -q run -- play --engine stockfish --tc 5+3 --as black
+//! cargo -q run -- tournament --engines stockfish,lc0,ethereal --openings book.epd --games 100
+//! cargo -q run -- selfplay --engine1 stockfish --engine2 stockfish --movetime 100 --save-pgn game.pgn
+//! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" --big --color-depth 256 --image-protocol none
+//! cargo -q run -- "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq b6 0 11" --bigascii
+//! cargo -q run -- "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" --play "e4 e5 Nf3 Nc6 Bb5 a6" -w
 //! ```
 
-use clap::Parser;
-use glutin_window::GlutinWindow as Window;
-use opengl_graphics::{GlGraphics, GlyphCache, OpenGL, TextureSettings};
-use piston::event_loop::{EventSettings, Events};
-use piston::input::{RenderArgs, RenderEvent, UpdateArgs, UpdateEvent};
-use piston::window::WindowSettings;
-use term_grid::{Cell, Direction, Filling, Grid, GridOptions};
+use capabilities::{ColorDepth, ImageProtocol};
+use clap::error::{ContextKind, ContextValue, ErrorKind};
+use clap::{CommandFactory, Parser, Subcommand};
+use diagram::{DiagramOptions, Layout, Palette, PaletteName, Quality, Region, SquareIndex};
+#[cfg(feature = "engine")]
+use fen::search;
+#[cfg(feature = "self-update")]
+use fen::update;
+use fen::{
+    anki, annotate, annotations, batch, bench, bitboard, book, candidates, capabilities, deck,
+    defaults, dgt, diagram, drill, echo, export, features, fonts, frc, gui, lang, lenient, listen,
+    mate, nag, pack, passers, path, pdf, pgn, pieceset, play, png, position, puzzles, qr,
+    recognize, relay, results, retro, rng, san, scan, selfplay, stats, store, style, svg, template,
+    terminal, texture, tournament, train, transpositions, tree, uci, url, version, window, wizard,
+    xray, zobrist,
+};
+use pgn::Game;
+use position::{Color, Position};
+use std::collections::HashMap;
+use std::io::{self, BufRead, IsTerminal};
+use std::sync::mpsc;
+use std::thread;
+use terminal::{board_glyphs, print_board, Charset, TerminalOptions};
+use texture::BoardStyle;
+use tracing_subscriber::EnvFilter;
+use window::WindowOptions;
+
+// `bench` reports allocations per position, which only the global
+// allocator sees. A program has one, chosen at compile time, so it is
+// installed for every command but counts only while a benchmark runs.
+#[global_allocator]
+static GLOBAL: bench::Counting = bench::Counting;
 
 #[derive(Parser)]
 #[command(name = "fen")]
 #[command(about = "Parse a Forsyth–Edwards Notation (FEN) string", long_about = None)]
+#[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
+#[command(args_override_self = true)]
 struct Args {
-    /// input FEN string
-    #[arg(required = true)]
-    fen: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// input FEN string, or a lichess or chess.com analysis link
+    #[arg(required_unless_present_any = ["from_image", "dgt", "list_piece_sets"])]
+    fen: Option<String>,
+    /// more FENs or links: all the positions are shown in turn, in a grid in the window, as by `boards`
+    #[arg(value_name = "MORE", requires = "fen")]
+    more: Vec<String>,
+    /// read the position from an image of a board instead of a FEN
+    #[arg(long("from-image"), value_name = "FILE", conflicts_with = "fen")]
+    from_image: Option<String>,
+    /// follow a DGT electronic board on this serial port, printing a FEN on every change
+    #[arg(long("dgt"), value_name = "PORT", conflicts_with_all = ["fen", "from_image"])]
+    dgt: Option<String>,
+    /// board-recognition backend for --from-image
+    #[arg(long("recognizer"), value_name = "NAME", default_value = "template")]
+    recognizer: String,
     /// spawn a graphical window containing the board
-    #[arg(short('w'), long("window"))]
+    #[arg(short('w'), long("window"), global = true)]
     window: bool,
+    /// check that the position could arise in a legal game (also for each line of `batch`)
+    #[arg(long("retro"), global = true)]
+    retro: bool,
+    /// correct common typos in the FEN, such as backslashes or O-O castling, reporting each
+    #[arg(long("lenient"))]
+    lenient: bool,
+    /// read the piece letters of the FEN in this language, e.g. de for S/L/T/D/K, and print the standard FEN
+    #[arg(long("piece-letters"), value_enum, value_name = "LANG")]
+    piece_letters: Option<lang::Lang>,
+    /// reprint the FEN with its fields coloured and labelled, underlining the first error
+    #[arg(long("echo"))]
+    echo: bool,
     /// show information extracted from the fen
-    #[arg(short('i'), long("info"))]
+    #[arg(short('i'), long("info"), global = true)]
     info: bool,
-    /// (unused) use debug mode
-    #[arg(short('d'), long("debug"))]
-    debug: bool,
+    /// moves to play from the given position, in SAN or UCI notation
+    #[arg(long("play"), value_name = "MOVES", global = true)]
+    play: Option<String>,
+    /// principal variation to check move by move from the given position, in SAN or UCI notation; its final position is shown
+    #[arg(
+        long("pv"),
+        value_name = "MOVES",
+        conflicts_with = "play",
+        global = true
+    )]
+    pv: Option<String>,
+    /// show the position after each move of the --pv side by side: in the terminal ("-"), as an image strip (.svg, otherwise PNG) or as captioned boards (.pdf)
+    #[arg(long("filmstrip"), value_name = "FILE", requires = "pv", global = true)]
+    filmstrip: Option<String>,
+    /// write the game (position plus any played moves) to a PGN file
+    #[arg(long("save-pgn"), value_name = "FILE", global = true)]
+    save_pgn: Option<String>,
+    /// open the window and accept FENs and commands (flip, highlight e4) on this Unix socket
+    #[arg(long("listen"), value_name = "SOCKET", global = true)]
+    listen: Option<String>,
+    /// hide the pieces (hold space in the window to reveal them)
+    #[arg(long("blindfold"), global = true)]
+    blindfold: bool,
+    /// UCI engine used to evaluate the position
+    #[arg(long("engine"), value_name = "PATH", global = true)]
+    engine: Option<String>,
+    /// play the side to move in the window against the --engine, which takes the other side
+    #[arg(long("vs-engine"), requires_all = ["engine", "window"], global = true)]
+    vs_engine: bool,
+    /// milliseconds the engine may think per move [default: 1000, 100 in selfplay]
+    #[arg(long("movetime"), value_name = "MS", global = true)]
+    movetime: Option<u64>,
+    /// analyse with the built-in toy engine (needs the "engine" feature)
+    #[arg(long("analyze"))]
+    analyze: bool,
+    /// plies the built-in engine searches with --analyze
+    #[arg(
+        long("depth"),
+        value_name = "PLIES",
+        default_value_t = 4,
+        requires = "analyze"
+    )]
+    depth: u32,
+    /// number of engine lines to show in an analysis table
+    #[arg(long("multipv"), value_name = "N", global = true)]
+    multipv: Option<usize>,
+    /// draw the engine's best move as an arrow, and the last --play move red if it is a blunder
+    #[arg(long("annotate-best"), global = true)]
+    annotate_best: bool,
+    /// PGN database whose statistics for the position are shown under --info
+    #[arg(long("games"), value_name = "PGN")]
+    games: Option<String>,
+    /// annotation shown as a badge on exported diagrams: a NAG ($3), symbol (!!, ±) or ASCII form (+/-)
+    #[arg(long("nag"), value_name = "NAG", global = true)]
+    nag: Option<String>,
+    /// export only part of the board, given by two corners such as a1-d4
+    #[arg(long("crop"), value_name = "SQUARES", global = true)]
+    crop: Option<String>,
+    /// list the legal moves grouped into checks, captures and quiet moves
+    #[arg(long("candidates"), global = true)]
+    candidates: bool,
+    /// evaluate with --engine both as given and with the move passed to the other side, reporting what the move is worth
+    #[arg(long("null-move-compare"), requires = "engine", global = true)]
+    null_move_compare: bool,
+    /// list passed pawns, whether the defending king is in their square, and draw their paths and squares on exported diagrams
+    #[arg(long("passers"), global = true)]
+    passers: bool,
+    /// list x-rays (a slider lined up with an enemy piece behind one blocker) and batteries
+    #[arg(long("xrays"), global = true)]
+    xrays: bool,
+    /// draw the --xrays as arrows through the blockers on exported diagrams and in the window
+    #[arg(long("xray-arrows"), requires = "xrays", global = true)]
+    xray_arrows: bool,
+    /// number each candidate move's target square on exported diagrams
+    #[arg(long("label-candidates"), global = true)]
+    label_candidates: bool,
+    /// draw the arrows, highlights and caption this JSON file keeps for the position
+    #[arg(long("annotations"), value_name = "FILE", global = true)]
+    annotations: Option<String>,
+    /// write an SVG diagram of the board
+    #[arg(long("svg"), value_name = "FILE", global = true)]
+    svg: Option<String>,
+    /// write SVG diagrams as compact markup: fonts named once, grouped colours, no newlines
+    #[arg(long("svg-optimize"), global = true)]
+    svg_optimize: bool,
+    /// write a PNG diagram of the board
+    #[arg(long("png"), value_name = "FILE", global = true)]
+    png: Option<String>,
+    /// write a vector PDF of the board, for printing
+    #[arg(long("pdf"), value_name = "FILE", global = true)]
+    pdf: Option<String>,
+    /// page size of PDF exports
+    #[arg(long("page-size"), value_enum, default_value_t = pdf::PageSize::A4, global = true)]
+    page_size: pdf::PageSize,
+    /// boards on each page of a PDF export [default: 1, 6 for a --filmstrip]
+    #[arg(long("per-page"), value_name = "N", value_parser = clap::value_parser!(u64).range(1..), global = true)]
+    per_page: Option<u64>,
+    /// write a PNG of the board with a QR code of the FEN beside it ("-" prints the code in the terminal)
+    #[arg(long("qr"), value_name = "FILE", global = true)]
+    qr: Option<String>,
+    /// encode a lichess analysis link in the QR code instead of the bare FEN
+    #[arg(long("qr-url"), global = true)]
+    qr_url: bool,
+    /// print a link to the position on an analysis board
+    #[arg(long("url"), value_enum, value_name = "SITE", global = true)]
+    url: Option<url::Site>,
+    /// open the --url link in the default browser
+    #[arg(long("open"), requires = "url", global = true)]
+    open: bool,
+    /// print the position in this format instead of the board
+    #[arg(long("format"), value_enum, value_name = "FORMAT")]
+    format: Option<export::Format>,
+    /// fill in this Handlebars-style template (e.g. an SVG or HTML page) with the position and print it instead of the board
+    #[arg(long("template"), value_name = "FILE", conflicts_with = "format")]
+    template: Option<String>,
+    /// how pieces are written to the terminal [default: detected]
+    #[arg(long("charset"), value_enum, global = true)]
+    charset: Option<Charset>,
+    /// label each square with its index (0-63, a1 first) or name, in the terminal, window and exported diagrams
+    #[arg(
+        long("index-overlay"),
+        value_enum,
+        value_name = "LABELS",
+        global = true
+    )]
+    index_overlay: Option<SquareIndex>,
+    /// draw a large terminal board: an inline image where the terminal takes one, else coloured squares
+    #[arg(long("big"), global = true)]
+    big: bool,
+    /// draw a large terminal board of plain ASCII art, 5x3 characters a square
+    #[arg(long("bigascii"), global = true, conflicts_with = "big")]
+    bigascii: bool,
+    /// colours the terminal draws, for --big [default: detected]
+    #[arg(long("color-depth"), value_enum, value_name = "DEPTH", global = true)]
+    color_depth: Option<ColorDepth>,
+    /// how the terminal takes inline images, for --big [default: detected]
+    #[arg(
+        long("image-protocol"),
+        value_enum,
+        value_name = "PROTOCOL",
+        global = true
+    )]
+    image_protocol: Option<ImageProtocol>,
+    /// colours of the squares, highlights and arrows in every renderer
+    #[arg(long("palette"), value_enum, default_value_t = PaletteName::Default, global = true)]
+    palette: PaletteName,
+    /// stronger square contrast and opaque highlights and arrows
+    #[arg(long("high-contrast"), global = true)]
+    high_contrast: bool,
+    /// rendering quality of the window and exported PNGs
+    #[arg(long("quality"), value_enum, default_value_t = Quality::Low, global = true)]
+    quality: Quality,
+    /// keep the window above all others
+    #[arg(long("on-top"), global = true)]
+    on_top: bool,
+    /// fill the screen with a borderless window and no cursor, e.g. for a club display board (Esc closes it)
+    #[arg(long("kiosk"), global = true)]
+    kiosk: bool,
+    /// cap the window at N frames a second (60 by default), e.g. 30 to save battery
+    #[arg(long("fps"), value_name = "N", value_parser = clap::value_parser!(u64).range(1..), global = true)]
+    fps: Option<u64>,
+    /// wait for the display's vertical sync before showing each frame
+    #[arg(long("vsync"), global = true)]
+    vsync: bool,
+    /// a bundle of diagram options: small and coordinate-free for text, or large for slides
+    #[arg(long("style"), value_enum, global = true)]
+    style: Option<style::Style>,
+    /// edge length of a board square in pixels, in exports and the window's starting size
+    #[arg(long("square-px"), value_name = "PX", value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    square_px: Option<u32>,
+    /// empty space in pixels around the board and its frame
+    #[arg(long("margin"), value_name = "PX", global = true)]
+    margin: Option<u32>,
+    /// width in pixels of a frame around the board, 0 for none
+    #[arg(long("border-width"), value_name = "PX", global = true)]
+    border_width: Option<u32>,
+    /// look of the squares in the window
+    #[arg(long("board-style"), value_enum, default_value_t = BoardStyle::Flat, global = true)]
+    board_style: BoardStyle,
+    /// text shown on empty squares in the terminal, e.g. ·
+    #[arg(long("placeholder"), value_name = "TEXT", global = true)]
+    placeholder: Option<String>,
+    /// font for chess pieces in the window and PNG export ("shapes" draws them without a font)
+    #[arg(long("piece-font"), value_name = "FILE", global = true)]
+    piece_font: Option<String>,
+    /// draw the pieces of the window and PNG export with a piece set installed under ~/.config/fen/pieces
+    #[arg(
+        long("piece-set"),
+        value_name = "NAME",
+        global = true,
+        conflicts_with = "piece_font"
+    )]
+    piece_set: Option<String>,
+    /// list the installed piece sets and whether each can be used
+    #[arg(long("list-piece-sets"), conflicts_with = "fen")]
+    list_piece_sets: bool,
+    /// write the pieces of printed moves as figurines, e.g. ♞f3
+    #[arg(long("figurine"), global = true)]
+    figurine: bool,
+    /// use figurines in PGN written with --save-pgn (or printed by `play`) too
+    #[arg(long("figurine-pgn"), requires = "figurine", global = true)]
+    figurine_pgn: bool,
+    /// language of the --info descriptions and of the piece letters of moves
+    #[arg(long("lang"), value_enum, default_value_t = lang::Lang::En, global = true)]
+    lang: lang::Lang,
+    /// seed for everything chosen at random, so that a run can be repeated
+    #[arg(long("seed"), value_name = "N", global = true)]
+    seed: Option<u64>,
+    /// log parse stages, validation and render timings to stderr, filtered
+    /// as in RUST_LOG, e.g. debug or fen::parse=debug,fen::render=debug
+    #[arg(long("trace"), value_name = "FILTER", global = true)]
+    trace: Option<String>,
 }
 
-pub struct Board {
-    gl: GlGraphics, // OpenGL drawing backend.
+#[derive(Subcommand)]
+enum Command {
+    /// render the position from a UCI command, e.g. "position fen <fen> moves e2e4 e7e5"
+    Uci {
+        /// UCI position command, as pasted from an engine log
+        line: String,
+    },
+    /// play a position out between two UCI engines
+    Selfplay {
+        /// path to the engine playing white
+        #[arg(long("engine1"), value_name = "PATH")]
+        engine1: String,
+        /// path to the engine playing black
+        #[arg(long("engine2"), value_name = "PATH")]
+        engine2: String,
+        /// FEN to start from
+        #[arg(long("from"), value_name = "FEN", default_value = position::STARTING_FEN)]
+        from: String,
+        /// stop (unfinished) after this many full moves
+        #[arg(long("max-moves"), value_name = "N", default_value_t = 200)]
+        max_moves: u32,
+        /// play this many random moves before the engines take over
+        #[arg(long("random-plies"), value_name = "N", default_value_t = 0)]
+        random_plies: u32,
+    },
+    /// validate a file of FENs, one per line ("-" reads standard input)
+    Batch {
+        /// file of FENs
+        file: String,
+        /// report aggregate statistics over all valid positions
+        #[arg(long("stats"))]
+        stats: bool,
+        /// emit the statistics as JSON
+        #[arg(long("json"))]
+        json: bool,
+    },
+    /// pack a file of FENs into a compact binary file of about 24 bytes a position
+    Pack {
+        /// file of FENs, one per line ("-" reads standard input)
+        file: String,
+        /// file to write, standard output if not given
+        #[arg(long("out"), value_name = "FILE")]
+        out: Option<String>,
+    },
+    /// print the positions of a file made by `pack` as FENs
+    Unpack {
+        /// packed file ("-" reads standard input)
+        file: String,
+        /// file to write, standard output if not given
+        #[arg(long("out"), value_name = "FILE")]
+        out: Option<String>,
+    },
+    /// print positions picked at random from a file made by `pack`
+    Sample {
+        /// packed file
+        file: String,
+        /// number of positions to pick
+        #[arg(long("count"), value_name = "N", default_value_t = 10)]
+        count: usize,
+    },
+    /// write neural-network input features of positions, as planes or HalfKP indices
+    Features {
+        /// file of FENs or EPD records, or a packed file ("-" reads standard input)
+        file: String,
+        /// output format
+        #[arg(long("format"), value_enum, default_value_t = features::Format::Csv)]
+        format: features::Format,
+        /// which features to write
+        #[arg(long("encoding"), value_enum, default_value_t = features::Encoding::Planes)]
+        encoding: features::Encoding,
+        /// file to write, standard output if not given (required for numpy)
+        #[arg(long("out"), value_name = "FILE")]
+        out: Option<String>,
+    },
+    /// show a 64-bit mask as marked squares, bit 0 on a1 and bit 63 on h8
+    Bitboard {
+        /// the mask, in hex (0x...), binary (0b...) or decimal
+        mask: String,
+        /// show the mask beside this position and tint its squares in diagrams and the window
+        #[arg(long("on"), value_name = "FEN")]
+        on: Option<String>,
+    },
+    /// show the squares a piece attacks from a square, on an empty board or the --on position
+    Attacks {
+        /// the piece, by name (knight) or FEN letter (N, or n for Black's)
+        piece: String,
+        /// the square it stands on
+        square: String,
+        /// the side the piece belongs to, which decides a pawn's direction
+        #[arg(long("color"), value_name = "SIDE", value_parser = ["white", "black"])]
+        color: Option<String>,
+        /// stop the sliding pieces at the pieces of this position
+        #[arg(long("on"), value_name = "FEN")]
+        on: Option<String>,
+    },
+    /// build a FEN by answering a question for each field
+    Wizard,
+    /// make Anki flashcards from a file of FENs or EPD records
+    Anki {
+        /// file of positions, one per line ("-" reads standard input)
+        positions: String,
+        /// CSV file for Anki's importer; diagrams go in a folder beside it
+        #[arg(long("deck"), value_name = "FILE", default_value = "deck.csv")]
+        deck: String,
+    },
+    /// draw the slides of a YAML study file as numbered images
+    Deck {
+        /// study file listing each slide's FEN, caption and annotations
+        study: String,
+        /// directory the slides are written to, or the file of a PDF
+        #[arg(long("out"), value_name = "DIR", default_value = "slides")]
+        out: String,
+        /// image format of the slides
+        #[arg(long("format"), value_enum, default_value_t = deck::Format::Png)]
+        format: deck::Format,
+    },
+    /// solve the puzzles of an EPD file, repeating each on an SM-2 schedule
+    Drill {
+        /// EPD file of puzzles with bm solutions
+        puzzles: String,
+        /// most puzzles to present in this session
+        #[arg(long("limit"), value_name = "N", default_value_t = 20)]
+        limit: usize,
+    },
+    /// practise naming squares: a random square is highlighted, type its name
+    TrainCoords {
+        /// number of squares to ask
+        #[arg(long("rounds"), value_name = "N", default_value_t = 20)]
+        rounds: u32,
+    },
+    /// a knight's tour visiting every square once, animated with -w
+    Tour {
+        /// square the knight starts on
+        #[arg(long("from"), value_name = "SQUARE", default_value = "a1")]
+        from: String,
+        /// milliseconds between moves in the window
+        #[arg(long("delay"), value_name = "MS", default_value_t = 300)]
+        delay: u64,
+    },
+    /// eight queens that do not attack one another
+    EightQueens {
+        /// show all 92 solutions, not just the first
+        #[arg(long("all"))]
+        all: bool,
+        /// milliseconds between solutions in the window
+        #[arg(long("delay"), value_name = "MS", default_value_t = 1000)]
+        delay: u64,
+    },
+    /// evaluate every move of a game with --engine and draw the mistakes
+    Scan {
+        /// PGN file of the game
+        pgn: String,
+        /// flag moves losing more than this many centipawns
+        #[arg(long("threshold"), value_name = "CP", default_value_t = 150)]
+        threshold: i32,
+        /// directory for the diagrams of each mistake
+        #[arg(long("out"), value_name = "DIR", default_value = "mistakes")]
+        out: String,
+    },
+    /// print the FEN of each position of a game, one per line
+    Positions {
+        /// PGN file of the game
+        pgn: String,
+        /// every position, from the start to the final one (the default)
+        #[arg(long("every-ply"), conflicts_with = "after_move")]
+        every_ply: bool,
+        /// only the position after Black's reply to move N (repeatable)
+        #[arg(long("after-move"), value_name = "N")]
+        after_move: Vec<u32>,
+    },
+    /// follow a board of a live broadcast, redrawing it as moves arrive
+    Relay {
+        /// lichess broadcast link, or any link or file giving the round's PGN
+        source: String,
+        /// which game of the round to follow, counting from 1
+        #[arg(long("board"), value_name = "N", default_value_t = 1)]
+        board: usize,
+        /// seconds between fetches of the round
+        #[arg(long("interval"), value_name = "SECS", default_value_t = 5)]
+        interval: u64,
+    },
+    /// save a position under a name [default: the position last viewed]
+    Save {
+        /// name to save the position as
+        name: String,
+        /// FEN to save
+        fen: Option<String>,
+        /// tags to file the position under, separated by commas
+        #[arg(long("tag"), value_name = "TAGS", value_delimiter = ',')]
+        tag: Vec<String>,
+    },
+    /// list saved positions with all the given tags and properties
+    Search {
+        /// only positions with these tags, separated by commas
+        #[arg(long("tag"), value_name = "TAGS", value_delimiter = ',')]
+        tag: Vec<String>,
+        /// only positions with this material besides kings, e.g. "R vs R" or "RP vs R"
+        #[arg(long("material"), value_name = "PIECES")]
+        material: Option<String>,
+        /// only positions with this side to move
+        #[arg(long("to-move"), value_name = "SIDE", value_parser = ["white", "black"])]
+        to_move: Option<String>,
+    },
+    /// show a position saved with `save`
+    Load {
+        /// name the position was saved as
+        name: String,
+    },
+    /// list the positions viewed most recently
+    History {
+        /// number of positions to list
+        #[arg(long("limit"), value_name = "N", default_value_t = 20)]
+        limit: usize,
+    },
+    /// print the opening tree of a PGN database with move frequencies and scores
+    Tree {
+        /// PGN file of games
+        pgn: String,
+        /// number of half-moves of each game to include
+        #[arg(long("depth"), value_name = "PLIES", default_value_t = 8)]
+        depth: usize,
+    },
+    /// build a Polyglot opening book from a PGN database
+    BuildBook {
+        /// PGN file of games
+        pgn: String,
+        /// file to write the book to
+        #[arg(long("out"), value_name = "FILE", default_value = "book.bin")]
+        out: String,
+        /// leave out moves played in fewer games than this
+        #[arg(long("min-games"), value_name = "N", default_value_t = 5)]
+        min_games: usize,
+    },
+    /// list positions reached in both of two games, with the move numbers in each
+    Transpositions {
+        /// PGN file of the first game
+        pgn1: String,
+        /// PGN file of the second game
+        pgn2: String,
+    },
+    /// convert moves made from a position between SAN and UCI form
+    ConvertMove {
+        /// FEN of the position
+        fen: String,
+        /// moves in SAN or UCI form, played in turn
+        #[arg(required = true)]
+        moves: Vec<String>,
+        /// write the moves in SAN instead of UCI form
+        #[arg(long("to-san"))]
+        to_san: bool,
+    },
+    /// the FEN of a Chess960 starting position by its number, or the number of a FEN
+    Frc {
+        /// a number from 0 to 959, a FEN, or "random" to draw one
+        position: String,
+    },
+    /// find the shortest route for a piece to a square, the other pieces standing still: the opponent passes after each move, so the route need not survive its replies
+    Path {
+        /// FEN of the position
+        fen: String,
+        /// the piece, e.g. Ng1 (or just its square)
+        piece: String,
+        /// the square to reach
+        target: String,
+        /// only stop on squares the opponent does not attack
+        #[arg(long("safe"))]
+        safe: bool,
+    },
+    /// time FEN parsing, move generation or SVG rendering over a built-in corpus
+    Bench {
+        /// the benchmark to run, or all of them
+        #[arg(value_enum)]
+        bench: Option<bench::Bench>,
+    },
+    /// find a forced mate and print its main line
+    Mate {
+        /// FEN of the position
+        fen: String,
+        /// longest mate to look for, in moves
+        #[arg(long("max"), value_name = "N", default_value_t = 4)]
+        max: u32,
+    },
+    /// play a game against the --engine in the terminal, with clocks
+    Play {
+        /// minutes per side and increment in seconds
+        #[arg(long("tc"), value_name = "MIN+SEC", default_value = "5+3")]
+        tc: String,
+        /// the side you play
+        #[arg(long("as"), value_name = "SIDE", value_parser = ["white", "black"], default_value = "white")]
+        side: String,
+        /// FEN to start from
+        #[arg(long("from"), value_name = "FEN", default_value = position::STARTING_FEN)]
+        from: String,
+    },
+    /// play UCI engines against each other from a book of openings and print a crosstable
+    Tournament {
+        /// paths of the engines, separated by commas
+        #[arg(
+            long("engines"),
+            value_name = "PATHS",
+            value_delimiter = ',',
+            required = true
+        )]
+        engines: Vec<String>,
+        /// EPD or FEN file of starting positions
+        #[arg(long("openings"), value_name = "FILE")]
+        openings: String,
+        /// number of games to play
+        #[arg(long("games"), value_name = "N", default_value_t = 100)]
+        games: usize,
+        /// first engine against each of the others only
+        #[arg(long("gauntlet"))]
+        gauntlet: bool,
+        /// adjudicate a game as a draw after this many full moves
+        #[arg(long("max-moves"), value_name = "N", default_value_t = 200)]
+        max_moves: u32,
+        /// stop once an SPRT of the first engine against the second accepts
+        /// an Elo gain of ELO0 or ELO1
+        #[arg(
+            long("sprt"),
+            value_name = "ELO0,ELO1",
+            value_delimiter = ',',
+            num_args = 1
+        )]
+        sprt: Vec<f64>,
+        /// SPRT false-positive rate
+        #[arg(long("alpha"), value_name = "P", default_value_t = 0.05)]
+        alpha: f64,
+        /// SPRT false-negative rate
+        #[arg(long("beta"), value_name = "P", default_value_t = 0.05)]
+        beta: f64,
+        /// print the final report as JSON
+        #[arg(long("json"))]
+        json: bool,
+    },
+    /// show several positions at once, in a grid when the window is open
+    Boards {
+        /// FENs of the boards, titled Board 1, Board 2 and so on
+        fens: Vec<String>,
+        /// also read "<id> <FEN>" lines from stdin, updating the board with that id
+        #[arg(long("follow"))]
+        follow: bool,
+    },
+    /// print the version and the optional features built in
+    Version {
+        /// print them as JSON, for scripts checking what the binary can do
+        #[arg(long("json"))]
+        json: bool,
+    },
+    /// replace this binary with the latest GitHub release (built with --features self-update)
+    SelfUpdate {
+        /// only say whether a newer release is out
+        #[arg(long("check"))]
+        check: bool,
+    },
 }
 
-impl Board {
-    fn render(&mut self, args: &RenderArgs, pieces: &Vec<Vec<String>>) {
-        use graphics::*;
+/// Print the FEN of every position of `game`, or with `after_move` only
+/// those once each listed move has been completed.
+fn print_positions(game: &Game, after_move: &[u32]) {
+    let positions = game.positions();
+    if after_move.is_empty() {
+        for pos in &positions {
+            println!("{}", pos.to_fen());
+        }
+        return;
+    }
+    for &n in after_move {
+        // move N is complete when White is next to play move N + 1
+        let found = positions
+            .iter()
+            .find(|p| p.side == position::Color::White && p.fullmove == n + 1);
+        match found {
+            Some(pos) => println!("{}", pos.to_fen()),
+            None => eprintln!("Warning: the game does not reach the end of move {}", n),
+        }
+    }
+}
 
-        // https://colorswall.com/palette/166635
-        const DARKSQ: [f32; 4] = [67.0 / 255.0, 74.0 / 255.0, 58.0 / 255.0, 1.0];
-        const LIGHTSQ: [f32; 4] = [180.0 / 255.0, 188.0 / 255.0, 170.0 / 255.0, 1.0];
-        let white_pieces: Vec<String> = vec!["♙", "♘", "♗", "♖", "♕", "♔"]
-            .into_iter()
-            .map(|s| s.to_owned())
-            .collect();
-        let black_pieces: Vec<String> = ["♟", "♞", "♝", "♜", "♛", "♚"]
-            .into_iter()
-            .map(|s| s.to_owned())
-            .collect();
+/// Recognise the board in the image at `path`, printing the FEN found.
+fn fen_from_image(path: &str, recognizer: &str) -> String {
+    let backend = recognize::backend(recognizer).unwrap_or_else(|| {
+        eprintln!(
+            "Error: Unknown recognizer {}, expected one of: {}",
+            recognizer,
+            recognize::BACKENDS.join(", ")
+        );
+        std::process::exit(1)
+    });
+    let fen = recognize::fen_from_image(path, backend.as_ref()).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1)
+    });
+    println!("FEN: {}", fen);
+    fen
+}
 
-        let mut glyph_cache =
-            GlyphCache::new("assets/FiraSans-Regular.ttf", (), TextureSettings::new()).unwrap();
-        let mut piece_glyph_cache =
-            GlyphCache::new("assets/FreeSerif-4aeK.ttf", (), TextureSettings::new()).unwrap();
-
-        // let rotation = self.rotation;
-        let (x, y) = (args.window_size[0], args.window_size[1]);
-        let square = rectangle::square(0.0, 0.0, x / 8.0);
-        let rank_corner = (0.05 * (x / 8.0), 0.25 * (y / 8.0));
-        let file_corner = (0.8 * (x / 8.0), 0.9 * (y / 8.0));
-        let files = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
-
-        self.gl.draw(args.viewport(), |c, gl| {
-            // Clear the screen.
-            clear(DARKSQ, gl);
-
-            let mut offset_x = 0.0;
-            let mut offset_y = 0.0;
-            let mut sq_col = DARKSQ;
-            let mut other_col;
-            let mut piece_col: [f32; 4];
-            let mut piece: String;
-            let mut tmp_piece: &str;
-            for _r in (1..=8).rev() {
-                for _f in 1..=8 {
-                    sq_col = if sq_col == LIGHTSQ { DARKSQ } else { LIGHTSQ };
-                    rectangle(sq_col, square, c.transform.trans(offset_x, offset_y), gl);
-                    other_col = if sq_col == LIGHTSQ { DARKSQ } else { LIGHTSQ };
-                    // annotate files
-                    if _r == 1 {
-                        text(
-                            other_col,
-                            16_u32,
-                            &files[_f - 1].to_string()[..],
-                            &mut glyph_cache,
-                            c.transform
-                                .trans(offset_x + file_corner.0, offset_y + file_corner.1),
-                            gl,
-                        )
-                        .unwrap();
+/// The command line with the defaults of `FEN_OPTS`, `FEN_DEFAULT_POSITION`
+/// and the config file filled in.
+fn parse_args() -> Args {
+    let defaults = defaults::load().unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1)
+    });
+    let mut command_line = defaults.command_line(&Args::command(), std::env::args_os());
+    match Args::try_parse_from(&command_line) {
+        Ok(args) => args,
+        // only a missing position is filled in, not a subcommand's argument
+        Err(e) if e.kind() == ErrorKind::MissingRequiredArgument => {
+            let missing_fen = matches!(
+                e.get(ContextKind::InvalidArg),
+                Some(ContextValue::Strings(names)) if names.iter().any(|n| n.starts_with("<FEN"))
+            );
+            match &defaults.position {
+                Some(position) if missing_fen => {
+                    command_line.push(position.into());
+                    Args::try_parse_from(&command_line).unwrap_or_else(|_| e.exit())
+                }
+                _ => e.exit(),
+            }
+        }
+        Err(e) => e.exit(),
+    }
+}
+
+fn main() {
+    let args = parse_args();
+    if let Some(filter) = &args.trace {
+        let filter = EnvFilter::try_new(filter).unwrap_or_else(|e| {
+            eprintln!("Error: Invalid --trace filter {}: {}", filter, e);
+            std::process::exit(1)
+        });
+        tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_writer(std::io::stderr)
+            .with_ansi(io::stderr().is_terminal())
+            .init();
+    }
+    if args.list_piece_sets {
+        list_piece_sets();
+        return;
+    }
+    let term = terminal_options(&args);
+
+    let (mut fen, moves) = match &args.command {
+        Some(Command::Uci { line }) => uci::parse_position_command(line).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1)
+        }),
+        Some(Command::Selfplay {
+            engine1,
+            engine2,
+            from,
+            max_moves,
+            random_plies,
+        }) => {
+            let movetime = args.movetime.unwrap_or(100);
+            selfplay(
+                &args,
+                engine1,
+                engine2,
+                from,
+                movetime,
+                *max_moves,
+                *random_plies,
+            );
+            return;
+        }
+        Some(Command::Batch { file, stats, json }) => {
+            if let Err(e) = batch::run(file, *stats, *json, args.retro) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Bitboard { mask, on }) => {
+            match bitboard::parse(mask) {
+                Ok(mask) => show_bitboard(&args, mask, on.as_deref()),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1)
+                }
+            }
+            return;
+        }
+        Some(Command::Attacks {
+            piece,
+            square,
+            color,
+            on,
+        }) => {
+            let found = bitboard::attacks(piece, square, color.as_deref(), on.as_deref());
+            match found {
+                Ok(mask) => show_bitboard(&args, mask, on.as_deref()),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1)
+                }
+            }
+            return;
+        }
+        Some(Command::Wizard) => {
+            if let Err(e) = wizard::run(&term) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Pack { file, out }) => {
+            if let Err(e) = pack::pack(file, out.as_deref()) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Unpack { file, out }) => {
+            if let Err(e) = pack::unpack(file, out.as_deref()) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Sample { file, count }) => {
+            if let Err(e) = pack::sample(file, *count, &mut random(&args)) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Features {
+            file,
+            format,
+            encoding,
+            out,
+        }) => {
+            if let Err(e) = features::run(file, *format, *encoding, out.as_deref()) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Anki { positions, deck }) => {
+            let pieces = piece_font(&args);
+            if let Err(e) = anki::run(positions, deck, &pieces, term.palette, args.quality) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Deck { study, out, format }) => {
+            let style = deck::Style {
+                pieces: &piece_font(&args),
+                diagram: diagram_options(&args),
+                quality: args.quality,
+                optimize: args.svg_optimize,
+                layout: pdf_layout(&args, 1),
+            };
+            if let Err(e) = deck::run(study, out, *format, &style) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Drill { puzzles, limit }) => {
+            if let Err(e) = drill::run(puzzles, *limit, &term) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::TrainCoords { rounds }) => {
+            train::coordinates(*rounds, args.window, &term, &mut random(&args));
+            return;
+        }
+        Some(Command::Tour { from, delay }) => {
+            if let Err(e) = puzzles::tour(from, args.window, *delay, &term) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::EightQueens { all, delay }) => {
+            puzzles::queens_puzzle(*all, args.window, *delay, &term);
+            return;
+        }
+        Some(Command::Scan {
+            pgn,
+            threshold,
+            out,
+        }) => {
+            let engine = args.engine.as_deref().unwrap_or_else(|| {
+                eprintln!("Error: scan needs an engine given with --engine");
+                std::process::exit(1)
+            });
+            let piece_font = piece_font(&args);
+            let opts = scan::ScanOptions {
+                engine,
+                movetime: args.movetime.unwrap_or(1000),
+                threshold: *threshold,
+                out,
+                piece_font: &piece_font,
+                palette: term.palette,
+                quality: args.quality,
+            };
+            if let Err(e) = scan::run(pgn, &opts) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Positions {
+            pgn, after_move, ..
+        }) => {
+            match pgn::first_game(pgn) {
+                Ok(game) => print_positions(&game, after_move),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1)
+                }
+            }
+            return;
+        }
+        Some(Command::Relay {
+            source,
+            board,
+            interval,
+        }) => {
+            if let Err(e) = relay::run(source, *board, *interval, &term, live_lines(&args)) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Save { name, fen, tag }) => {
+            if let Err(e) = save_position(name, fen.as_deref(), tag) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Search {
+            tag,
+            material,
+            to_move,
+        }) => {
+            if let Err(e) = search_positions(tag, material.as_deref(), to_move.as_deref()) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Load { name }) => {
+            let store = store::Store::load().unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            });
+            match store.find(name) {
+                Some(saved) => (saved.fen.clone(), Vec::new()),
+                None => {
+                    eprintln!("Error: No position saved as {}", name);
+                    std::process::exit(1)
+                }
+            }
+        }
+        Some(Command::History { limit }) => {
+            match store::Store::load() {
+                Ok(store) => {
+                    for viewed in store.history.iter().rev().take(*limit) {
+                        println!("{}  {}", store::format_time(viewed.viewed), viewed.fen);
                     }
-                    // annotate ranks
-                    if _f == 1 {
-                        text(
-                            other_col,
-                            16_u32,
-                            &_r.to_string()[..],
-                            &mut glyph_cache,
-                            c.transform
-                                .trans(offset_x + rank_corner.0, offset_y + rank_corner.1),
-                            gl,
-                        )
-                        .unwrap();
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1)
+                }
+            }
+            return;
+        }
+        Some(Command::Tree { pgn, depth }) => {
+            match pgn::read_games(pgn) {
+                Ok(games) => tree::Tree::build(&games, *depth).print(),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1)
+                }
+            }
+            return;
+        }
+        Some(Command::BuildBook {
+            pgn,
+            out,
+            min_games,
+        }) => {
+            if let Err(e) = book::run(pgn, out, *min_games) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Transpositions { pgn1, pgn2 }) => {
+            if let Err(e) = transpositions::run(pgn1, pgn2) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::ConvertMove { fen, moves, to_san }) => {
+            let moves: Vec<String> = moves
+                .iter()
+                .map(|mv| lang::delocalize(mv, args.lang))
+                .collect();
+            match san::convert(fen, &moves, *to_san) {
+                Ok(converted) => println!(
+                    "{}",
+                    notation(args.figurine, args.lang, &converted.join(" "))
+                ),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1)
+                }
+            }
+            return;
+        }
+        Some(Command::Frc { position }) => {
+            let converted = match position.parse::<u32>() {
+                Ok(n) => frc::fen(n),
+                Err(_) if position == "random" => {
+                    let n = random(&args).below(960) as u32;
+                    frc::fen(n).map(|fen| format!("{}\n{}", n, fen))
+                }
+                Err(_) => frc::index(position).map(|n| n.to_string()),
+            };
+            match converted {
+                Ok(converted) => println!("{}", converted),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1)
+                }
+            }
+            return;
+        }
+        Some(Command::Path {
+            fen,
+            piece,
+            target,
+            safe,
+        }) => {
+            show_path(&args, fen, piece, target, *safe);
+            return;
+        }
+        Some(Command::Bench { bench }) => {
+            if let Err(e) = bench::run(*bench) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Mate { fen, max }) => {
+            if let Err(e) = mate::run(fen, *max) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Play { tc, side, from }) => {
+            play_engine(&args, tc, side, from);
+            return;
+        }
+        Some(Command::Tournament {
+            engines,
+            openings,
+            games,
+            gauntlet,
+            max_moves,
+            sprt,
+            alpha,
+            beta,
+            json,
+        }) => {
+            let sprt = match sprt[..] {
+                [] => None,
+                [elo0, elo1] if elo0 < elo1 && *alpha > 0.0 && *beta > 0.0 => Some(results::Sprt {
+                    elo0,
+                    elo1,
+                    alpha: *alpha,
+                    beta: *beta,
+                }),
+                _ => {
+                    eprintln!("Error: Expected --sprt ELO0,ELO1 with ELO0 below ELO1");
+                    std::process::exit(1)
+                }
+            };
+            if sprt.is_some() && engines.len() != 2 {
+                eprintln!("Error: --sprt compares exactly two engines");
+                std::process::exit(1)
+            }
+            let opts = tournament::TournamentOptions {
+                games: *games,
+                movetime: args.movetime.unwrap_or(100),
+                max_moves: *max_moves,
+                gauntlet: *gauntlet,
+            };
+            run_tournament(&args, engines, openings, &opts, sprt, *json);
+            return;
+        }
+        Some(Command::Version { json }) => {
+            if *json {
+                println!("{}", version::to_json());
+            } else {
+                println!("{}", version::text());
+            }
+            return;
+        }
+        Some(Command::SelfUpdate { check }) => {
+            if let Err(e) = self_update(*check) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+            return;
+        }
+        Some(Command::Boards { fens, follow }) => {
+            show_boards(&args, fens, *follow || fens.is_empty());
+            return;
+        }
+        None if !args.more.is_empty() => {
+            let fens: Vec<String> = args
+                .fen
+                .iter()
+                .chain(&args.more)
+                .map(|input| match url::fen_from_url(input) {
+                    Some(Ok(fen)) => fen,
+                    Some(Err(e)) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1)
                     }
-                    // add the pieces
-                    piece = pieces[8 - _r][_f - 1].clone();
-                    if white_pieces.contains(&piece) {
-                        piece_col = [1.0, 1.0, 1.0, 1.0];
-                        // replace with corresponding white piece for fill
-                        tmp_piece = match &piece[..] {
-                            "♙" => "♟",
-                            "♘" => "♞",
-                            "♗" => "♝",
-                            "♖" => "♜",
-                            "♕" => "♛",
-                            "♔" => "♚",
-                            _ => "",
-                        };
-                        piece = tmp_piece.to_string();
-                    } else if black_pieces.contains(&piece) {
-                        piece_col = [0.0, 0.0, 0.0, 1.0]
-                    } else {
-                        piece_col = [1.0, 0.0, 0.0, 0.0]
-                    };
-                    text(
-                        piece_col,
-                        60_u32,
-                        &piece[..],
-                        &mut piece_glyph_cache,
-                        c.transform
-                            .trans(offset_x + (0.15 * (x / 8.0)), offset_y + (0.8 * (y / 8.0))),
-                        gl,
-                    )
-                    .unwrap();
-                    offset_x = offset_x + (x / 8.0);
+                    None => input.clone(),
+                })
+                .collect();
+            show_boards(&args, &fens, false);
+            return;
+        }
+        None if args.dgt.is_some() => {
+            follow_dgt(&args, args.dgt.as_deref().unwrap_or_default());
+            return;
+        }
+        None => match &args.from_image {
+            Some(path) => (fen_from_image(path, &args.recognizer), Vec::new()),
+            None => {
+                let input = args.fen.clone().unwrap_or_default();
+                match url::fen_from_url(&input) {
+                    Some(Ok(fen)) => (fen, Vec::new()),
+                    Some(Err(e)) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1)
+                    }
+                    None => (input, Vec::new()),
                 }
-                offset_x = 0.0;
-                offset_y = offset_y + (y / 8.0);
-                sq_col = if sq_col == LIGHTSQ { DARKSQ } else { LIGHTSQ };
             }
+        },
+    };
+
+    if let Some(letters) = args.piece_letters {
+        fen = lang::standard_fen(&fen, letters).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1)
         });
+        println!("{}", fen);
     }
-
-    #[allow(unused)]
-    fn update(&mut self, args: &UpdateArgs) {
-        // Rotate 2 radians per second.
-        // self.rotation += 2.0 * args.dt;
+    if args.lenient {
+        let (fixed, notes) = lenient::repair(&fen);
+        for note in notes {
+            eprintln!("Corrected: {}", note);
+        }
+        tracing::debug!(target: "fen::validate", before = %fen, after = %fixed, "lenient repair");
+        fen = fixed;
+    }
+    if args.echo {
+        echo::print(&fen);
     }
-}
 
-fn main() {
-    let args = Args::parse();
+    let mut badge = args.nag.as_ref().map(|n| {
+        nag::symbol(n).unwrap_or_else(|| {
+            eprintln!("Error: Unknown annotation {}", n);
+            std::process::exit(1)
+        })
+    });
+
+    let region = match &args.crop {
+        Some(text) => Region::parse(text).unwrap_or_else(|| {
+            eprintln!(
+                "Error: Expected two squares such as a1-d4 for --crop, found {}",
+                text
+            );
+            std::process::exit(1)
+        }),
+        None => Region::FULL,
+    };
+
+    // play out any requested moves, continuing with the resulting position
+    let mut repetitions = 1;
+    // the last move played and the position it was played from
+    let mut last_move = None;
+    // the game so far, continued in the window
+    let mut continued = None;
+    // the positions along the --pv, each with the move that reached it
+    let mut frames = Vec::new();
+    if !moves.is_empty() || args.play.is_some() || args.pv.is_some() || args.save_pgn.is_some() {
+        let start = Position::from_fen(&fen).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1)
+        });
+        let mut game = Game::new(start);
+        let mut pos = game.start.clone();
+        let mut keys = vec![zobrist::hash(&pos)];
+        let played = args.play.iter().flat_map(|m| m.split_whitespace());
+        let pv = args.pv.iter().flat_map(|m| m.split_whitespace());
+        let tokens = moves.iter().map(String::as_str).chain(played);
+        let mut pv_plies = 0;
+        for (token, in_pv) in tokens.map(|t| (t, false)).chain(pv.map(|t| (t, true))) {
+            // skip move numbers and results in pasted movetext
+            if token.ends_with('.') || ["1-0", "0-1", "1/2-1/2", "*"].contains(&token) {
+                continue;
+            }
+            // annotations of the final move badge the diagram unless --nag is given
+            if token.starts_with('$') {
+                if args.nag.is_none() {
+                    badge = nag::symbol(token);
+                }
+                continue;
+            }
+            let token = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+            let (token, suffix) = nag::split_suffix(token);
+            let token = &lang::delocalize(token, args.lang);
+            let parsed = san::parse_move(&pos, token)
+                .or_else(|e| terminal::ask_promotion(&san::promotions(&pos, token)).ok_or(e));
+            match parsed {
+                Ok(mv) => {
+                    let number = match pos.side {
+                        position::Color::White => format!("{}.", pos.fullmove),
+                        position::Color::Black => format!("{}...", pos.fullmove),
+                    };
+                    let san = san::to_san(&pos, mv);
+                    last_move = Some((pos.clone(), mv));
+                    game.moves.push(mv);
+                    pos = pos.make_move(mv);
+                    keys.push(zobrist::hash(&pos));
+                    repetitions = pgn::occurrences(&keys);
+                    if repetitions == 3 || repetitions == 5 {
+                        eprintln!(
+                            "Warning: the position after {} {} has occurred {} times",
+                            number, san, repetitions
+                        );
+                    }
+                    if args.nag.is_none() {
+                        badge = suffix.and_then(nag::symbol);
+                    }
+                    if in_pv {
+                        pv_plies += 1;
+                        frames.push((format!("{} {}", number, san), mv, pos.clone()));
+                    }
+                }
+                Err(e) if in_pv => {
+                    eprintln!("Error: move {} of the --pv: {}", pv_plies + 1, e);
+                    std::process::exit(1)
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1)
+                }
+            }
+        }
+        game.conclude();
+        if let Some(path) = &args.save_pgn {
+            if let Err(e) = std::fs::write(path, pgn_text(args.figurine_pgn, &game)) {
+                eprintln!("Error: Could not write {}: {}", path, e);
+                std::process::exit(1)
+            }
+        }
+        fen = pos.to_fen();
+        continued = Some(game);
+    }
 
-    let fenvec: Vec<String> = args.fen.split_whitespace().map(str::to_string).collect();
+    let fenvec: Vec<String> = fen.split_whitespace().map(str::to_string).collect();
 
     // starting FEN: rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1
     // has 6 parts
+    tracing::debug!(target: "fen::validate", fields = fenvec.len(), "checking the field count");
     if fenvec.len() != 6 {
         eprintln!("Error: FEN does not contain 6 elements");
         eprintln!("Example FEN: rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
-        if fenvec.len() < 1 {
+        if fenvec.is_empty() {
             std::process::exit(1)
         };
     }
 
     // process layout
     let legal_chars = "KQBNRPkqbnrp12345678/".chars().collect::<Vec<_>>();
-    if fenvec.len() > 0 {
-        if !fenvec[0].chars().all(|s| legal_chars.contains(&s)) {
-            eprintln!("Error: Unexpected symbol in layout string {}", fenvec[0]);
-            std::process::exit(1)
-        }
+    let layout_ok = fenvec.is_empty() || fenvec[0].chars().all(|s| legal_chars.contains(&s));
+    tracing::debug!(target: "fen::validate", layout_ok, "checked the layout symbols");
+    if !layout_ok {
+        eprintln!("Error: Unexpected symbol in layout string {}", fenvec[0]);
+        std::process::exit(1)
     }
 
     // process nextmove
     if fenvec.len() > 1 && args.info {
         match fenvec[1].as_str() {
-            "w" => println!("{}", "White to move"),
-            "b" => println!("{}", "Black to move"),
+            "w" => println!("{}", args.lang.to_move(Color::White)),
+            "b" => println!("{}", args.lang.to_move(Color::Black)),
             _ => eprintln!("Error: Expected 'w' or 'b' in second element"),
         }
     }
@@ -192,19 +1391,27 @@ fn main() {
     // process castling rights
     if fenvec.len() > 2 {
         if fenvec[2].as_str() == "-" && args.info {
-            println!("{}", "Neither side can castle");
+            println!("{}", args.lang.no_castling());
         } else if args.info {
-            if fenvec[2].find('K').is_some() {
-                println!("{}", "White can castle kingside");
-            }
-            if fenvec[2].find('Q').is_some() {
-                println!("{}", "White can castle queenside");
-            }
-            if fenvec[2].find('k').is_some() {
-                println!("{}", "Black can castle kingside");
-            }
-            if fenvec[2].find('q').is_some() {
-                println!("{}", "Black can castle queenside");
+            // whether each right held can be used on the board as it stands,
+            // this move by the side to move and only later by the other
+            let pos = Position::from_fen(&fen).ok();
+            for (letter, color, kingside) in [
+                ('K', Color::White, true),
+                ('Q', Color::White, false),
+                ('k', Color::Black, true),
+                ('q', Color::Black, false),
+            ] {
+                if !fenvec[2].contains(letter) {
+                    continue;
+                }
+                let now = pos
+                    .as_ref()
+                    .map(|pos| match pos.castling_obstacle(color, kingside) {
+                        Some(reason) => Err(reason),
+                        None => Ok(pos.side == color),
+                    });
+                println!("{}", args.lang.castling(color, kingside, now));
             }
         }
         if fenvec[2].find(['-', 'K', 'Q', 'k', 'q']).is_none() {
@@ -214,7 +1421,7 @@ fn main() {
         }
         if !fenvec[2]
             .chars()
-            .all(|s| vec!['-', 'K', 'Q', 'k', 'q'].contains(&s))
+            .all(|s| ['-', 'K', 'Q', 'k', 'q'].contains(&s))
         {
             eprintln!("Error: Unexpected symbol in third element (castling rights)")
         }
@@ -222,105 +1429,1272 @@ fn main() {
 
     // process en-passant
     if fenvec.len() > 3 && args.info {
-        if fenvec[3].as_str() == "-" {
-            println!("{}", "No en-passant target square is available")
+        let target = Some(fenvec[3].as_str()).filter(|&sq| sq != "-");
+        println!("{}", args.lang.en_passant(target));
+    }
+
+    // reasons the position cannot come from a legal game
+    if args.retro {
+        if let Ok(pos) = Position::from_fen(&fen) {
+            let problems = retro::problems(&pos);
+            tracing::debug!(target: "fen::validate", problems = problems.len(), "retro analysis");
+            if problems.is_empty() {
+                println!("No check rules out reaching this position in a legal game");
+            }
+            for problem in problems {
+                println!("Illegal: {}", problem);
+            }
+        }
+    }
+
+    // statistics from a games database
+    if let Some(path) = args.games.as_ref().filter(|_| args.info) {
+        match pgn::read_games(path).and_then(|games| Ok((games, Position::from_fen(&fen)?))) {
+            Ok((games, pos)) => println!(
+                "{}",
+                tree::summary(&tree::Tree::build(&games, usize::MAX), &pos)
+            ),
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+
+    // repetitions of the final position during --play
+    if let Some(note) = pgn::repetition_note(repetitions).filter(|_| args.info) {
+        println!("{}", note);
+    }
+
+    // evaluate with an engine, reported from White's point of view
+    let mut eval = None;
+    if let Some(path) = &args.engine {
+        let multipv = args.multipv.unwrap_or(1);
+        match evaluate(path, &fen, args.movetime.unwrap_or(1000), multipv) {
+            Ok(lines) => {
+                if let Some(best) = lines.first() {
+                    if args.info {
+                        println!("Evaluation: {}", best.score);
+                    }
+                    eval = Some(best.score.bar_share());
+                }
+                if args.multipv.is_some() {
+                    print_lines(&fen, &lines, args.figurine, args.lang);
+                }
+            }
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+    if let Some(path) = args.engine.as_ref().filter(|_| args.null_move_compare) {
+        if let Err(e) = compare_null_move(path, &fen, args.movetime.unwrap_or(1000)) {
+            eprintln!("Error: {}", e);
+        }
+    }
+    if args.analyze {
+        match analyze(&fen, args.depth) {
+            Ok(line) => {
+                eval = Some(line.score.bar_share());
+                print_lines(&fen, &[line], args.figurine, args.lang);
+            }
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+
+    // engine arrows, compared against the last played move if there is one
+    let mut arrows = Vec::new();
+    if args.annotate_best {
+        match &args.engine {
+            Some(path) => {
+                let before = match &last_move {
+                    Some((before, mv)) => Ok((before.clone(), Some(*mv))),
+                    None => Position::from_fen(&fen).map(|pos| (pos, None)),
+                };
+                let found = before.and_then(|(before, played)| {
+                    let mut engine = uci::Engine::spawn(path)?;
+                    let movetime = args.movetime.unwrap_or(1000);
+                    annotate::best_move_arrows(
+                        &mut engine,
+                        &before,
+                        played,
+                        movetime,
+                        args.info,
+                        &term.palette,
+                    )
+                });
+                match found {
+                    Ok(found) => arrows = found,
+                    Err(e) => eprintln!("Error: {}", e),
+                }
+            }
+            None => eprintln!("Error: --annotate-best needs an engine given with --engine"),
+        }
+    }
+
+    // squares tinted on exported diagrams
+    let mut highlights = Vec::new();
+    if args.passers {
+        match Position::from_fen(&fen) {
+            Ok(pos) => {
+                let found = passers::find(&pos);
+                if found.is_empty() {
+                    println!("No passed pawns");
+                }
+                for passer in found {
+                    println!("{}", passer.description);
+                    arrows.push((passer.pawn, passer.promotion, term.palette.best));
+                    highlights.extend(passer.region);
+                }
+            }
+            Err(e) => eprintln!("Error: Cannot find passed pawns: {}", e),
+        }
+    }
+
+    if args.xrays {
+        match Position::from_fen(&fen) {
+            Ok(pos) => {
+                let found = xray::find(&pos);
+                if found.is_empty() {
+                    println!("No x-rays or batteries");
+                }
+                for alignment in found {
+                    let (label, colour) = match alignment.kind {
+                        xray::Kind::XRay => ("X-ray", term.palette.played),
+                        xray::Kind::Battery => ("Battery", term.palette.best),
+                    };
+                    println!("{}: {}", label, alignment.description);
+                    if let ([from, ..], Some(&to)) =
+                        (alignment.squares.as_slice(), alignment.squares.last())
+                    {
+                        if args.xray_arrows {
+                            arrows.push((*from, to, colour));
+                        }
+                    }
+                }
+            }
+            Err(e) => eprintln!("Error: Cannot find x-rays: {}", e),
+        }
+    }
+
+    // the layers saved for the position in the --annotations file
+    let mut caption = None;
+    if let Some(path) = &args.annotations {
+        match annotations::load(path, &fen, &term.palette) {
+            Ok(layer) => {
+                arrows.extend(layer.arrows);
+                highlights.extend(layer.highlights);
+                caption = layer.caption;
+            }
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+
+    if args.candidates {
+        match Position::from_fen(&fen) {
+            Ok(pos) => candidates::print(&pos, args.figurine),
+            Err(e) => eprintln!("Error: Cannot list candidate moves: {}", e),
+        }
+    }
+
+    let fentranslated = board_glyphs(&fenvec[0]);
+
+    // remember valid positions for `history` when someone is looking at
+    // them, not for every board a script renders; a store that cannot be
+    // written should not get in the way of showing the board
+    let viewed = args.window || io::stdout().is_terminal();
+    let opted_out = std::env::var_os("FEN_NO_HISTORY").is_some_and(|v| !v.is_empty());
+    if viewed && !opted_out && Position::from_fen(&fen).is_ok() {
+        if let Ok(mut store) = store::Store::load() {
+            store.record_view(&fen);
+            let _ = store.save();
+        }
+    }
+
+    // print board in terminal
+    if let Some(format) = args.format {
+        match Position::from_fen(&fen) {
+            Ok(pos) => print!("{}", export::export(&pos, format)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+        }
+    } else if let Some(path) = &args.template {
+        let filled = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path, e))
+            .and_then(|text| {
+                let pos = Position::from_fen(&fen)?;
+                template::render(&text, &template::data(&pos))
+            });
+        match filled {
+            Ok(text) => print!("{}", text),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1)
+            }
+        }
+    } else if args.blindfold {
+        print_board(&vec![vec!["·".to_string(); 8]; 8], &term);
+    } else {
+        print_board(&fentranslated, &term);
+    }
+    if let Some(caption) = caption
+        .as_ref()
+        .filter(|_| args.format.is_none() && args.template.is_none())
+    {
+        println!("{}", caption);
+    }
+
+    if let Some(site) = args.url {
+        let link = url::analysis_url(site, &fen);
+        println!("{}", link);
+        if args.open {
+            if let Err(e) = url::open(&link) {
+                eprintln!("Error: {}", e);
+            }
+        }
+    }
+
+    let qr = args.qr.as_ref().and_then(|path| {
+        let payload = if args.qr_url {
+            url::analysis_url(url::Site::Lichess, &fen)
         } else {
-            println!("En-passant target square is {}", fenvec[3])
+            fen.clone()
+        };
+        match qr::QrCode::encode(payload.as_bytes()) {
+            Ok(code) => Some((path, code)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                None
+            }
         }
+    });
+    if let Some((_, code)) = qr.as_ref().filter(|(path, _)| path.as_str() == "-") {
+        print!("{}", code.to_terminal(term.charset));
     }
 
-    // split at separators
-    let fenarray: Vec<String> = fenvec[0].split("/").map(str::to_string).collect();
+    // export diagrams
+    let qr_file = qr.as_ref().filter(|(path, _)| path.as_str() != "-");
+    if args.svg.is_some() || args.png.is_some() || args.pdf.is_some() || qr_file.is_some() {
+        match Position::from_fen(&fen) {
+            Ok(pos) => {
+                let mut diagram = diagram::board(
+                    &pos,
+                    &DiagramOptions {
+                        eval,
+                        badge,
+                        pieces: !args.blindfold,
+                        region,
+                        labels: if args.label_candidates {
+                            candidates::target_labels(&pos)
+                        } else {
+                            Vec::new()
+                        },
+                        arrows: arrows.clone(),
+                        highlights: highlights.clone(),
+                        ..diagram_options(&args)
+                    },
+                );
+                if let Some(caption) = &caption {
+                    diagram::add_caption(&mut diagram, caption);
+                }
+                if let Some(path) = &args.svg {
+                    if let Err(e) = std::fs::write(path, svg_text(args.svg_optimize, &diagram)) {
+                        eprintln!("Error: Could not write {}: {}", path, e);
+                    }
+                }
+                let piece_font = piece_font(&args);
+                if let Some(path) = &args.png {
+                    if let Err(e) = png::save(&diagram, path, &piece_font, args.quality) {
+                        eprintln!("Error: {}", e);
+                    }
+                }
+                if let Some(path) = &args.pdf {
+                    let layout = pdf_layout(&args, 1);
+                    if let Err(e) =
+                        pdf::save(std::slice::from_ref(&diagram), path, &piece_font, layout)
+                    {
+                        eprintln!("Error: {}", e);
+                    }
+                }
+                if let Some((path, code)) = qr_file {
+                    let mut diagram = diagram;
+                    diagram::add_qr(&mut diagram, code);
+                    if let Err(e) = png::save(&diagram, path, &piece_font, args.quality) {
+                        eprintln!("Error: {}", e);
+                    }
+                }
+            }
+            Err(e) => eprintln!("Error: Cannot export diagram: {}", e),
+        }
+    }
 
-    // translate to glyphs
-    let mut fentranslated: Vec<Vec<String>> = Vec::with_capacity(64);
-    for rank in fenarray {
-        let mut rankchars: Vec<String> = Vec::new();
-        for chars in rank.split_inclusive("").filter(|&x| !x.is_empty()) {
-            let t: String = translate_piece(chars).to_string().split("").collect();
-            let i = t.parse::<i32>();
-            let tvec: Vec<String> = match i {
-                Ok(v) => std::iter::repeat(String::from(""))
-                    .take(v as usize)
-                    .collect(),
-                Err(_) => vec![t],
+    // the positions along the --pv, with an arrow for the move reaching each
+    match args.filmstrip.as_deref() {
+        Some("-") => {
+            let boards: Vec<(String, Vec<Vec<String>>)> = frames
+                .iter()
+                .map(|(caption, _, pos)| (caption.clone(), board_glyphs(&pos.placement())))
+                .collect();
+            terminal::print_strip(&boards, &term);
+        }
+        Some(path) => {
+            let base = diagram_options(&args);
+            let boards: Vec<(String, diagram::Diagram)> = frames
+                .iter()
+                .map(|(caption, mv, pos)| {
+                    let opts = DiagramOptions {
+                        layout: Layout {
+                            square: args.square_px.map_or(40.0, f64::from),
+                            ..base.layout
+                        },
+                        arrows: vec![(mv.from, mv.to, term.palette.played)],
+                        index: None,
+                        ..base.clone()
+                    };
+                    (caption.clone(), diagram::board(pos, &opts))
+                })
+                .collect();
+            let written = if path.ends_with(".pdf") {
+                let pages: Vec<diagram::Diagram> = boards
+                    .into_iter()
+                    .map(|(caption, mut board)| {
+                        diagram::add_caption(&mut board, &caption);
+                        board
+                    })
+                    .collect();
+                pdf::save(&pages, path, &piece_font(&args), pdf_layout(&args, 6))
+            } else if path.ends_with(".svg") {
+                std::fs::write(
+                    path,
+                    svg_text(args.svg_optimize, &diagram::filmstrip(&boards)),
+                )
+                .map_err(|e| format!("Could not write {}: {}", path, e))
+            } else {
+                let piece_font = piece_font(&args);
+                png::save(
+                    &diagram::filmstrip(&boards),
+                    path,
+                    &piece_font,
+                    args.quality,
+                )
             };
-            for el in tvec {
-                rankchars.push(el);
+            if let Err(e) = written {
+                eprintln!("Error: {}", e);
             }
         }
-        fentranslated.push(rankchars);
+        None => {}
     }
 
-    let mut grid = Grid::new(GridOptions {
-        filling: Filling::Spaces(1),
-        direction: Direction::LeftToRight,
+    // spawn graphical window and show pieces, playable unless it takes
+    // its positions from a socket
+    let playable = match continued {
+        Some(game) => Some(game),
+        None => Position::from_fen(&fen).ok().map(Game::new),
+    };
+    if let Some(game) = playable.filter(|_| args.window && args.listen.is_none()) {
+        // the engine plays a side with --vs-engine, and otherwise only gives hints
+        let opponent = args.engine.as_ref().map(|path| {
+            let side = args
+                .vs_engine
+                .then(|| game.final_position().side.opponent());
+            let movetime = args.movetime.unwrap_or(1000);
+            uci::Engine::spawn(path)
+                .and_then(|engine| gui::Opponent::spawn(engine, side, movetime))
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1)
+                })
+        });
+        gui::run(
+            game,
+            args.save_pgn.as_deref(),
+            opponent,
+            WindowOptions {
+                eval,
+                arrows,
+                ..window_options(&args)
+            },
+        );
+    } else if args.window || args.listen.is_some() {
+        // the socket is removed once the window has closed and it is dropped
+        let (socket, updates) = match &args.listen {
+            Some(path) => {
+                let (sender, receiver) = mpsc::channel();
+                let socket = listen::spawn(path, sender).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1)
+                });
+                (Some(socket), Some(receiver))
+            }
+            None => (None, None),
+        };
+        window::show(
+            fentranslated,
+            updates,
+            WindowOptions {
+                eval,
+                arrows,
+                title: Position::from_fen(&fen)
+                    .map(|pos| window::summary(&pos))
+                    .unwrap_or_default(),
+                ..window_options(&args)
+            },
+        );
+        drop(socket);
+    }
+}
+
+/// Save `fen`, or the position viewed last, under `name` with `tags`.
+fn save_position(name: &str, fen: Option<&str>, tags: &[String]) -> Result<(), String> {
+    let mut store = store::Store::load()?;
+    let fen = match fen {
+        Some(fen) => Position::from_fen(fen)?.to_fen(),
+        None => store
+            .history
+            .last()
+            .map(|v| v.fen.clone())
+            .ok_or("No FEN given and no position viewed yet")?,
+    };
+    store.put(store::Saved {
+        name: name.to_string(),
+        fen: fen.clone(),
+        saved: store::now(),
+        tags: tags
+            .iter()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect(),
     });
+    store.save()?;
+    println!("Saved {} as {}", fen, name);
+    Ok(())
+}
 
-    for s in fentranslated.concat() {
-        grid.add(Cell::from(s.to_string()));
+/// Print the saved positions having every tag in `tags` and matching the
+/// material and side to move, if given.
+fn search_positions(
+    tags: &[String],
+    material: Option<&str>,
+    to_move: Option<&str>,
+) -> Result<(), String> {
+    let store = store::Store::load()?;
+    let mut found = 0;
+    for saved in &store.positions {
+        if !tags.iter().all(|t| saved.tags.contains(t)) {
+            continue;
+        }
+        let pos = match Position::from_fen(&saved.fen) {
+            Ok(pos) => pos,
+            Err(_) => continue,
+        };
+        if let Some(pattern) = material {
+            if !stats::material_matches(&pos, pattern)? {
+                continue;
+            }
+        }
+        let side = match pos.side {
+            position::Color::White => "white",
+            position::Color::Black => "black",
+        };
+        if to_move.is_some_and(|s| s != side) {
+            continue;
+        }
+        found += 1;
+        let tags = if saved.tags.is_empty() {
+            String::new()
+        } else {
+            format!("  [{}]", saved.tags.join(", "))
+        };
+        println!("{}  {}{}", saved.name, saved.fen, tags);
+    }
+    if found == 0 {
+        println!("No saved positions match");
     }
+    Ok(())
+}
 
-    // print board in terminal
-    println!("\n{}", grid.fit_into_columns(8));
+/// `text` with figurines for the pieces of its moves when `figurine`, or
+/// else the piece letters of `lang`
+fn notation(figurine: bool, lang: lang::Lang, text: &str) -> String {
+    if figurine {
+        san::figurine(text)
+    } else {
+        lang::localize(text, lang)
+    }
+}
+
+/// the game as PGN, with figurines when --figurine-pgn asks for them
+fn pgn_text(figurine: bool, game: &pgn::Game) -> String {
+    if figurine {
+        game.to_figurine_pgn()
+    } else {
+        game.to_pgn()
+    }
+}
+
+/// how pieces are drawn: with the --piece-set, or else the --piece-font
+fn piece_font(args: &Args) -> fonts::PieceFont {
+    match &args.piece_set {
+        Some(name) => fonts::piece_set(name),
+        None => fonts::resolve(args.piece_font.as_deref()),
+    }
+}
+
+/// Print the installed piece sets, each with its author and any reason it
+/// cannot be used.
+fn list_piece_sets() {
+    let sets = pieceset::installed().unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1)
+    });
+    let dir = pieceset::sets_dir().map_or(String::new(), |d| d.display().to_string());
+    if sets.is_empty() {
+        println!("No piece sets are installed in {}", dir);
+    }
+    for set in sets {
+        let mut line = set.name.clone();
+        if let Some(title) = set.title.filter(|t| *t != set.name) {
+            line.push_str(&format!(" ({})", title));
+        }
+        if let Some(author) = set.author {
+            line.push_str(&format!(" by {}", author));
+        }
+        if let Some(problem) = set.problem {
+            line.push_str(&format!(": unusable, {}", problem));
+        }
+        println!("{}", line);
+    }
+}
+
+/// the diagram as SVG, compacted when --svg-optimize asks for it
+fn svg_text(optimize: bool, diagram: &diagram::Diagram) -> String {
+    if optimize {
+        svg::to_optimized_svg(diagram)
+    } else {
+        svg::to_svg(diagram)
+    }
+}
+
+/// How PDF exports lay out their boards, `per_page` of them unless
+/// `--per-page` says otherwise.
+fn pdf_layout(args: &Args, per_page: usize) -> pdf::Layout {
+    pdf::Layout {
+        page: args.page_size,
+        per_page: args.per_page.map_or(per_page, |n| n as usize),
+    }
+}
+
+/// the random number generator, from `--seed` if given
+fn random(args: &Args) -> rng::Rng {
+    args.seed
+        .map(rng::Rng::new)
+        .unwrap_or_else(rng::Rng::from_time)
+}
+
+/// the --palette, or the --style's when it is left as the default
+fn palette(args: &Args) -> Palette {
+    let name = match args.style {
+        Some(style) if args.palette == PaletteName::Default => style.preset().palette,
+        _ => args.palette,
+    };
+    Palette::new(name, args.high_contrast)
+}
+
+/// `base` with the sizes given by --square-px, --margin and --border-width
+fn layout(args: &Args, base: Layout) -> Layout {
+    Layout {
+        square: args.square_px.map_or(base.square, f64::from),
+        margin: args.margin.map_or(base.margin, f64::from),
+        border: args.border_width.map_or(base.border, f64::from),
+    }
+}
+
+/// diagram settings shared by every command that exports one
+fn diagram_options(args: &Args) -> DiagramOptions {
+    let preset = args.style.map(style::Style::preset).unwrap_or_default();
+    DiagramOptions {
+        layout: layout(args, preset.layout),
+        coordinates: preset.coordinates,
+        palette: palette(args),
+        shadows: preset.shadows || args.quality == Quality::High,
+        index: args.index_overlay,
+        ..DiagramOptions::default()
+    }
+}
+
+fn terminal_options(args: &Args) -> TerminalOptions {
+    let caps = capabilities::probe();
+    TerminalOptions {
+        charset: args.charset.unwrap_or_else(Charset::detect),
+        placeholder: args.placeholder.clone().unwrap_or_default(),
+        big: args.big,
+        bigascii: args.bigascii,
+        color: args.color_depth.unwrap_or(caps.color),
+        images: args.image_protocol.unwrap_or(caps.images),
+        palette: palette(args),
+        index: args.index_overlay,
+    }
+}
+
+/// window settings shared by every command that opens one
+fn window_options(args: &Args) -> WindowOptions {
+    WindowOptions {
+        blindfold: args.blindfold,
+        piece_font: piece_font(args),
+        palette: palette(args),
+        style: args.board_style,
+        quality: args.quality,
+        on_top: args.on_top,
+        kiosk: args.kiosk,
+        index: args.index_overlay,
+        fps: args.fps,
+        vsync: args.vsync,
+        layout: layout(args, Layout::default()),
+        ..WindowOptions::default()
+    }
+}
+
+/// The engine's top `multipv` lines for `fen`, with scores converted to
+/// White's point of view. Positions without legal moves have no lines.
+fn evaluate(
+    path: &str,
+    fen: &str,
+    movetime: u64,
+    multipv: usize,
+) -> Result<Vec<uci::Line>, String> {
+    let mut engine = multipv_engine(path, multipv)?;
+    engine_lines(&mut engine, fen, movetime)
+}
+
+// the engine at `path`, set to report its top `multipv` lines
+fn multipv_engine(path: &str, multipv: usize) -> Result<uci::Engine, String> {
+    let mut engine = uci::Engine::spawn(path)?;
+    if multipv > 1 {
+        engine.set_option("MultiPV", &multipv.to_string())?;
+    }
+    Ok(engine)
+}
+
+// `engine`'s lines for `fen`, as `evaluate` gives them
+fn engine_lines(
+    engine: &mut uci::Engine,
+    fen: &str,
+    movetime: u64,
+) -> Result<Vec<uci::Line>, String> {
+    let pos = Position::from_fen(fen)?;
+    let analysis = engine.analyse(fen, &[], &format!("movetime {}", movetime))?;
+    Ok(analysis
+        .lines
+        .into_iter()
+        .map(|mut line| {
+            if pos.side == position::Color::Black {
+                line.score = line.score.negate();
+            }
+            line
+        })
+        .collect())
+}
+
+/// Evaluate `fen` as given and with the side to move passing, then report
+/// the difference from the side to move's point of view: a move that is
+/// worth less than nothing means zugzwang.
+fn compare_null_move(path: &str, fen: &str, movetime: u64) -> Result<(), String> {
+    let pos = Position::from_fen(fen)?;
+    if pos.in_check() {
+        return Err("The side to move is in check, so cannot pass".to_string());
+    }
+    let mut passed = pos.clone();
+    passed.side = pos.side.opponent();
+    passed.ep = None;
+    let name = |color: Color| match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    };
+    // both scores from the point of view of the side to move in `fen`
+    let score = |fen: &str| -> Result<uci::Score, String> {
+        let line = evaluate(path, fen, movetime, 1)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("No evaluation of {}", fen))?;
+        Ok(match pos.side {
+            Color::White => line.score,
+            Color::Black => line.score.negate(),
+        })
+    };
+    let with_move = score(fen)?;
+    let without = score(&passed.to_fen())?;
+    let mover = name(pos.side);
+    println!("Evaluation with {} to move: {}", mover, with_move);
+    println!(
+        "Evaluation with the move passed to {}: {}",
+        name(passed.side),
+        without
+    );
+    match (with_move, without) {
+        (uci::Score::Centipawns(a), uci::Score::Centipawns(b)) if a >= b => println!(
+            "The move is worth {:.2} to {}",
+            (a - b) as f64 / 100.0,
+            mover
+        ),
+        (uci::Score::Centipawns(a), uci::Score::Centipawns(b)) => println!(
+            "{} is in zugzwang: having the move costs {:.2}",
+            mover,
+            (b - a) as f64 / 100.0
+        ),
+        (a, b) if a.centipawns() < b.centipawns() => {
+            println!("{} is in zugzwang: passing would be better", mover)
+        }
+        _ => println!("{} would rather have the move", mover),
+    }
+    Ok(())
+}
+
+/// The built-in engine's line for `fen`, with its score converted to
+/// White's point of view.
+#[cfg(feature = "engine")]
+fn analyze(fen: &str, depth: u32) -> Result<uci::Line, String> {
+    let pos = Position::from_fen(fen)?;
+    if pos.legal_moves().is_empty() {
+        return Err("No legal moves to analyse".to_string());
+    }
+    let (mut line, nodes) = search::analyse(&pos, depth);
+    if pos.side == position::Color::Black {
+        line.score = line.score.negate();
+    }
+    println!(
+        "Evaluation: {} (toy engine, depth {}, {} nodes)",
+        line.score, line.depth, nodes
+    );
+    Ok(line)
+}
+
+#[cfg(not(feature = "engine"))]
+fn analyze(_fen: &str, _depth: u32) -> Result<uci::Line, String> {
+    Err("--analyze needs the built-in engine; rebuild with --features engine".to_string())
+}
+
+#[cfg(feature = "self-update")]
+fn self_update(check: bool) -> Result<(), String> {
+    update::run(check)
+}
+
+#[cfg(not(feature = "self-update"))]
+fn self_update(_check: bool) -> Result<(), String> {
+    Err("self-update is not built in; rebuild with --features self-update".to_string())
+}
+
+/// print a table of engine lines with their first few moves in SAN
+fn print_lines(fen: &str, lines: &[uci::Line], figurine: bool, lang: lang::Lang) {
+    if let Ok(pos) = Position::from_fen(fen) {
+        println!("\n{}", lines_table(&pos, lines, figurine, lang));
+    }
+}
 
-    // spawn graphical window and show pieces
+// the table `print_lines` prints, one line per engine line
+fn lines_table(pos: &Position, lines: &[uci::Line], figurine: bool, lang: lang::Lang) -> String {
+    let mut table = format!("{:>2}  {:>6}  {:>5}  Line", "#", "Score", "Depth");
+    for line in lines {
+        table.push_str(&format!(
+            "\n{:>2}  {:>6}  {:>5}  {}",
+            line.multipv,
+            line.score.to_string(),
+            line.depth,
+            notation(figurine, lang, &san::format_line(pos, &line.pv, 8))
+        ));
+    }
+    table
+}
+
+/// With --engine and --multipv, the analysis table to show under each
+/// position of a live screen, from one engine kept running for them all.
+/// Tables are remembered, so a position stepped back to is not searched
+/// again; without both flags there are none.
+fn live_lines(args: &Args) -> impl FnMut(&Position) -> Option<String> + Send + 'static {
+    let mut engine = match (&args.engine, args.multipv) {
+        (Some(path), Some(multipv)) => Some(multipv_engine(path, multipv).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1)
+        })),
+        _ => None,
+    };
+    let movetime = args.movetime.unwrap_or(1000);
+    let (figurine, lang) = (args.figurine, args.lang);
+    let mut tables: HashMap<String, String> = HashMap::new();
+    move |pos| {
+        let engine = engine.as_mut()?;
+        let fen = pos.to_fen();
+        if let Some(table) = tables.get(&fen) {
+            return Some(table.clone());
+        }
+        let table = match engine_lines(engine, &fen, movetime) {
+            Ok(lines) if lines.is_empty() => return None,
+            Ok(lines) => lines_table(pos, &lines, figurine, lang),
+            Err(e) => return Some(format!("Warning: {}", e)),
+        };
+        tables.insert(fen, table.clone());
+        Some(table)
+    }
+}
+
+/// Follow a DGT board, printing the FEN and board (and the engine's lines,
+/// under --multipv) on each change and updating the window when one is
+/// open.
+fn follow_dgt(args: &Args, port: &str) {
+    let term = terminal_options(args);
+    let (sender, receiver) = mpsc::channel();
+    let port = port.to_string();
+    let mut lines = live_lines(args);
+    let mut follow = move || {
+        let mut screen = terminal::Screen::new();
+        let result = dgt::follow(&port, |pos| {
+            screen.text(&pos.to_fen());
+            screen.board(&board_glyphs(&pos.placement()), &term);
+            if let Some(table) = lines(pos) {
+                screen.text(&table);
+            }
+            screen.show();
+            let _ = sender.send(window::Update::Position(pos.clone()));
+        });
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(1)
+        }
+    };
+
+    // the window must own the main thread, so the board is read in the background
+    if args.window {
+        thread::spawn(follow);
+        window::show(
+            board_glyphs("8/8/8/8/8/8/8/8"),
+            Some(receiver),
+            window_options(args),
+        );
+    } else {
+        follow();
+    }
+}
+
+/// Print the shortest route for `piece` to `target` and draw it as
+/// numbered arrows in any diagrams or window asked for.
+fn show_path(args: &Args, fen: &str, piece: &str, target: &str, safe: bool) {
+    let fail = |e: String| -> ! {
+        eprintln!("Error: {}", e);
+        std::process::exit(1)
+    };
+    let pos = Position::from_fen(fen).unwrap_or_else(|e| fail(e));
+    let route = match path::find(&pos, piece, target, safe) {
+        Ok(Some(route)) => route,
+        Ok(None) => fail(format!("{} cannot reach {}", piece, target)),
+        Err(e) => fail(e),
+    };
+    let term = terminal_options(args);
+    print_board(&board_glyphs(&pos.placement()), &term);
+    let moves: Vec<String> = route
+        .iter()
+        .enumerate()
+        .map(|(i, (from, mv))| {
+            format!(
+                "{}. {}",
+                i + 1,
+                notation(args.figurine, args.lang, &san::to_san(from, *mv))
+            )
+        })
+        .collect();
+    println!(
+        "{} reaches {} in {} move{}: {}",
+        piece,
+        target,
+        route.len(),
+        if route.len() == 1 { "" } else { "s" },
+        moves.join(" ")
+    );
+
+    let arrows: Vec<_> = route
+        .iter()
+        .map(|(_, mv)| (mv.from, mv.to, term.palette.best))
+        .collect();
+    if args.svg.is_some() || args.png.is_some() {
+        let diagram = diagram::board(
+            &pos,
+            &DiagramOptions {
+                arrows: arrows.clone(),
+                labels: route
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (_, mv))| (mv.to, (i + 1).to_string()))
+                    .collect(),
+                ..diagram_options(args)
+            },
+        );
+        if let Some(path) = &args.svg {
+            if let Err(e) = std::fs::write(path, svg_text(args.svg_optimize, &diagram)) {
+                eprintln!("Error: Could not write {}: {}", path, e);
+            }
+        }
+        if let Some(path) = &args.png {
+            let piece_font = piece_font(args);
+            if let Err(e) = png::save(&diagram, path, &piece_font, args.quality) {
+                eprintln!("Error: {}", e);
+            }
+        }
+    }
+    if args.window {
+        let opts = WindowOptions {
+            arrows,
+            lazy: true,
+            title: window::summary(&pos),
+            ..window_options(args)
+        };
+        window::run(board_glyphs(&pos.placement()), opts, |_, _| true);
+    }
+}
+
+/// Show a bitboard as marked squares on an empty board or on the `on`
+/// position, in the terminal and in any diagrams or window asked for.
+fn show_bitboard(args: &Args, mask: u64, on: Option<&str>) {
+    let pos = on.map(|fen| {
+        Position::from_fen(fen).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1)
+        })
+    });
+    let term = terminal_options(args);
+    bitboard::print(mask, pos.as_ref(), &term);
+
+    let marked = bitboard::squares(mask);
+    let board = pos.unwrap_or_else(|| Position::from_placement([None; 64], Color::White));
+    if args.svg.is_some() || args.png.is_some() {
+        let diagram = diagram::board(
+            &board,
+            &DiagramOptions {
+                highlights: marked.clone(),
+                ..diagram_options(args)
+            },
+        );
+        if let Some(path) = &args.svg {
+            if let Err(e) = std::fs::write(path, svg_text(args.svg_optimize, &diagram)) {
+                eprintln!("Error: Could not write {}: {}", path, e);
+            }
+        }
+        if let Some(path) = &args.png {
+            let piece_font = piece_font(args);
+            if let Err(e) = png::save(&diagram, path, &piece_font, args.quality) {
+                eprintln!("Error: {}", e);
+            }
+        }
+    }
+    if args.window {
+        let opts = WindowOptions {
+            lazy: true,
+            ..window_options(args)
+        };
+        let tint = window::gl_colour(opts.palette.highlight);
+        let highlights: Vec<_> = marked.iter().map(|&sq| (sq, tint)).collect();
+        window::run(board_glyphs(&board.placement()), opts, move |board, _| {
+            board.highlights = highlights.clone();
+            true
+        });
+    }
+}
+
+/// Show the boards subcommand's positions, then with `follow` update them
+/// from `<id> <FEN>` lines on stdin, one window holding them all when
+/// requested. On a terminal the boards are redrawn in place; otherwise
+/// each update prints its board again. A board whose position has come up
+/// three times is noted under its title, and with --engine and --multipv
+/// the engine's lines for each board follow it.
+fn show_boards(args: &Args, fens: &[String], follow: bool) {
+    let term = terminal_options(args);
+    let mut boards = Vec::new();
+    // the Zobrist keys each board has shown, for spotting repetitions
+    let mut history: HashMap<String, Vec<u64>> = HashMap::new();
+    // and the engine's lines for the position on each, under --multipv
+    let mut lines = live_lines(args);
+    let mut tables = HashMap::new();
+    for (n, fen) in fens.iter().enumerate() {
+        let pos = Position::from_fen(fen).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1)
+        });
+        let title = format!("Board {}", n + 1);
+        history.insert(title.clone(), vec![zobrist::hash(&pos)]);
+        if let Some(table) = lines(&pos) {
+            tables.insert(title.clone(), table);
+        }
+        boards.push((title, pos.placement()));
+    }
+    let mut screen = terminal::Screen::new();
+    for (title, layout) in &boards {
+        screen.text(title);
+        screen.board(&board_glyphs(layout), &term);
+        if let Some(table) = tables.get(title) {
+            screen.text(table);
+        }
+    }
+    screen.show();
+
+    let (sender, receiver) = mpsc::channel();
+    let mut shown = boards.clone();
+    let mut read = move || {
+        for (n, line) in io::stdin().lock().lines().enumerate() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (id, fen) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            match Position::from_fen(fen.trim()) {
+                Ok(pos) => {
+                    let layout = pos.placement();
+                    // a position sent again without a move in between is no repetition
+                    let keys = history.entry(id.to_string()).or_default();
+                    let key = zobrist::hash(&pos);
+                    if keys.last() != Some(&key) {
+                        keys.push(key);
+                    }
+                    match lines(&pos) {
+                        Some(table) => tables.insert(id.to_string(), table),
+                        None => tables.remove(id),
+                    };
+                    let note = |title: &str| {
+                        history
+                            .get(title)
+                            .and_then(|keys| pgn::repetition_note(pgn::occurrences(keys)))
+                    };
+                    if screen.is_live() {
+                        match shown.iter_mut().find(|(title, _)| title == id) {
+                            Some((_, shown)) => *shown = layout.clone(),
+                            None => shown.push((id.to_string(), layout.clone())),
+                        }
+                        for (title, layout) in &shown {
+                            screen.text(title);
+                            if let Some(note) = note(title) {
+                                screen.text(&note);
+                            }
+                            screen.board(&board_glyphs(layout), &term);
+                            if let Some(table) = tables.get(title) {
+                                screen.text(table);
+                            }
+                        }
+                        screen.show();
+                    } else {
+                        screen.text(id);
+                        if let Some(note) = note(id) {
+                            screen.text(&note);
+                        }
+                        screen.board(&board_glyphs(&layout), &term);
+                        if let Some(table) = tables.get(id) {
+                            screen.text(table);
+                        }
+                    }
+                    let _ = sender.send((id.to_string(), layout));
+                }
+                Err(e) => eprintln!("line {}: Warning: {}, skipped", n + 1, e),
+            }
+        }
+    };
+
+    // as with a DGT board, the window keeps the main thread
     if args.window {
-        // Change this to OpenGL::V2_1 if not working.
-        let opengl = OpenGL::V3_2;
-
-        // Create a Glutin window.
-        let mut window: Window = WindowSettings::new("Chess Board", [600, 600])
-            .graphics_api(opengl)
-            .exit_on_esc(true)
-            .build()
-            .unwrap();
-
-        // Create a new game and run it.
-        let mut board = Board {
-            gl: GlGraphics::new(opengl),
+        let updates = if follow {
+            thread::spawn(read);
+            Some(receiver)
+        } else {
+            None
+        };
+        let boards = boards
+            .into_iter()
+            .map(|(title, layout)| (title, board_glyphs(&layout)))
+            .collect();
+        let opts = WindowOptions {
+            lazy: !follow,
+            ..window_options(args)
         };
+        window::show_grid(boards, updates, opts);
+    } else if follow {
+        read();
+    }
+}
+
+/// Play the play subcommand's game against the engine, showing each
+/// position in the terminal (and the window, when requested) and printing
+/// or saving the game at the end.
+fn play_engine(args: &Args, tc: &str, side: &str, from: &str) {
+    let fail = |e: String| -> ! {
+        eprintln!("Error: {}", e);
+        std::process::exit(1)
+    };
+    let path = args
+        .engine
+        .as_deref()
+        .unwrap_or_else(|| fail("play needs an engine given with --engine".to_string()));
+    let tc = play::TimeControl::parse(tc).unwrap_or_else(|e| fail(e));
+    let start = Position::from_fen(from).unwrap_or_else(|e| fail(e));
+    let mut engine = uci::Engine::spawn(path).unwrap_or_else(|e| fail(e));
+    let human = match side {
+        "black" => position::Color::Black,
+        _ => position::Color::White,
+    };
+    let term = terminal_options(args);
+    let glyphs = board_glyphs(&start.placement());
+    print_board(&glyphs, &term);
+
+    let (sender, receiver) = mpsc::channel();
+    let save_pgn = args.save_pgn.clone();
+    let (figurine, figurine_pgn, lang) = (args.figurine, args.figurine_pgn, args.lang);
+    let glyphs = board_glyphs(&start.placement());
+    let game = move || {
+        let result = play::play(start, &mut engine, tc, human, |pos, san| {
+            if let Some(san) = san {
+                println!("{}", notation(figurine, lang, san));
+            }
+            print_board(&board_glyphs(&pos.placement()), &term);
+            let _ = sender.send(window::Update::Position(pos.clone()));
+        });
+        match result {
+            Ok(game) => match &save_pgn {
+                Some(path) => {
+                    if let Err(e) = std::fs::write(path, pgn_text(figurine_pgn, &game)) {
+                        eprintln!("Error: Could not write {}: {}", path, e);
+                    }
+                }
+                None => print!("{}", pgn_text(figurine_pgn, &game)),
+            },
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    };
+
+    // moves are typed in the terminal; the window only follows the game
+    if args.window {
+        let handle = thread::spawn(game);
+        window::show(glyphs, Some(receiver), window_options(args));
+        let _ = handle.join();
+    } else {
+        game();
+    }
+}
 
-        // allow for the option of events and updating; not currently used
-        let mut events = Events::new(EventSettings::new());
-        while let Some(e) = events.next(&mut window) {
-            if let Some(args) = e.render_args() {
-                board.render(&args, &fentranslated);
-            }
-
-            if let Some(args) = e.update_args() {
-                board.update(&args);
-            }
-        }
-    }
-}
-
-// sub symbols
-// White pieces are designated using uppercase letters ("PNBRQK"),
-// while black pieces use lowercase letters ("pnbrqk").
-fn translate_piece(x: &str) -> &str {
-    let newsym: &str = match x {
-        "p" => "♟",
-        "n" => "♞",
-        "b" => "♝",
-        "r" => "♜",
-        "q" => "♛",
-        "k" => "♚",
-        "P" => "♙",
-        "N" => "♘",
-        "B" => "♗",
-        "R" => "♖",
-        "Q" => "♕",
-        "K" => "♔",
-        "1" => "1",
-        "2" => "2",
-        "3" => "3",
-        "4" => "4",
-        "5" => "5",
-        "6" => "6",
-        "7" => "7",
-        "8" => "8",
-        _ => "",
+/// Play the tournament subcommand's games, then print the crosstable and
+/// the results report (as JSON with `json`).
+fn run_tournament(
+    args: &Args,
+    engines: &[String],
+    openings: &str,
+    opts: &tournament::TournamentOptions,
+    sprt: Option<results::Sprt>,
+    json: bool,
+) {
+    let played = tournament::run(
+        engines,
+        openings,
+        opts,
+        args.save_pgn.as_deref(),
+        |so_far| {
+            // progress goes to stderr when stdout is for the report
+            let mut progress = Vec::new();
+            if let Some(game) = so_far.games.last() {
+                progress.push(format!(
+                    "Game {}: {} - {} {} (opening {})",
+                    so_far.games.len(),
+                    so_far.names[game.white],
+                    so_far.names[game.black],
+                    game.result,
+                    game.opening + 1
+                ));
+            }
+            if let Some(sprt) = &sprt {
+                progress.push(format!("  {}", sprt.summary(so_far)));
+            }
+            for line in progress {
+                if json {
+                    eprintln!("{}", line);
+                } else {
+                    println!("{}", line);
+                }
+            }
+            sprt.is_none_or(|sprt| {
+                sprt.verdict(&results::Record::of(so_far, 0, Some(1)))
+                    .is_none()
+            })
+        },
+    );
+    match played {
+        Ok(played) if json => println!("{}", results::to_json(&played, sprt.as_ref())),
+        Ok(played) => print!(
+            "\n{}\n{}",
+            played.crosstable(),
+            results::report(&played, sprt.as_ref())
+        ),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1)
+        }
+    }
+}
+
+/// Play the selfplay subcommand, streaming each position to the terminal
+/// (and the window, when requested) and saving the finished game.
+fn selfplay(
+    args: &Args,
+    engine1: &str,
+    engine2: &str,
+    from: &str,
+    movetime: u64,
+    max_moves: u32,
+    random_plies: u32,
+) {
+    let term = terminal_options(args);
+    let start = Position::from_fen(from).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1)
+    });
+    let start = selfplay::random_opening(start, random_plies, &mut random(args));
+    let mut white = uci::Engine::spawn(engine1).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1)
+    });
+    let mut black = uci::Engine::spawn(engine2).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1)
+    });
+    let glyphs = board_glyphs(&start.placement());
+    print_board(&glyphs, &term);
+
+    let (sender, receiver) = mpsc::channel();
+    let save_pgn = args.save_pgn.clone();
+    let (figurine, figurine_pgn, lang) = (args.figurine, args.figurine_pgn, args.lang);
+    let playout = move || {
+        let result = selfplay::play(
+            start,
+            &mut white,
+            &mut black,
+            movetime,
+            max_moves,
+            |pos, san| {
+                println!("{}", notation(figurine, lang, san));
+                print_board(&board_glyphs(&pos.placement()), &term);
+                let _ = sender.send(window::Update::Position(pos.clone()));
+            },
+        );
+        match result {
+            Ok(game) => {
+                println!("{}", game.result);
+                if let Some(path) = &save_pgn {
+                    if let Err(e) = std::fs::write(path, pgn_text(figurine_pgn, &game)) {
+                        eprintln!("Error: Could not write {}: {}", path, e);
+                    }
+                }
+            }
+            Err(e) => eprintln!("Error: {}", e),
+        }
     };
-    newsym
+
+    // the window must own the main thread, so the engines play in the background
+    if args.window {
+        let handle = thread::spawn(playout);
+        window::show(glyphs, Some(receiver), window_options(args));
+        let _ = handle.join();
+    } else {
+        playout();
+    }
 }
@@ -0,0 +1,117 @@
+//! Forced mate search
+//!
+//! A plain depth-bounded search over legal moves: the side to move mates in
+//! `n` if some move either mates at once or leaves every reply mating in
+//! `n - 1`. Checks and captures are tried first, which finds most puzzle
+//! solutions quickly, but quiet first moves in a mate-in-4 can take a while.
+
+use crate::candidates::grouped;
+use crate::movegen::Move;
+use crate::position::Position;
+use crate::san::format_line;
+
+// the side to move's moves, checks and captures first
+fn ordered(pos: &Position) -> Vec<Move> {
+    grouped(pos)
+        .into_iter()
+        .flat_map(|(_, moves)| moves)
+        .collect()
+}
+
+/// a move of the side to move that forces mate in at most `n` moves
+fn mating_move(pos: &Position, n: u32) -> Option<Move> {
+    ordered(pos).into_iter().find(|&mv| {
+        let next = pos.make_move(mv);
+        if n == 1 {
+            return next.is_checkmate();
+        }
+        let replies = next.legal_moves();
+        if replies.is_empty() {
+            // stalemate is no win
+            return next.in_check();
+        }
+        replies
+            .into_iter()
+            .all(|reply| mating_move(&next.make_move(reply), n - 1).is_some())
+    })
+}
+
+/// the fewest moves, no more than `max`, in which `pos` mates by force
+fn mate_distance(pos: &Position, max: u32) -> Option<u32> {
+    (1..=max).find(|&n| mating_move(pos, n).is_some())
+}
+
+/// The shortest forced mate from `pos`, at most `max` moves long, as its
+/// length and principal variation in UCI notation. The defender's replies
+/// in the variation are ones that hold out longest.
+pub fn find(pos: &Position, max: u32) -> Option<(u32, Vec<String>)> {
+    let length = mate_distance(pos, max)?;
+    let mut pv = Vec::new();
+    let mut pos = pos.clone();
+    let mut n = length;
+    loop {
+        let mv = mating_move(&pos, n)?;
+        pv.push(mv.to_uci());
+        pos = pos.make_move(mv);
+        if pos.is_checkmate() {
+            return Some((length, pv));
+        }
+        let (reply, rest) = pos
+            .legal_moves()
+            .into_iter()
+            .filter_map(|reply| Some((reply, mate_distance(&pos.make_move(reply), n - 1)?)))
+            .max_by_key(|&(_, rest)| rest)?;
+        pv.push(reply.to_uci());
+        pos = pos.make_move(reply);
+        n = rest;
+    }
+}
+
+/// Print the mate in at most `max` moves from `fen`, if there is one.
+pub fn run(fen: &str, max: u32) -> Result<(), String> {
+    if max == 0 {
+        return Err("--max should be at least 1".to_string());
+    }
+    let pos = Position::from_fen(fen)?;
+    match find(&pos, max) {
+        Some((n, pv)) => println!("Mate in {}: {}", n, format_line(&pos, &pv, pv.len())),
+        None => println!(
+            "No forced mate in {} move{}",
+            max,
+            if max == 1 { "" } else { "s" }
+        ),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mate(fen: &str, max: u32) -> Option<(u32, Vec<String>)> {
+        find(&Position::from_fen(fen).unwrap(), max)
+    }
+
+    #[test]
+    fn mate_in_one() {
+        // back-rank mate
+        let found = mate("6k1/5ppp/8/8/8/8/8/3R2K1 w - - 0 1", 2);
+        assert_eq!(found, Some((1, vec!["d1d8".to_string()])));
+    }
+
+    #[test]
+    fn mate_in_two() {
+        // doubled rooks against a defended back rank: 1. Re8+ Rxe8 2. Rxe8#
+        let fen = "2r3k1/5ppp/8/8/8/8/4RPPP/4R1K1 w - - 0 1";
+        let line = ["e2e8", "c8e8", "e1e8"].map(str::to_string).to_vec();
+        assert_eq!(mate(fen, 3), Some((2, line)));
+        assert_eq!(mate(fen, 1), None);
+    }
+
+    #[test]
+    fn no_mate_within_the_depth() {
+        assert_eq!(mate(crate::position::STARTING_FEN, 2), None);
+        // a lone king and rook cannot mate in two from the middle
+        assert_eq!(mate("8/8/8/3k4/8/8/8/R3K3 w - - 0 1", 2), None);
+    }
+}
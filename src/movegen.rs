@@ -0,0 +1,577 @@
+//! Legal move generation and move application
+//!
+//! A straightforward mailbox generator: pseudo-legal moves are produced
+//! per piece and discarded if they leave the mover's king attacked.
+
+use crate::position::{
+    file_of, parse_square, rank_of, square, square_name, Color, Piece, PieceKind, Position, Square,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<PieceKind>,
+}
+
+impl Move {
+    pub fn new(from: Square, to: Square) -> Move {
+        Move {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+
+    /// long algebraic form used by UCI, e.g. e2e4 or e7e8q
+    pub fn to_uci(self) -> String {
+        let mut s = format!("{}{}", square_name(self.from), square_name(self.to));
+        if let Some(p) = self.promotion {
+            s.push(p.letter().to_ascii_lowercase());
+        }
+        s
+    }
+
+    pub fn from_uci(s: &str) -> Option<Move> {
+        if s.len() < 4 || s.len() > 5 || !s.is_ascii() {
+            return None;
+        }
+        let from = parse_square(&s[0..2])?;
+        let to = parse_square(&s[2..4])?;
+        let promotion = match s[4..].chars().next() {
+            Some(c) => match PieceKind::from_letter(c)? {
+                PieceKind::Pawn | PieceKind::King => return None,
+                k => Some(k),
+            },
+            None => None,
+        };
+        Some(Move {
+            from,
+            to,
+            promotion,
+        })
+    }
+}
+
+const KNIGHT_STEPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_STEPS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+const ROOK_DIRS: [(i8, i8); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+const BISHOP_DIRS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+const PROMOTIONS: [PieceKind; 4] = [
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+];
+
+/// the square reached by stepping (df, dr) from `sq`, if still on the board
+pub fn offset(sq: Square, df: i8, dr: i8) -> Option<Square> {
+    let f = file_of(sq) as i8 + df;
+    let r = rank_of(sq) as i8 + dr;
+    if (0..8).contains(&f) && (0..8).contains(&r) {
+        Some(square(f as u8, r as u8))
+    } else {
+        None
+    }
+}
+
+/// squares named in a list, e.g. "b1, c1 and d1"
+fn name_squares(squares: &[Square]) -> String {
+    let names: Vec<String> = squares.iter().map(|&sq| square_name(sq)).collect();
+    match names.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+fn pawn_dir(color: Color) -> i8 {
+    match color {
+        Color::White => 1,
+        Color::Black => -1,
+    }
+}
+
+impl Position {
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        (0..64).find(|&sq| self.board[sq as usize] == Some(Piece::new(color, PieceKind::King)))
+    }
+
+    /// whether `sq` is attacked by any piece of colour `by`
+    pub fn is_attacked(&self, sq: Square, by: Color) -> bool {
+        let is = |s: Option<Square>, kinds: &[PieceKind]| {
+            s.and_then(|s| self.board[s as usize])
+                .is_some_and(|p| p.color == by && kinds.contains(&p.kind))
+        };
+        let back = -pawn_dir(by);
+        if is(offset(sq, -1, back), &[PieceKind::Pawn])
+            || is(offset(sq, 1, back), &[PieceKind::Pawn])
+        {
+            return true;
+        }
+        if KNIGHT_STEPS
+            .iter()
+            .any(|&(df, dr)| is(offset(sq, df, dr), &[PieceKind::Knight]))
+        {
+            return true;
+        }
+        if KING_STEPS
+            .iter()
+            .any(|&(df, dr)| is(offset(sq, df, dr), &[PieceKind::King]))
+        {
+            return true;
+        }
+        for (dirs, kinds) in [
+            (ROOK_DIRS, [PieceKind::Rook, PieceKind::Queen]),
+            (BISHOP_DIRS, [PieceKind::Bishop, PieceKind::Queen]),
+        ] {
+            for (df, dr) in dirs {
+                let mut cur = offset(sq, df, dr);
+                while let Some(s) = cur {
+                    if let Some(p) = self.board[s as usize] {
+                        if p.color == by && kinds.contains(&p.kind) {
+                            return true;
+                        }
+                        break;
+                    }
+                    cur = offset(s, df, dr);
+                }
+            }
+        }
+        false
+    }
+
+    /// The squares `piece` would attack from `from`, as a bitboard (bit 0
+    /// for a1): sliders stop at the first piece of either colour, which is
+    /// included. Whatever stands on `from` itself is ignored.
+    pub fn attacks(&self, piece: Piece, from: Square) -> u64 {
+        let step = |steps: &[(i8, i8)]| {
+            steps
+                .iter()
+                .filter_map(|&(df, dr)| offset(from, df, dr))
+                .fold(0u64, |set, to| set | 1 << to)
+        };
+        let slide = |dirs: &[(i8, i8)]| {
+            let mut set = 0u64;
+            for &(df, dr) in dirs {
+                let mut cur = offset(from, df, dr);
+                while let Some(to) = cur {
+                    set |= 1 << to;
+                    if self.board[to as usize].is_some() {
+                        break;
+                    }
+                    cur = offset(to, df, dr);
+                }
+            }
+            set
+        };
+        let forward = pawn_dir(piece.color);
+        match piece.kind {
+            PieceKind::Pawn => step(&[(-1, forward), (1, forward)]),
+            PieceKind::Knight => step(&KNIGHT_STEPS),
+            PieceKind::King => step(&KING_STEPS),
+            PieceKind::Bishop => slide(&BISHOP_DIRS),
+            PieceKind::Rook => slide(&ROOK_DIRS),
+            PieceKind::Queen => slide(&BISHOP_DIRS) | slide(&ROOK_DIRS),
+        }
+    }
+
+    /// whether the side to move is in check; positions without a king are never in check
+    pub fn in_check(&self) -> bool {
+        self.king_square(self.side)
+            .is_some_and(|k| self.is_attacked(k, self.side.opponent()))
+    }
+
+    pub fn is_checkmate(&self) -> bool {
+        self.in_check() && self.legal_moves().is_empty()
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        !self.in_check() && self.legal_moves().is_empty()
+    }
+
+    /// neither side has enough material to deliver mate (K v K, K+minor v K)
+    pub fn is_insufficient_material(&self) -> bool {
+        let others: Vec<PieceKind> = self
+            .board
+            .iter()
+            .flatten()
+            .map(|p| p.kind)
+            .filter(|&k| k != PieceKind::King)
+            .collect();
+        match others.as_slice() {
+            [] => true,
+            [k] => matches!(k, PieceKind::Knight | PieceKind::Bishop),
+            _ => false,
+        }
+    }
+
+    pub fn is_capture(&self, mv: Move) -> bool {
+        self.board[mv.to as usize].is_some() || self.is_en_passant(mv)
+    }
+
+    pub fn is_en_passant(&self, mv: Move) -> bool {
+        Some(mv.to) == self.ep
+            && self.board[mv.from as usize].map(|p| p.kind) == Some(PieceKind::Pawn)
+            && file_of(mv.from) != file_of(mv.to)
+    }
+
+    pub fn is_castle(&self, mv: Move) -> bool {
+        self.board[mv.from as usize].map(|p| p.kind) == Some(PieceKind::King)
+            && (file_of(mv.from) as i8 - file_of(mv.to) as i8).abs() == 2
+    }
+
+    fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        let us = self.side;
+        for from in 0..64u8 {
+            let piece = match self.board[from as usize] {
+                Some(p) if p.color == us => p,
+                _ => continue,
+            };
+            match piece.kind {
+                PieceKind::Pawn => self.pawn_moves(from, &mut moves),
+                PieceKind::Knight => self.step_moves(from, &KNIGHT_STEPS, &mut moves),
+                PieceKind::King => {
+                    self.step_moves(from, &KING_STEPS, &mut moves);
+                    self.castling_moves(from, &mut moves);
+                }
+                PieceKind::Bishop => self.slide_moves(from, &BISHOP_DIRS, &mut moves),
+                PieceKind::Rook => self.slide_moves(from, &ROOK_DIRS, &mut moves),
+                PieceKind::Queen => {
+                    self.slide_moves(from, &BISHOP_DIRS, &mut moves);
+                    self.slide_moves(from, &ROOK_DIRS, &mut moves);
+                }
+            }
+        }
+        moves
+    }
+
+    fn pawn_moves(&self, from: Square, moves: &mut Vec<Move>) {
+        let us = self.side;
+        let dir = pawn_dir(us);
+        let last_rank = if us == Color::White { 7 } else { 0 };
+        let start_rank = if us == Color::White { 1 } else { 6 };
+        let mut push = |to: Square| {
+            if rank_of(to) == last_rank {
+                for k in PROMOTIONS {
+                    moves.push(Move {
+                        from,
+                        to,
+                        promotion: Some(k),
+                    });
+                }
+            } else {
+                moves.push(Move::new(from, to));
+            }
+        };
+        if let Some(one) = offset(from, 0, dir) {
+            if self.board[one as usize].is_none() {
+                push(one);
+                if rank_of(from) == start_rank {
+                    if let Some(two) = offset(one, 0, dir) {
+                        if self.board[two as usize].is_none() {
+                            push(two);
+                        }
+                    }
+                }
+            }
+        }
+        for df in [-1, 1] {
+            if let Some(to) = offset(from, df, dir) {
+                let enemy = self.board[to as usize].is_some_and(|p| p.color != us);
+                if enemy || Some(to) == self.ep {
+                    push(to);
+                }
+            }
+        }
+    }
+
+    fn step_moves(&self, from: Square, steps: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for &(df, dr) in steps {
+            if let Some(to) = offset(from, df, dr) {
+                if self.board[to as usize].is_none_or(|p| p.color != self.side) {
+                    moves.push(Move::new(from, to));
+                }
+            }
+        }
+    }
+
+    fn slide_moves(&self, from: Square, dirs: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for &(df, dr) in dirs {
+            let mut cur = offset(from, df, dr);
+            while let Some(to) = cur {
+                match self.board[to as usize] {
+                    None => moves.push(Move::new(from, to)),
+                    Some(p) => {
+                        if p.color != self.side {
+                            moves.push(Move::new(from, to));
+                        }
+                        break;
+                    }
+                }
+                cur = offset(to, df, dr);
+            }
+        }
+    }
+
+    fn castling_moves(&self, from: Square, moves: &mut Vec<Move>) {
+        let us = self.side;
+        let them = us.opponent();
+        let (home, kingside, queenside) = match us {
+            Color::White => (
+                square(4, 0),
+                self.castling.white_kingside,
+                self.castling.white_queenside,
+            ),
+            Color::Black => (
+                square(4, 7),
+                self.castling.black_kingside,
+                self.castling.black_queenside,
+            ),
+        };
+        if from != home || self.is_attacked(home, them) {
+            return;
+        }
+        let rook = Some(Piece::new(us, PieceKind::Rook));
+        let empty = |files: &[u8]| {
+            files
+                .iter()
+                .all(|&f| self.board[square(f, rank_of(home)) as usize].is_none())
+        };
+        let safe = |files: &[u8]| {
+            files
+                .iter()
+                .all(|&f| !self.is_attacked(square(f, rank_of(home)), them))
+        };
+        if kingside
+            && self.board[square(7, rank_of(home)) as usize] == rook
+            && empty(&[5, 6])
+            && safe(&[5, 6])
+        {
+            moves.push(Move::new(home, home + 2));
+        }
+        if queenside
+            && self.board[square(0, rank_of(home)) as usize] == rook
+            && empty(&[1, 2, 3])
+            && safe(&[2, 3])
+        {
+            moves.push(Move::new(home, home - 2));
+        }
+    }
+
+    /// Why `color` cannot castle on the given wing with the board as it
+    /// stands (whoever is to move), or `None` if it can. The right itself
+    /// is not consulted.
+    pub fn castling_obstacle(&self, color: Color, kingside: bool) -> Option<String> {
+        let rank = match color {
+            Color::White => 0,
+            Color::Black => 7,
+        };
+        let them = color.opponent();
+        let (rook_file, between, crossed): (u8, &[u8], &[u8]) = if kingside {
+            (7, &[5, 6], &[5, 6])
+        } else {
+            (0, &[1, 2, 3], &[2, 3])
+        };
+        let home = square(4, rank);
+        if self.board[home as usize] != Some(Piece::new(color, PieceKind::King)) {
+            return Some(format!("the king is not on {}", square_name(home)));
+        }
+        let corner = square(rook_file, rank);
+        if self.board[corner as usize] != Some(Piece::new(color, PieceKind::Rook)) {
+            return Some(format!("the rook is not on {}", square_name(corner)));
+        }
+        let occupied: Vec<Square> = between
+            .iter()
+            .map(|&f| square(f, rank))
+            .filter(|&sq| self.board[sq as usize].is_some())
+            .collect();
+        if !occupied.is_empty() {
+            let verb = if occupied.len() == 1 { "is" } else { "are" };
+            return Some(format!("{} {} occupied", name_squares(&occupied), verb));
+        }
+        if self.is_attacked(home, them) {
+            return Some("the king is in check".to_string());
+        }
+        let attacked: Vec<Square> = crossed
+            .iter()
+            .map(|&f| square(f, rank))
+            .filter(|&sq| self.is_attacked(sq, them))
+            .collect();
+        if !attacked.is_empty() {
+            let verb = if attacked.len() == 1 { "is" } else { "are" };
+            return Some(format!(
+                "the king would cross or land on {}, which {} attacked",
+                name_squares(&attacked),
+                verb
+            ));
+        }
+        None
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|&mv| {
+                let next = self.make_move(mv);
+                next.king_square(self.side)
+                    .is_none_or(|k| !next.is_attacked(k, next.side))
+            })
+            .collect()
+    }
+
+    pub fn is_legal(&self, mv: Move) -> bool {
+        self.legal_moves().contains(&mv)
+    }
+
+    /// Apply a move (assumed legal) and return the resulting position.
+    pub fn make_move(&self, mv: Move) -> Position {
+        let mut next = self.clone();
+        let piece = match self.board[mv.from as usize] {
+            Some(p) => p,
+            None => return next,
+        };
+        let capture = self.is_capture(mv);
+
+        if self.is_en_passant(mv) {
+            let victim = square(file_of(mv.to), rank_of(mv.from));
+            next.board[victim as usize] = None;
+        }
+        if self.is_castle(mv) {
+            let r = rank_of(mv.from);
+            let (rook_from, rook_to) = if file_of(mv.to) == 6 {
+                (square(7, r), square(5, r))
+            } else {
+                (square(0, r), square(3, r))
+            };
+            next.board[rook_to as usize] = next.board[rook_from as usize].take();
+        }
+        next.board[mv.from as usize] = None;
+        next.board[mv.to as usize] = Some(match mv.promotion {
+            Some(k) => Piece::new(piece.color, k),
+            None => piece,
+        });
+
+        // any move from or to a corner/king square revokes the matching rights
+        for sq in [mv.from, mv.to] {
+            match square_name(sq).as_str() {
+                "e1" => {
+                    next.castling.white_kingside = false;
+                    next.castling.white_queenside = false;
+                }
+                "h1" => next.castling.white_kingside = false,
+                "a1" => next.castling.white_queenside = false,
+                "e8" => {
+                    next.castling.black_kingside = false;
+                    next.castling.black_queenside = false;
+                }
+                "h8" => next.castling.black_kingside = false,
+                "a8" => next.castling.black_queenside = false,
+                _ => {}
+            }
+        }
+
+        next.ep = None;
+        if piece.kind == PieceKind::Pawn
+            && (rank_of(mv.from) as i8 - rank_of(mv.to) as i8).abs() == 2
+        {
+            next.ep = offset(mv.from, 0, pawn_dir(piece.color));
+        }
+        if piece.kind == PieceKind::Pawn || capture {
+            next.halfmove = 0;
+        } else {
+            next.halfmove += 1;
+        }
+        if self.side == Color::Black {
+            next.fullmove += 1;
+        }
+        next.side = self.side.opponent();
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perft(pos: &Position, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = pos.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves
+            .into_iter()
+            .map(|mv| perft(&pos.make_move(mv), depth - 1))
+            .sum()
+    }
+
+    fn nodes(fen: &str, depth: u32) -> u64 {
+        perft(&Position::from_fen(fen).unwrap(), depth)
+    }
+
+    // counts from the Chess Programming Wiki's perft results page
+
+    #[test]
+    fn perft_start() {
+        let start = crate::position::STARTING_FEN;
+        assert_eq!(nodes(start, 1), 20);
+        assert_eq!(nodes(start, 2), 400);
+        assert_eq!(nodes(start, 3), 8902);
+        assert_eq!(nodes(start, 4), 197281);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(nodes(fen, 1), 48);
+        assert_eq!(nodes(fen, 2), 2039);
+        assert_eq!(nodes(fen, 3), 97862);
+    }
+
+    #[test]
+    fn perft_position_3() {
+        let fen = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+        assert_eq!(nodes(fen, 1), 14);
+        assert_eq!(nodes(fen, 2), 191);
+        assert_eq!(nodes(fen, 3), 2812);
+        assert_eq!(nodes(fen, 4), 43238);
+    }
+
+    #[test]
+    fn perft_position_4() {
+        let fen = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+        assert_eq!(nodes(fen, 1), 6);
+        assert_eq!(nodes(fen, 2), 264);
+        assert_eq!(nodes(fen, 3), 9467);
+    }
+
+    #[test]
+    fn perft_position_5() {
+        let fen = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+        assert_eq!(nodes(fen, 1), 44);
+        assert_eq!(nodes(fen, 2), 1486);
+        assert_eq!(nodes(fen, 3), 62379);
+    }
+}
@@ -0,0 +1,71 @@
+//! Numeric Annotation Glyphs (NAGs) and their conventional symbols
+
+const NAGS: [(u8, &str); 26] = [
+    (1, "!"),
+    (2, "?"),
+    (3, "!!"),
+    (4, "??"),
+    (5, "!?"),
+    (6, "?!"),
+    (7, "□"),
+    (10, "="),
+    (13, "∞"),
+    (14, "+/="),
+    (15, "=/+"),
+    (16, "±"),
+    (17, "∓"),
+    (18, "+−"),
+    (19, "−+"),
+    (22, "⨀"),
+    (23, "⨀"),
+    (32, "⟳"),
+    (33, "⟳"),
+    (36, "↑"),
+    (37, "↑"),
+    (40, "→"),
+    (41, "→"),
+    (132, "⇆"),
+    (133, "⇆"),
+    (146, "N"),
+];
+
+// other spellings commonly typed in place of the symbols; the bundled
+// font has no glyphs for ⩲ and ⩱, so those display in their ASCII forms
+const ASCII: [(&str, &str); 8] = [
+    ("+=", "+/="),
+    ("⩲", "+/="),
+    ("=+", "=/+"),
+    ("⩱", "=/+"),
+    ("+/-", "±"),
+    ("-/+", "∓"),
+    ("+-", "+−"),
+    ("-+", "−+"),
+];
+
+/// Resolve an annotation given as a NAG (`$3` or `3`), a symbol (`!!`, `±`)
+/// or its ASCII spelling (`+/-`) to the symbol to display.
+pub fn symbol(annotation: &str) -> Option<String> {
+    let text = annotation.trim();
+    if let Ok(code) = text.trim_start_matches('$').parse::<u8>() {
+        return NAGS
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, s)| s.to_string());
+    }
+    if let Some((_, s)) = ASCII.iter().find(|(a, _)| *a == text) {
+        return Some(s.to_string());
+    }
+    NAGS.iter()
+        .find(|(_, s)| *s == text)
+        .map(|(_, s)| s.to_string())
+}
+
+/// split a trailing move annotation such as `!?` from SAN movetext
+pub fn split_suffix(token: &str) -> (&str, Option<&str>) {
+    let body = token.trim_end_matches(['!', '?']);
+    if body.len() < token.len() {
+        (body, Some(&token[body.len()..]))
+    } else {
+        (token, None)
+    }
+}
@@ -0,0 +1,413 @@
+//! A compact binary encoding of positions, for large datasets
+//!
+//! Each position takes an 8-byte occupancy bitboard, then a nibble for
+//! every occupied square from a1 to h8 (padded to a whole byte), then the
+//! halfmove clock in a byte and the fullmove number in two, the top bit of
+//! which is set when Black is to move. Castling rights and the en-passant
+//! square are folded into the nibbles: a rook that may still castle and a
+//! pawn that may be taken en passant have codes of their own. A full board
+//! packs into 27 bytes and a typical middlegame into about 24.
+//!
+//! Files start with the bytes `FENP` and then hold the positions back to
+//! back; each one's length follows from its bitboard. A [`Dataset`] maps
+//! such a file into memory for random and parallel access.
+
+use crate::batch;
+use crate::position::{file_of, rank_of, square, Castling, Color, Piece, PieceKind, Position};
+use crate::rng::Rng;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{self, BufRead, Read, Write};
+
+const MAGIC: &[u8; 4] = b"FENP";
+
+// nibbles 0-5 are White's pawn to king and 6-11 Black's
+const PASSED_PAWN: u8 = 12;
+const CASTLING_ROOK: u8 = 13;
+const PADDING: u8 = 15;
+
+const KINDS: [PieceKind; 6] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+    PieceKind::King,
+];
+
+fn nibble(piece: Piece) -> u8 {
+    let kind = KINDS.iter().position(|&k| k == piece.kind).unwrap_or(0) as u8;
+    match piece.color {
+        Color::White => kind,
+        Color::Black => 6 + kind,
+    }
+}
+
+/// the corner rooks each castling right needs: (right held, rook square)
+fn castling_rooks(pos: &Position) -> [(bool, u8); 4] {
+    [
+        (pos.castling.white_kingside, square(7, 0)),
+        (pos.castling.white_queenside, square(0, 0)),
+        (pos.castling.black_kingside, square(7, 7)),
+        (pos.castling.black_queenside, square(0, 7)),
+    ]
+}
+
+/// the square of the pawn that just moved two squares past `ep`
+fn passed_pawn(ep: u8) -> u8 {
+    if rank_of(ep) == 2 {
+        ep + 8
+    } else {
+        ep - 8
+    }
+}
+
+/// Encode `pos`. Positions whose castling rights lack a rook in the
+/// corner, whose en-passant square has no pawn in front of it, or whose
+/// clocks are out of range cannot be packed.
+pub fn encode(pos: &Position) -> Result<Vec<u8>, String> {
+    let rooks = castling_rooks(pos);
+    for &(held, sq) in &rooks {
+        let rook = Piece::new(
+            if rank_of(sq) == 0 {
+                Color::White
+            } else {
+                Color::Black
+            },
+            PieceKind::Rook,
+        );
+        if held && pos.piece_at(sq) != Some(rook) {
+            return Err(
+                "Castling rights without a rook in the corner cannot be packed".to_string(),
+            );
+        }
+    }
+    let passed = pos.ep.map(passed_pawn);
+    if let Some(sq) = passed {
+        if pos.piece_at(sq).is_none_or(|p| p.kind != PieceKind::Pawn) {
+            return Err(
+                "An en-passant square without a pawn in front of it cannot be packed".to_string(),
+            );
+        }
+    }
+    if pos.halfmove > 255 || pos.fullmove > 0x7fff {
+        return Err("Move clocks this large cannot be packed".to_string());
+    }
+
+    let mut occupancy: u64 = 0;
+    let mut nibbles = Vec::new();
+    for (sq, piece) in pos.board.iter().enumerate() {
+        if let Some(piece) = piece {
+            occupancy |= 1 << sq;
+            let sq = sq as u8;
+            nibbles.push(if Some(sq) == passed {
+                PASSED_PAWN
+            } else if rooks.contains(&(true, sq)) {
+                CASTLING_ROOK
+            } else {
+                nibble(*piece)
+            });
+        }
+    }
+    if nibbles.len() % 2 == 1 {
+        nibbles.push(PADDING);
+    }
+
+    let mut out = occupancy.to_be_bytes().to_vec();
+    out.extend(nibbles.chunks(2).map(|pair| pair[0] << 4 | pair[1]));
+    out.push(pos.halfmove as u8);
+    let black = if pos.side == Color::Black { 0x8000 } else { 0 };
+    out.extend((pos.fullmove as u16 | black).to_be_bytes());
+    Ok(out)
+}
+
+/// bytes taken by a position with these squares occupied
+fn record_len(occupancy: u64) -> usize {
+    8 + (occupancy.count_ones() as usize).div_ceil(2) + 3
+}
+
+/// Read the next position from `input`, or `None` at the end.
+pub fn decode(input: &mut impl Read) -> Result<Option<Position>, String> {
+    let truncated = |e: io::Error| format!("Truncated position: {}", e);
+    let mut bitboard = [0; 8];
+    match input.read(&mut bitboard[..1]) {
+        Ok(0) => return Ok(None),
+        Ok(_) => input.read_exact(&mut bitboard[1..]).map_err(truncated)?,
+        Err(e) => return Err(format!("Could not read position: {}", e)),
+    }
+    let occupancy = u64::from_be_bytes(bitboard);
+    let mut rest = vec![0; record_len(occupancy) - 8];
+    input.read_exact(&mut rest).map_err(truncated)?;
+
+    let mut board = [None; 64];
+    let mut castling = Castling::default();
+    let mut ep = None;
+    let squares = (0..64u8).filter(|&sq| occupancy & (1 << sq) != 0);
+    for (i, sq) in squares.enumerate() {
+        let code = (rest[i / 2] >> (4 * (1 - i % 2))) & 0xf;
+        let piece = match code {
+            0..=11 => Piece::new(
+                if code < 6 { Color::White } else { Color::Black },
+                KINDS[code as usize % 6],
+            ),
+            PASSED_PAWN if rank_of(sq) == 3 || rank_of(sq) == 4 => {
+                let (color, target) = if rank_of(sq) == 3 {
+                    (Color::White, sq - 8)
+                } else {
+                    (Color::Black, sq + 8)
+                };
+                ep = Some(target);
+                Piece::new(color, PieceKind::Pawn)
+            }
+            CASTLING_ROOK if matches!(rank_of(sq), 0 | 7) && matches!(file_of(sq), 0 | 7) => {
+                let kingside = file_of(sq) == 7;
+                let color = if rank_of(sq) == 0 {
+                    castling.white_kingside |= kingside;
+                    castling.white_queenside |= !kingside;
+                    Color::White
+                } else {
+                    castling.black_kingside |= kingside;
+                    castling.black_queenside |= !kingside;
+                    Color::Black
+                };
+                Piece::new(color, PieceKind::Rook)
+            }
+            _ => return Err(format!("Invalid piece code {} in packed position", code)),
+        };
+        board[sq as usize] = Some(piece);
+    }
+
+    let clocks = &rest[rest.len() - 3..];
+    let fullmove = u16::from_be_bytes([clocks[1], clocks[2]]);
+    Ok(Some(Position {
+        board,
+        side: if fullmove & 0x8000 != 0 {
+            Color::Black
+        } else {
+            Color::White
+        },
+        castling,
+        ep,
+        halfmove: clocks[0] as u32,
+        fullmove: (fullmove & 0x7fff) as u32,
+    }))
+}
+
+/// whether the file at `path` was made by `pack`
+pub fn is_packed(path: &str) -> bool {
+    let mut magic = [0; 4];
+    File::open(path).is_ok_and(|mut f| f.read_exact(&mut magic).is_ok() && &magic == MAGIC)
+}
+
+/// A packed file mapped into memory, with the offset of every position so
+/// that any one can be decoded on its own.
+pub struct Dataset {
+    map: Mmap,
+    offsets: Vec<usize>,
+}
+
+impl Dataset {
+    /// Map the packed file at `path` and index its positions.
+    pub fn open(path: &str) -> Result<Dataset, String> {
+        let file = File::open(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        let not_packed = || format!("{} is not a packed position file", path);
+        if size < MAGIC.len() as u64 {
+            return Err(not_packed());
+        }
+        // the file is only read, and a dataset is not expected to change
+        // while it is open
+        let map =
+            unsafe { Mmap::map(&file) }.map_err(|e| format!("Could not map {}: {}", path, e))?;
+        if &map[..MAGIC.len()] != MAGIC {
+            return Err(not_packed());
+        }
+        let mut offsets = Vec::new();
+        let mut at = MAGIC.len();
+        while at < map.len() {
+            let bitboard = map
+                .get(at..at + 8)
+                .ok_or_else(|| format!("{}: truncated position {}", path, offsets.len() + 1))?;
+            let len = record_len(u64::from_be_bytes(bitboard.try_into().expect("8 bytes")));
+            if at + len > map.len() {
+                return Err(format!(
+                    "{}: truncated position {}",
+                    path,
+                    offsets.len() + 1
+                ));
+            }
+            offsets.push(at);
+            at += len;
+        }
+        Ok(Dataset { map, offsets })
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// the position at `index`, counting from 0
+    pub fn get(&self, index: usize) -> Result<Position, String> {
+        let at = *self
+            .offsets
+            .get(index)
+            .ok_or_else(|| format!("No position {} in a dataset of {}", index, self.len()))?;
+        decode(&mut &self.map[at..])
+            .and_then(|pos| pos.ok_or_else(|| "Truncated position".to_string()))
+            .map_err(|e| format!("position {}: {}", index + 1, e))
+    }
+
+    /// every position in order, decoded on rayon's thread pool
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item = Result<Position, String>> + '_ {
+        (0..self.len()).into_par_iter().map(move |i| self.get(i))
+    }
+
+    /// `count` positions picked at random, with repeats
+    pub fn sample(&self, count: usize, rng: &mut Rng) -> Result<Vec<Position>, String> {
+        if self.is_empty() {
+            return Err("The dataset is empty".to_string());
+        }
+        let picks: Vec<usize> = (0..count)
+            .map(|_| rng.below(self.len() as u64) as usize)
+            .collect();
+        picks.into_par_iter().map(|i| self.get(i)).collect()
+    }
+}
+
+/// Pack the FENs of `input`, one per line, into `out` or standard output.
+/// Lines that cannot be parsed or packed are reported and skipped.
+pub fn pack(input: &str, out: Option<&str>) -> Result<(), String> {
+    let write_error = |e: io::Error| format!("Could not write {}: {}", out.unwrap_or("output"), e);
+    let mut writer = batch::create(out)?;
+    writer.write_all(MAGIC).map_err(write_error)?;
+    let (mut packed, mut bytes) = (0, MAGIC.len());
+    for (n, line) in batch::open(input)?.lines().enumerate() {
+        let line = line.map_err(|e| format!("Could not read {}: {}", input, e))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match Position::from_fen(line).and_then(|pos| encode(&pos)) {
+            Ok(record) => {
+                writer.write_all(&record).map_err(write_error)?;
+                packed += 1;
+                bytes += record.len();
+            }
+            Err(e) => eprintln!("line {}: Error: {}, skipped", n + 1, e),
+        }
+    }
+    writer.flush().map_err(write_error)?;
+    eprintln!("Packed {} positions into {} bytes", packed, bytes);
+    Ok(())
+}
+
+/// Unpack the positions of `input` as FENs, one per line, into `out` or
+/// standard output. Files are mapped and decoded in parallel; standard
+/// input is read as a stream.
+pub fn unpack(input: &str, out: Option<&str>) -> Result<(), String> {
+    let write_error = |e: io::Error| format!("Could not write {}: {}", out.unwrap_or("output"), e);
+    if input != "-" {
+        let dataset = Dataset::open(input)?;
+        let mut writer = batch::create(out)?;
+        // a chunk at a time, to stay within memory for large files
+        const CHUNK: usize = 1 << 16;
+        for start in (0..dataset.len()).step_by(CHUNK) {
+            let fens: Vec<String> = dataset
+                .par_iter()
+                .skip(start)
+                .take(CHUNK)
+                .map(|pos| pos.map(|pos| pos.to_fen()))
+                .collect::<Result<_, _>>()?;
+            for fen in fens {
+                writeln!(writer, "{}", fen).map_err(write_error)?;
+            }
+        }
+        return writer.flush().map_err(write_error);
+    }
+    let mut reader = batch::open(input)?;
+    let mut magic = [0; 4];
+    if reader.read_exact(&mut magic).is_err() || &magic != MAGIC {
+        return Err(format!("{} is not a packed position file", input));
+    }
+    let mut writer = batch::create(out)?;
+    let mut count = 0;
+    while let Some(pos) =
+        decode(&mut reader).map_err(|e| format!("position {}: {}", count + 1, e))?
+    {
+        writeln!(writer, "{}", pos.to_fen()).map_err(write_error)?;
+        count += 1;
+    }
+    writer.flush().map_err(write_error)
+}
+
+/// Print `count` positions drawn at random from the packed file at `path`.
+pub fn sample(path: &str, count: usize, rng: &mut Rng) -> Result<(), String> {
+    let dataset = Dataset::open(path)?;
+    for pos in dataset.sample(count, rng)? {
+        println!("{}", pos.to_fen());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(fen: &str) -> Vec<u8> {
+        let pos = Position::from_fen(fen).unwrap();
+        let bytes = encode(&pos).unwrap();
+        let mut reader = &bytes[..];
+        assert_eq!(decode(&mut reader), Ok(Some(pos)), "{}", fen);
+        assert!(reader.is_empty(), "{} left bytes unread", fen);
+        bytes
+    }
+
+    #[test]
+    fn positions_round_trip() {
+        assert_eq!(round_trip(crate::position::STARTING_FEN).len(), 27);
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w Kq d6 0 3",
+            "8/8/8/8/8/8/8/K6k b - - 99 300",
+        ] {
+            round_trip(fen);
+        }
+    }
+
+    #[test]
+    fn positions_stream_back_to_back() {
+        let fens = [
+            crate::position::STARTING_FEN,
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        ];
+        let mut bytes = Vec::new();
+        for fen in fens {
+            bytes.extend(encode(&Position::from_fen(fen).unwrap()).unwrap());
+        }
+        let mut reader = &bytes[..];
+        for fen in fens {
+            assert_eq!(decode(&mut reader).unwrap().unwrap().to_fen(), fen);
+        }
+        assert_eq!(decode(&mut reader), Ok(None));
+    }
+
+    #[test]
+    fn unpackable_positions() {
+        let no_rook = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w K - 0 1").unwrap();
+        assert!(encode(&no_rook).is_err());
+        let mut clocks = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        clocks.halfmove = 256;
+        assert!(encode(&clocks).is_err());
+        let truncated =
+            encode(&Position::from_fen(crate::position::STARTING_FEN).unwrap()).unwrap();
+        assert!(decode(&mut &truncated[..20]).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+//! Passed pawns and the rule of the square
+//!
+//! A pawn is passed when no enemy pawn stands ahead of it on its own or a
+//! neighbouring file. Its square is the region within as many king moves
+//! of its promotion square as the pawn needs moves to promote (counting
+//! the double step from its starting rank); a defending king inside it, or
+//! stepping into it with the move, catches the pawn when nothing else
+//! interferes.
+
+use crate::position::{
+    file_of, rank_of, square, square_name, Color, Piece, PieceKind, Position, Square,
+};
+
+pub struct Passer {
+    pub pawn: Square,
+    pub promotion: Square,
+    /// the squares from which the defending king catches the pawn
+    pub region: Vec<Square>,
+    pub description: String,
+}
+
+fn is_passed(pos: &Position, sq: Square, color: Color) -> bool {
+    let enemy = Some(Piece::new(color.opponent(), PieceKind::Pawn));
+    let ahead = |rank: u8| match color {
+        Color::White => rank > rank_of(sq),
+        Color::Black => rank < rank_of(sq),
+    };
+    (0..64u8).all(|other| {
+        pos.piece_at(other) != enemy
+            || file_of(other).abs_diff(file_of(sq)) > 1
+            || !ahead(rank_of(other))
+    })
+}
+
+fn distance(a: Square, b: Square) -> u8 {
+    file_of(a)
+        .abs_diff(file_of(b))
+        .max(rank_of(a).abs_diff(rank_of(b)))
+}
+
+/// Every passed pawn, White's first, with whether the defending king is in
+/// its square.
+pub fn find(pos: &Position) -> Vec<Passer> {
+    let mut found = Vec::new();
+    for color in [Color::White, Color::Black] {
+        let (last, start) = match color {
+            Color::White => (7, 1),
+            Color::Black => (0, 6),
+        };
+        for pawn in 0..64u8 {
+            if pos.piece_at(pawn) != Some(Piece::new(color, PieceKind::Pawn))
+                || !is_passed(pos, pawn, color)
+            {
+                continue;
+            }
+            let promotion = square(file_of(pawn), last);
+            let mut moves = rank_of(pawn).abs_diff(last);
+            if rank_of(pawn) == start {
+                moves -= 1;
+            }
+            let region: Vec<Square> = (0..64u8)
+                .filter(|&sq| distance(sq, promotion) <= moves)
+                .collect();
+
+            let side = match color {
+                Color::White => "White",
+                Color::Black => "Black",
+            };
+            let mut description = format!(
+                "{}'s pawn on {} is passed and promotes on {} in {} move{}",
+                side,
+                square_name(pawn),
+                square_name(promotion),
+                moves,
+                if moves == 1 { "" } else { "s" }
+            );
+            if let Some(king) = pos.king_square(color.opponent()) {
+                // with the move, the king may step into the square first
+                let tempo = (pos.side != color) as u8;
+                let inside = region.contains(&king);
+                let catches = distance(king, promotion) <= moves + tempo;
+                description.push_str(&match (inside, catches) {
+                    (true, _) => format!("; the king on {} is in its square", square_name(king)),
+                    (false, true) => {
+                        format!("; the king on {} steps into its square", square_name(king))
+                    }
+                    (false, false) => format!(
+                        "; the king on {} is outside its square and cannot catch it",
+                        square_name(king)
+                    ),
+                });
+            }
+            found.push(Passer {
+                pawn,
+                promotion,
+                region,
+                description,
+            });
+        }
+    }
+    found
+}
@@ -0,0 +1,127 @@
+//! Shortest routes for a single piece
+//!
+//! The piece moves while every other piece stays put, as if the opponent
+//! passed after each move: a breadth-first search over its legal moves,
+//! capturing whatever stands in the way. The opponent never replies, so
+//! these are the routes on an unchanging board, not ones that survive
+//! the opponent's moves. With `safe` it may only stop on squares the
+//! opponent's pieces do not attack.
+
+use crate::movegen::Move;
+use crate::position::{parse_square, Piece, PieceKind, Position, Square};
+use crate::zobrist;
+use std::collections::{HashSet, VecDeque};
+
+/// Read a piece given as `Ng1` (or just `g1`) and check that it stands there.
+fn start(pos: &Position, text: &str) -> Result<(Square, Piece), String> {
+    let (letter, name) = match text.chars().next() {
+        Some(c) if c.is_ascii_uppercase() => (Some(c), &text[1..]),
+        _ => (None, text),
+    };
+    let from = parse_square(name).ok_or_else(|| format!("Invalid square {}", name))?;
+    let piece = pos
+        .piece_at(from)
+        .ok_or_else(|| format!("There is no piece on {}", name))?;
+    if let Some(letter) = letter {
+        if PieceKind::from_letter(letter) != Some(piece.kind) {
+            return Err(format!("The piece on {} is not a {}", name, letter));
+        }
+    }
+    Ok((from, piece))
+}
+
+/// The fewest moves taking the piece on `from` to `to`, each move with the
+/// position it is played from; `None` if it cannot get there.
+pub fn find(
+    pos: &Position,
+    from: &str,
+    to: &str,
+    safe: bool,
+) -> Result<Option<Vec<(Position, Move)>>, String> {
+    let (from, piece) = start(pos, from)?;
+    let to = parse_square(to).ok_or_else(|| format!("Invalid square {}", to))?;
+    let mut first = pos.clone();
+    first.side = piece.color;
+    first.ep = None;
+
+    let mut seen = HashSet::from([zobrist::hash(&first)]);
+    let mut queue = VecDeque::from([(first, from, Vec::new())]);
+    while let Some((pos, at, route)) = queue.pop_front() {
+        if at == to {
+            return Ok(Some(route));
+        }
+        for mv in pos.legal_moves().into_iter().filter(|mv| mv.from == at) {
+            let mut next = pos.make_move(mv);
+            if safe && next.is_attacked(mv.to, piece.color.opponent()) {
+                continue;
+            }
+            // the opponent passes
+            next.side = piece.color;
+            next.ep = None;
+            if seen.insert(zobrist::hash(&next)) {
+                let mut route: Vec<(Position, Move)> = route.clone();
+                route.push((pos.clone(), mv));
+                queue.push_back((next, mv.to, route));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::{square_name, Color, STARTING_FEN};
+
+    fn squares(pos: &Position, from: &str, to: &str, safe: bool) -> Option<Vec<String>> {
+        let route = find(pos, from, to, safe).unwrap()?;
+        Some(route.iter().map(|(_, mv)| square_name(mv.to)).collect())
+    }
+
+    #[test]
+    fn knight_routes_are_shortest() {
+        let pos = Position::from_fen(STARTING_FEN).unwrap();
+        // g1 and f6 are both dark squares, so a knight needs an even number
+        // of moves between them
+        let route = squares(&pos, "Ng1", "f6", false).unwrap();
+        assert_eq!(route.len(), 4);
+        assert_eq!(route.last().map(String::as_str), Some("f6"));
+        assert_eq!(squares(&pos, "Ng1", "e6", false).map(|r| r.len()), Some(3));
+        assert_eq!(
+            squares(&pos, "g1", "f3", false),
+            Some(vec!["f3".to_string()])
+        );
+        assert_eq!(squares(&pos, "g1", "g1", false), Some(vec![]));
+        // each move is played from the position the last one left, with
+        // the opponent passing in between
+        let full = find(&pos, "Ng1", "f6", false).unwrap().unwrap();
+        for (before, mv) in &full {
+            assert_eq!(before.side, Color::White);
+            assert!(before.legal_moves().contains(mv));
+        }
+    }
+
+    #[test]
+    fn a_route_may_capture_but_safe_ones_avoid_attacked_squares() {
+        let pos = Position::from_fen(STARTING_FEN).unwrap();
+        // d7 holds a pawn, taken on the way in
+        assert_eq!(
+            squares(&pos, "Ng1", "d7", false),
+            Some(vec!["f3".to_string(), "e5".to_string(), "d7".to_string()])
+        );
+        // every square on the seventh rank is covered by a black piece
+        assert_eq!(squares(&pos, "Ng1", "d7", true), None);
+        let safe = squares(&pos, "Ng1", "f5", true).unwrap();
+        assert!(safe.len() >= 3, "{:?}", safe);
+    }
+
+    #[test]
+    fn blocked_and_misnamed_pieces() {
+        let blocked = Position::from_fen("4k3/8/8/8/8/4N3/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(squares(&blocked, "e2", "e4", false), None);
+        let pos = Position::from_fen(STARTING_FEN).unwrap();
+        assert!(find(&pos, "Bg1", "f3", false).is_err());
+        assert!(find(&pos, "e4", "e5", false).is_err());
+        assert!(find(&pos, "Ng1", "i9", false).is_err());
+    }
+}
@@ -0,0 +1,315 @@
+//! PDF export of board diagrams, for printing
+//!
+//! The diagrams are laid out in a grid, a given number to a page, each
+//! scaled to fit its cell. Everything is drawn as vector paths: squares and
+//! arrows as rectangles and polygons, and text and pieces as the outlines of
+//! their glyphs, so no fonts are embedded and the pages print sharply at any
+//! size. Pieces come from the `--piece-font`; piece sets and `shapes` are
+//! drawn with the bundled font, as the pictures and silhouettes only exist
+//! as pixels.
+
+use crate::diagram::{Diagram, Font, Rgba, Shape};
+use crate::fonts::{self, PieceFont};
+use crate::svg::num;
+use clap::ValueEnum;
+use rusttype::{point, OutlineBuilder, Scale};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum PageSize {
+    /// 210 × 297 mm
+    #[default]
+    A4,
+    /// 148 × 210 mm
+    A5,
+    /// 297 × 420 mm
+    A3,
+    /// 8.5 × 11 in
+    Letter,
+    /// 8.5 × 14 in
+    Legal,
+}
+
+impl PageSize {
+    /// width and height in points
+    fn points(self) -> (f64, f64) {
+        let mm = 72.0 / 25.4;
+        match self {
+            PageSize::A4 => (210.0 * mm, 297.0 * mm),
+            PageSize::A5 => (148.0 * mm, 210.0 * mm),
+            PageSize::A3 => (297.0 * mm, 420.0 * mm),
+            PageSize::Letter => (612.0, 792.0),
+            PageSize::Legal => (612.0, 1008.0),
+        }
+    }
+}
+
+/// How diagrams are put on pages
+#[derive(Clone, Copy, Debug)]
+pub struct Layout {
+    pub page: PageSize,
+    /// diagrams on each page, in a grid filled row by row
+    pub per_page: usize,
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout {
+            page: PageSize::default(),
+            per_page: 1,
+        }
+    }
+}
+
+/// space around the grid and between its cells, in points
+const MARGIN: f64 = 36.0;
+const GAP: f64 = 18.0;
+
+// glyph outlines as path operators, moved to the glyph's origin
+struct Outline<'a> {
+    out: &'a mut String,
+    x: f32,
+    y: f32,
+    last: (f32, f32),
+}
+
+impl Outline<'_> {
+    fn point(&self, x: f32, y: f32) -> String {
+        format!("{} {}", num((self.x + x) as f64), num((self.y + y) as f64))
+    }
+}
+
+impl OutlineBuilder for Outline<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.out.push_str(&format!("{} m\n", p));
+        self.last = (x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.out.push_str(&format!("{} l\n", p));
+        self.last = (x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        // PDF has only cubic curves; raise the degree of the quadratic one
+        let (x0, y0) = self.last;
+        let c1 = self.point(x0 + 2.0 / 3.0 * (x1 - x0), y0 + 2.0 / 3.0 * (y1 - y0));
+        let c2 = self.point(x + 2.0 / 3.0 * (x1 - x), y + 2.0 / 3.0 * (y1 - y));
+        let p = self.point(x, y);
+        self.out.push_str(&format!("{} {} {} c\n", c1, c2, p));
+        self.last = (x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (c1, c2, p) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.out.push_str(&format!("{} {} {} c\n", c1, c2, p));
+        self.last = (x, y);
+    }
+
+    fn close(&mut self) {
+        self.out.push_str("h\n");
+    }
+}
+
+// the operators setting the fill colour and, through a graphics state
+// named after it, the opacity
+fn paint(color: Rgba, alphas: &mut Vec<u8>) -> String {
+    let [r, g, b, a] = color;
+    if !alphas.contains(&a) {
+        alphas.push(a);
+    }
+    let channel = |c: u8| num(c as f64 / 255.0);
+    format!(
+        "/A{} gs {} {} {} rg\n",
+        a,
+        channel(r),
+        channel(g),
+        channel(b)
+    )
+}
+
+// the content stream drawing `diagram` with its top-left corner at (left,
+// top) on a page `height` points tall, `scale` points to a pixel
+fn draw(
+    diagram: &Diagram,
+    fonts: (&rusttype::Font, &rusttype::Font),
+    at: (f64, f64, f64),
+    height: f64,
+    alphas: &mut Vec<u8>,
+) -> String {
+    let (left, top, scale) = at;
+    // PDF counts y up from the bottom of the page; diagrams count it down
+    let mut out = format!(
+        "q {} 0 0 {} {} {} cm\n",
+        num(scale),
+        num(-scale),
+        num(left),
+        num(height - top)
+    );
+    for shape in &diagram.shapes {
+        match shape {
+            Shape::Rect { x, y, w, h, color } => {
+                out.push_str(&paint(*color, alphas));
+                out.push_str(&format!(
+                    "{} {} {} {} re f\n",
+                    num(*x),
+                    num(*y),
+                    num(*w),
+                    num(*h)
+                ));
+            }
+            Shape::Polygon { points, color } => {
+                out.push_str(&paint(*color, alphas));
+                for (i, (x, y)) in points.iter().enumerate() {
+                    let op = if i == 0 { "m" } else { "l" };
+                    out.push_str(&format!("{} {} {}\n", num(*x), num(*y), op));
+                }
+                out.push_str("h f\n");
+            }
+            Shape::Text {
+                x,
+                y,
+                size,
+                text,
+                color,
+                font,
+            } => {
+                let face = match font {
+                    Font::Text => fonts.0,
+                    Font::Pieces => fonts.1,
+                };
+                out.push_str(&paint(*color, alphas));
+                let glyphs = face.layout(
+                    text,
+                    Scale::uniform(*size as f32),
+                    point(*x as f32, *y as f32),
+                );
+                for glyph in glyphs {
+                    let at = glyph.position();
+                    glyph.unpositioned().build_outline(&mut Outline {
+                        out: &mut out,
+                        x: at.x,
+                        y: at.y,
+                        last: (0.0, 0.0),
+                    });
+                }
+                out.push_str("f\n");
+            }
+        }
+    }
+    out.push_str("Q\n");
+    out
+}
+
+/// The diagrams as the pages of a PDF document, drawing chess glyphs with
+/// `pieces`.
+pub fn to_pdf(diagrams: &[Diagram], pieces: &PieceFont, layout: Layout) -> Vec<u8> {
+    let text_font = fonts::text_font();
+    let piece_font = match pieces {
+        PieceFont::Font(font) => font.clone(),
+        _ => rusttype::Font::try_from_bytes(fonts::PIECE_FONT).expect("bundled font is valid"),
+    };
+    let (width, height) = layout.page.points();
+    let per_page = layout.per_page.max(1);
+    let cell = |cols: usize| {
+        let rows = per_page.div_ceil(cols);
+        (
+            (width - 2.0 * MARGIN - (cols - 1) as f64 * GAP) / cols as f64,
+            (height - 2.0 * MARGIN - (rows - 1) as f64 * GAP) / rows as f64,
+        )
+    };
+    // the number of columns drawing the first diagram largest
+    let fit = |cols: usize| {
+        let (w, h) = cell(cols);
+        diagrams
+            .first()
+            .map_or(w.min(h), |d| (w / d.width).min(h / d.height))
+    };
+    let cols = (1..=per_page)
+        .max_by(|&a, &b| fit(a).total_cmp(&fit(b)))
+        .unwrap_or(1);
+    let (cell_w, cell_h) = cell(cols);
+
+    // objects 1 and 2 are the catalogue and the page tree; each page then
+    // takes two, itself and its content
+    let pages: Vec<&[Diagram]> = diagrams.chunks(per_page).collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len())
+                .map(|i| format!("{} 0 R", 3 + 2 * i))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        ),
+    ];
+    for (p, page) in pages.iter().enumerate() {
+        let mut alphas = Vec::new();
+        let mut content = String::new();
+        for (i, diagram) in page.iter().enumerate() {
+            let (col, row) = (i % cols, i / cols);
+            let scale = (cell_w / diagram.width).min(cell_h / diagram.height);
+            let left =
+                MARGIN + col as f64 * (cell_w + GAP) + (cell_w - scale * diagram.width) / 2.0;
+            let top = MARGIN + row as f64 * (cell_h + GAP);
+            content.push_str(&draw(
+                diagram,
+                (&text_font, &piece_font),
+                (left, top, scale),
+                height,
+                &mut alphas,
+            ));
+        }
+        let states = alphas
+            .iter()
+            .map(|a| format!("/A{} << /ca {} >>", a, num(*a as f64 / 255.0)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R /Resources << /ExtGState << {} >> >> >>",
+            num(width),
+            num(height),
+            4 + 2 * p,
+            states
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref = out.len();
+    out.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        out.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    out.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    out.into_bytes()
+}
+
+/// Write the diagrams to the PDF file `path`.
+pub fn save(
+    diagrams: &[Diagram],
+    path: &str,
+    pieces: &PieceFont,
+    layout: Layout,
+) -> Result<(), String> {
+    std::fs::write(path, to_pdf(diagrams, pieces, layout))
+        .map_err(|e| format!("Could not write {}: {}", path, e))
+}
@@ -0,0 +1,360 @@
+//! Portable Game Notation (PGN) input and output
+
+use crate::movegen::Move;
+use crate::nag;
+use crate::position::{Color, Position, STARTING_FEN};
+use crate::san::{parse_move, to_san};
+use crate::zobrist;
+
+pub struct Game {
+    pub tags: Vec<(String, String)>,
+    pub start: Position,
+    pub moves: Vec<Move>,
+    pub result: String,
+}
+
+impl Game {
+    pub fn new(start: Position) -> Game {
+        Game {
+            tags: Vec::new(),
+            start,
+            moves: Vec::new(),
+            result: "*".to_string(),
+        }
+    }
+
+    /// the position after every move has been played
+    pub fn final_position(&self) -> Position {
+        self.moves
+            .iter()
+            .fold(self.start.clone(), |pos, &mv| pos.make_move(mv))
+    }
+
+    /// the starting position followed by the position after each move
+    pub fn positions(&self) -> Vec<Position> {
+        let mut positions = vec![self.start.clone()];
+        for &mv in &self.moves {
+            let next = positions[positions.len() - 1].make_move(mv);
+            positions.push(next);
+        }
+        positions
+    }
+
+    /// how many times the final position has occurred, counting itself
+    pub fn repetitions(&self) -> usize {
+        let keys: Vec<u64> = self.positions().iter().map(zobrist::hash).collect();
+        occurrences(&keys)
+    }
+
+    /// Set the result from the final position: decisive on checkmate,
+    /// drawn on stalemate or fivefold repetition, otherwise left as
+    /// unfinished (`*`).
+    pub fn conclude(&mut self) {
+        let last = self.final_position();
+        self.result = if last.is_checkmate() {
+            match last.side {
+                Color::White => "0-1",
+                Color::Black => "1-0",
+            }
+        } else if last.is_stalemate() || self.repetitions() >= 5 {
+            "1/2-1/2"
+        } else {
+            "*"
+        }
+        .to_string();
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// SAN for each move, in order
+    pub fn san_moves(&self) -> Vec<String> {
+        let mut pos = self.start.clone();
+        let mut out = Vec::with_capacity(self.moves.len());
+        for &mv in &self.moves {
+            out.push(to_san(&pos, mv));
+            pos = pos.make_move(mv);
+        }
+        out
+    }
+
+    /// Serialise as PGN: the Seven Tag Roster, then SetUp/FEN tags
+    /// recording the starting position, then any other tags and the movetext.
+    pub fn to_pgn(&self) -> String {
+        self.write(false)
+    }
+
+    /// As `to_pgn`, with figurines for the piece letters of the moves. Such
+    /// files are for reading, since most PGN software expects letters.
+    pub fn to_figurine_pgn(&self) -> String {
+        self.write(true)
+    }
+
+    fn write(&self, figurine: bool) -> String {
+        let mut out = String::new();
+        for (name, default) in [
+            ("Event", "?"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "?"),
+            ("White", "?"),
+            ("Black", "?"),
+        ] {
+            let value = self.tag(name).unwrap_or(default);
+            out.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+        }
+        out.push_str(&format!("[Result \"{}\"]\n", self.result));
+        out.push_str("[SetUp \"1\"]\n");
+        out.push_str(&format!("[FEN \"{}\"]\n", self.start.to_fen()));
+        for (name, value) in &self.tags {
+            if ![
+                "Event", "Site", "Date", "Round", "White", "Black", "Result", "SetUp", "FEN",
+            ]
+            .contains(&name.as_str())
+            {
+                out.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+            }
+        }
+        out.push('\n');
+
+        let mut tokens: Vec<String> = Vec::new();
+        let mut number = self.start.fullmove;
+        let mut side = self.start.side;
+        for (i, san) in self.san_moves().into_iter().enumerate() {
+            if side == Color::White {
+                tokens.push(format!("{}.", number));
+            } else if i == 0 {
+                tokens.push(format!("{}...", number));
+            }
+            tokens.push(if figurine {
+                crate::san::figurine(&san)
+            } else {
+                san
+            });
+            if side == Color::Black {
+                number += 1;
+            }
+            side = side.opponent();
+        }
+        tokens.push(self.result.clone());
+        out.push_str(&wrap(&tokens, 79));
+        out.push('\n');
+        out
+    }
+}
+
+/// How often the last of `keys` (Zobrist keys of the positions of a game,
+/// in order) has occurred, counting itself.
+pub fn occurrences(keys: &[u64]) -> usize {
+    keys.last()
+        .map_or(0, |last| keys.iter().filter(|&k| k == last).count())
+}
+
+/// What a position occurring `count` times means for the game: a draw can
+/// be claimed at three and the game is drawn at five.
+pub fn repetition_note(count: usize) -> Option<String> {
+    match count {
+        0..=2 => None,
+        3 | 4 => Some(format!(
+            "Position has occurred {} times: a draw can be claimed",
+            count
+        )),
+        _ => Some(format!(
+            "Position has occurred {} times: the game is drawn",
+            count
+        )),
+    }
+}
+
+/// Parse every game in a PGN file. Comments, variations and NAGs are
+/// skipped; a `FEN` tag gives the starting position.
+pub fn parse_games(text: &str) -> Result<Vec<Game>, String> {
+    let mut games = Vec::new();
+    let mut tags: Vec<(String, String)> = Vec::new();
+    // the game being read and its current position
+    let mut game: Option<(Game, Position)> = None;
+    let mut chars = text.chars().peekable();
+    let mut depth = 0;
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => {
+                line_start = true;
+                continue;
+            }
+            '%' if line_start => {
+                // escaped line
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '{' => for _ in chars.by_ref().take_while(|&c| c != '}') {},
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '[' if depth == 0 => {
+                if let Some((done, _)) = game.take() {
+                    games.push(done);
+                }
+                let mut inner = String::new();
+                let mut quoted = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' if quoted => inner.extend(chars.next()),
+                        '"' => quoted = !quoted,
+                        ']' if !quoted => break,
+                        _ => inner.push(c),
+                    }
+                }
+                if let Some((name, value)) = inner.trim().split_once(char::is_whitespace) {
+                    tags.push((name.to_string(), value.trim().to_string()));
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "[{}();".contains(c) {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+                if depth == 0 {
+                    let number = games.len() + 1;
+                    if game.is_none() {
+                        game = Some(
+                            start_game(&mut tags).map_err(|e| format!("game {}: {}", number, e))?,
+                        );
+                    }
+                    if let Some((game, pos)) = &mut game {
+                        movetext_token(game, pos, &token)
+                            .map_err(|e| format!("game {}: {}", number, e))?;
+                    }
+                }
+            }
+        }
+        line_start = false;
+    }
+    if let Some((done, _)) = game {
+        games.push(done);
+    }
+    Ok(games)
+}
+
+/// every game in the PGN file at `path`
+pub fn read_games(path: &str) -> Result<Vec<Game>, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    parse_games(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// the first game in the PGN file at `path`
+pub fn first_game(path: &str) -> Result<Game, String> {
+    read_games(path)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("No games in {}", path))
+}
+
+// begin a game from the tags read so far, which are consumed
+fn start_game(tags: &mut Vec<(String, String)>) -> Result<(Game, Position), String> {
+    let fen = tags
+        .iter()
+        .find(|(n, _)| n == "FEN")
+        .map(|(_, v)| v.as_str())
+        .unwrap_or(STARTING_FEN);
+    let start = Position::from_fen(fen)?;
+    let mut game = Game::new(start.clone());
+    for (name, value) in tags.drain(..) {
+        if name == "Result" {
+            game.result = value;
+        } else if name != "SetUp" && name != "FEN" {
+            game.tags.push((name, value));
+        }
+    }
+    Ok((game, start))
+}
+
+fn movetext_token(game: &mut Game, pos: &mut Position, token: &str) -> Result<(), String> {
+    if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token) {
+        game.result = token.to_string();
+        return Ok(());
+    }
+    if token.starts_with('$') {
+        return Ok(());
+    }
+    // move numbers, possibly run into the move as in `12.e4`
+    let digits = token.trim_start_matches(|c: char| c.is_ascii_digit());
+    let token = if digits.starts_with('.') {
+        digits.trim_start_matches('.')
+    } else {
+        token
+    };
+    let (token, _) = nag::split_suffix(token);
+    if token.is_empty() {
+        return Ok(());
+    }
+    let mv = parse_move(pos, token).map_err(|e| format!("move {}: {}", pos.fullmove, e))?;
+    game.moves.push(mv);
+    *pos = pos.make_move(mv);
+    Ok(())
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// PGN export format keeps movetext lines under 80 characters
+fn wrap(tokens: &[String], width: usize) -> String {
+    let mut out = String::new();
+    let mut line_len = 0;
+    for token in tokens {
+        if line_len > 0 && line_len + 1 + token.len() > width {
+            out.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            out.push(' ');
+            line_len += 1;
+        }
+        out.push_str(token);
+        line_len += token.len();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repetitions_of_the_final_position() {
+        let games = parse_games("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 *").unwrap();
+        assert_eq!(games[0].repetitions(), 3);
+        assert_eq!(occurrences(&[]), 0);
+        assert_eq!(occurrences(&[1, 2, 1, 3]), 1);
+        assert_eq!(repetition_note(2), None);
+        assert!(repetition_note(3).unwrap().contains("claimed"));
+        assert!(repetition_note(5).unwrap().contains("drawn"));
+    }
+}
@@ -0,0 +1,270 @@
+//! User piece sets, installed as images under the configuration directory
+//!
+//! Each set is a directory `pieces/<name>/` under `~/.config/fen` (see
+//! `store::config_dir`) holding twelve images, one a piece, and a
+//! `manifest.json` naming them:
+//!
+//! ```text
+//! { "name": "Merida", "author": "Armando Marroquin",
+//!   "pieces": { "K": "wK.png", "Q": "wQ.png", ..., "p": "bP.png" } }
+//! ```
+//!
+//! Pieces are keyed by FEN letter; by default they are looked for as
+//! `wK.png` to `bP.png`, or as `wK.svg` to `bP.svg` where there is no PNG.
+//! Each image fills its square, so the piece should sit in it with some
+//! margin. Only PNG images can be read, as there is no SVG renderer; a set
+//! with SVG pieces is listed as unsupported, and loading it fails.
+
+use crate::json::Json;
+use crate::store::config_dir;
+use image::RgbaImage;
+use std::path::{Path, PathBuf};
+
+/// FEN letters, in the order the images are kept
+const LETTERS: [char; 12] = ['P', 'N', 'B', 'R', 'Q', 'K', 'p', 'n', 'b', 'r', 'q', 'k'];
+
+pub struct PieceSet {
+    pub name: String,
+    /// a picture of each piece, White's pawn first, in `LETTERS` order
+    images: Vec<RgbaImage>,
+}
+
+impl PieceSet {
+    /// the picture of the piece with FEN letter `letter`
+    pub fn image(&self, letter: char) -> Option<&RgbaImage> {
+        LETTERS
+            .iter()
+            .position(|&l| l == letter)
+            .map(|i| &self.images[i])
+    }
+}
+
+/// an installed set, as `--list-piece-sets` shows it
+pub struct Installed {
+    /// the directory name, which `--piece-set` takes
+    pub name: String,
+    /// as the manifest gives it
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// why the set cannot be used, if it cannot
+    pub problem: Option<String>,
+}
+
+/// the directory the sets are installed in
+pub fn sets_dir() -> Result<PathBuf, String> {
+    Ok(config_dir()?.join("pieces"))
+}
+
+// the file of each piece in `LETTERS` order, from the manifest in `dir`
+fn files(dir: &Path) -> Result<(Json, Vec<PathBuf>), String> {
+    let path = dir.join("manifest.json");
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let manifest = Json::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let files = LETTERS
+        .iter()
+        .map(|&letter| {
+            let stem = format!(
+                "{}{}",
+                if letter.is_ascii_uppercase() {
+                    'w'
+                } else {
+                    'b'
+                },
+                letter.to_ascii_uppercase()
+            );
+            let named = manifest
+                .get("pieces")
+                .and_then(|pieces| pieces.get(&letter.to_string()))
+                .and_then(Json::as_str);
+            match named {
+                Some(file) => dir.join(file),
+                None => {
+                    let png = dir.join(format!("{}.png", stem));
+                    let svg = dir.join(format!("{}.svg", stem));
+                    if !png.is_file() && svg.is_file() {
+                        svg
+                    } else {
+                        png
+                    }
+                }
+            }
+        })
+        .collect();
+    Ok((manifest, files))
+}
+
+fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("svg"))
+}
+
+fn read(path: &Path) -> Result<RgbaImage, String> {
+    if is_svg(path) {
+        return Err(format!(
+            "{} is an SVG image, and only PNG pieces can be drawn",
+            path.display()
+        ));
+    }
+    image::open(path)
+        .map(|image| image.to_rgba8())
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))
+}
+
+/// Load the set installed as `name`.
+pub fn load(name: &str) -> Result<PieceSet, String> {
+    let dir = sets_dir()?.join(name);
+    if !dir.is_dir() {
+        return Err(format!(
+            "There is no piece set {} in {}",
+            name,
+            sets_dir()?.display()
+        ));
+    }
+    let (_, files) = files(&dir)?;
+    let images = files
+        .iter()
+        .map(|file| read(file))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(PieceSet {
+        name: name.to_string(),
+        images,
+    })
+}
+
+// the set in `dir`, with the first reason it cannot be used
+fn check(name: String, dir: &Path) -> Installed {
+    match files(dir) {
+        Ok((manifest, files)) => {
+            let text = |key: &str| manifest.get(key).and_then(Json::as_str).map(String::from);
+            let svg = files.iter().filter(|file| is_svg(file)).count();
+            let problem = if svg > 0 {
+                Some(format!(
+                    "{} of its pieces are SVG images, which are not supported (only PNG)",
+                    svg
+                ))
+            } else {
+                files.iter().find_map(|file| {
+                    if !file.is_file() {
+                        Some(format!("{} is missing", file.display()))
+                    } else {
+                        read(file).err()
+                    }
+                })
+            };
+            Installed {
+                name,
+                title: text("name"),
+                author: text("author"),
+                problem,
+            }
+        }
+        Err(e) => Installed {
+            name,
+            title: None,
+            author: None,
+            problem: Some(e),
+        },
+    }
+}
+
+/// Every set installed, by name, each checked for use.
+pub fn installed() -> Result<Vec<Installed>, String> {
+    let dir = sets_dir()?;
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut sets: Vec<Installed> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            check(name, &entry.path())
+        })
+        .collect();
+    sets.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(sets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a directory of its own for each test, with `files` written in it
+    fn set_dir(test: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("fen-pieceset-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, contents) in files {
+            std::fs::write(dir.join(name), contents).unwrap();
+        }
+        dir
+    }
+
+    fn stems() -> Vec<String> {
+        LETTERS
+            .iter()
+            .map(|l| {
+                format!(
+                    "{}{}",
+                    if l.is_ascii_uppercase() { 'w' } else { 'b' },
+                    l.to_ascii_uppercase()
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_png_set_is_usable() {
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(RgbaImage::new(2, 2))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let names: Vec<String> = stems().iter().map(|s| format!("{}.png", s)).collect();
+        let mut files: Vec<(&str, &[u8])> = names.iter().map(|n| (n.as_str(), &png[..])).collect();
+        files.push(("manifest.json", br#"{"name": "Tiny", "author": "Me"}"#));
+        let dir = set_dir("png", &files);
+        let set = check("tiny".to_string(), &dir);
+        assert_eq!(set.problem, None);
+        assert_eq!(set.title.as_deref(), Some("Tiny"));
+        assert_eq!(set.author.as_deref(), Some("Me"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn an_svg_set_is_listed_as_unsupported() {
+        let names: Vec<String> = stems().iter().map(|s| format!("{}.svg", s)).collect();
+        let mut files: Vec<(&str, &[u8])> =
+            names.iter().map(|n| (n.as_str(), &b"<svg/>"[..])).collect();
+        files.push(("manifest.json", b"{}"));
+        let dir = set_dir("svg", &files);
+        let problem = check("cburnett".to_string(), &dir).problem.unwrap();
+        assert!(
+            problem.starts_with("12 of its pieces are SVG images"),
+            "{}",
+            problem
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_pieces_and_manifests_are_reported() {
+        let dir = set_dir(
+            "missing",
+            &[("manifest.json", br#"{"pieces": {"K": "king.svg"}}"#)],
+        );
+        let problem = check("odd".to_string(), &dir).problem.unwrap();
+        assert!(problem.starts_with("1 of its pieces"), "{}", problem);
+        std::fs::write(dir.join("manifest.json"), "{}").unwrap();
+        let problem = check("odd".to_string(), &dir).problem.unwrap();
+        assert!(problem.ends_with("wP.png is missing"), "{}", problem);
+        std::fs::remove_file(dir.join("manifest.json")).unwrap();
+        assert!(check("odd".to_string(), &dir)
+            .problem
+            .unwrap()
+            .starts_with("Could not read"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,339 @@
+//! A game against a UCI engine in the terminal, with clocks
+//!
+//! Moves are typed in SAN or UCI notation; `resign` and `draw` (offering
+//! one, which the engine takes when it does not think it is better) end
+//! the game early, and `takeback` asks to take back the last move. The
+//! engine keeps to the manners of `Conduct`: it resigns a lost game, offers
+//! a draw in a dead level one and will not take back a move that has handed
+//! it a winning game. The clocks run while each side thinks, with the
+//! increment added after every move, and a side whose time runs out loses.
+
+use crate::movegen::Move;
+use crate::pgn::Game;
+use crate::position::{Color, Position};
+use crate::san::{parse_move, promotions, to_san};
+use crate::selfplay::is_drawn;
+use crate::terminal::ask_promotion;
+use crate::uci::Engine;
+use crate::zobrist;
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
+
+/// base time and increment, both in milliseconds
+#[derive(Clone, Copy, Debug)]
+pub struct TimeControl {
+    pub base: u64,
+    pub increment: u64,
+}
+
+impl TimeControl {
+    /// Parse minutes and an optional increment in seconds, e.g. `5+3`,
+    /// `0.5+1` or `15`.
+    pub fn parse(text: &str) -> Result<TimeControl, String> {
+        let (minutes, increment) = text.split_once('+').unwrap_or((text, "0"));
+        let error = || format!("Expected a time control such as 5+3, found {}", text);
+        let minutes: f64 = minutes.trim().parse().map_err(|_| error())?;
+        let increment: f64 = increment.trim().parse().map_err(|_| error())?;
+        if minutes <= 0.0 || increment < 0.0 {
+            return Err(error());
+        }
+        Ok(TimeControl {
+            base: (minutes * 60_000.0).round() as u64,
+            increment: (increment * 1000.0).round() as u64,
+        })
+    }
+
+    /// the PGN TimeControl tag, in seconds
+    fn tag(self) -> String {
+        let seconds = |ms: u64| {
+            if ms.is_multiple_of(1000) {
+                (ms / 1000).to_string()
+            } else {
+                format!("{}", ms as f64 / 1000.0)
+            }
+        };
+        format!("{}+{}", seconds(self.base), seconds(self.increment))
+    }
+}
+
+/// a clock reading as m:ss, with tenths in the last twenty seconds
+fn format_clock(ms: i64) -> String {
+    let ms = ms.max(0);
+    if ms < 20_000 {
+        format!("0:{:02}.{}", ms / 1000, (ms % 1000) / 100)
+    } else {
+        format!("{}:{:02}", ms / 60_000, (ms / 1000) % 60)
+    }
+}
+
+/// the name of the side `color`, as in the PGN tags
+pub fn name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
+
+/// the result of a game won by `color`
+pub fn win_for(color: Color) -> &'static str {
+    match color {
+        Color::White => "1-0",
+        Color::Black => "0-1",
+    }
+}
+
+/// an engine score this low for itself, in centipawns, is a lost game
+const RESIGN_CP: i32 = -1000;
+/// searches in a row scoring the game lost before the engine resigns
+const RESIGN_SEARCHES: usize = 3;
+/// a score within this many centipawns of level is a dead draw
+const DRAW_CP: i32 = 10;
+/// searches in a row scoring the game level before the engine offers a draw
+const DRAW_SEARCHES: usize = 8;
+/// the engine offers no draw before this move
+const DRAW_AFTER: u32 = 40;
+/// a score this high for the engine is a won game, which it will not give
+/// back with a takeback
+const WINNING_CP: i32 = 300;
+
+/// something the engine says across the board, besides its moves
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gesture {
+    Resign,
+    OfferDraw,
+}
+
+/// How the engine behaves towards the player, judged from the scores of
+/// its searches: it accepts a draw unless it thinks it is better, resigns
+/// after a few searches in a row have scored the game lost, offers a draw
+/// when late in the game a run of searches has found it level, and grants
+/// a takeback unless the move taken back left it winning.
+#[derive(Clone, Debug, Default)]
+pub struct Conduct {
+    /// the score of the last search, for the engine
+    score: Option<i32>,
+    hopeless: usize,
+    level: usize,
+}
+
+impl Conduct {
+    /// Take in the score, for the engine, of a search at move `fullmove`,
+    /// and say whether the engine resigns or offers a draw instead of (or
+    /// along with) its move.
+    pub fn searched(&mut self, score: Option<i32>, fullmove: u32) -> Option<Gesture> {
+        self.score = score;
+        let cp = score?;
+        self.hopeless = if cp <= RESIGN_CP {
+            self.hopeless + 1
+        } else {
+            0
+        };
+        self.level = if cp.abs() <= DRAW_CP {
+            self.level + 1
+        } else {
+            0
+        };
+        if self.hopeless >= RESIGN_SEARCHES {
+            Some(Gesture::Resign)
+        } else if self.level >= DRAW_SEARCHES && fullmove >= DRAW_AFTER {
+            self.level = 0;
+            Some(Gesture::OfferDraw)
+        } else {
+            None
+        }
+    }
+
+    pub fn accepts_draw(&self) -> bool {
+        self.score.is_some_and(|cp| cp <= 0)
+    }
+
+    pub fn grants_takeback(&self) -> bool {
+        self.score.is_none_or(|cp| cp < WINNING_CP)
+    }
+
+    /// Forget the searches of moves taken back.
+    pub fn taken_back(&mut self) {
+        *self = Conduct::default();
+    }
+}
+
+/// what the player typed on their turn
+enum Answer {
+    Move(Move),
+    Resign,
+    Draw,
+    Takeback,
+    Quit,
+}
+
+fn ask(pos: &Position) -> Answer {
+    let stdin = io::stdin();
+    loop {
+        print!("Move (or resign, draw, takeback)? ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            return Answer::Quit;
+        }
+        let line = line.trim();
+        match line {
+            "" => continue,
+            "resign" => return Answer::Resign,
+            "draw" => return Answer::Draw,
+            "takeback" => return Answer::Takeback,
+            "quit" => return Answer::Quit,
+            _ => {}
+        }
+        match parse_move(pos, line).or_else(|e| ask_promotion(&promotions(pos, line)).ok_or(e)) {
+            Ok(mv) => return Answer::Move(mv),
+            Err(e) => println!("{}, try again", e),
+        }
+    }
+}
+
+/// Play `engine` from `start`, the player taking `human`, under `tc`.
+/// `on_move` is called with each new position and the move that led to
+/// it, or no move after a takeback, so callers can show the board. The
+/// finished game is returned with its result, or `*` if the player quit.
+pub fn play(
+    start: Position,
+    engine: &mut Engine,
+    tc: TimeControl,
+    human: Color,
+    mut on_move: impl FnMut(&Position, Option<&str>),
+) -> Result<Game, String> {
+    let mut game = Game::new(start.clone());
+    game.set_tag("Event", "fen play");
+    game.set_tag(name(human), "Human");
+    game.set_tag(name(human.opponent()), &engine.name);
+    game.set_tag("TimeControl", &tc.tag());
+    engine.new_game()?;
+
+    let fen = start.to_fen();
+    let mut pos = start;
+    let mut played: Vec<String> = Vec::new();
+    let mut seen = vec![zobrist::hash(&pos)];
+    // milliseconds left for White and Black
+    let mut clocks = [tc.base as i64, tc.base as i64];
+    let clock = |color: Color| match color {
+        Color::White => 0,
+        Color::Black => 1,
+    };
+    let mut conduct = Conduct::default();
+    // a draw the engine offered with its last move, taken by answering `draw`
+    let mut offered = false;
+    let ending = loop {
+        if pos.legal_moves().is_empty() {
+            game.conclude();
+            break if pos.in_check() {
+                "checkmate".to_string()
+            } else {
+                "stalemate".to_string()
+            };
+        }
+        if is_drawn(&pos, &seen) {
+            game.result = "1/2-1/2".to_string();
+            break "draw by rule".to_string();
+        }
+        println!(
+            "White {}  Black {}",
+            format_clock(clocks[0]),
+            format_clock(clocks[1])
+        );
+
+        let side = pos.side;
+        let started = Instant::now();
+        let mv = if side == human {
+            match ask(&pos) {
+                Answer::Move(mv) => {
+                    offered = false;
+                    mv
+                }
+                Answer::Resign => {
+                    game.result = win_for(side.opponent()).to_string();
+                    break format!("{} resigned", name(side));
+                }
+                Answer::Draw => {
+                    if offered || conduct.accepts_draw() {
+                        println!("{} accepts the draw", engine.name);
+                        game.result = "1/2-1/2".to_string();
+                        break "draw agreed".to_string();
+                    }
+                    println!("{} declines the draw", engine.name);
+                    clocks[clock(side)] -= started.elapsed().as_millis() as i64;
+                    continue;
+                }
+                Answer::Takeback => {
+                    clocks[clock(side)] -= started.elapsed().as_millis() as i64;
+                    // the player's last move and the engine's reply
+                    if game.moves.len() < 2 {
+                        println!("There is no move of yours to take back");
+                    } else if !conduct.grants_takeback() {
+                        println!("{} declines the takeback", engine.name);
+                    } else {
+                        println!("{} agrees to the takeback", engine.name);
+                        let kept = game.moves.len() - 2;
+                        game.moves.truncate(kept);
+                        played.truncate(kept);
+                        seen.truncate(kept + 1);
+                        pos = game.final_position();
+                        conduct.taken_back();
+                        offered = false;
+                        on_move(&pos, None);
+                    }
+                    continue;
+                }
+                Answer::Quit => break "game abandoned".to_string(),
+            }
+        } else {
+            let go = format!(
+                "wtime {} btime {} winc {} binc {}",
+                clocks[0].max(1),
+                clocks[1].max(1),
+                tc.increment,
+                tc.increment
+            );
+            let analysis = engine.analyse(&fen, &played, &go)?;
+            let score = analysis.lines.first().map(|l| l.score.centipawns());
+            match conduct.searched(score, pos.fullmove) {
+                Some(Gesture::Resign) => {
+                    println!("{} resigns", engine.name);
+                    game.result = win_for(human).to_string();
+                    break format!("{} resigned", name(side));
+                }
+                Some(Gesture::OfferDraw) => {
+                    println!("{} offers a draw (answer draw to accept)", engine.name);
+                    offered = true;
+                }
+                None => {}
+            }
+            let reply = analysis
+                .best
+                .ok_or_else(|| format!("Engine {} returned no move", engine.name))?;
+            Move::from_uci(&reply)
+                .filter(|&m| pos.is_legal(m))
+                .ok_or_else(|| format!("Engine {} played illegal move {}", engine.name, reply))?
+        };
+        let used = started.elapsed().as_millis() as i64;
+        clocks[clock(side)] -= used;
+        if clocks[clock(side)] <= 0 {
+            game.result = win_for(side.opponent()).to_string();
+            game.set_tag("Termination", "time forfeit");
+            break format!("{} lost on time", name(side));
+        }
+        clocks[clock(side)] += tc.increment as i64;
+
+        let number = match side {
+            Color::White => format!("{}.", pos.fullmove),
+            Color::Black => format!("{}...", pos.fullmove),
+        };
+        let san = format!("{} {}", number, to_san(&pos, mv));
+        game.moves.push(mv);
+        played.push(mv.to_uci());
+        pos = pos.make_move(mv);
+        seen.push(zobrist::hash(&pos));
+        on_move(&pos, Some(&san));
+    };
+    println!("{} ({})", game.result, ending);
+    Ok(game)
+}
@@ -0,0 +1,310 @@
+//! Raster (PNG) export of board diagrams
+//!
+//! The bundled fonts are compiled in, so image export works from any
+//! directory, unlike the window. With them `render_to_rgba` draws the same
+//! pixels on every platform, which suits golden-image tests.
+
+use crate::diagram::{self, Diagram, DiagramOptions, Font, Quality, Rgba, Shape};
+use crate::fonts::{self, PieceFont};
+use crate::pieceset::PieceSet;
+use crate::position::{Color, Position};
+use image::imageops::FilterType;
+use image::RgbaImage;
+use rusttype::{point, Scale};
+
+fn blend(image: &mut RgbaImage, x: i64, y: i64, color: Rgba, coverage: f32) {
+    if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+        return;
+    }
+    let alpha = coverage * color[3] as f32 / 255.0;
+    let pixel = image.get_pixel_mut(x as u32, y as u32);
+    for c in 0..3 {
+        pixel[c] = (pixel[c] as f32 * (1.0 - alpha) + color[c] as f32 * alpha).round() as u8;
+    }
+    pixel[3] = pixel[3].max((alpha * 255.0) as u8);
+}
+
+// 4x4 supersampled coverage of the pixels in a bounding box
+fn paint(
+    image: &mut RgbaImage,
+    (x0, y0, x1, y1): (f64, f64, f64, f64),
+    color: Rgba,
+    inside: impl Fn(f64, f64) -> bool,
+) {
+    for py in y0.floor() as i64..y1.ceil() as i64 {
+        for px in x0.floor() as i64..x1.ceil() as i64 {
+            let hits = (0..16)
+                .filter(|i| {
+                    let sx = px as f64 + (i % 4) as f64 / 4.0 + 0.125;
+                    let sy = py as f64 + (i / 4) as f64 / 4.0 + 0.125;
+                    inside(sx, sy)
+                })
+                .count();
+            if hits > 0 {
+                blend(image, px, py, color, hits as f32 / 16.0);
+            }
+        }
+    }
+}
+
+// (left, top, right, bottom) of a polygon
+fn bounds(points: &[[f64; 2]]) -> (f64, f64, f64, f64) {
+    points.iter().fold(
+        (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+        |(x0, y0, x1, y1), p| (x0.min(p[0]), y0.min(p[1]), x1.max(p[0]), y1.max(p[1])),
+    )
+}
+
+fn contains(points: &[[f64; 2]], x: f64, y: f64) -> bool {
+    let mut inside = false;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        if (a[1] > y) != (b[1] > y) && x < a[0] + (y - a[1]) / (b[1] - a[1]) * (b[0] - a[0]) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn near_edge(points: &[[f64; 2]], x: f64, y: f64, width: f64) -> bool {
+    points.iter().enumerate().any(|(i, a)| {
+        let b = points[(i + 1) % points.len()];
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let t = (((x - a[0]) * dx + (y - a[1]) * dy) / (dx * dx + dy * dy)).clamp(0.0, 1.0);
+        let (ex, ey) = (a[0] + t * dx - x, a[1] + t * dy - y);
+        ex * ex + ey * ey <= width * width / 4.0
+    })
+}
+
+// draw a chess glyph as vector shapes, filled when solid and outlined otherwise
+fn silhouette(image: &mut RgbaImage, glyph: char, x: f64, y: f64, size: f64, color: Rgba) {
+    let (parts, solid) = match fonts::silhouette(glyph) {
+        Some(s) => s,
+        None => return,
+    };
+    let (left, top, edge) = fonts::silhouette_box(x, y, size);
+    let width = 0.04 * edge;
+    let parts: Vec<Vec<[f64; 2]>> = parts
+        .iter()
+        .map(|part| {
+            part.iter()
+                .map(|p| [left + p[0] * edge, top + p[1] * edge])
+                .collect()
+        })
+        .collect();
+    for (i, points) in parts.iter().enumerate() {
+        let (x0, y0, x1, y1) = bounds(points);
+        let bounds = (x0 - width, y0 - width, x1 + width, y1 + width);
+        if solid {
+            paint(image, bounds, color, |x, y| contains(points, x, y));
+        } else {
+            // outline the union: skip edges that lie inside another part
+            paint(image, bounds, color, |x, y| {
+                near_edge(points, x, y, width)
+                    && !parts
+                        .iter()
+                        .enumerate()
+                        .any(|(j, other)| j != i && contains(other, x, y))
+            });
+        }
+    }
+}
+
+/// what an installed set draws for a piece glyph
+enum Picture<'a> {
+    Piece(&'a RgbaImage),
+    /// the piece's shape in a (translucent) colour
+    Shadow(&'a RgbaImage, Rgba),
+    /// nothing: the white fill under a White piece's outline
+    Hidden,
+}
+
+// Diagrams draw a White piece as its outline glyph over the solid one in
+// white, a Black piece as the solid glyph in black and a shadow as the
+// solid glyph in a translucent colour; `None` for any other text.
+fn picture<'a>(set: &'a PieceSet, text: &str, color: Rgba) -> Option<Picture<'a>> {
+    let mut chars = text.chars();
+    let (c, None) = (chars.next()?, chars.next()) else {
+        return None;
+    };
+    let letter = |side| {
+        ['P', 'N', 'B', 'R', 'Q', 'K']
+            .into_iter()
+            .find(|&l| diagram::glyph(l, side).starts_with(c))
+    };
+    if let Some(l) = letter(Color::White) {
+        return set.image(l).map(Picture::Piece);
+    }
+    let black = set.image(letter(Color::Black)?.to_ascii_lowercase())?;
+    Some(match color {
+        diagram::WHITE => Picture::Hidden,
+        [_, _, _, 255] => Picture::Piece(black),
+        _ => Picture::Shadow(black, color),
+    })
+}
+
+// a set's picture filling the square of a glyph at baseline (x, y)
+fn draw_picture(image: &mut RgbaImage, picture: Picture, x: f64, y: f64, size: f64) {
+    let (source, tint) = match picture {
+        Picture::Piece(source) => (source, None),
+        Picture::Shadow(source, colour) => (source, Some(colour)),
+        Picture::Hidden => return,
+    };
+    let side = size / 0.8;
+    let (left, top) = (
+        (x - 0.15 * side).round() as i64,
+        (y - 0.8 * side).round() as i64,
+    );
+    let edge = side.round().max(1.0) as u32;
+    let scaled = image::imageops::resize(source, edge, edge, FilterType::Triangle);
+    for (px, py, pixel) in scaled.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+        let coverage = a as f32 / 255.0;
+        let colour = tint.unwrap_or([r, g, b, 255]);
+        blend(image, left + px as i64, top + py as i64, colour, coverage);
+    }
+}
+
+/// how many times larger high quality images are drawn before being reduced
+const SUPERSAMPLE: u32 = 3;
+
+/// Rasterise a diagram into an RGBA image, drawing chess glyphs with
+/// `pieces`. At high quality it is drawn larger and scaled down, averaging
+/// each block of pixels.
+pub fn render(diagram: &Diagram, pieces: &PieceFont, quality: Quality) -> RgbaImage {
+    if quality == Quality::Low {
+        return rasterise(diagram, pieces);
+    }
+    let n = SUPERSAMPLE;
+    let large = rasterise(&diagram.scaled(n as f64), pieces);
+    let (width, height) = (diagram.width.ceil() as u32, diagram.height.ceil() as u32);
+    RgbaImage::from_fn(width, height, |x, y| {
+        let mut sum = [0u32; 4];
+        for dy in 0..n {
+            for dx in 0..n {
+                let (sx, sy) = (x * n + dx, y * n + dy);
+                if sx < large.width() && sy < large.height() {
+                    for (total, v) in sum.iter_mut().zip(large.get_pixel(sx, sy).0) {
+                        *total += v as u32;
+                    }
+                }
+            }
+        }
+        image::Rgba(sum.map(|total| (total / (n * n)) as u8))
+    })
+}
+
+/// How `render_to_rgba` draws a position
+#[derive(Default)]
+pub struct RenderOptions {
+    /// square size, colours, arrows, highlights and the rest of the diagram
+    pub diagram: DiagramOptions,
+    /// the bundled piece font by default, so that output does not depend
+    /// on the fonts installed
+    pub pieces: PieceFont,
+    pub quality: Quality,
+}
+
+/// The diagram of `pos` as raw RGBA pixels, for programs that want the
+/// image itself rather than a PNG file.
+pub fn render_to_rgba(pos: &Position, opts: RenderOptions) -> RgbaImage {
+    render(
+        &diagram::board(pos, &opts.diagram),
+        &opts.pieces,
+        opts.quality,
+    )
+}
+
+fn rasterise(diagram: &Diagram, pieces: &PieceFont) -> RgbaImage {
+    let text_font = fonts::text_font();
+    let mut image = RgbaImage::new(diagram.width.ceil() as u32, diagram.height.ceil() as u32);
+    for shape in &diagram.shapes {
+        match shape {
+            Shape::Rect { x, y, w, h, color } => {
+                let (x0, y0) = (x.round() as i64, y.round() as i64);
+                let (x1, y1) = ((x + w).round() as i64, (y + h).round() as i64);
+                for py in y0..y1 {
+                    for px in x0..x1 {
+                        blend(&mut image, px, py, *color, 1.0);
+                    }
+                }
+            }
+            Shape::Polygon { points, color } => {
+                let points: Vec<[f64; 2]> = points.iter().map(|&(x, y)| [x, y]).collect();
+                paint(&mut image, bounds(&points), *color, |x, y| {
+                    contains(&points, x, y)
+                });
+            }
+            Shape::Text {
+                x,
+                y,
+                size,
+                text,
+                color,
+                font,
+            } => {
+                let font = match (font, pieces) {
+                    (Font::Pieces, PieceFont::Set(set)) if picture(set, text, *color).is_some() => {
+                        if let Some(picture) = picture(set, text, *color) {
+                            draw_picture(&mut image, picture, *x, *y, *size);
+                        }
+                        continue;
+                    }
+                    (Font::Pieces, PieceFont::Font(piece_font)) => piece_font,
+                    (Font::Pieces, PieceFont::Shapes)
+                        if text.chars().all(|c| fonts::silhouette(c).is_some()) =>
+                    {
+                        for c in text.chars() {
+                            silhouette(&mut image, c, *x, *y, *size, *color);
+                        }
+                        continue;
+                    }
+                    _ => &text_font,
+                };
+                let glyphs = font.layout(
+                    text,
+                    Scale::uniform(*size as f32),
+                    point(*x as f32, *y as f32),
+                );
+                for glyph in glyphs {
+                    if let Some(bb) = glyph.pixel_bounding_box() {
+                        glyph.draw(|gx, gy, v| {
+                            blend(
+                                &mut image,
+                                bb.min.x as i64 + gx as i64,
+                                bb.min.y as i64 + gy as i64,
+                                *color,
+                                v,
+                            )
+                        });
+                    }
+                }
+            }
+        }
+    }
+    image
+}
+
+pub fn save(
+    diagram: &Diagram,
+    path: &str,
+    pieces: &PieceFont,
+    quality: Quality,
+) -> Result<(), String> {
+    let start = std::time::Instant::now();
+    let image = render(diagram, pieces, quality);
+    let rendered = start.elapsed();
+    let saved = image
+        .save_with_format(path, image::ImageFormat::Png)
+        .map_err(|e| format!("Could not write {}: {}", path, e));
+    tracing::debug!(
+        target: "fen::render",
+        path,
+        width = image.width(),
+        height = image.height(),
+        render_ms = rendered.as_secs_f64() * 1000.0,
+        total_ms = start.elapsed().as_secs_f64() * 1000.0,
+        "saved PNG"
+    );
+    saved
+}
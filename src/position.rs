@@ -0,0 +1,402 @@
+//! Structured representation of a chess position
+//!
+//! Squares are indexed 0..64 from a1 (0) to h8 (63), rank by rank.
+
+use std::fmt;
+
+pub type Square = u8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    pub fn opponent(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl PieceKind {
+    /// uppercase SAN letter; pawns have none
+    pub fn letter(self) -> char {
+        match self {
+            PieceKind::Pawn => 'P',
+            PieceKind::Knight => 'N',
+            PieceKind::Bishop => 'B',
+            PieceKind::Rook => 'R',
+            PieceKind::Queen => 'Q',
+            PieceKind::King => 'K',
+        }
+    }
+
+    pub fn from_letter(c: char) -> Option<PieceKind> {
+        match c.to_ascii_uppercase() {
+            'P' => Some(PieceKind::Pawn),
+            'N' => Some(PieceKind::Knight),
+            'B' => Some(PieceKind::Bishop),
+            'R' => Some(PieceKind::Rook),
+            'Q' => Some(PieceKind::Queen),
+            'K' => Some(PieceKind::King),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Piece {
+    pub color: Color,
+    pub kind: PieceKind,
+}
+
+impl Piece {
+    pub fn new(color: Color, kind: PieceKind) -> Piece {
+        Piece { color, kind }
+    }
+
+    /// FEN letter: uppercase for white, lowercase for black
+    pub fn to_char(self) -> char {
+        match self.color {
+            Color::White => self.kind.letter(),
+            Color::Black => self.kind.letter().to_ascii_lowercase(),
+        }
+    }
+
+    pub fn from_char(c: char) -> Option<Piece> {
+        let kind = PieceKind::from_letter(c)?;
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        Some(Piece { color, kind })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Castling {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool,
+}
+
+impl Castling {
+    pub fn any(&self) -> bool {
+        self.white_kingside || self.white_queenside || self.black_kingside || self.black_queenside
+    }
+}
+
+impl fmt::Display for Castling {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.any() {
+            return write!(f, "-");
+        }
+        for (held, c) in [
+            (self.white_kingside, 'K'),
+            (self.white_queenside, 'Q'),
+            (self.black_kingside, 'k'),
+            (self.black_queenside, 'q'),
+        ] {
+            if held {
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub board: [Option<Piece>; 64],
+    pub side: Color,
+    pub castling: Castling,
+    pub ep: Option<Square>,
+    pub halfmove: u32,
+    pub fullmove: u32,
+}
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+pub fn square(file: u8, rank: u8) -> Square {
+    rank * 8 + file
+}
+
+pub fn file_of(sq: Square) -> u8 {
+    sq % 8
+}
+
+pub fn rank_of(sq: Square) -> u8 {
+    sq / 8
+}
+
+pub fn square_name(sq: Square) -> String {
+    format!("{}{}", (b'a' + file_of(sq)) as char, rank_of(sq) + 1)
+}
+
+pub fn parse_square(s: &str) -> Option<Square> {
+    let b = s.as_bytes();
+    if b.len() != 2 || !(b'a'..=b'h').contains(&b[0]) || !(b'1'..=b'8').contains(&b[1]) {
+        return None;
+    }
+    Some(square(b[0] - b'a', b[1] - b'1'))
+}
+
+impl Position {
+    /// Parse a FEN string. The two clock fields may be omitted (as in EPD)
+    /// and default to `0 1`.
+    pub fn from_fen(fen: &str) -> Result<Position, String> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        tracing::debug!(target: "fen::parse", fen, fields = fields.len(), "split fields");
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(format!(
+                "FEN should contain 6 elements, found {}",
+                fields.len()
+            ));
+        }
+
+        let mut board = [None; 64];
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(format!(
+                "Layout string {} should contain 8 ranks, found {}",
+                fields[0],
+                ranks.len()
+            ));
+        }
+        for (i, rank) in ranks.iter().enumerate() {
+            let r = 7 - i as u8;
+            let mut f: u8 = 0;
+            for c in rank.chars() {
+                if let Some(n) = c.to_digit(10).filter(|n| (1..=8).contains(n)) {
+                    f += n as u8;
+                } else if let Some(p) = Piece::from_char(c) {
+                    if f < 8 {
+                        board[square(f, r) as usize] = Some(p);
+                    }
+                    f += 1;
+                } else {
+                    return Err(format!("Unexpected symbol '{}' in layout string", c));
+                }
+                if f > 8 {
+                    break;
+                }
+            }
+            if f != 8 {
+                return Err(format!(
+                    "Rank {} ({}) does not describe exactly 8 squares",
+                    r + 1,
+                    rank
+                ));
+            }
+        }
+
+        tracing::debug!(target: "fen::parse", placement = fields[0], "read placement");
+
+        let side = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err("Expected 'w' or 'b' in second element".to_string()),
+        };
+
+        let mut castling = Castling::default();
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                match c {
+                    'K' => castling.white_kingside = true,
+                    'Q' => castling.white_queenside = true,
+                    'k' => castling.black_kingside = true,
+                    'q' => castling.black_queenside = true,
+                    _ => {
+                        return Err(
+                            "Unexpected symbol in third element (castling rights)".to_string()
+                        )
+                    }
+                }
+            }
+        }
+
+        tracing::debug!(target: "fen::parse", side = fields[1], castling = %castling, "read side and castling");
+
+        let ep = match fields[3] {
+            "-" => None,
+            s => {
+                let sq = parse_square(s)
+                    .ok_or_else(|| format!("Invalid en-passant target square {}", s))?;
+                // the target is behind a pawn of the side that just moved,
+                // so it is on the sixth rank with White to move and the
+                // third with Black to move
+                let (rank, pawn, moved, name) = match side {
+                    Color::White => (5, 4, Color::Black, "White"),
+                    Color::Black => (2, 3, Color::White, "Black"),
+                };
+                if rank_of(sq) != rank {
+                    return Err(format!(
+                        "Invalid en-passant target square {} with {} to move",
+                        s, name
+                    ));
+                }
+                let ahead = board[square(file_of(sq), pawn) as usize];
+                if ahead != Some(Piece::new(moved, PieceKind::Pawn)) {
+                    return Err(format!(
+                        "Invalid en-passant target square {}: no pawn could have just moved past it",
+                        s
+                    ));
+                }
+                Some(sq)
+            }
+        };
+
+        let halfmove = match fields.get(4) {
+            Some(s) => s
+                .parse::<u32>()
+                .map_err(|_| format!("Invalid halfmove clock {}", s))?,
+            None => 0,
+        };
+        let fullmove = match fields.get(5) {
+            Some(s) => s
+                .parse::<u32>()
+                .map_err(|_| format!("Invalid fullmove number {}", s))?,
+            None => 1,
+        };
+
+        tracing::debug!(
+            target: "fen::parse",
+            ep = fields[3],
+            halfmove,
+            fullmove,
+            "read en passant and clocks"
+        );
+        Ok(Position {
+            board,
+            side,
+            castling,
+            ep,
+            halfmove,
+            fullmove,
+        })
+    }
+
+    /// the piece placement field of the FEN
+    pub fn placement(&self) -> String {
+        let mut out = String::new();
+        for r in (0..8).rev() {
+            let mut empty = 0;
+            for f in 0..8 {
+                match self.board[square(f, r) as usize] {
+                    Some(p) => {
+                        if empty > 0 {
+                            out.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        out.push(p.to_char());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                out.push_str(&empty.to_string());
+            }
+            if r > 0 {
+                out.push('/');
+            }
+        }
+        out
+    }
+
+    pub fn to_fen(&self) -> String {
+        format!(
+            "{} {} {} {} {} {}",
+            self.placement(),
+            match self.side {
+                Color::White => "w",
+                Color::Black => "b",
+            },
+            self.castling,
+            self.ep.map(square_name).unwrap_or_else(|| "-".to_string()),
+            self.halfmove,
+            self.fullmove
+        )
+    }
+
+    pub fn piece_at(&self, sq: Square) -> Option<Piece> {
+        self.board[sq as usize]
+    }
+
+    /// A position with only the pieces known, as read from a picture or an
+    /// electronic board: castling is allowed wherever king and rook stand
+    /// on their home squares, with no en passant square.
+    pub fn from_placement(board: [Option<Piece>; 64], side: Color) -> Position {
+        let at_home = |sq: Square, c: char| board[sq as usize].map(Piece::to_char) == Some(c);
+        let white_king = at_home(4, 'K');
+        let black_king = at_home(60, 'k');
+        Position {
+            board,
+            side,
+            castling: Castling {
+                white_kingside: white_king && at_home(7, 'R'),
+                white_queenside: white_king && at_home(0, 'R'),
+                black_kingside: black_king && at_home(63, 'r'),
+                black_queenside: black_king && at_home(56, 'r'),
+            },
+            ep: None,
+            halfmove: 0,
+            fullmove: 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fen_round_trips() {
+        for fen in [
+            STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        ] {
+            assert_eq!(Position::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn en_passant_square_must_fit_the_side_to_move() {
+        // rank 3 with White to move
+        assert!(
+            Position::from_fen("rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR w KQkq d3 0 1")
+                .is_err()
+        );
+        // rank 6 with Black to move
+        assert!(
+            Position::from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq d6 0 3")
+                .is_err()
+        );
+        // no pawn in front of the target
+        assert!(
+            Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq e3 0 1")
+                .is_err()
+        );
+        // a pawn of the side to move is not one that just moved
+        assert!(
+            Position::from_fen("rnbqkbnr/ppp1pppp/8/3P4/8/8/PPP1PPPP/RNBQKBNR w KQkq d6 0 3")
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Classic board puzzles: the knight's tour and the eight queens
+//!
+//! The tour follows Warnsdorff's rule, always moving to the square with
+//! the fewest onward moves, and backs up when it runs into a dead end. The
+//! queens are placed a file at a time, backtracking over every placement,
+//! which gives all 92 solutions in rank order.
+//!
+//! In the window each puzzle is animated, a move or a solution at a time.
+
+use crate::diagram::Palette;
+use crate::position::{
+    file_of, parse_square, rank_of, square, square_name, Color, Piece, PieceKind, Square,
+};
+use crate::terminal::{glyph, print_board, TerminalOptions};
+use crate::window::{self, WindowOptions};
+use piston::input::{Event, Loop};
+use std::time::{Duration, Instant};
+
+const KNIGHT_STEPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+
+fn jumps(sq: Square, visited: &[bool; 64]) -> Vec<Square> {
+    KNIGHT_STEPS
+        .iter()
+        .filter_map(|&(df, dr)| {
+            let f = file_of(sq) as i8 + df;
+            let r = rank_of(sq) as i8 + dr;
+            ((0..8).contains(&f) && (0..8).contains(&r)).then(|| square(f as u8, r as u8))
+        })
+        .filter(|&to| !visited[to as usize])
+        .collect()
+}
+
+fn extend(tour: &mut Vec<Square>, visited: &mut [bool; 64]) -> bool {
+    if tour.len() == 64 {
+        return true;
+    }
+    let at = tour[tour.len() - 1];
+    let mut next = jumps(at, visited);
+    next.sort_by_key(|&to| jumps(to, visited).len());
+    for to in next {
+        visited[to as usize] = true;
+        tour.push(to);
+        if extend(tour, visited) {
+            return true;
+        }
+        tour.pop();
+        visited[to as usize] = false;
+    }
+    false
+}
+
+/// the 64 squares of a knight's tour starting on `from`
+pub fn knights_tour(from: Square) -> Option<Vec<Square>> {
+    let mut visited = [false; 64];
+    visited[from as usize] = true;
+    let mut tour = vec![from];
+    extend(&mut tour, &mut visited).then_some(tour)
+}
+
+fn place(ranks: &mut Vec<u8>, found: &mut Vec<Vec<u8>>) {
+    let file = ranks.len() as i8;
+    if file == 8 {
+        found.push(ranks.clone());
+        return;
+    }
+    for rank in 0..8u8 {
+        let safe = ranks
+            .iter()
+            .enumerate()
+            .all(|(f, &r)| r != rank && (file - f as i8).abs() != (rank as i8 - r as i8).abs());
+        if safe {
+            ranks.push(rank);
+            place(ranks, found);
+            ranks.pop();
+        }
+    }
+}
+
+/// every way of placing eight queens that do not attack one another, as
+/// the rank of the queen on each file from a
+pub fn eight_queens() -> Vec<Vec<u8>> {
+    let mut found = Vec::new();
+    place(&mut Vec::new(), &mut found);
+    found
+}
+
+// a glyph grid, rank 8 first, each square filled by `fill`
+fn grid(fill: impl Fn(Square) -> String) -> Vec<Vec<String>> {
+    (0..8u8)
+        .rev()
+        .map(|rank| (0..8u8).map(|file| fill(square(file, rank))).collect())
+        .collect()
+}
+
+// the queens of a solution, with `empty` on the other squares
+fn queens(ranks: &[u8], empty: &str) -> Vec<Vec<String>> {
+    let queen = glyph(Piece::new(Color::White, PieceKind::Queen));
+    grid(|sq| {
+        if ranks.get(file_of(sq) as usize) == Some(&rank_of(sq)) {
+            queen.clone()
+        } else {
+            empty.to_string()
+        }
+    })
+}
+
+/// Show `frames` in a window one after another, `delay` apart, holding
+/// the last; each frame is a glyph grid with squares to tint.
+fn animate(frames: Vec<(Vec<Vec<String>>, Vec<Square>)>, delay: Duration, palette: Palette) {
+    let opts = WindowOptions {
+        palette,
+        ..WindowOptions::default()
+    };
+    let tint = window::gl_colour(palette.highlight);
+    let mut shown = 0;
+    let mut since = Instant::now();
+    let first = frames
+        .first()
+        .map(|(pieces, _)| pieces.clone())
+        .unwrap_or_default();
+    window::run(first, opts, move |board, event| {
+        if let Event::Loop(Loop::Update(_)) = event {
+            if since.elapsed() >= delay && shown + 1 < frames.len() {
+                shown += 1;
+                since = Instant::now();
+            }
+            let (pieces, marked) = &frames[shown];
+            board.pieces = pieces.clone();
+            board.highlights = marked.iter().map(|&sq| (sq, tint)).collect();
+        }
+        true
+    });
+}
+
+/// Print a knight's tour from `from` as the move number on each square,
+/// and animate it in the window when `graphical`.
+pub fn tour(from: &str, graphical: bool, delay: u64, term: &TerminalOptions) -> Result<(), String> {
+    let start = parse_square(from).ok_or_else(|| format!("Invalid square {}", from))?;
+    let tour = knights_tour(start).ok_or_else(|| format!("No knight's tour starts on {}", from))?;
+    let mut numbers = [0; 64];
+    for (i, &sq) in tour.iter().enumerate() {
+        numbers[sq as usize] = i + 1;
+    }
+    print_board(&grid(|sq| numbers[sq as usize].to_string()), term);
+    let names: Vec<String> = tour.iter().map(|&sq| square_name(sq)).collect();
+    println!("{}", names.join(" "));
+
+    if graphical {
+        let knight = glyph(Piece::new(Color::White, PieceKind::Knight));
+        let frames = (0..tour.len())
+            .map(|i| {
+                let pieces = grid(|sq| {
+                    if sq == tour[i] {
+                        knight.clone()
+                    } else {
+                        String::new()
+                    }
+                });
+                (pieces, tour[..i].to_vec())
+            })
+            .collect();
+        animate(frames, Duration::from_millis(delay), term.palette);
+    }
+    Ok(())
+}
+
+/// Print the first solution to the eight queens puzzle, or every one with
+/// `all`, and step through them in the window when `graphical`.
+pub fn queens_puzzle(all: bool, graphical: bool, delay: u64, term: &TerminalOptions) {
+    let solutions = eight_queens();
+    let shown = if all { solutions.len() } else { 1 };
+    for (n, ranks) in solutions.iter().take(shown).enumerate() {
+        let squares: Vec<String> = ranks
+            .iter()
+            .enumerate()
+            .map(|(f, &r)| square_name(square(f as u8, r)))
+            .collect();
+        println!(
+            "Solution {} of {}: {}",
+            n + 1,
+            solutions.len(),
+            squares.join(" ")
+        );
+        print_board(&queens(ranks, "·"), term);
+    }
+    if graphical {
+        let frames = solutions
+            .iter()
+            .take(shown)
+            .map(|ranks| (queens(ranks, ""), Vec::new()))
+            .collect();
+        animate(frames, Duration::from_millis(delay), term.palette);
+    }
+}
@@ -0,0 +1,550 @@
+//! QR codes for sharing a position
+//!
+//! A minimal encoder following ISO/IEC 18004: byte mode at error
+//! correction level M, versions 1 to 10 (up to 213 bytes), which is
+//! plenty for a FEN or an analysis URL.
+
+use crate::terminal::Charset;
+
+/// a square grid of modules, `true` for dark
+pub struct QrCode {
+    pub size: usize,
+    modules: Vec<bool>,
+    function: Vec<bool>,
+}
+
+// error correction codewords per block and number of blocks at level M
+const ECC_PER_BLOCK: [usize; 10] = [10, 16, 26, 18, 24, 16, 18, 22, 22, 26];
+const BLOCKS: [usize; 10] = [1, 1, 1, 2, 2, 4, 4, 4, 5, 5];
+
+// codewords (data plus error correction) a version holds
+fn raw_codewords(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let align = version / 7 + 2;
+        modules -= (25 * align - 10) * align - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules / 8
+}
+
+fn data_codewords(version: usize) -> usize {
+    raw_codewords(version) - ECC_PER_BLOCK[version - 1] * BLOCKS[version - 1]
+}
+
+// multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u8 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x1d);
+        z ^= ((y >> i) & 1) * x;
+    }
+    z
+}
+
+// Reed-Solomon generator polynomial of the given degree, leading term omitted
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0; degree];
+    result[degree - 1] = 1;
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; divisor.len()];
+    for &b in data {
+        let factor = b ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_multiply(d, factor);
+        }
+    }
+    result
+}
+
+// mode indicator, length, payload and padding, as codewords
+fn data_bits(data: &[u8], version: usize) -> Vec<u8> {
+    let mut bits = Vec::new();
+    let mut push = |value: usize, len: usize| {
+        for i in (0..len).rev() {
+            bits.push((value >> i) & 1 == 1);
+        }
+    };
+    push(0b0100, 4);
+    push(data.len(), if version < 10 { 8 } else { 16 });
+    for &b in data {
+        push(b as usize, 8);
+    }
+    let capacity = data_codewords(version) * 8;
+    let terminator = (capacity - bits.len()).min(4);
+    bits.extend(std::iter::repeat_n(false, terminator));
+    while bits.len() % 8 != 0 {
+        bits.push(false);
+    }
+    let mut bytes: Vec<u8> = bits
+        .chunks(8)
+        .map(|c| c.iter().fold(0, |acc, &b| acc << 1 | b as u8))
+        .collect();
+    for pad in [0xec, 0x11].iter().cycle() {
+        if bytes.len() == data_codewords(version) {
+            break;
+        }
+        bytes.push(*pad);
+    }
+    bytes
+}
+
+// split into blocks, append error correction to each and interleave
+fn interleave(data: &[u8], version: usize) -> Vec<u8> {
+    let blocks = BLOCKS[version - 1];
+    let ecc = ECC_PER_BLOCK[version - 1];
+    let raw = raw_codewords(version);
+    let short_blocks = blocks - raw % blocks;
+    let short_len = raw / blocks;
+    let divisor = rs_divisor(ecc);
+    let mut split = Vec::with_capacity(blocks);
+    let mut k = 0;
+    for i in 0..blocks {
+        let len = short_len - ecc + usize::from(i >= short_blocks);
+        let mut block = data[k..k + len].to_vec();
+        k += len;
+        let remainder = rs_remainder(&block, &divisor);
+        if i < short_blocks {
+            block.push(0);
+        }
+        block.extend(remainder);
+        split.push(block);
+    }
+    let mut result = Vec::with_capacity(raw);
+    for i in 0..split[0].len() {
+        for (j, block) in split.iter().enumerate() {
+            // skip the padding byte of the short blocks
+            if i != short_len - ecc || j >= short_blocks {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+fn masked(mask: u8, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+    }
+}
+
+impl QrCode {
+    /// Encode `data`, choosing the smallest version that holds it.
+    pub fn encode(data: &[u8]) -> Result<QrCode, String> {
+        let version = (1..=10)
+            .find(|&v| {
+                let header = 4 + if v < 10 { 8 } else { 16 };
+                header + 8 * data.len() <= data_codewords(v) * 8
+            })
+            .ok_or_else(|| {
+                format!(
+                    "{} bytes is too long for a QR code (at most {})",
+                    data.len(),
+                    data_codewords(10) - 3
+                )
+            })?;
+        let size = version * 4 + 17;
+        let mut code = QrCode {
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+        };
+        code.draw_function_patterns(version);
+        let codewords = interleave(&data_bits(data, version), version);
+        code.draw_codewords(&codewords);
+
+        // keep the mask with the lowest penalty
+        let mut best = (0, usize::MAX);
+        for mask in 0..8 {
+            code.apply_mask(mask);
+            code.draw_format(mask);
+            let penalty = code.penalty();
+            if penalty < best.1 {
+                best = (mask, penalty);
+            }
+            code.apply_mask(mask);
+        }
+        code.apply_mask(best.0);
+        code.draw_format(best.0);
+        Ok(code)
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i.is_multiple_of(2));
+            self.set_function(i, 6, i.is_multiple_of(2));
+        }
+        for (cx, cy) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i64..=4 {
+                for dx in -4i64..=4 {
+                    let (x, y) = (cx as i64 + dx, cy as i64 + dy);
+                    if (0..size as i64).contains(&x) && (0..size as i64).contains(&y) {
+                        let dist = dx.abs().max(dy.abs());
+                        self.set_function(x as usize, y as usize, dist != 2 && dist != 4);
+                    }
+                }
+            }
+        }
+        if version >= 2 {
+            let count = version / 7 + 2;
+            let step = (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2;
+            let mut centres: Vec<usize> = (0..count - 1).map(|i| size - 7 - i * step).collect();
+            centres.push(6);
+            centres.reverse();
+            for (i, &cx) in centres.iter().enumerate() {
+                for (j, &cy) in centres.iter().enumerate() {
+                    // the corners with finder patterns
+                    let last = count - 1;
+                    if (i == 0 && (j == 0 || j == last)) || (i == last && j == 0) {
+                        continue;
+                    }
+                    for dy in -2i64..=2 {
+                        for dx in -2i64..=2 {
+                            self.set_function(
+                                (cx as i64 + dx) as usize,
+                                (cy as i64 + dy) as usize,
+                                dx.abs().max(dy.abs()) != 1,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        // reserve the format areas until the mask is chosen
+        self.draw_format(0);
+        if version >= 7 {
+            let mut rem = version;
+            for _ in 0..12 {
+                rem = (rem << 1) ^ ((rem >> 11) * 0x1f25);
+            }
+            let bits = version << 12 | rem;
+            for i in 0..18 {
+                let dark = (bits >> i) & 1 == 1;
+                let (a, b) = (size - 11 + i % 3, i / 3);
+                self.set_function(a, b, dark);
+                self.set_function(b, a, dark);
+            }
+        }
+    }
+
+    fn draw_format(&mut self, mask: u8) {
+        let size = self.size;
+        // level M is 00, followed by the mask
+        let data = mask as usize;
+        let mut rem = data;
+        for _ in 0..10 {
+            rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+        }
+        let bits = (data << 10 | rem) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 == 1;
+        for i in 0..=5 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    // zigzag through the non-function modules in pairs of columns
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let mut i = 0;
+        let mut right = size as i64 - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vert in 0..size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { size - 1 - vert } else { vert };
+                    if !self.function[y * size + x] && i < codewords.len() * 8 {
+                        self.modules[y * size + x] = (codewords[i / 8] >> (7 - i % 8)) & 1 == 1;
+                        i += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if !self.function[y * self.size + x] && masked(mask, x, y) {
+                    self.modules[y * self.size + x] ^= true;
+                }
+            }
+        }
+    }
+
+    // the standard's penalty for runs, blocks, finder-like patterns and
+    // an unbalanced share of dark modules
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let mut penalty = 0;
+        let lines = (0..size).flat_map(|i| {
+            [
+                (0..size).map(|j| self.is_dark(j, i)).collect::<Vec<_>>(),
+                (0..size).map(|j| self.is_dark(i, j)).collect::<Vec<_>>(),
+            ]
+        });
+        let finder = [true, false, true, true, true, false, true];
+        for line in lines {
+            let mut run = 1;
+            for j in 1..=size {
+                if j < size && line[j] == line[j - 1] {
+                    run += 1;
+                } else {
+                    if run >= 5 {
+                        penalty += run - 2;
+                    }
+                    run = 1;
+                }
+            }
+            for j in 0..=size - 7 {
+                if line[j..j + 7] == finder {
+                    let light = |from: usize, to: usize| line[from..to].iter().all(|&d| !d);
+                    if (j >= 4 && light(j - 4, j)) || (j + 11 <= size && light(j + 7, j + 11)) {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.is_dark(x, y);
+                if dark == self.is_dark(x + 1, y)
+                    && dark == self.is_dark(x, y + 1)
+                    && dark == self.is_dark(x + 1, y + 1)
+                {
+                    penalty += 3;
+                }
+            }
+        }
+        let total = size * size;
+        let dark = self.modules.iter().filter(|&&d| d).count();
+        let k = (dark * 20).abs_diff(total * 10).div_ceil(total) - 1;
+        penalty + k * 10
+    }
+
+    /// Text drawing of the code (with its quiet zone) for the terminal:
+    /// two modules per character cell in half blocks, or `##` per dark
+    /// module in ASCII.
+    pub fn to_terminal(&self, charset: Charset) -> String {
+        const QUIET: i64 = 2;
+        let size = self.size as i64;
+        let dark = |x: i64, y: i64| {
+            (0..size).contains(&x) && (0..size).contains(&y) && self.is_dark(x as usize, y as usize)
+        };
+        let mut out = String::new();
+        if charset == Charset::Ascii {
+            for y in -QUIET..size + QUIET {
+                for x in -QUIET..size + QUIET {
+                    out.push_str(if dark(x, y) { "##" } else { "  " });
+                }
+                out.push('\n');
+            }
+            return out;
+        }
+        // black on white regardless of the terminal's own colours
+        for y in (-QUIET..size + QUIET).step_by(2) {
+            out.push_str("\x1b[30;107m");
+            for x in -QUIET..size + QUIET {
+                out.push(match (dark(x, y), dark(x, y + 1)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // read a code back the way a scanner would: format, mask, codewords,
+    // then the blocks with their error correction checked
+    fn decode(code: &QrCode) -> Vec<u8> {
+        let size = code.size;
+        let version = (size - 17) / 4;
+        let dark = |x: usize, y: usize| code.is_dark(x, y) as usize;
+
+        let mut first = 0;
+        let mut second = 0;
+        let place = |bits: &mut usize, i: usize, module: usize| *bits |= module << i;
+        for i in 0..=5 {
+            place(&mut first, i, dark(8, i));
+        }
+        place(&mut first, 6, dark(8, 7));
+        place(&mut first, 7, dark(8, 8));
+        place(&mut first, 8, dark(7, 8));
+        for i in 9..15 {
+            place(&mut first, i, dark(14 - i, 8));
+        }
+        for i in 0..8 {
+            place(&mut second, i, dark(size - 1 - i, 8));
+        }
+        for i in 8..15 {
+            place(&mut second, i, dark(8, size - 15 + i));
+        }
+        assert_eq!(first, second, "the two format copies differ");
+        let format = first ^ 0x5412;
+        let mut rem = format >> 10;
+        for _ in 0..10 {
+            rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+        }
+        assert_eq!(format & 0x3ff, rem, "bad format checksum");
+        assert_eq!(format >> 13, 0, "not level M");
+        let mask = (format >> 10) as u8 & 7;
+
+        let mut bits = Vec::new();
+        let mut right = size as i64 - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vert in 0..size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let y = if (right + 1) & 2 == 0 {
+                        size - 1 - vert
+                    } else {
+                        vert
+                    };
+                    if !code.function[y * size + x] {
+                        bits.push(code.is_dark(x, y) ^ masked(mask, x, y));
+                    }
+                }
+            }
+            right -= 2;
+        }
+        let raw: Vec<u8> = bits
+            .chunks_exact(8)
+            .take(raw_codewords(version))
+            .map(|c| c.iter().fold(0, |acc, &b| acc << 1 | b as u8))
+            .collect();
+
+        let blocks = BLOCKS[version - 1];
+        let ecc = ECC_PER_BLOCK[version - 1];
+        let short_blocks = blocks - raw.len() % blocks;
+        let short_len = raw.len() / blocks;
+        let mut split = vec![Vec::new(); blocks];
+        let mut next = raw.iter();
+        for i in 0..=short_len {
+            for (j, block) in split.iter_mut().enumerate() {
+                if i != short_len - ecc || j >= short_blocks {
+                    if let Some(&b) = next.next() {
+                        block.push(b);
+                    }
+                }
+            }
+        }
+        let divisor = rs_divisor(ecc);
+        let mut data = Vec::new();
+        for block in split {
+            let (payload, check) = block.split_at(block.len() - ecc);
+            assert_eq!(
+                rs_remainder(payload, &divisor),
+                check,
+                "bad error correction"
+            );
+            data.extend_from_slice(payload);
+        }
+
+        let bit = |i: usize| (data[i / 8] >> (7 - i % 8)) as usize & 1;
+        let read = |from: usize, len: usize| (from..from + len).fold(0, |acc, i| acc << 1 | bit(i));
+        assert_eq!(read(0, 4), 0b0100, "not byte mode");
+        let count_bits = if version < 10 { 8 } else { 16 };
+        let len = read(4, count_bits);
+        (0..len)
+            .map(|i| read(4 + count_bits + 8 * i, 8) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn reed_solomon_matches_the_standard_example() {
+        // "HELLO WORLD" at 1-M
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        assert_eq!(
+            rs_remainder(&data, &rs_divisor(10)),
+            [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+    }
+
+    #[test]
+    fn codes_read_back() {
+        for text in [
+            "e4",
+            "8/8/8/8/8/8/8/K6k w - - 0 1",
+            crate::position::STARTING_FEN,
+            "https://lichess.org/analysis/r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R_w_KQkq_-_0_1",
+        ] {
+            let code = QrCode::encode(text.as_bytes()).unwrap();
+            assert_eq!(decode(&code), text.as_bytes(), "{}", text);
+        }
+        for len in [14, 15, 100, 200, 213] {
+            let data = vec![b'x'; len];
+            assert_eq!(decode(&QrCode::encode(&data).unwrap()), data);
+        }
+    }
+
+    #[test]
+    fn smallest_version_is_chosen() {
+        assert_eq!(QrCode::encode(&[b'a'; 14]).unwrap().size, 21);
+        assert_eq!(QrCode::encode(&[b'a'; 15]).unwrap().size, 25);
+        assert_eq!(QrCode::encode(&[b'a'; 213]).unwrap().size, 57);
+        assert!(QrCode::encode(&[b'a'; 214]).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! Read a board position back out of an image
+//!
+//! Recognition backends implement [`Recognizer`]; `--recognizer` picks one
+//! by name. The only backend so far is a grid and template matcher that
+//! expects the image to be the board itself, as exported by `--png`.
+
+use crate::diagram::{self, DiagramOptions, Layout, Quality};
+use crate::fonts::PieceFont;
+use crate::png;
+use crate::position::{square, Color, Piece, PieceKind, Position, STARTING_FEN};
+use image::RgbaImage;
+
+/// contents of the 64 squares, a1 first
+pub type Placement = [Option<Piece>; 64];
+
+/// a board-recognition backend
+pub trait Recognizer {
+    /// Read the pieces off `image`.
+    fn recognize(&self, image: &RgbaImage) -> Result<Placement, String>;
+}
+
+/// the backend called `name`, if there is one
+pub fn backend(name: &str) -> Option<Box<dyn Recognizer>> {
+    match name {
+        "template" => Some(Box::new(TemplateMatcher)),
+        _ => None,
+    }
+}
+
+/// names accepted by [`backend`]
+pub const BACKENDS: &[&str] = &["template"];
+
+// samples taken across each square, per side
+const SAMPLES: u32 = 12;
+
+/// Splits the largest square in the top-left of the image into an 8x8
+/// grid and compares each square with pieces rendered by this crate, in
+/// both the bundled piece font and the vector shapes.
+pub struct TemplateMatcher;
+
+// grey levels sampled from the middle of square (file, rank), away from
+// the coordinate labels in the corners of the edge squares
+fn features(image: &RgbaImage, size: f64, file: u8, rank: u8) -> Vec<f64> {
+    let x0 = file as f64 * size;
+    let y0 = (7 - rank) as f64 * size;
+    let mut out = Vec::with_capacity((SAMPLES * SAMPLES) as usize);
+    for j in 0..SAMPLES {
+        for i in 0..SAMPLES {
+            let x = x0 + size * (0.2 + 0.58 * (i as f64 + 0.5) / SAMPLES as f64);
+            let y = y0 + size * (0.25 + 0.53 * (j as f64 + 0.5) / SAMPLES as f64);
+            let p = image.get_pixel(x as u32, y as u32);
+            out.push(0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64);
+        }
+    }
+    out
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+impl Recognizer for TemplateMatcher {
+    fn recognize(&self, image: &RgbaImage) -> Result<Placement, String> {
+        let side = image.width().min(image.height());
+        if side < 64 {
+            return Err(format!(
+                "Image is {}x{}, too small to hold a board",
+                image.width(),
+                image.height()
+            ));
+        }
+        let size = side as f64 / 8.0;
+
+        // every kind of piece (and an empty board) rendered at the image's
+        // scale, sampled on a dark (d4) and a light (e4) square for each set
+        let mut contents: Vec<Option<Piece>> = vec![None];
+        for color in [Color::White, Color::Black] {
+            for kind in [
+                PieceKind::King,
+                PieceKind::Queen,
+                PieceKind::Rook,
+                PieceKind::Bishop,
+                PieceKind::Knight,
+                PieceKind::Pawn,
+            ] {
+                contents.push(Some(Piece::new(color, kind)));
+            }
+        }
+        let opts = DiagramOptions {
+            layout: Layout {
+                square: size,
+                ..Layout::default()
+            },
+            ..DiagramOptions::default()
+        };
+        let mut templates = Vec::new();
+        for pieces in [PieceFont::default(), PieceFont::Shapes] {
+            for &content in &contents {
+                let mut pos = Position::from_fen(STARTING_FEN).expect("starting position is valid");
+                pos.board = [content; 64];
+                let rendered = png::render(&diagram::board(&pos, &opts), &pieces, Quality::Low);
+                for (file, rank) in [(3, 3), (4, 3)] {
+                    templates.push((content, features(&rendered, size, file, rank)));
+                }
+            }
+        }
+
+        let mut board = [None; 64];
+        for rank in 0..8 {
+            for file in 0..8 {
+                let sample = features(image, size, file, rank);
+                board[square(file, rank) as usize] = templates
+                    .iter()
+                    .min_by(|a, b| distance(&a.1, &sample).total_cmp(&distance(&b.1, &sample)))
+                    .and_then(|(content, _)| *content);
+            }
+        }
+        Ok(board)
+    }
+}
+
+/// Recognise the board in the image at `path` and complete it to a FEN,
+/// assuming White is to move.
+pub fn fen_from_image(path: &str, recognizer: &dyn Recognizer) -> Result<String, String> {
+    let image = image::open(path)
+        .map_err(|e| format!("Could not read {}: {}", path, e))?
+        .to_rgba8();
+    let board = recognizer.recognize(&image)?;
+    Ok(Position::from_placement(board, Color::White).to_fen())
+}
@@ -0,0 +1,194 @@
+//! Follow a game of a live broadcast in the terminal
+//!
+//! The round's PGN is fetched again every few seconds (with `curl`, for
+//! links) and the chosen board redrawn whenever a move arrives. A PGN file
+//! of finished games can be stepped through the same way.
+
+use crate::pgn::{occurrences, parse_games, repetition_note, Game};
+use crate::position::{Color, Position};
+use crate::san::{move_rows, to_san};
+use crate::terminal::{
+    board_glyphs, flipped, keys, move_list, Key, RawMode, Screen, TerminalOptions,
+};
+use crate::zobrist;
+use std::process::Command;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The PGN export for a lichess broadcast page such as
+/// `https://lichess.org/broadcast/<tour>/<round>/<id>`; other links and
+/// files are read as they are.
+pub fn pgn_source(source: &str) -> String {
+    let path = source
+        .strip_prefix("https://lichess.org/broadcast/")
+        .or_else(|| source.strip_prefix("https://www.lichess.org/broadcast/"));
+    match path.map(|p| p.split('/').collect::<Vec<_>>()) {
+        Some(parts) if parts.len() >= 3 => {
+            format!("https://lichess.org/api/broadcast/round/{}.pgn", parts[2])
+        }
+        _ => source.to_string(),
+    }
+}
+
+fn fetch(source: &str) -> Result<String, String> {
+    if !source.starts_with("http://") && !source.starts_with("https://") {
+        return std::fs::read_to_string(source)
+            .map_err(|e| format!("Could not read {}: {}", source, e));
+    }
+    let output = Command::new("curl")
+        .args(["-sSfL", source])
+        .output()
+        .map_err(|e| format!("Could not run curl to fetch {}: {}", source, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Could not fetch {}: {}",
+            source,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("{} is not valid UTF-8", source))
+}
+
+// players, move count and the move that led to ply `ply`, above the board
+fn header(game: &Game, positions: &[Position], ply: usize) -> String {
+    let players = format!(
+        "{} - {}",
+        game.tag("White").unwrap_or("?"),
+        game.tag("Black").unwrap_or("?")
+    );
+    let last = match ply.checked_sub(1) {
+        Some(i) => {
+            let before = &positions[i];
+            let dots = match before.side {
+                Color::White => ".",
+                Color::Black => "...",
+            };
+            format!(
+                "{}{} {}",
+                before.fullmove,
+                dots,
+                to_san(before, game.moves[i])
+            )
+        }
+        None => "no moves yet".to_string(),
+    };
+    if ply < game.moves.len() {
+        format!(
+            "{}  {}  (ply {} of {})",
+            players,
+            last,
+            ply,
+            game.moves.len()
+        )
+    } else {
+        format!("{}  {}  {}", players, last, game.result)
+    }
+}
+
+/// rows of the move list shown under the board
+const MOVE_ROWS: usize = 6;
+
+// game number `board` (counting from 1) of a round
+fn select(pgn: &str, board: usize) -> Result<Game, String> {
+    let games = parse_games(pgn)?;
+    let count = games.len();
+    games
+        .into_iter()
+        .nth(board.saturating_sub(1))
+        .ok_or_else(|| {
+            format!(
+                "The broadcast has {} board{}, not {}",
+                count,
+                if count == 1 { "" } else { "s" },
+                board
+            )
+        })
+}
+
+/// Poll `source` every `interval` seconds and draw board number `board`
+/// (counting from 1) whenever it changes, until its game is finished, with
+/// a note once its position has occurred three times, the moves around the
+/// ply shown listed underneath and then any table `lines` gives for the
+/// position (an engine's analysis, say). On a terminal the arrow keys step
+/// through the plies (Home and End jump to either end), `f` flips the
+/// board and `q` quits; new moves are shown as they arrive while the last
+/// ply is on the board, and a finished game stays up until `q`.
+pub fn run(
+    source: &str,
+    board: usize,
+    interval: u64,
+    term: &TerminalOptions,
+    mut lines: impl FnMut(&Position) -> Option<String>,
+) -> Result<(), String> {
+    let url = pgn_source(source);
+    let mut screen = Screen::new();
+    let raw = RawMode::enable();
+    let keys = raw.as_ref().map(|_| keys());
+    let interval = Duration::from_secs(interval);
+    let mut game: Option<(Game, Vec<Position>)> = None;
+    let mut next_fetch = Instant::now();
+    // the ply on the board, or None to follow the latest
+    let mut ply: Option<usize> = None;
+    let mut flip = false;
+    let mut shown = None;
+    loop {
+        let finished = game.as_ref().is_some_and(|(g, _)| g.result != "*");
+        if !finished && Instant::now() >= next_fetch {
+            match fetch(&url).and_then(|pgn| select(&pgn, board)) {
+                Ok(fetched) => {
+                    let positions = fetched.positions();
+                    game = Some((fetched, positions));
+                }
+                // once the board is up, ride out network hiccups and half-written files
+                Err(e) if game.is_some() => eprintln!("Warning: {}", e),
+                Err(e) => return Err(e),
+            }
+            next_fetch = Instant::now() + interval;
+        }
+        let (current, positions) = game.as_ref().expect("fetched above");
+        let last = current.moves.len();
+        let at = ply.unwrap_or(last).min(last);
+        let state = (last, current.result.clone(), at, flip);
+        if shown.as_ref() != Some(&state) {
+            screen.text(&header(current, positions, at));
+            let glyphs = board_glyphs(&positions[at].placement());
+            screen.board(&if flip { flipped(&glyphs) } else { glyphs }, term);
+            let seen: Vec<u64> = positions[..=at].iter().map(zobrist::hash).collect();
+            if let Some(note) = repetition_note(occurrences(&seen)) {
+                screen.text(&note);
+            }
+            if last > 0 {
+                let rows = move_rows(&current.start, &current.moves);
+                screen.text(&move_list(&rows, at, MOVE_ROWS));
+            }
+            if let Some(table) = lines(&positions[at]) {
+                screen.text(&table);
+            }
+            if keys.is_some() {
+                screen.text("←/→ step  Home/End  f flip  q quit");
+            }
+            screen.show();
+            shown = Some(state);
+        }
+        let finished = current.result != "*";
+        let key = match &keys {
+            None if finished => return Ok(()),
+            None => {
+                thread::sleep(interval);
+                continue;
+            }
+            Some(keys) if finished => keys.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            Some(keys) => keys.recv_timeout(next_fetch.saturating_duration_since(Instant::now())),
+        };
+        match key {
+            Ok(Key::Left) => ply = Some(at.saturating_sub(1)),
+            Ok(Key::Right) if at + 1 < last => ply = Some(at + 1),
+            Ok(Key::Right) | Ok(Key::End) => ply = None,
+            Ok(Key::Home) => ply = Some(0),
+            Ok(Key::Char('f')) => flip = !flip,
+            Ok(Key::Char('q')) | Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! Tournament statistics: scores, Elo differences and the SPRT
+//!
+//! Elo differences follow the logistic model, with a 95% interval from the
+//! win/draw/loss variance of the score. The sequential probability ratio
+//! test uses the usual normal approximation of its log-likelihood ratio, so
+//! a match can stop as soon as one engine is shown to be stronger by
+//! `elo1` (or not stronger than `elo0`) at the chosen error rates.
+
+use crate::json::Json;
+use crate::tournament::Tournament;
+
+/// wins, draws and losses of one engine against one or all of the others
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Record {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+fn elo_from_score(score: f64) -> f64 {
+    // adding zero turns an even score's -0.0 into 0.0
+    -400.0 * (1.0 / score - 1.0).log10() + 0.0
+}
+
+fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl Record {
+    /// the record of engine `a` against `b`, or against all when `None`
+    pub fn of(tournament: &Tournament, a: usize, b: Option<usize>) -> Record {
+        let mut record = Record::default();
+        for game in &tournament.games {
+            let (points, opponent) = if game.white == a {
+                (game.points().0, game.black)
+            } else if game.black == a {
+                (game.points().1, game.white)
+            } else {
+                continue;
+            };
+            if b.is_some_and(|b| b != opponent) {
+                continue;
+            }
+            match (points * 2.0) as u32 {
+                2 => record.wins += 1,
+                0 => record.losses += 1,
+                _ => record.draws += 1,
+            }
+        }
+        record
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// the share of the points scored, from 0 to 1
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games().max(1) as f64
+    }
+
+    /// variance of the points of a single game
+    fn variance(&self) -> f64 {
+        let n = self.games().max(1) as f64;
+        let s = self.score();
+        (self.wins as f64 * (1.0 - s).powi(2)
+            + self.draws as f64 * (0.5 - s).powi(2)
+            + self.losses as f64 * s.powi(2))
+            / n
+    }
+
+    /// the Elo difference the score implies, while it is neither 0 nor 1
+    pub fn elo(&self) -> Option<f64> {
+        let s = self.score();
+        (self.games() > 0 && s > 0.0 && s < 1.0).then(|| elo_from_score(s))
+    }
+
+    /// half the width of the 95% interval of the Elo difference
+    pub fn margin(&self) -> Option<f64> {
+        let s = self.score();
+        let spread = 1.96 * (self.variance() / self.games().max(1) as f64).sqrt();
+        let (low, high) = (s - spread, s + spread);
+        (self.games() > 1 && low > 0.0 && high < 1.0)
+            .then(|| (elo_from_score(high) - elo_from_score(low)) / 2.0)
+    }
+
+    /// log-likelihood ratio of an `elo1` advantage over an `elo0` one
+    pub fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        let variance = self.variance();
+        if self.games() == 0 || variance == 0.0 {
+            return 0.0;
+        }
+        let (s0, s1) = (score_from_elo(elo0), score_from_elo(elo1));
+        self.games() as f64 * (s1 - s0) * (2.0 * self.score() - s0 - s1) / (2.0 * variance)
+    }
+}
+
+/// a sequential probability ratio test of the first engine against the second
+#[derive(Clone, Copy, Debug)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// chance of accepting `elo1` when `elo0` holds
+    pub alpha: f64,
+    /// chance of accepting `elo0` when `elo1` holds
+    pub beta: f64,
+}
+
+impl Sprt {
+    /// the log-likelihood ratios at which `elo0` and `elo1` are accepted
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// `Some(true)` once `elo1` is accepted, `Some(false)` once `elo0` is,
+    /// `None` while the test goes on
+    pub fn verdict(&self, record: &Record) -> Option<bool> {
+        let llr = record.llr(self.elo0, self.elo1);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            Some(true)
+        } else if llr <= lower {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// the log-likelihood ratio of the first two engines' games so far,
+    /// against the bounds
+    pub fn summary(&self, tournament: &Tournament) -> String {
+        let record = Record::of(tournament, 0, Some(1));
+        let (lower, upper) = self.bounds();
+        let state = match self.verdict(&record) {
+            Some(true) => "H1 accepted",
+            Some(false) => "H0 accepted",
+            None => "inconclusive",
+        };
+        format!(
+            "SPRT elo0 {} elo1 {}: LLR {:.2} ({:.2}, {:.2}), {}",
+            self.elo0,
+            self.elo1,
+            record.llr(self.elo0, self.elo1),
+            lower,
+            upper,
+            state
+        )
+    }
+}
+
+fn format_elo(record: &Record) -> String {
+    match (record.elo(), record.margin()) {
+        (Some(elo), Some(margin)) => format!("{:+.1} ± {:.1}", elo, margin),
+        (Some(elo), None) => format!("{:+.1}", elo),
+        (None, _) => "-".to_string(),
+    }
+}
+
+/// Each engine's record, score and Elo difference against the field, then
+/// the state of the SPRT if one is running.
+pub fn report(tournament: &Tournament, sprt: Option<&Sprt>) -> String {
+    let width = tournament
+        .names
+        .iter()
+        .map(|n| n.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(6);
+    let mut out = format!(
+        "{:<width$}  {:>5} {:>4} {:>4} {:>4}  {:>6}  Elo\n",
+        "Engine",
+        "Games",
+        "W",
+        "D",
+        "L",
+        "Score",
+        width = width
+    );
+    for (a, name) in tournament.names.iter().enumerate() {
+        let record = Record::of(tournament, a, None);
+        out.push_str(&format!(
+            "{:<width$}  {:>5} {:>4} {:>4} {:>4}  {:>5.1}%  {}\n",
+            name,
+            record.games(),
+            record.wins,
+            record.draws,
+            record.losses,
+            100.0 * record.score(),
+            format_elo(&record),
+            width = width
+        ));
+    }
+    if let Some(sprt) = sprt {
+        out.push_str(&sprt.summary(tournament));
+        out.push('\n');
+    }
+    out
+}
+
+fn record_json(record: &Record) -> Vec<(&'static str, Json)> {
+    let optional = |value: Option<f64>| match value {
+        Some(v) => Json::Num((v * 10.0).round() / 10.0),
+        None => Json::Null,
+    };
+    vec![
+        ("games", Json::Num(record.games() as f64)),
+        ("wins", Json::Num(record.wins as f64)),
+        ("draws", Json::Num(record.draws as f64)),
+        ("losses", Json::Num(record.losses as f64)),
+        (
+            "score",
+            Json::Num((record.score() * 1000.0).round() / 1000.0),
+        ),
+        ("elo", optional(record.elo())),
+        ("elo_margin", optional(record.margin())),
+    ]
+}
+
+/// the report as JSON, with the records of every pairing as well
+pub fn to_json(tournament: &Tournament, sprt: Option<&Sprt>) -> Json {
+    let names = &tournament.names;
+    let engines = names
+        .iter()
+        .enumerate()
+        .map(|(a, name)| {
+            let mut fields = vec![("name", Json::Str(name.clone()))];
+            fields.extend(record_json(&Record::of(tournament, a, None)));
+            Json::obj(fields)
+        })
+        .collect();
+    let mut pairings = Vec::new();
+    for a in 0..names.len() {
+        for b in a + 1..names.len() {
+            let record = Record::of(tournament, a, Some(b));
+            if record.games() > 0 {
+                let mut fields = vec![
+                    ("engine", Json::Str(names[a].clone())),
+                    ("opponent", Json::Str(names[b].clone())),
+                ];
+                fields.extend(record_json(&record));
+                pairings.push(Json::obj(fields));
+            }
+        }
+    }
+    let mut fields = vec![
+        ("engines", Json::Arr(engines)),
+        ("pairings", Json::Arr(pairings)),
+    ];
+    if let Some(sprt) = sprt {
+        let record = Record::of(tournament, 0, Some(1));
+        let (lower, upper) = sprt.bounds();
+        let round = |v: f64| Json::Num((v * 1000.0).round() / 1000.0);
+        fields.push((
+            "sprt",
+            Json::obj(vec![
+                ("elo0", Json::Num(sprt.elo0)),
+                ("elo1", Json::Num(sprt.elo1)),
+                ("alpha", Json::Num(sprt.alpha)),
+                ("beta", Json::Num(sprt.beta)),
+                ("llr", round(record.llr(sprt.elo0, sprt.elo1))),
+                ("lower", round(lower)),
+                ("upper", round(upper)),
+                (
+                    "verdict",
+                    match sprt.verdict(&record) {
+                        Some(true) => Json::Str("H1".to_string()),
+                        Some(false) => Json::Str("H0".to_string()),
+                        None => Json::Null,
+                    },
+                ),
+            ]),
+        ));
+    }
+    Json::obj(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    fn record(wins: u32, draws: u32, losses: u32) -> Record {
+        Record {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    #[test]
+    fn elo_of_a_score() {
+        assert_eq!(elo_from_score(0.5), 0.0);
+        assert!(close(elo_from_score(0.75), 190.85));
+        assert!(close(elo_from_score(0.25), -190.85));
+        assert!(close(score_from_elo(elo_from_score(0.64)), 0.64));
+        assert_eq!(record(0, 10, 0).elo(), Some(0.0));
+        assert_eq!(record(10, 0, 0).elo(), None);
+    }
+
+    #[test]
+    fn margin_of_a_match() {
+        // 50 wins and 50 losses: a standard error of 0.05 in the score
+        assert!(close(record(50, 0, 50).margin().unwrap(), 68.99));
+        // draws lower the variance and so the margin
+        assert!(record(25, 50, 25).margin().unwrap() < 68.99);
+        assert_eq!(record(1, 0, 0).margin(), None);
+    }
+
+    #[test]
+    fn log_likelihood_ratio() {
+        assert!(close(record(60, 0, 40).llr(0.0, 10.0), 0.556));
+        // a score below both hypotheses counts against `elo1`
+        assert!(record(40, 0, 60).llr(0.0, 10.0) < 0.0);
+        assert_eq!(record(0, 0, 0).llr(0.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn sprt_bounds_and_verdicts() {
+        let sprt = Sprt {
+            elo0: 0.0,
+            elo1: 10.0,
+            alpha: 0.05,
+            beta: 0.05,
+        };
+        let (lower, upper) = sprt.bounds();
+        assert!(close(lower, -2.94));
+        assert!(close(upper, 2.94));
+        assert_eq!(sprt.verdict(&record(600, 0, 400)), Some(true));
+        assert_eq!(sprt.verdict(&record(400, 0, 600)), Some(false));
+        assert_eq!(sprt.verdict(&record(60, 0, 40)), None);
+    }
+}
@@ -0,0 +1,313 @@
+//! Checks that a position could have arisen in a legal game
+//!
+//! These are necessary conditions rather than a proof: material is
+//! accounted for (promotions need missing pawns, and pawns that changed
+//! file need captured enemy pieces), the king that just moved out of turn
+//! must not be in check, checks come from at most two pieces (one of them
+//! a line piece whose line was opened), and castling
+//! and en-passant rights need their pieces in place. A position passing
+//! every check may still be unreachable.
+
+use crate::movegen::offset;
+use crate::position::{file_of, rank_of, square, square_name, Color, Piece, PieceKind, Position};
+
+fn name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
+
+fn count(pos: &Position, color: Color, kind: PieceKind) -> usize {
+    pos.board
+        .iter()
+        .flatten()
+        .filter(|p| p.color == color && p.kind == kind)
+        .count()
+}
+
+/// Fewest captures `color`'s pawns need to have made to stand on their
+/// files, each having started on a different file (pawns keep their
+/// order, so matching the sorted files is enough).
+fn pawn_captures(pos: &Position, color: Color) -> usize {
+    let mut files: Vec<usize> = (0..64u8)
+        .filter(|&sq| pos.piece_at(sq) == Some(Piece::new(color, PieceKind::Pawn)))
+        .map(|sq| file_of(sq) as usize)
+        .collect();
+    files.sort_unstable();
+    if files.len() > 8 {
+        return usize::MAX;
+    }
+    // best[j]: cost of matching the pawns so far to starting files before j
+    let mut best = vec![0usize; 9];
+    for (i, &file) in files.iter().enumerate() {
+        let mut next = vec![usize::MAX; 9];
+        for j in i + 1..=8 {
+            let cost = best[j - 1].saturating_add(file.abs_diff(j - 1));
+            next[j] = next[j - 1].min(cost);
+        }
+        best = next;
+    }
+    best[8]
+}
+
+/// the squares of `by`'s pieces attacking square `sq`
+fn attackers(pos: &Position, sq: u8, by: Color) -> Vec<u8> {
+    (0..64u8)
+        .filter(|&from| pos.piece_at(from).is_some_and(|p| p.color == by))
+        .filter(|&from| {
+            // every other attacker becomes a blocker that attacks nothing
+            let mut alone = pos.clone();
+            for other in 0..64u8 {
+                if other != from && alone.piece_at(other).is_some_and(|p| p.color == by) {
+                    alone.board[other as usize] = Some(Piece::new(by.opponent(), PieceKind::Pawn));
+                }
+            }
+            alone.is_attacked(sq, by)
+        })
+        .collect()
+}
+
+fn material(pos: &Position, color: Color, problems: &mut Vec<String>) {
+    let side = name(color);
+    let kings = count(pos, color, PieceKind::King);
+    if kings != 1 {
+        problems.push(format!("{} has {} kings", side, kings));
+    }
+    let pawns = count(pos, color, PieceKind::Pawn);
+    if pawns > 8 {
+        problems.push(format!("{} has {} pawns", side, pawns));
+    }
+    let pieces = pos
+        .board
+        .iter()
+        .flatten()
+        .filter(|p| p.color == color)
+        .count();
+    if pieces > 16 {
+        problems.push(format!("{} has {} pieces", side, pieces));
+    }
+
+    let bishops = |light: bool| {
+        (0..64u8)
+            .filter(|&sq| pos.piece_at(sq) == Some(Piece::new(color, PieceKind::Bishop)))
+            .filter(|&sq| ((file_of(sq) + rank_of(sq)) % 2 == 1) == light)
+            .count()
+    };
+    let promoted = count(pos, color, PieceKind::Queen).saturating_sub(1)
+        + count(pos, color, PieceKind::Rook).saturating_sub(2)
+        + count(pos, color, PieceKind::Knight).saturating_sub(2)
+        + bishops(true).saturating_sub(1)
+        + bishops(false).saturating_sub(1);
+    if pawns <= 8 && promoted > 8 - pawns {
+        problems.push(format!(
+            "{} has at least {} promoted pieces but only {} pawns are missing",
+            side,
+            promoted,
+            8 - pawns
+        ));
+    }
+
+    let captured = 16usize.saturating_sub(
+        pos.board
+            .iter()
+            .flatten()
+            .filter(|p| p.color == color.opponent())
+            .count(),
+    );
+    let needed = pawn_captures(pos, color);
+    if pawns <= 8 && needed > captured {
+        problems.push(format!(
+            "{}'s pawns need at least {} capture{} to reach their files, but only {} of {}'s pieces are missing",
+            side,
+            needed,
+            if needed == 1 { "" } else { "s" },
+            captured,
+            name(color.opponent())
+        ));
+    }
+
+    let home = match color {
+        Color::White => 0,
+        Color::Black => 7,
+    };
+    for far in [0, 7] {
+        let stranded = (0..8)
+            .filter(|&f| pos.piece_at(square(f, far)) == Some(Piece::new(color, PieceKind::Pawn)))
+            .count();
+        if stranded > 0 {
+            let rank = if far == home {
+                "its first rank"
+            } else {
+                "the last rank"
+            };
+            problems.push(format!("{} has a pawn on {}", side, rank));
+        }
+    }
+}
+
+fn checks(pos: &Position, problems: &mut Vec<String>) {
+    let moved = pos.side.opponent();
+    if let Some(king) = pos.king_square(moved) {
+        if pos.is_attacked(king, pos.side) {
+            problems.push(format!(
+                "{} is in check but it is {}'s move",
+                name(moved),
+                name(pos.side)
+            ));
+        }
+    }
+    if let Some(king) = pos.king_square(pos.side) {
+        let checkers = attackers(pos, king, moved);
+        // one move gives two checks only by opening a line, so one of the
+        // pair must be a bishop, rook or queen
+        let slider = |&sq: &u8| {
+            pos.piece_at(sq).is_some_and(|p| {
+                matches!(
+                    p.kind,
+                    PieceKind::Bishop | PieceKind::Rook | PieceKind::Queen
+                )
+            })
+        };
+        if checkers.len() > 2 {
+            problems.push(format!(
+                "{} is in check from {} pieces; no move gives more than a double check",
+                name(pos.side),
+                checkers.len()
+            ));
+        } else if checkers.len() == 2 && !checkers.iter().any(slider) {
+            problems.push(format!(
+                "{} is in double check from {} and {}, but neither can have been uncovered",
+                name(pos.side),
+                square_name(checkers[0]),
+                square_name(checkers[1])
+            ));
+        }
+    }
+}
+
+fn rights(pos: &Position, problems: &mut Vec<String>) {
+    let c = pos.castling;
+    for (held, color, rook_file, wing) in [
+        (c.white_kingside, Color::White, 7, "kingside"),
+        (c.white_queenside, Color::White, 0, "queenside"),
+        (c.black_kingside, Color::Black, 7, "kingside"),
+        (c.black_queenside, Color::Black, 0, "queenside"),
+    ] {
+        let rank = if color == Color::White { 0 } else { 7 };
+        let king = pos.piece_at(square(4, rank)) == Some(Piece::new(color, PieceKind::King));
+        let rook =
+            pos.piece_at(square(rook_file, rank)) == Some(Piece::new(color, PieceKind::Rook));
+        if held && !(king && rook) {
+            problems.push(format!(
+                "{} may castle {} but its king or rook has moved",
+                name(color),
+                wing
+            ));
+        }
+    }
+
+    if let Some(ep) = pos.ep {
+        let moved = pos.side.opponent();
+        // the pawn passed over `ep` from the square behind it
+        let (expected_rank, dr) = match moved {
+            Color::White => (2, 1),
+            Color::Black => (5, -1),
+        };
+        let pawn = offset(ep, 0, dr);
+        let start = offset(ep, 0, -dr);
+        let fits = rank_of(ep) == expected_rank
+            && pos.piece_at(ep).is_none()
+            && start.is_some_and(|s| pos.piece_at(s).is_none())
+            && pawn.is_some_and(|s| pos.piece_at(s) == Some(Piece::new(moved, PieceKind::Pawn)));
+        if !fits {
+            problems.push(format!(
+                "the en-passant square does not follow a double step by {}",
+                name(moved)
+            ));
+        }
+    }
+}
+
+/// Why `pos` cannot have arisen in a legal game, or nothing if no check
+/// rules it out.
+pub fn problems(pos: &Position) -> Vec<String> {
+    let mut problems = Vec::new();
+    material(pos, Color::White, &mut problems);
+    material(pos, Color::Black, &mut problems);
+    checks(pos, &mut problems);
+    rights(pos, &mut problems);
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems_of(fen: &str) -> Vec<String> {
+        problems(&Position::from_fen(fen).unwrap())
+    }
+
+    fn pawns_on(files: &[u8]) -> Position {
+        let mut pos = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        // one pawn a rank, so that pawns can share a file
+        for (rank, &file) in files.iter().enumerate() {
+            pos.board[square(file, rank as u8 + 1) as usize] =
+                Some(Piece::new(Color::White, PieceKind::Pawn));
+        }
+        pos
+    }
+
+    #[test]
+    fn pawn_captures_match_pawns_to_files() {
+        let start = Position::from_fen(crate::position::STARTING_FEN).unwrap();
+        assert_eq!(pawn_captures(&start, Color::White), 0);
+        // tripled on the a-file: from a, b and c
+        assert_eq!(pawn_captures(&pawns_on(&[0, 0, 0]), Color::White), 3);
+        // doubled on the h-file: from g and h
+        assert_eq!(pawn_captures(&pawns_on(&[7, 7]), Color::White), 1);
+        // a and h need nothing
+        assert_eq!(pawn_captures(&pawns_on(&[0, 7]), Color::White), 0);
+    }
+
+    #[test]
+    fn pawn_structures() {
+        assert!(problems_of(crate::position::STARTING_FEN).is_empty());
+        // doubled pawns with Black's pieces missing to have been taken
+        assert!(problems_of("4k3/8/8/8/8/P7/P7/4K3 w - - 0 1").is_empty());
+        // tripled pawns while Black still has all sixteen
+        let tripled = problems_of("rnbqkbnr/pppppppp/8/8/P7/P7/P7/4K3 w kq - 0 1");
+        assert!(
+            tripled
+                .iter()
+                .any(|p| p.contains("need at least 3 captures")),
+            "{:?}",
+            tripled
+        );
+    }
+
+    #[test]
+    fn checkers() {
+        let king = |fen: &str| {
+            let pos = Position::from_fen(fen).unwrap();
+            attackers(&pos, pos.king_square(Color::Black).unwrap(), Color::White).len()
+        };
+        // rook down the file and bishop on the diagonal: a discovered check
+        let double = "4k3/8/8/1B6/8/8/8/4RK2 b - - 0 1";
+        assert_eq!(king(double), 2);
+        assert!(problems_of(double).is_empty());
+        // two knights cannot uncover each other
+        let knights = problems_of("4k3/8/3N1N2/8/8/8/8/4K3 b - - 0 1");
+        assert!(
+            knights.iter().any(|p| p.contains("double check")),
+            "{:?}",
+            knights
+        );
+        // three checkers are never possible
+        let triple = "4k3/8/3N4/1B6/8/8/8/4RK2 b - - 0 1";
+        assert_eq!(king(triple), 3);
+        assert!(problems_of(triple).iter().any(|p| p.contains("3 pieces")));
+        // and the side not to move cannot be in check
+        assert!(!problems_of("4k3/8/8/8/8/8/8/4RK2 w - - 0 1").is_empty());
+    }
+}
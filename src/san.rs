@@ -0,0 +1,350 @@
+//! Standard Algebraic Notation (SAN) for moves relative to a position
+
+use crate::movegen::Move;
+use crate::position::{file_of, parse_square, rank_of, square_name, Color, PieceKind, Position};
+
+/// Format a legal move in SAN, including check/mate suffixes.
+pub fn to_san(pos: &Position, mv: Move) -> String {
+    let piece = match pos.piece_at(mv.from) {
+        Some(p) => p,
+        None => return mv.to_uci(),
+    };
+    let mut san = if pos.is_castle(mv) {
+        if file_of(mv.to) == 6 {
+            "O-O".to_string()
+        } else {
+            "O-O-O".to_string()
+        }
+    } else {
+        let mut s = String::new();
+        let capture = pos.is_capture(mv);
+        if piece.kind == PieceKind::Pawn {
+            if capture {
+                s.push((b'a' + file_of(mv.from)) as char);
+            }
+        } else {
+            s.push(piece.kind.letter());
+            // disambiguate between identical pieces reaching the same square
+            let rivals: Vec<Move> = pos
+                .legal_moves()
+                .into_iter()
+                .filter(|m| {
+                    m.to == mv.to && m.from != mv.from && pos.piece_at(m.from) == Some(piece)
+                })
+                .collect();
+            if !rivals.is_empty() {
+                let same_file = rivals.iter().any(|m| file_of(m.from) == file_of(mv.from));
+                let same_rank = rivals.iter().any(|m| rank_of(m.from) == rank_of(mv.from));
+                if !same_file {
+                    s.push((b'a' + file_of(mv.from)) as char);
+                } else if !same_rank {
+                    s.push((b'1' + rank_of(mv.from)) as char);
+                } else {
+                    s.push_str(&square_name(mv.from));
+                }
+            }
+        }
+        if capture {
+            s.push('x');
+        }
+        s.push_str(&square_name(mv.to));
+        if let Some(k) = mv.promotion {
+            s.push('=');
+            s.push(k.letter());
+        }
+        s
+    };
+    let next = pos.make_move(mv);
+    if next.in_check() {
+        san.push(if next.legal_moves().is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+    san
+}
+
+/// `text` with the piece letters of its SAN moves written as figurines
+/// (`♞f3`, `e8=♛`); move numbers, castling and results are left alone.
+pub fn figurine(text: &str) -> String {
+    crate::lang::replace_pieces(text, |kind| {
+        match kind {
+            PieceKind::King => "♚",
+            PieceKind::Queen => "♛",
+            PieceKind::Rook => "♜",
+            PieceKind::Bishop => "♝",
+            _ => "♞",
+        }
+        .to_string()
+    })
+}
+
+/// Parse a SAN move such as `Nf3`, `exd5`, `O-O` or `e8=Q+` into a legal move.
+pub fn parse_san(pos: &Position, san: &str) -> Result<Move, String> {
+    let s = san.trim_end_matches(['+', '#', '!', '?']);
+    let legal = pos.legal_moves();
+
+    if s == "O-O" || s == "0-0" || s == "O-O-O" || s == "0-0-0" {
+        let kingside = s.len() == 3;
+        return legal
+            .into_iter()
+            .find(|&m| pos.is_castle(m) && (file_of(m.to) == 6) == kingside)
+            .ok_or_else(|| format!("Illegal move {}", san));
+    }
+
+    let mut chars: Vec<char> = s.chars().collect();
+    let mut promotion = None;
+    if let Some(eq) = chars.iter().position(|&c| c == '=') {
+        promotion = chars
+            .get(eq + 1)
+            .and_then(|&c| PieceKind::from_letter(c))
+            .filter(|k| !matches!(k, PieceKind::Pawn | PieceKind::King));
+        if promotion.is_none() {
+            return Err(format!("Invalid promotion in {}", san));
+        }
+        chars.truncate(eq);
+    } else if chars.len() > 2
+        && chars[chars.len() - 1].is_ascii_uppercase()
+        && chars[chars.len() - 2].is_ascii_digit()
+    {
+        // tolerate e8Q without '='
+        promotion = PieceKind::from_letter(chars[chars.len() - 1]);
+        chars.pop();
+    }
+
+    let kind = match chars.first() {
+        Some(&c) if c.is_ascii_uppercase() => {
+            chars.remove(0);
+            PieceKind::from_letter(c).ok_or_else(|| format!("Invalid piece in {}", san))?
+        }
+        _ => PieceKind::Pawn,
+    };
+    chars.retain(|&c| c != 'x' && c != '-');
+    if chars.len() < 2 {
+        return Err(format!("Invalid move {}", san));
+    }
+    let target: String = chars[chars.len() - 2..].iter().collect();
+    let to = parse_square(&target).ok_or_else(|| format!("Invalid move {}", san))?;
+    let hint = &chars[..chars.len() - 2];
+    let from_file = hint
+        .iter()
+        .find(|c| ('a'..='h').contains(c))
+        .map(|&c| c as u8 - b'a');
+    let from_rank = hint
+        .iter()
+        .find(|c| ('1'..='8').contains(c))
+        .map(|&c| c as u8 - b'1');
+
+    let candidates: Vec<Move> = legal
+        .into_iter()
+        .filter(|m| {
+            m.to == to
+                && m.promotion == promotion
+                && pos.piece_at(m.from).map(|p| p.kind) == Some(kind)
+                && from_file.is_none_or(|f| file_of(m.from) == f)
+                && from_rank.is_none_or(|r| rank_of(m.from) == r)
+        })
+        .collect();
+    match candidates.len() {
+        1 => Ok(candidates[0]),
+        0 => Err(format!("Illegal move {}", san)),
+        _ => Err(format!("Ambiguous move {}", san)),
+    }
+}
+
+/// Parse a move given either in SAN or in UCI long algebraic form.
+pub fn parse_move(pos: &Position, text: &str) -> Result<Move, String> {
+    if let Some(mv) = Move::from_uci(text) {
+        if pos.is_legal(mv) {
+            return Ok(mv);
+        }
+    }
+    parse_san(pos, text).map_err(|e| {
+        if promotions(pos, text).is_empty() {
+            e
+        } else if Move::from_uci(text).is_some() {
+            format!("{} needs a promotion piece, e.g. {}q", text, text)
+        } else {
+            format!("{} needs a promotion piece, e.g. {}=Q", text, text)
+        }
+    })
+}
+
+/// Write each of `moves`, played in turn from `fen` and given in SAN or
+/// UCI form, in SAN (`to_san`) or UCI form.
+pub fn convert(fen: &str, moves: &[String], to_san: bool) -> Result<Vec<String>, String> {
+    let mut pos = Position::from_fen(fen)?;
+    let mut out = Vec::new();
+    for text in moves {
+        let mv = parse_move(&pos, text)?;
+        out.push(if to_san {
+            self::to_san(&pos, mv)
+        } else {
+            mv.to_uci()
+        });
+        pos = pos.make_move(mv);
+    }
+    Ok(out)
+}
+
+/// The legal promotions that `text`, a pawn move to the last rank such as
+/// `e8`, `dxe8` or `e7e8`, would be with its promotion piece given; none
+/// when `text` is some other move.
+pub fn promotions(pos: &Position, text: &str) -> Vec<Move> {
+    let text = text.trim_end_matches(['+', '#', '!', '?']);
+    let target = match Move::from_uci(text) {
+        Some(mv) if mv.promotion.is_none() => Some(mv),
+        _ => parse_san(pos, &format!("{}=Q", text)).ok(),
+    };
+    match target {
+        Some(target) => pos
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.from == target.from && mv.to == target.to && mv.promotion.is_some())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Format up to `max` plies of a UCI move list as numbered SAN movetext
+/// (e.g. `11. f5 exf5 12. Nxf5`), stopping early at any illegal move.
+pub fn format_line(pos: &Position, moves: &[String], max: usize) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut pos = pos.clone();
+    for (i, text) in moves.iter().take(max).enumerate() {
+        let mv = match Move::from_uci(text).filter(|&m| pos.is_legal(m)) {
+            Some(mv) => mv,
+            None => break,
+        };
+        if pos.side == Color::White {
+            out.push(format!("{}.", pos.fullmove));
+        } else if i == 0 {
+            out.push(format!("{}...", pos.fullmove));
+        }
+        out.push(to_san(&pos, mv));
+        pos = pos.make_move(mv);
+    }
+    out.join(" ")
+}
+
+/// A row of a move list: the move number, and White's and then Black's
+/// move in SAN, each with the ply it leads to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveRow {
+    pub number: u32,
+    pub moves: [Option<(usize, String)>; 2],
+}
+
+/// The moves played from `start` as the rows of a move list, a game
+/// starting with Black to move leaving White's first move empty.
+pub fn move_rows(start: &Position, moves: &[Move]) -> Vec<MoveRow> {
+    let mut rows: Vec<MoveRow> = Vec::new();
+    let mut pos = start.clone();
+    for (i, &mv) in moves.iter().enumerate() {
+        if pos.side == Color::White || rows.is_empty() {
+            rows.push(MoveRow {
+                number: pos.fullmove,
+                moves: [None, None],
+            });
+        }
+        let side = match pos.side {
+            Color::White => 0,
+            Color::Black => 1,
+        };
+        if let Some(row) = rows.last_mut() {
+            row.moves[side] = Some((i + 1, to_san(&pos, mv)));
+        }
+        pos = pos.make_move(mv);
+    }
+    rows
+}
+
+/// the row of `rows` holding the move that leads to ply `ply`
+pub fn row_of(rows: &[MoveRow], ply: usize) -> Option<usize> {
+    rows.iter()
+        .position(|row| row.moves.iter().flatten().any(|(p, _)| *p == ply))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san(fen: &str, uci: &str) -> String {
+        let pos = Position::from_fen(fen).unwrap();
+        to_san(&pos, Move::from_uci(uci).unwrap())
+    }
+
+    #[test]
+    fn every_legal_move_round_trips() {
+        for fen in [
+            crate::position::STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ] {
+            let root = Position::from_fen(fen).unwrap();
+            // one ply deeper as well, to see the other side's moves
+            let positions = std::iter::once(root.clone())
+                .chain(root.legal_moves().into_iter().map(|mv| root.make_move(mv)));
+            for pos in positions {
+                for mv in pos.legal_moves() {
+                    let text = to_san(&pos, mv);
+                    assert_eq!(
+                        parse_san(&pos, &text),
+                        Ok(mv),
+                        "{} in {}",
+                        text,
+                        pos.to_fen()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn disambiguation() {
+        // knights on b1 and f1 / rooks on a1 and a5 / queens on a1, a3 and c1
+        assert_eq!(san("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "b1d2"), "Nbd2");
+        assert_eq!(san("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3"), "R1a3");
+        assert_eq!(san("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "a1b2"), "Qa1b2");
+    }
+
+    #[test]
+    fn suffixes_and_special_moves() {
+        assert_eq!(san(crate::position::STARTING_FEN, "g1f3"), "Nf3");
+        assert_eq!(san("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1"), "O-O");
+        assert_eq!(san("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8"), "O-O-O");
+        assert_eq!(
+            san(
+                "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+                "e5d6"
+            ),
+            "exd6"
+        );
+        assert_eq!(san("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", "e7e8n"), "e8=N");
+        assert_eq!(san("3k4/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"), "Ra8+");
+        assert_eq!(
+            san(
+                "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2",
+                "d8h4"
+            ),
+            "Qh4#"
+        );
+    }
+
+    #[test]
+    fn lenient_input() {
+        let pos = Position::from_fen("8/4P3/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        let promotion = Move::from_uci("e7e8q").unwrap();
+        assert_eq!(parse_san(&pos, "e8Q"), Ok(promotion));
+        assert_eq!(parse_san(&pos, "e8=Q+!"), Ok(promotion));
+        let castles = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(
+            parse_san(&castles, "0-0"),
+            Ok(Move::from_uci("e1g1").unwrap())
+        );
+        assert!(parse_san(&pos, "Kh8").is_err());
+        assert!(parse_san(&pos, "e8=K").is_err());
+    }
+}
@@ -0,0 +1,137 @@
+//! Find the mistakes in a game with a UCI engine
+
+use crate::annotate::BLUNDER;
+use crate::diagram::{self, DiagramOptions, Palette, Quality};
+use crate::fonts::PieceFont;
+use crate::movegen::Move;
+use crate::pgn::first_game;
+use crate::png;
+use crate::position::{Color, Position};
+use crate::san::to_san;
+use crate::uci::{Engine, Score};
+use std::fs;
+use std::path::Path;
+
+pub struct ScanOptions<'a> {
+    pub engine: &'a str,
+    /// milliseconds of search per position
+    pub movetime: u64,
+    /// flag moves losing more than this many centipawns
+    pub threshold: i32,
+    /// directory for the mistake diagrams
+    pub out: &'a str,
+    pub piece_font: &'a PieceFont,
+    pub palette: Palette,
+    pub quality: Quality,
+}
+
+// score for the side to move, plus the engine's choice if there are legal moves
+fn evaluate(
+    engine: &mut Engine,
+    pos: &Position,
+    movetime: u64,
+) -> Result<(Score, Option<Move>), String> {
+    if pos.legal_moves().is_empty() {
+        let score = if pos.in_check() {
+            Score::Mate(0)
+        } else {
+            Score::Centipawns(0)
+        };
+        return Ok((score, None));
+    }
+    let analysis = engine.analyse(&pos.to_fen(), &[], &format!("movetime {}", movetime))?;
+    let score = analysis
+        .lines
+        .first()
+        .map(|l| l.score)
+        .ok_or_else(|| format!("Engine {} reported no score", engine.name))?;
+    Ok((score, analysis.best.as_deref().and_then(Move::from_uci)))
+}
+
+/// Evaluate every position of the first game in `path` and report each
+/// move losing more than the threshold, writing a PNG of the position
+/// before it (best move green, played move red) into the output directory.
+pub fn run(path: &str, opts: &ScanOptions) -> Result<(), String> {
+    let game = first_game(path)?;
+    let mut engine = Engine::spawn(opts.engine)?;
+    engine.new_game()?;
+    fs::create_dir_all(opts.out).map_err(|e| format!("Could not create {}: {}", opts.out, e))?;
+
+    let positions = game.positions();
+    let mut evals = Vec::with_capacity(positions.len());
+    for pos in &positions {
+        evals.push(evaluate(&mut engine, pos, opts.movetime)?);
+    }
+
+    let mut mistakes = 0;
+    for (ply, &mv) in game.moves.iter().enumerate() {
+        let before = &positions[ply];
+        let (score, best) = evals[ply];
+        // the next position is scored for the opponent
+        let after = evals[ply + 1].0.negate();
+        let loss = score.centipawns() - after.centipawns();
+        if loss <= opts.threshold || best == Some(mv) {
+            continue;
+        }
+        mistakes += 1;
+
+        let san = to_san(before, mv);
+        let suffix = if loss >= BLUNDER { "??" } else { "?" };
+        let number = match before.side {
+            Color::White => format!("{}.", before.fullmove),
+            Color::Black => format!("{}...", before.fullmove),
+        };
+        let white = |s: Score| match before.side {
+            Color::White => s,
+            Color::Black => s.negate(),
+        };
+        let best_text = best.map_or(String::new(), |b| {
+            format!(", best {} ({})", to_san(before, b), white(score))
+        });
+        println!(
+            "{} {}{} ({}){}",
+            number,
+            san,
+            suffix,
+            white(after),
+            best_text
+        );
+
+        let mut arrows = Vec::new();
+        if let Some(b) = best {
+            arrows.push((b.from, b.to, opts.palette.best));
+        }
+        arrows.push((mv.from, mv.to, opts.palette.blunder));
+        let diagram = diagram::board(
+            before,
+            &DiagramOptions {
+                eval: Some(white(score).bar_share()),
+                badge: Some(suffix.to_string()),
+                arrows,
+                palette: opts.palette,
+                shadows: opts.quality == Quality::High,
+                ..DiagramOptions::default()
+            },
+        );
+        let name = format!(
+            "{:03}-{}{}.png",
+            ply + 1,
+            number,
+            san.replace(['+', '#'], "")
+        );
+        let file = Path::new(opts.out).join(name);
+        png::save(
+            &diagram,
+            &file.to_string_lossy(),
+            opts.piece_font,
+            opts.quality,
+        )?;
+    }
+    println!(
+        "{} move{} losing more than {} centipawns",
+        mistakes,
+        if mistakes == 1 { "" } else { "s" },
+        opts.threshold
+    );
+    Ok(())
+}
@@ -0,0 +1,275 @@
+//! A toy built-in engine for `--analyze`
+//!
+//! Alpha-beta search to a fixed depth with a quiescence search of captures,
+//! evaluating material and piece-square tables only. It finds short tactics
+//! but knows nothing of king safety, pawn structure or the clock, so treat
+//! its scores as a rough guide; a real UCI engine is the one to trust.
+
+use crate::movegen::Move;
+use crate::position::{file_of, rank_of, Color, PieceKind, Position};
+use crate::uci::{Line, Score};
+use crate::zobrist;
+use std::collections::HashMap;
+
+const MATE: i32 = 100_000;
+const INFINITY: i32 = MATE + 1;
+
+// piece-square tables from White's side, a8 first (Tomasz Michniewski's
+// "simplified evaluation function")
+#[rustfmt::skip]
+const PAWN: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+#[rustfmt::skip]
+const KNIGHT: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+#[rustfmt::skip]
+const BISHOP: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+#[rustfmt::skip]
+const ROOK: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+#[rustfmt::skip]
+const QUEEN: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+#[rustfmt::skip]
+const KING: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+fn piece_value(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::Pawn => 100,
+        PieceKind::Knight => 320,
+        PieceKind::Bishop => 330,
+        PieceKind::Rook => 500,
+        PieceKind::Queen => 900,
+        PieceKind::King => 0,
+    }
+}
+
+/// material and placement, in centipawns for the side to move
+fn evaluate(pos: &Position) -> i32 {
+    let mut score = 0;
+    for (sq, piece) in pos.board.iter().enumerate() {
+        let piece = match piece {
+            Some(p) => p,
+            None => continue,
+        };
+        let (file, rank) = (file_of(sq as u8) as usize, rank_of(sq as u8) as usize);
+        let index = match piece.color {
+            Color::White => (7 - rank) * 8 + file,
+            Color::Black => rank * 8 + file,
+        };
+        let table = match piece.kind {
+            PieceKind::Pawn => &PAWN,
+            PieceKind::Knight => &KNIGHT,
+            PieceKind::Bishop => &BISHOP,
+            PieceKind::Rook => &ROOK,
+            PieceKind::Queen => &QUEEN,
+            PieceKind::King => &KING,
+        };
+        let value = piece_value(piece.kind) + table[index];
+        score += if piece.color == pos.side {
+            value
+        } else {
+            -value
+        };
+    }
+    score
+}
+
+// the best move found earlier in the position first, then captures (most
+// valuable victim, then least valuable attacker), then killer moves
+fn order(pos: &Position, moves: &mut [Move], first: Option<Move>, killers: &[Move]) {
+    moves.sort_by_key(|&mv| {
+        if Some(mv) == first {
+            return i32::MIN;
+        }
+        let victim = match pos.board[mv.to as usize] {
+            Some(p) => piece_value(p.kind),
+            None if pos.is_en_passant(mv) => piece_value(PieceKind::Pawn),
+            None if killers.contains(&mv) => return -1,
+            None => return 0,
+        };
+        let attacker = pos.board[mv.from as usize].map_or(0, |p| piece_value(p.kind));
+        attacker / 10 - victim
+    });
+}
+
+struct Search {
+    nodes: u64,
+    /// best or refuting move last found in each position, by Zobrist key
+    best: HashMap<u64, Move>,
+    /// quiet moves that caused a cutoff, by ply
+    killers: Vec<[Option<Move>; 2]>,
+}
+
+impl Search {
+    /// captures only, until the position is quiet
+    fn quiesce(&mut self, pos: &Position, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        let stand_pat = evaluate(pos);
+        if stand_pat >= beta {
+            return beta;
+        }
+        alpha = alpha.max(stand_pat);
+        let mut captures: Vec<Move> = pos
+            .legal_moves()
+            .into_iter()
+            .filter(|&mv| pos.is_capture(mv))
+            .collect();
+        order(pos, &mut captures, None, &[]);
+        for mv in captures {
+            let score = -self.quiesce(&pos.make_move(mv), -beta, -alpha);
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    /// negamax score of `pos` and its principal variation
+    fn alpha_beta(
+        &mut self,
+        pos: &Position,
+        depth: u32,
+        ply: i32,
+        mut alpha: i32,
+        beta: i32,
+        hint: &[Move],
+    ) -> (i32, Vec<Move>) {
+        let mut moves = pos.legal_moves();
+        if moves.is_empty() {
+            self.nodes += 1;
+            return (if pos.in_check() { ply - MATE } else { 0 }, Vec::new());
+        }
+        if depth == 0 {
+            return (self.quiesce(pos, alpha, beta), Vec::new());
+        }
+        self.nodes += 1;
+        let key = zobrist::hash(pos);
+        let first = hint.first().or(self.best.get(&key)).copied();
+        let ply_index = ply as usize;
+        if self.killers.len() <= ply_index {
+            self.killers.resize(ply_index + 1, [None; 2]);
+        }
+        let killers: Vec<Move> = self.killers[ply_index].iter().flatten().copied().collect();
+        order(pos, &mut moves, first, &killers);
+        let mut pv = Vec::new();
+        for mv in moves {
+            let rest = if hint.first() == Some(&mv) {
+                &hint[1..]
+            } else {
+                &[]
+            };
+            let (score, line) =
+                self.alpha_beta(&pos.make_move(mv), depth - 1, ply + 1, -beta, -alpha, rest);
+            let score = -score;
+            if score >= beta {
+                self.best.insert(key, mv);
+                if !pos.is_capture(mv) && self.killers[ply_index][0] != Some(mv) {
+                    self.killers[ply_index] = [Some(mv), self.killers[ply_index][0]];
+                }
+                return (beta, Vec::new());
+            }
+            if score > alpha {
+                alpha = score;
+                pv = std::iter::once(mv).chain(line).collect();
+            }
+        }
+        if let Some(&mv) = pv.first() {
+            self.best.insert(key, mv);
+        }
+        (alpha, pv)
+    }
+}
+
+fn to_score(score: i32) -> Score {
+    let plies = MATE - score.abs();
+    if plies > 1000 {
+        return Score::Centipawns(score);
+    }
+    let moves = (plies + 1) / 2;
+    Score::Mate(if score > 0 { moves } else { -moves })
+}
+
+/// Search `pos` to `depth` plies, deepening one ply at a time, and report
+/// the result as an engine line from the side to move's point of view,
+/// with the number of positions visited.
+pub fn analyse(pos: &Position, depth: u32) -> (Line, u64) {
+    let mut search = Search {
+        nodes: 0,
+        best: HashMap::new(),
+        killers: Vec::new(),
+    };
+    let mut line = Line {
+        multipv: 1,
+        depth: 0,
+        score: Score::Centipawns(0),
+        pv: Vec::new(),
+    };
+    let mut pv: Vec<Move> = Vec::new();
+    for d in 1..=depth.max(1) {
+        let (score, best) = search.alpha_beta(pos, d, 0, -INFINITY, INFINITY, &pv);
+        pv = best;
+        line = Line {
+            multipv: 1,
+            depth: d,
+            score: to_score(score),
+            pv: pv.iter().map(|mv| mv.to_uci()).collect(),
+        };
+        // nothing deeper changes a forced mate
+        if matches!(line.score, Score::Mate(_)) {
+            break;
+        }
+    }
+    (line, search.nodes)
+}
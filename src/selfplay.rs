@@ -0,0 +1,90 @@
+//! Play a position out between two UCI engines
+
+use crate::movegen::Move;
+use crate::pgn::Game;
+use crate::position::{Color, Position};
+use crate::rng::Rng;
+use crate::uci::Engine;
+use crate::zobrist;
+
+/// Whether `pos` is drawn by the fifty-move rule, insufficient material
+/// or threefold repetition, `seen` holding the Zobrist key of every
+/// position of the game so far, `pos` included.
+pub fn is_drawn(pos: &Position, seen: &[u64]) -> bool {
+    pos.halfmove >= 100
+        || pos.is_insufficient_material()
+        || seen.iter().filter(|&&k| k == zobrist::hash(pos)).count() >= 3
+}
+
+/// `start` after `plies` moves picked at random, for varied openings; it
+/// stops early at mate or stalemate.
+pub fn random_opening(start: Position, plies: u32, rng: &mut Rng) -> Position {
+    let mut pos = start;
+    for _ in 0..plies {
+        let moves = pos.legal_moves();
+        if moves.is_empty() {
+            break;
+        }
+        pos = pos.make_move(moves[rng.below(moves.len() as u64) as usize]);
+    }
+    pos
+}
+
+/// Play from `start` with `white` and `black` engines searching for
+/// `movetime` milliseconds per move. `on_move` is called with each new
+/// position so callers can stream the board. The game ends on mate,
+/// stalemate, the fifty-move rule, threefold repetition, insufficient
+/// material, or after `max_moves` full moves.
+pub fn play(
+    start: Position,
+    white: &mut Engine,
+    black: &mut Engine,
+    movetime: u64,
+    max_moves: u32,
+    mut on_move: impl FnMut(&Position, &str),
+) -> Result<Game, String> {
+    let mut game = Game::new(start.clone());
+    game.set_tag("Event", "fen selfplay");
+    game.set_tag("White", &white.name);
+    game.set_tag("Black", &black.name);
+    white.new_game()?;
+    black.new_game()?;
+
+    let fen = start.to_fen();
+    let mut pos = start;
+    let mut played: Vec<String> = Vec::new();
+    let mut seen = vec![zobrist::hash(&pos)];
+    let go = format!("movetime {}", movetime);
+    loop {
+        if pos.legal_moves().is_empty() {
+            game.conclude();
+            break;
+        }
+        if is_drawn(&pos, &seen) {
+            game.result = "1/2-1/2".to_string();
+            break;
+        }
+        if game.moves.len() as u32 >= max_moves * 2 {
+            break;
+        }
+
+        let engine = match pos.side {
+            Color::White => &mut *white,
+            Color::Black => &mut *black,
+        };
+        let reply = engine
+            .best_move(&fen, &played, &go)?
+            .ok_or_else(|| format!("Engine {} returned no move", engine.name))?;
+        let mv = Move::from_uci(&reply)
+            .filter(|&m| pos.is_legal(m))
+            .ok_or_else(|| format!("Engine {} played illegal move {}", engine.name, reply))?;
+
+        let san = crate::san::to_san(&pos, mv);
+        game.moves.push(mv);
+        played.push(reply);
+        pos = pos.make_move(mv);
+        seen.push(zobrist::hash(&pos));
+        on_move(&pos, &san);
+    }
+    Ok(game)
+}
@@ -0,0 +1,258 @@
+//! Aggregate statistics over a collection of positions
+
+use crate::json::Json;
+use crate::position::{Color, PieceKind, Position};
+use std::collections::HashMap;
+
+const VALUES: [(PieceKind, i32); 5] = [
+    (PieceKind::Queen, 9),
+    (PieceKind::Rook, 5),
+    (PieceKind::Bishop, 3),
+    (PieceKind::Knight, 3),
+    (PieceKind::Pawn, 1),
+];
+
+/// conventional value of a piece (Q=9, R=5, B=N=3, P=1); kings count nothing
+pub fn value(kind: PieceKind) -> i32 {
+    VALUES
+        .iter()
+        .find(|(k, _)| *k == kind)
+        .map_or(0, |(_, v)| *v)
+}
+
+/// conventional material value (Q=9, R=5, B=N=3, P=1) of one side
+pub fn material(pos: &Position, color: Color) -> i32 {
+    pos.board
+        .iter()
+        .flatten()
+        .filter(|p| p.color == color)
+        .map(|p| value(p.kind))
+        .sum()
+}
+
+/// material signature such as `KRPPvKR`, pieces in KQRBNP order
+pub fn signature(pos: &Position) -> String {
+    let side = |color: Color| -> String {
+        [
+            PieceKind::King,
+            PieceKind::Queen,
+            PieceKind::Rook,
+            PieceKind::Bishop,
+            PieceKind::Knight,
+            PieceKind::Pawn,
+        ]
+        .iter()
+        .flat_map(|&kind| {
+            let n = pos
+                .board
+                .iter()
+                .flatten()
+                .filter(|p| p.color == color && p.kind == kind)
+                .count();
+            std::iter::repeat_n(kind.letter(), n)
+        })
+        .collect()
+    };
+    format!("{}v{}", side(Color::White), side(Color::Black))
+}
+
+/// Whether `pos` has the material in `pattern`, such as `R vs R` or
+/// `RP vs R`: the pieces of each side besides the king, in either order.
+/// Pawns only count when the pattern mentions them.
+pub fn material_matches(pos: &Position, pattern: &str) -> Result<bool, String> {
+    let order = "QRBNP";
+    let normalise = |side: &str, pawns: bool| -> String {
+        let mut pieces: Vec<char> = side
+            .chars()
+            .map(|c| c.to_ascii_uppercase())
+            .filter(|&c| !c.is_whitespace() && c != 'K' && (pawns || c != 'P'))
+            .collect();
+        pieces.sort_by_key(|&c| order.find(c));
+        pieces.into_iter().collect()
+    };
+    let text = pattern.replace("vs", "v");
+    let wanted: Vec<&str> = text.split('v').collect();
+    let bad = || format!("Expected material such as \"R vs R\", found {}", pattern);
+    if wanted.len() != 2
+        || !wanted
+            .concat()
+            .chars()
+            .all(|c| c.is_whitespace() || "KQRBNPkqrbnp".contains(c))
+    {
+        return Err(bad());
+    }
+    let pawns = pattern.contains(['P', 'p']);
+    let signature = signature(pos);
+    let (white, black) = signature.split_once('v').ok_or_else(bad)?;
+    let have = [normalise(white, pawns), normalise(black, pawns)];
+    let want = [normalise(wanted[0], pawns), normalise(wanted[1], pawns)];
+    Ok(have == want || have == [want[1].clone(), want[0].clone()])
+}
+
+#[derive(Default)]
+pub struct Stats {
+    pub positions: usize,
+    pub invalid: usize,
+    white_to_move: usize,
+    castling: [usize; 4],
+    no_castling: usize,
+    signatures: HashMap<String, usize>,
+    balance: HashMap<i32, usize>,
+    // FEN without the move clocks, so transposed move counts still match
+    placements: HashMap<String, usize>,
+}
+
+fn top<K: Clone + Ord>(counts: &HashMap<K, usize>, n: usize) -> Vec<(K, usize)> {
+    let mut entries: Vec<(K, usize)> = counts.iter().map(|(k, v)| (k.clone(), *v)).collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    entries.truncate(n);
+    entries
+}
+
+fn percent(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        100.0 * part as f64 / whole as f64
+    }
+}
+
+impl Stats {
+    pub fn add(&mut self, pos: &Position) {
+        self.positions += 1;
+        if pos.side == Color::White {
+            self.white_to_move += 1;
+        }
+        let c = pos.castling;
+        for (i, held) in [
+            c.white_kingside,
+            c.white_queenside,
+            c.black_kingside,
+            c.black_queenside,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if held {
+                self.castling[i] += 1;
+            }
+        }
+        if !c.any() {
+            self.no_castling += 1;
+        }
+        *self.signatures.entry(signature(pos)).or_default() += 1;
+        let diff = material(pos, Color::White) - material(pos, Color::Black);
+        *self.balance.entry(diff).or_default() += 1;
+        let fen = pos.to_fen();
+        let key: Vec<&str> = fen.split_whitespace().take(4).collect();
+        *self.placements.entry(key.join(" ")).or_default() += 1;
+    }
+
+    pub fn to_text(&self) -> String {
+        let n = self.positions;
+        let mut out = format!("Positions: {} ({} invalid skipped)\n", n, self.invalid);
+        if n == 0 {
+            return out;
+        }
+        out.push_str(&format!(
+            "\nSide to move\n  White  {:>7} ({:.1}%)\n  Black  {:>7} ({:.1}%)\n",
+            self.white_to_move,
+            percent(self.white_to_move, n),
+            n - self.white_to_move,
+            percent(n - self.white_to_move, n)
+        ));
+        out.push_str("\nCastling rights\n");
+        for (i, label) in ["K", "Q", "k", "q"].iter().enumerate() {
+            out.push_str(&format!(
+                "  {:<5}  {:>7} ({:.1}%)\n",
+                label,
+                self.castling[i],
+                percent(self.castling[i], n)
+            ));
+        }
+        out.push_str(&format!(
+            "  {:<5}  {:>7} ({:.1}%)\n",
+            "-",
+            self.no_castling,
+            percent(self.no_castling, n)
+        ));
+        out.push_str("\nMaterial balance (White - Black)\n");
+        let mut balance: Vec<(i32, usize)> = self.balance.iter().map(|(k, v)| (*k, *v)).collect();
+        balance.sort();
+        for (diff, count) in balance {
+            out.push_str(&format!(
+                "  {:>+5}  {:>7} ({:.1}%)\n",
+                diff,
+                count,
+                percent(count, n)
+            ));
+        }
+        out.push_str("\nMost common material\n");
+        for (sig, count) in top(&self.signatures, 10) {
+            out.push_str(&format!("  {:<34} {:>7}\n", sig, count));
+        }
+        out.push_str("\nMost common positions\n");
+        for (fen, count) in top(&self.placements, 10) {
+            out.push_str(&format!("  {:<72} {:>7}\n", fen, count));
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> Json {
+        let counted = |entries: Vec<(String, usize)>, key: &str| {
+            Json::Arr(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| {
+                        Json::obj(vec![(key, Json::Str(k)), ("count", Json::Num(v as f64))])
+                    })
+                    .collect(),
+            )
+        };
+        let mut balance: Vec<(i32, usize)> = self.balance.iter().map(|(k, v)| (*k, *v)).collect();
+        balance.sort();
+        Json::obj(vec![
+            ("positions", Json::Num(self.positions as f64)),
+            ("invalid", Json::Num(self.invalid as f64)),
+            (
+                "side_to_move",
+                Json::obj(vec![
+                    ("white", Json::Num(self.white_to_move as f64)),
+                    (
+                        "black",
+                        Json::Num((self.positions - self.white_to_move) as f64),
+                    ),
+                ]),
+            ),
+            (
+                "castling",
+                Json::obj(vec![
+                    ("K", Json::Num(self.castling[0] as f64)),
+                    ("Q", Json::Num(self.castling[1] as f64)),
+                    ("k", Json::Num(self.castling[2] as f64)),
+                    ("q", Json::Num(self.castling[3] as f64)),
+                    ("none", Json::Num(self.no_castling as f64)),
+                ]),
+            ),
+            (
+                "material_balance",
+                Json::Arr(
+                    balance
+                        .into_iter()
+                        .map(|(d, c)| {
+                            Json::obj(vec![
+                                ("balance", Json::Num(d as f64)),
+                                ("count", Json::Num(c as f64)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            ("material", counted(top(&self.signatures, 10), "signature")),
+            (
+                "common_positions",
+                counted(top(&self.placements, 10), "fen"),
+            ),
+        ])
+    }
+}
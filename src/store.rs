@@ -0,0 +1,273 @@
+//! Local store of named positions and recently viewed FENs
+//!
+//! Kept as JSON in `store.json` under `$FEN_HOME`, or else the platform's
+//! data directory (`$XDG_DATA_HOME/fen`, `~/.local/share/fen` or
+//! `%APPDATA%\fen`).
+
+use crate::json::Json;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// how many viewed positions the history keeps
+const HISTORY_LIMIT: usize = 100;
+
+/// a position saved under a name
+pub struct Saved {
+    pub name: String,
+    pub fen: String,
+    /// seconds since the Unix epoch
+    pub saved: u64,
+    pub tags: Vec<String>,
+}
+
+/// a position shown on the board
+pub struct Viewed {
+    pub fen: String,
+    /// seconds since the Unix epoch
+    pub viewed: u64,
+}
+
+#[derive(Default)]
+pub struct Store {
+    pub positions: Vec<Saved>,
+    /// oldest first
+    pub history: Vec<Viewed>,
+}
+
+/// directory holding the store and other local data
+pub fn data_dir() -> Result<PathBuf, String> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+    if let Some(dir) = var("FEN_HOME") {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = var("XDG_DATA_HOME") {
+        return Ok(PathBuf::from(dir).join("fen"));
+    }
+    if let Some(dir) = var("APPDATA") {
+        return Ok(PathBuf::from(dir).join("fen"));
+    }
+    match var("HOME") {
+        Some(home) => Ok(PathBuf::from(home).join(".local/share/fen")),
+        None => Err("Could not find a data directory; set FEN_HOME".to_string()),
+    }
+}
+
+/// directory holding the user's configuration, such as piece sets:
+/// `$XDG_CONFIG_HOME/fen`, `~/.config/fen` or `%APPDATA%\fen`
+pub fn config_dir() -> Result<PathBuf, String> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+    if let Some(dir) = var("XDG_CONFIG_HOME") {
+        return Ok(PathBuf::from(dir).join("fen"));
+    }
+    if let Some(dir) = var("APPDATA") {
+        return Ok(PathBuf::from(dir).join("fen"));
+    }
+    match var("HOME") {
+        Some(home) => Ok(PathBuf::from(home).join(".config/fen")),
+        None => Err("Could not find a configuration directory".to_string()),
+    }
+}
+
+/// Write `contents` to `path` by way of a temporary file beside it, so
+/// that an interrupted write or another run writing at the same time
+/// leaves the old file or the new one, never a truncated mix.
+pub fn write_atomic(path: &Path, contents: &str) -> Result<(), String> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.tmp", std::process::id()));
+    let temp = path.with_file_name(name);
+    std::fs::write(&temp, contents)
+        .and_then(|_| std::fs::rename(&temp, path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            format!("Could not write {}: {}", path.display(), e)
+        })
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// UTC date and time, e.g. `2024-03-09 14:05`
+pub fn format_time(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let minutes = secs % 86_400 / 60;
+    // civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+fn string(value: &Json, key: &str) -> String {
+    value
+        .get(key)
+        .and_then(Json::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn time(value: &Json, key: &str) -> u64 {
+    value.get(key).and_then(Json::as_f64).unwrap_or(0.0) as u64
+}
+
+fn list<'a>(value: &'a Json, key: &str) -> &'a [Json] {
+    value.get(key).and_then(Json::as_array).unwrap_or(&[])
+}
+
+impl Store {
+    fn path() -> Result<PathBuf, String> {
+        Ok(data_dir()?.join("store.json"))
+    }
+
+    /// Read the store, which is empty before anything has been saved.
+    pub fn load() -> Result<Store, String> {
+        let path = Store::path()?;
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Store::default()),
+            Err(e) => return Err(format!("Could not read {}: {}", path.display(), e)),
+        };
+        let json = Json::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Store {
+            positions: list(&json, "positions")
+                .iter()
+                .map(|p| Saved {
+                    name: string(p, "name"),
+                    fen: string(p, "fen"),
+                    saved: time(p, "saved"),
+                    tags: list(p, "tags")
+                        .iter()
+                        .filter_map(Json::as_str)
+                        .map(str::to_string)
+                        .collect(),
+                })
+                .collect(),
+            history: list(&json, "history")
+                .iter()
+                .map(|v| Viewed {
+                    fen: string(v, "fen"),
+                    viewed: time(v, "viewed"),
+                })
+                .collect(),
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Store::path()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+        }
+        let json = Json::obj(vec![
+            (
+                "positions",
+                Json::Arr(
+                    self.positions
+                        .iter()
+                        .map(|p| {
+                            Json::obj(vec![
+                                ("name", Json::Str(p.name.clone())),
+                                ("fen", Json::Str(p.fen.clone())),
+                                ("saved", Json::Num(p.saved as f64)),
+                                (
+                                    "tags",
+                                    Json::Arr(p.tags.iter().cloned().map(Json::Str).collect()),
+                                ),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "history",
+                Json::Arr(
+                    self.history
+                        .iter()
+                        .map(|v| {
+                            Json::obj(vec![
+                                ("fen", Json::Str(v.fen.clone())),
+                                ("viewed", Json::Num(v.viewed as f64)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ]);
+        write_atomic(&path, &format!("{}\n", json))
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Saved> {
+        self.positions.iter().find(|p| p.name == name)
+    }
+
+    /// Save `position`, replacing any other under the same name.
+    pub fn put(&mut self, position: Saved) {
+        self.positions.retain(|p| p.name != position.name);
+        self.positions.push(position);
+    }
+
+    /// Note that `fen` was viewed now, keeping the history to its limit.
+    pub fn record_view(&mut self, fen: &str) {
+        if self.history.last().is_some_and(|v| v.fen == fen) {
+            self.history.pop();
+        }
+        self.history.push(Viewed {
+            fen: fen.to_string(),
+            viewed: now(),
+        });
+        let excess = self.history.len().saturating_sub(HISTORY_LIMIT);
+        self.history.drain(..excess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atomic_writes_replace_the_file() {
+        let dir = std::env::temp_dir().join(format!("fen-store-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("store.json");
+        write_atomic(&path, "old").unwrap();
+        write_atomic(&path, "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        // nothing is left beside it
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn history_keeps_to_its_limit() {
+        let mut store = Store::default();
+        store.record_view("a");
+        store.record_view("a");
+        assert_eq!(store.history.len(), 1);
+        for i in 0..HISTORY_LIMIT + 5 {
+            store.record_view(&i.to_string());
+        }
+        assert_eq!(store.history.len(), HISTORY_LIMIT);
+        assert_eq!(store.history[0].fen, "5");
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(format_time(0), "1970-01-01 00:00");
+        assert_eq!(format_time(1_709_993_100), "2024-03-09 14:05");
+    }
+}
@@ -0,0 +1,66 @@
+//! Named bundles of diagram options
+//!
+//! `--style minimal` draws small diagrams to set inline in text: no
+//! coordinates, a thin frame and the muted palette. `--style presentation`
+//! draws large ones for slides and projectors, with a heavier frame and
+//! piece shadows. A `--palette` given as well keeps its colours.
+
+use crate::diagram::{DiagramOptions, Layout, PaletteName};
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Style {
+    /// small, coordinate-free diagrams with a thin frame and muted colours
+    Minimal,
+    /// large diagrams with coordinates, a frame and piece shadows
+    Presentation,
+}
+
+/// the options a style sets
+pub struct Preset {
+    /// square size and frame width; styles leave no margin
+    pub layout: Layout,
+    pub coordinates: bool,
+    pub palette: PaletteName,
+    pub shadows: bool,
+}
+
+impl Default for Preset {
+    /// the options without a style
+    fn default() -> Preset {
+        let plain = DiagramOptions::default();
+        Preset {
+            layout: plain.layout,
+            coordinates: plain.coordinates,
+            palette: PaletteName::Default,
+            shadows: plain.shadows,
+        }
+    }
+}
+
+impl Style {
+    pub fn preset(self) -> Preset {
+        match self {
+            Style::Minimal => Preset {
+                layout: Layout {
+                    square: 28.0,
+                    border: 1.0,
+                    ..Layout::default()
+                },
+                coordinates: false,
+                palette: PaletteName::Muted,
+                shadows: false,
+            },
+            Style::Presentation => Preset {
+                layout: Layout {
+                    square: 120.0,
+                    border: 6.0,
+                    ..Layout::default()
+                },
+                coordinates: true,
+                palette: PaletteName::Default,
+                shadows: true,
+            },
+        }
+    }
+}
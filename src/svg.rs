@@ -0,0 +1,166 @@
+//! SVG export of board diagrams
+//!
+//! The markup depends only on the diagram: shapes are written in the order
+//! they were drawn, numbers with at most two decimals, and there are no
+//! generated IDs or timestamps, so the same position always gives the same
+//! file and diagrams kept in version control diff cleanly. The optimized
+//! form is smaller still: it names the fonts once, shortens colours, puts
+//! runs of shapes of one colour in a group and leaves out the newlines.
+
+use crate::diagram::{Diagram, Font, Rgba, Shape};
+
+/// a coordinate or size, rounded to hundredths, without trailing zeros
+pub fn num(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
+
+// `#rrggbb`, or `#rgb` when optimizing and each channel repeats a digit
+fn colour(color: Rgba, optimize: bool) -> String {
+    let [r, g, b, _] = color;
+    if optimize && [r, g, b].iter().all(|c| c >> 4 == c & 0xf) {
+        format!("#{:x}{:x}{:x}", r & 0xf, g & 0xf, b & 0xf)
+    } else {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+// fill colour, plus an opacity attribute for translucent colours
+fn fill(color: Rgba, optimize: bool) -> String {
+    let mut out = format!("fill=\"{}\"", colour(color, optimize));
+    if color[3] < 255 {
+        out.push_str(&format!(" fill-opacity=\"{:.2}\"", color[3] as f64 / 255.0));
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn colour_of(shape: &Shape) -> Rgba {
+    match shape {
+        Shape::Rect { color, .. } | Shape::Text { color, .. } | Shape::Polygon { color, .. } => {
+            *color
+        }
+    }
+}
+
+// one shape as an element, with `paint` as its fill attributes
+fn element(shape: &Shape, paint: &str, optimize: bool) -> String {
+    match shape {
+        Shape::Rect { x, y, w, h, .. } => {
+            // both default to zero
+            let at = |name: &str, value: f64| match num(value) {
+                zero if optimize && zero == "0" => String::new(),
+                value => format!(" {}=\"{}\"", name, value),
+            };
+            format!(
+                "<rect{}{} width=\"{}\" height=\"{}\"{}/>",
+                at("x", *x),
+                at("y", *y),
+                num(*w),
+                num(*h),
+                paint
+            )
+        }
+        Shape::Text {
+            x,
+            y,
+            size,
+            text,
+            font,
+            ..
+        } => {
+            let family = match (font, optimize) {
+                (Font::Text, false) => "font-family=\"Fira Sans, sans-serif\"",
+                (Font::Pieces, false) => "font-family=\"FreeSerif, DejaVu Sans, serif\"",
+                (Font::Text, true) => "class=\"t\"",
+                (Font::Pieces, true) => "class=\"p\"",
+            };
+            format!(
+                "<text x=\"{}\" y=\"{}\" font-size=\"{}\" {}{}>{}</text>",
+                num(*x),
+                num(*y),
+                num(*size),
+                family,
+                paint,
+                escape(text)
+            )
+        }
+        Shape::Polygon { points, .. } => {
+            let points: Vec<String> = points
+                .iter()
+                .map(|(x, y)| format!("{},{}", num(*x), num(*y)))
+                .collect();
+            format!("<polygon points=\"{}\"{}/>", points.join(" "), paint)
+        }
+    }
+}
+
+/// The diagram as an SVG document.
+pub fn to_svg(diagram: &Diagram) -> String {
+    write(diagram, false)
+}
+
+/// The diagram as a compact SVG document, for `--svg-optimize`.
+pub fn to_optimized_svg(diagram: &Diagram) -> String {
+    write(diagram, true)
+}
+
+fn write(diagram: &Diagram, optimize: bool) -> String {
+    let start = std::time::Instant::now();
+    let (w, h) = (num(diagram.width), num(diagram.height));
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+    );
+    if !optimize {
+        for shape in &diagram.shapes {
+            let paint = format!(" {}", fill(colour_of(shape), false));
+            out.push_str(&element(shape, &paint, false));
+            out.push('\n');
+        }
+    } else {
+        if diagram
+            .shapes
+            .iter()
+            .any(|shape| matches!(shape, Shape::Text { .. }))
+        {
+            out.push_str("<style>.t{font-family:Fira Sans,sans-serif}.p{font-family:FreeSerif,DejaVu Sans,serif}</style>");
+        }
+        // runs of shapes in the same colour share a group's fill
+        let mut shapes = diagram.shapes.iter().peekable();
+        while let Some(shape) = shapes.next() {
+            let color = colour_of(shape);
+            let mut run = vec![shape];
+            while let Some(next) = shapes.next_if(|next| colour_of(next) == color) {
+                run.push(next);
+            }
+            if run.len() == 1 {
+                out.push_str(&element(shape, &format!(" {}", fill(color, true)), true));
+            } else {
+                out.push_str(&format!("<g {}>", fill(color, true)));
+                for shape in run {
+                    out.push_str(&element(shape, "", true));
+                }
+                out.push_str("</g>");
+            }
+        }
+        out.push('\n');
+    }
+    out.push_str("</svg>\n");
+    tracing::debug!(
+        target: "fen::render",
+        shapes = diagram.shapes.len(),
+        bytes = out.len(),
+        elapsed_ms = start.elapsed().as_secs_f64() * 1000.0,
+        "wrote SVG"
+    );
+    out
+}
@@ -0,0 +1,379 @@
+//! User templates for custom diagrams
+//!
+//! `--template` fills in a text file, such as an SVG or HTML page, with the
+//! position's data using a small subset of Handlebars:
+//!
+//! - `{{fen}}` writes a value, escaped for HTML and XML; `{{{fen}}}` writes
+//!   it as it is. Dotted paths such as `{{square.name}}` reach into objects.
+//! - `{{#each squares}}...{{/each}}` repeats its body for each item of a
+//!   list, where names are looked up in the item first and then outside it;
+//!   `{{this}}` is the item and `{{@index}}` its place, counting from 0.
+//! - `{{#if piece}}...{{else}}...{{/if}}` keeps the first part when the
+//!   value is set (not empty, false, zero or missing) and the second when not.
+//!
+//! The data is the FEN and its fields, `squares` (all 64 from a8 to h1),
+//! `ranks` (the 8th first, each with its `squares`) and `pieces` (the
+//! occupied squares only). Each square has its `name`, `file`, `rank`, its
+//! `col` and `row` counted from the top-left corner, whether it is `light`,
+//! and any `piece` (a FEN letter), `glyph` and `color`.
+
+use crate::json::Json;
+use crate::lang::Lang;
+use crate::position::{square, square_name, Color, Position};
+use crate::terminal::glyph;
+
+enum Node {
+    Text(String),
+    Value {
+        path: String,
+        raw: bool,
+    },
+    Each {
+        path: String,
+        body: Vec<Node>,
+    },
+    If {
+        path: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// what ends a run of nodes: the end of the template, or a closing tag
+enum End {
+    Eof,
+    Else,
+    Close(String),
+}
+
+fn parse(text: &str) -> Result<Vec<Node>, String> {
+    let mut rest = text;
+    let (nodes, end) = parse_nodes(&mut rest)?;
+    match end {
+        End::Eof => Ok(nodes),
+        End::Else => Err("{{else}} outside {{#if}} in the template".to_string()),
+        End::Close(name) => Err(format!("{{{{/{}}}}} without a block to close", name)),
+    }
+}
+
+fn parse_nodes(rest: &mut &str) -> Result<(Vec<Node>, End), String> {
+    let mut nodes = Vec::new();
+    loop {
+        let Some(open) = rest.find("{{") else {
+            if !rest.is_empty() {
+                nodes.push(Node::Text(rest.to_string()));
+            }
+            *rest = "";
+            return Ok((nodes, End::Eof));
+        };
+        if open > 0 {
+            nodes.push(Node::Text(rest[..open].to_string()));
+        }
+        let raw = rest[open..].starts_with("{{{");
+        let (start, close) = if raw {
+            (open + 3, "}}}")
+        } else {
+            (open + 2, "}}")
+        };
+        let length = rest[start..]
+            .find(close)
+            .ok_or("A {{ tag in the template is never closed")?;
+        let tag = rest[start..start + length].trim().to_string();
+        *rest = &rest[start + length + close.len()..];
+
+        if let Some(block) = tag.strip_prefix('#') {
+            let (helper, path) = block.split_once(' ').unwrap_or((block, ""));
+            let path = path.trim().to_string();
+            let (body, end) = parse_nodes(rest)?;
+            match (helper, end) {
+                ("each", End::Close(name)) if name == "each" => {
+                    nodes.push(Node::Each { path, body })
+                }
+                ("if", End::Close(name)) if name == "if" => nodes.push(Node::If {
+                    path,
+                    then: body,
+                    otherwise: Vec::new(),
+                }),
+                ("if", End::Else) => {
+                    let (otherwise, end) = parse_nodes(rest)?;
+                    if !matches!(&end, End::Close(name) if name == "if") {
+                        return Err(format!("{{{{#if {}}}}} is never closed", path));
+                    }
+                    nodes.push(Node::If {
+                        path,
+                        then: body,
+                        otherwise,
+                    });
+                }
+                ("each" | "if", _) => {
+                    return Err(format!("{{{{#{} {}}}}} is never closed", helper, path))
+                }
+                _ => return Err(format!("Unknown block {{{{#{}}}}} in the template", helper)),
+            }
+        } else if let Some(name) = tag.strip_prefix('/') {
+            return Ok((nodes, End::Close(name.trim().to_string())));
+        } else if tag == "else" {
+            return Ok((nodes, End::Else));
+        } else {
+            nodes.push(Node::Value { path: tag, raw });
+        }
+    }
+}
+
+// `path` in the innermost scope that has its first part
+fn find<'a>(path: &str, scopes: &[(&'a Json, usize)]) -> Option<&'a Json> {
+    let &(this, _) = scopes.last()?;
+    let (mut value, rest) = match path.strip_prefix("this") {
+        Some("") => return Some(this),
+        Some(rest) if rest.starts_with('.') => (this, &rest[1..]),
+        _ => {
+            let first = path.split('.').next().unwrap_or(path);
+            let scope = scopes.iter().rev().find_map(|(s, _)| s.get(first))?;
+            (scope, path.split_once('.').map_or("", |(_, rest)| rest))
+        }
+    };
+    for key in rest.split('.').filter(|k| !k.is_empty()) {
+        value = value.get(key)?;
+    }
+    Some(value)
+}
+
+// as `find`, and `@index` for the place of the current item
+fn lookup(path: &str, scopes: &[(&Json, usize)]) -> Option<Json> {
+    match path {
+        "@index" => scopes.last().map(|&(_, i)| Json::Num(i as f64)),
+        path => find(path, scopes).cloned(),
+    }
+}
+
+fn truthy(value: &Option<Json>) -> bool {
+    match value {
+        None | Some(Json::Null) | Some(Json::Bool(false)) => false,
+        Some(Json::Num(n)) => *n != 0.0,
+        Some(Json::Str(s)) => !s.is_empty(),
+        Some(Json::Arr(items)) => !items.is_empty(),
+        Some(_) => true,
+    }
+}
+
+fn text(value: &Json) -> String {
+    match value {
+        Json::Null => String::new(),
+        Json::Str(s) => s.clone(),
+        Json::Num(n) if n.fract() == 0.0 => format!("{}", *n as i64),
+        other => other.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn write(nodes: &[Node], scopes: &mut Vec<(&Json, usize)>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Value { path, raw } => {
+                let value = lookup(path, scopes).map(|v| text(&v)).unwrap_or_default();
+                out.push_str(&if *raw { value } else { escape(&value) });
+            }
+            Node::If {
+                path,
+                then,
+                otherwise,
+            } => {
+                let branch = if truthy(&lookup(path, scopes)) {
+                    then
+                } else {
+                    otherwise
+                };
+                write(branch, scopes, out);
+            }
+            Node::Each { path, body } => {
+                if let Some(Json::Arr(items)) = find(path, scopes) {
+                    for (i, item) in items.iter().enumerate() {
+                        scopes.push((item, i));
+                        write(body, scopes, out);
+                        scopes.pop();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Fill in `template` with `data`.
+pub fn render(template: &str, data: &Json) -> Result<String, String> {
+    let nodes = parse(template)?;
+    let mut out = String::new();
+    write(&nodes, &mut vec![(data, 0)], &mut out);
+    Ok(out)
+}
+
+fn side(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+/// The data a template sees for `pos`.
+pub fn data(pos: &Position) -> Json {
+    let square_data = |file: u8, rank: u8| {
+        let sq = square(file, rank);
+        let piece = pos.piece_at(sq);
+        Json::obj(vec![
+            ("name", Json::Str(square_name(sq))),
+            ("file", Json::Str(((b'a' + file) as char).to_string())),
+            ("rank", Json::Num((rank + 1) as f64)),
+            ("col", Json::Num(file as f64)),
+            ("row", Json::Num((7 - rank) as f64)),
+            ("light", Json::Bool((file + rank) % 2 == 1)),
+            (
+                "piece",
+                Json::Str(piece.map(|p| p.to_char().to_string()).unwrap_or_default()),
+            ),
+            ("glyph", Json::Str(piece.map(glyph).unwrap_or_default())),
+            (
+                "color",
+                Json::Str(piece.map(|p| side(p.color)).unwrap_or_default().to_string()),
+            ),
+        ])
+    };
+    let ranks: Vec<Json> = (0..8u8)
+        .rev()
+        .map(|rank| {
+            Json::obj(vec![
+                ("rank", Json::Num((rank + 1) as f64)),
+                (
+                    "squares",
+                    Json::Arr((0..8u8).map(|file| square_data(file, rank)).collect()),
+                ),
+            ])
+        })
+        .collect();
+    let squares: Vec<Json> = (0..8u8)
+        .rev()
+        .flat_map(|rank| (0..8u8).map(move |file| (file, rank)))
+        .map(|(file, rank)| square_data(file, rank))
+        .collect();
+    let pieces = squares
+        .iter()
+        .filter(|sq| sq.get("piece").and_then(Json::as_str) != Some(""))
+        .cloned()
+        .collect();
+    Json::obj(vec![
+        ("fen", Json::Str(pos.to_fen())),
+        ("placement", Json::Str(pos.placement())),
+        ("side", Json::Str(side(pos.side).to_string())),
+        ("to_move", Json::Str(Lang::En.to_move(pos.side).to_string())),
+        ("castling", Json::Str(pos.castling.to_string())),
+        (
+            "en_passant",
+            Json::Str(pos.ep.map(square_name).unwrap_or_else(|| "-".to_string())),
+        ),
+        ("halfmove", Json::Num(pos.halfmove as f64)),
+        ("fullmove", Json::Num(pos.fullmove as f64)),
+        ("squares", Json::Arr(squares)),
+        ("ranks", Json::Arr(ranks)),
+        ("pieces", Json::Arr(pieces)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::STARTING_FEN;
+
+    fn start() -> Json {
+        data(&Position::from_fen(STARTING_FEN).unwrap())
+    }
+
+    #[test]
+    fn values_are_written_escaped_or_raw() {
+        let data = Json::obj(vec![
+            ("name", Json::Str("<b>&'\"".to_string())),
+            ("n", Json::Num(3.0)),
+            ("x", Json::obj(vec![("y", Json::Str("deep".to_string()))])),
+        ]);
+        assert_eq!(
+            render("{{name}}|{{{name}}}|{{ n }}|{{x.y}}", &data),
+            Ok("&lt;b&gt;&amp;&#39;&quot;|<b>&'\"|3|deep".to_string())
+        );
+        // an unknown placeholder is left empty, as in Handlebars
+        assert_eq!(
+            render("a{{missing}}b{{x.z}}c", &data),
+            Ok("abc".to_string())
+        );
+        assert_eq!(render("no tags", &data), Ok("no tags".to_string()));
+    }
+
+    #[test]
+    fn blocks_repeat_and_choose() {
+        assert_eq!(
+            render(
+                "{{#each ranks}}{{#if @index}}/{{/if}}{{rank}}{{/each}}",
+                &start()
+            ),
+            Ok("8/7/6/5/4/3/2/1".to_string())
+        );
+        assert_eq!(
+            render(
+                "{{#each pieces}}{{#if light}}{{piece}}{{else}}.{{/if}}{{/each}}",
+                &start()
+            ),
+            Ok("r.b.k.n..p.p.p.pP.P.P.P..N.Q.B.R".to_string())
+        );
+        // names outside the item are still found inside it
+        assert_eq!(
+            render(
+                "{{#each ranks}}{{#if side}}{{/if}}{{side}}{{/each}}",
+                &start()
+            )
+            .map(|s| s.len()),
+            Ok(8 * "white".len())
+        );
+        assert_eq!(
+            render("{{#if halfmove}}yes{{else}}no{{/if}} {{fen}}", &start()),
+            Ok(format!("no {}", STARTING_FEN))
+        );
+    }
+
+    #[test]
+    fn malformed_templates_are_refused() {
+        let data = start();
+        for template in [
+            "{{fen",
+            "{{{fen}}",
+            "{{#each squares}}{{name}}",
+            "{{#if fen}}a{{else}}b",
+            "{{#if fen}}{{/each}}",
+            "{{/if}}",
+            "{{else}}",
+            "{{#with fen}}{{/with}}",
+        ] {
+            assert!(render(template, &data).is_err(), "{}", template);
+        }
+    }
+
+    #[test]
+    fn data_describes_the_squares() {
+        let data = start();
+        let Some(Json::Arr(squares)) = data.get("squares") else {
+            panic!("no squares");
+        };
+        assert_eq!(squares.len(), 64);
+        assert_eq!(
+            render("{{#each squares}}{{#if @index}}{{else}}{{name}} {{piece}} {{color}} {{row}} {{col}}{{/if}}{{/each}}", &data),
+            Ok("a8 r black 0 0".to_string())
+        );
+        assert_eq!(
+            render("{{castling}} {{en_passant}} {{side}}", &data),
+            Ok("KQkq - white".to_string())
+        );
+    }
+}
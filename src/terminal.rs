@@ -0,0 +1,910 @@
+//! Terminal representation of the board
+
+use crate::capabilities::{ColorDepth, ImageProtocol};
+use crate::diagram::{self, DiagramOptions, Layout, Palette, Rgba, SquareIndex};
+use crate::fonts::PieceFont;
+use crate::movegen::Move;
+use crate::png;
+use crate::position::{square, Piece, PieceKind, Position};
+use crate::san::{row_of, MoveRow};
+use clap::ValueEnum;
+use std::io::{BufRead, IsTerminal, Write};
+use unicode_width::UnicodeWidthChar;
+
+/// how pieces are written to the terminal
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Charset {
+    /// FEN letters, uppercase for White
+    Ascii,
+    /// outline chess glyphs for White, solid for Black
+    Unicode,
+    /// solid chess glyphs for both sides, White's in bold
+    Figurine,
+}
+
+impl Charset {
+    /// Guess whether the terminal can show chess glyphs: dumb terminals,
+    /// non-UTF-8 locales and the legacy Windows console get letters.
+    pub fn detect() -> Charset {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        if var("TERM").as_deref() == Some("dumb") {
+            return Charset::Ascii;
+        }
+        // the first locale variable set decides, as in setlocale(3)
+        if let Some(locale) = var("LC_ALL")
+            .or_else(|| var("LC_CTYPE"))
+            .or_else(|| var("LANG"))
+        {
+            let locale = locale.to_ascii_lowercase();
+            if locale != "c.utf-8" && !locale.contains("utf-8") && !locale.contains("utf8") {
+                return Charset::Ascii;
+            }
+        }
+        // Windows Terminal and most third-party terminals announce themselves
+        if cfg!(windows) && var("WT_SESSION").is_none() && var("TERM_PROGRAM").is_none() {
+            return Charset::Ascii;
+        }
+        Charset::Unicode
+    }
+}
+
+pub struct TerminalOptions {
+    pub charset: Charset,
+    /// shown on empty squares
+    pub placeholder: String,
+    /// draw the board large: as an inline image where the terminal takes
+    /// one, else each square a coloured block of 2x4 characters
+    pub big: bool,
+    /// the colours the big board may use; without any it is drawn plain
+    pub color: ColorDepth,
+    pub images: ImageProtocol,
+    /// draw each square as 5x3 characters of plain ASCII art
+    pub bigascii: bool,
+    /// square colours of the big board
+    pub palette: Palette,
+    /// each square's index or name before its piece (not on the big board)
+    pub index: Option<SquareIndex>,
+}
+
+/// translate the layout element of a FEN into a grid of piece glyphs
+pub fn board_glyphs(layout: &str) -> Vec<Vec<String>> {
+    // split at separators
+    let fenarray: Vec<String> = layout.split("/").map(str::to_string).collect();
+
+    // translate to glyphs
+    let mut fentranslated: Vec<Vec<String>> = Vec::with_capacity(64);
+    for rank in fenarray {
+        let mut rankchars: Vec<String> = Vec::new();
+        for chars in rank.split_inclusive("").filter(|&x| !x.is_empty()) {
+            let t: String = translate_piece(chars).split("").collect();
+            let i = t.parse::<i32>();
+            let tvec: Vec<String> = match i {
+                Ok(v) => std::iter::repeat_n(String::from(""), v as usize).collect(),
+                Err(_) => vec![t],
+            };
+            for el in tvec {
+                rankchars.push(el);
+            }
+        }
+        fentranslated.push(rankchars);
+    }
+    fentranslated
+}
+
+/// Print the board as an 8-column grid. Columns are as wide as the widest
+/// cell; on a terminal each cell is also placed with a cursor movement so
+/// glyphs the terminal draws double-width cannot push later columns along.
+pub fn print_board(pieces: &[Vec<String>], opts: &TerminalOptions) {
+    if opts.bigascii {
+        return println!("{}", ascii_board(pieces, opts));
+    }
+    if opts.big && opts.images != ImageProtocol::None {
+        if let Some(image) = inline_image(pieces, opts) {
+            return println!("{}", image);
+        }
+    }
+    if opts.big && opts.color != ColorDepth::None {
+        return println!("{}", big_board(pieces, opts));
+    }
+    let (rows, width) = board_cells(pieces, opts);
+    let position = std::io::stdout().is_terminal();
+
+    let mut out = String::from("\n");
+    for row in rows {
+        let mut line = String::new();
+        for (i, cell) in row.iter().enumerate() {
+            if position && !cell.is_empty() {
+                line.push_str(&format!("\x1b[{}G", 1 + i * (width + 1)));
+            } else if i > 0 {
+                line.push(' ');
+            }
+            line.push_str(cell);
+            if !position {
+                line.push_str(&" ".repeat(width - display_width(cell)));
+            }
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    println!("{}", out);
+}
+
+// the cells of `print_board`, rank 8 first, and the width of a column
+fn board_cells(pieces: &[Vec<String>], opts: &TerminalOptions) -> (Vec<Vec<String>>, usize) {
+    let rows: Vec<Vec<String>> = pieces
+        .iter()
+        .enumerate()
+        .map(|(r, rank)| {
+            rank.iter()
+                .enumerate()
+                .map(|(f, glyph)| {
+                    let piece = match glyph.as_str() {
+                        "" => cell(&opts.placeholder, opts.charset),
+                        glyph => cell(glyph, opts.charset),
+                    };
+                    match opts.index {
+                        Some(index) => {
+                            format!("{}{}", index.label(square(f as u8, 7 - r as u8)), piece)
+                        }
+                        None => piece,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let width = rows
+        .iter()
+        .flatten()
+        .map(|c| display_width(c))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    (rows, width)
+}
+
+// a screen line as its cells, each written from a column
+type Line = Vec<(usize, String)>;
+
+/// A display kept in place on a terminal, for following a game. Text and
+/// boards are gathered, then `show` draws them from the top of the screen
+/// the first time and afterwards rewrites only the squares and lines that
+/// changed, by moving the cursor to them, so a fast stream of positions
+/// neither flickers nor scrolls. When stdout is not a terminal everything
+/// is printed at once, as `println!` and `print_board` would.
+pub struct Screen {
+    live: bool,
+    shown: Vec<Line>,
+    next: Vec<Line>,
+}
+
+impl Default for Screen {
+    fn default() -> Screen {
+        Screen::new()
+    }
+}
+
+impl Screen {
+    pub fn new() -> Screen {
+        Screen {
+            live: std::io::stdout().is_terminal(),
+            shown: Vec::new(),
+            next: Vec::new(),
+        }
+    }
+
+    /// whether the display is redrawn in place rather than printed
+    pub fn is_live(&self) -> bool {
+        self.live
+    }
+
+    /// Add lines of text.
+    pub fn text(&mut self, text: &str) {
+        if !self.live {
+            return println!("{}", text);
+        }
+        for line in text.split('\n') {
+            self.next.push(vec![(1, line.to_string())]);
+        }
+    }
+
+    /// Add a board, laid out as `print_board` does. The big board is
+    /// compared a line at a time, each holding a rank's worth of squares.
+    pub fn board(&mut self, pieces: &[Vec<String>], opts: &TerminalOptions) {
+        if !self.live {
+            return print_board(pieces, opts);
+        }
+        let big = if opts.bigascii {
+            Some(ascii_board(pieces, opts))
+        } else if opts.big && opts.color != ColorDepth::None {
+            Some(big_board(pieces, opts))
+        } else {
+            None
+        };
+        if let Some(board) = big {
+            for line in board.split('\n') {
+                self.next.push(vec![(1, line.to_string())]);
+            }
+            return;
+        }
+        let (rows, width) = board_cells(pieces, opts);
+        self.next.push(Vec::new());
+        for row in rows {
+            let line = row
+                .into_iter()
+                .enumerate()
+                .map(|(i, cell)| (1 + i * (width + 1), cell))
+                .collect();
+            self.next.push(line);
+        }
+        self.next.push(Vec::new());
+    }
+
+    /// Bring the terminal up to date with what was added since the last
+    /// call.
+    pub fn show(&mut self) {
+        if !self.live {
+            return;
+        }
+        let next = std::mem::take(&mut self.next);
+        let mut out = String::new();
+        let write_line = |out: &mut String, row: usize, line: &Line| {
+            out.push_str(&format!("\x1b[{};1H\x1b[2K", row));
+            for (col, text) in line {
+                out.push_str(&format!("\x1b[{}G{}", col, text));
+            }
+        };
+        if next.len() != self.shown.len() {
+            out.push_str("\x1b[2J");
+            for (row, line) in next.iter().enumerate() {
+                write_line(&mut out, row + 1, line);
+            }
+        } else {
+            for (row, (old, new)) in self.shown.iter().zip(&next).enumerate() {
+                let same_cells =
+                    old.len() == new.len() && old.iter().zip(new).all(|((a, _), (b, _))| a == b);
+                if !same_cells {
+                    write_line(&mut out, row + 1, new);
+                    continue;
+                }
+                for ((col, was), (_, text)) in old.iter().zip(new) {
+                    if was != text {
+                        let blank = display_width(was).saturating_sub(display_width(text));
+                        out.push_str(&format!(
+                            "\x1b[{};{}H{}{}",
+                            row + 1,
+                            col,
+                            text,
+                            " ".repeat(blank)
+                        ));
+                    }
+                }
+            }
+        }
+        out.push_str(&format!("\x1b[{};1H", next.len() + 1));
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(out.as_bytes());
+        let _ = stdout.flush();
+        self.shown = next;
+    }
+}
+
+/// boards side by side in a row of the terminal filmstrip
+const STRIP_COLUMNS: usize = 4;
+
+/// Print boards side by side, a few to a row, each under its caption.
+/// Empty squares are dotted unless a placeholder is given, so that the
+/// boards stay apart.
+pub fn print_strip(frames: &[(String, Vec<Vec<String>>)], opts: &TerminalOptions) {
+    let empty = if opts.placeholder.is_empty() {
+        "·"
+    } else {
+        &opts.placeholder
+    };
+    let boards: Vec<Vec<Vec<String>>> = frames
+        .iter()
+        .map(|(_, pieces)| {
+            pieces
+                .iter()
+                .map(|rank| {
+                    rank.iter()
+                        .map(|glyph| match glyph.as_str() {
+                            "" => cell(empty, opts.charset),
+                            glyph => cell(glyph, opts.charset),
+                        })
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+    let width = boards
+        .iter()
+        .flatten()
+        .flatten()
+        .map(|c| display_width(c))
+        .max()
+        .unwrap_or(1)
+        .max(1);
+    let board_width = 8 * (width + 1) - 1;
+    let pad = |text: &str, to: usize| {
+        format!(
+            "{}{}",
+            text,
+            " ".repeat(to.saturating_sub(display_width(text)))
+        )
+    };
+
+    let mut out = String::new();
+    for (captions, boards) in frames
+        .chunks(STRIP_COLUMNS)
+        .zip(boards.chunks(STRIP_COLUMNS))
+    {
+        let line: Vec<String> = captions
+            .iter()
+            .map(|(caption, _)| pad(caption, board_width))
+            .collect();
+        out.push_str(&format!("\n{}\n", line.join("   ").trim_end()));
+        for rank in 0..8 {
+            let line: Vec<String> = boards
+                .iter()
+                .map(|board| {
+                    let cells: Vec<String> = board[rank].iter().map(|c| pad(c, width)).collect();
+                    pad(&cells.join(" "), board_width)
+                })
+                .collect();
+            out.push_str(line.join("   ").trim_end());
+            out.push('\n');
+        }
+    }
+    println!("{}", out);
+}
+
+// Each square is a 2x4 block in the diagram colours with its piece near the
+// middle. Everything is placed with cursor movements, so double-width glyphs
+// only ever overwrite their own square.
+fn big_board(pieces: &[Vec<String>], opts: &TerminalOptions) -> String {
+    let mut out = String::from("\n");
+    for (r, rank) in pieces.iter().enumerate() {
+        for line in 0..2 {
+            if line == 0 {
+                out.push_str(&format!("{} ", 8 - r));
+            }
+            for (f, glyph) in rank.iter().enumerate() {
+                let col = 3 + 4 * f;
+                let light = (r + f) % 2 == 0;
+                out.push_str(&format!(
+                    "\x1b[{}G\x1b[{}m    ",
+                    col,
+                    background(&opts.palette, light, opts.color)
+                ));
+                if line == 0 {
+                    let glyph = if glyph.is_empty() {
+                        &opts.placeholder
+                    } else {
+                        glyph
+                    };
+                    out.push_str(&format!(
+                        "\x1b[{}G{}",
+                        col + 1,
+                        big_cell(glyph, opts.charset)
+                    ));
+                }
+            }
+            out.push_str("\x1b[0m\n");
+        }
+    }
+    out.push_str("   ");
+    for file in 'a'..='h' {
+        out.push_str(&format!("{:<4}", file));
+    }
+    format!("{}\n", out.trim_end())
+}
+
+/// A grid of characters that text is drawn onto at any row and column,
+/// later text overwriting earlier, for boards made of more than one
+/// character a square.
+struct Canvas {
+    cells: Vec<Vec<char>>,
+}
+
+impl Canvas {
+    fn new(rows: usize, columns: usize) -> Canvas {
+        Canvas {
+            cells: vec![vec![' '; columns]; rows],
+        }
+    }
+
+    /// Draw `text` from `row` and `column`; what falls off the edge is lost.
+    fn put(&mut self, row: usize, column: usize, text: &str) {
+        let Some(line) = self.cells.get_mut(row) else {
+            return;
+        };
+        for (cell, c) in line.iter_mut().skip(column).zip(text.chars()) {
+            *cell = c;
+        }
+    }
+
+    fn render(&self) -> String {
+        let lines: Vec<String> = self
+            .cells
+            .iter()
+            .map(|line| line.iter().collect::<String>().trim_end().to_string())
+            .collect();
+        lines.join("\n")
+    }
+}
+
+// the 5x3 drawings of the pieces, pawn to king, `*` standing for the
+// piece's FEN letter so the two sides stay apart
+const ART: [[&str; 3]; 6] = [
+    ["  _  ", " (*) ", " /_\\ "],
+    ["  /) ", " (*( ", " /__\\"],
+    [" (/) ", " (*) ", " /_\\ "],
+    ["[_|_]", " |*| ", "/___\\"],
+    [" \\^/ ", " (*) ", "/___\\"],
+    [" _+_ ", " (*) ", "/___\\"],
+];
+
+// Each square is 5x3 characters inside a grid of lines, with the piece
+// drawn in ASCII art and the dark squares, when empty, dotted, so the
+// board reads on terminals without colours or chess glyphs.
+fn ascii_board(pieces: &[Vec<String>], opts: &TerminalOptions) -> String {
+    const WIDTH: usize = 6;
+    const HEIGHT: usize = 4;
+    let mut canvas = Canvas::new(8 * HEIGHT + 2, 2 + 8 * WIDTH + 1);
+    for r in 0..=8 {
+        canvas.put(r * HEIGHT, 2, &format!("{}+", "+-----".repeat(8)));
+    }
+    for (r, rank) in pieces.iter().enumerate() {
+        let top = r * HEIGHT + 1;
+        canvas.put(top + 1, 0, &(8 - r).to_string());
+        for (f, glyph) in rank.iter().enumerate() {
+            let left = 2 + f * WIDTH;
+            for line in 0..3 {
+                canvas.put(top + line, left, "|");
+            }
+            let index = WHITE_GLYPHS
+                .iter()
+                .chain(BLACK_GLYPHS.iter())
+                .position(|g| g == glyph);
+            match index {
+                Some(i) => {
+                    let letter = &"PNBRQKpnbrqk"[i..i + 1];
+                    for (line, art) in ART[i % 6].iter().enumerate() {
+                        canvas.put(top + line, left + 1, &art.replace('*', letter));
+                    }
+                }
+                None => {
+                    if (r + f) % 2 == 1 {
+                        for line in 0..3 {
+                            canvas.put(top + line, left + 1, ":::::");
+                        }
+                    }
+                    let mark = cell(&opts.placeholder, Charset::Ascii);
+                    if !mark.is_empty() {
+                        canvas.put(top + 1, left + 3, &mark);
+                    }
+                }
+            }
+        }
+        for line in 0..3 {
+            canvas.put(top + line, 2 + 8 * WIDTH, "|");
+        }
+    }
+    for (f, file) in ('a'..='h').enumerate() {
+        canvas.put(8 * HEIGHT + 1, 2 + f * WIDTH + 3, &file.to_string());
+    }
+    format!("\n{}\n", canvas.render())
+}
+
+// the 16 ANSI colours, as xterm draws them by default
+const ANSI: [Rgba; 16] = [
+    [0, 0, 0, 255],
+    [205, 0, 0, 255],
+    [0, 205, 0, 255],
+    [205, 205, 0, 255],
+    [0, 0, 238, 255],
+    [205, 0, 205, 255],
+    [0, 205, 205, 255],
+    [229, 229, 229, 255],
+    [127, 127, 127, 255],
+    [255, 0, 0, 255],
+    [0, 255, 0, 255],
+    [255, 255, 0, 255],
+    [92, 92, 255, 255],
+    [255, 0, 255, 255],
+    [0, 255, 255, 255],
+    [255, 255, 255, 255],
+];
+
+// the ANSI colour nearest `colour`, skipping `not`
+fn nearest_ansi(colour: Rgba, not: Option<usize>) -> usize {
+    let distance = |c: &Rgba| {
+        (0..3)
+            .map(|i| (c[i] as i32 - colour[i] as i32).pow(2))
+            .sum::<i32>()
+    };
+    (0..16)
+        .filter(|&i| Some(i) != not)
+        .min_by_key(|&i| distance(&ANSI[i]))
+        .unwrap_or(0)
+}
+
+// the SGR parameters giving a big-board square its palette colour, as
+// closely as `depth` allows; with 16 colours the dark squares never take
+// the light squares' colour
+fn background(palette: &Palette, light: bool, depth: ColorDepth) -> String {
+    let colour = if light { palette.light } else { palette.dark };
+    let [r, g, b, _] = colour;
+    match depth {
+        ColorDepth::Truecolor => format!("48;2;{};{};{}", r, g, b),
+        ColorDepth::Ansi256 => {
+            let level = |c: u8| (c as u16 * 5 + 127) / 255;
+            format!("48;5;{}", 16 + 36 * level(r) + 6 * level(g) + level(b))
+        }
+        ColorDepth::Ansi16 | ColorDepth::None => {
+            let not = (!light).then(|| nearest_ansi(palette.light, None));
+            match nearest_ansi(colour, not) {
+                i @ 0..=7 => format!("{}", 40 + i),
+                i => format!("{}", 100 + i - 8),
+            }
+        }
+    }
+}
+
+// the placement field of the FEN the glyphs of `board_glyphs` came from
+fn placement(pieces: &[Vec<String>]) -> String {
+    let ranks: Vec<String> = pieces
+        .iter()
+        .map(|rank| {
+            let mut out = String::new();
+            let mut empty = 0;
+            for glyph in rank {
+                let index = WHITE_GLYPHS
+                    .iter()
+                    .chain(BLACK_GLYPHS.iter())
+                    .position(|g| g == glyph);
+                match index {
+                    Some(i) => {
+                        if empty > 0 {
+                            out.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        out.push_str(&"PNBRQKpnbrqk"[i..i + 1]);
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                out.push_str(&empty.to_string());
+            }
+            out
+        })
+        .collect();
+    ranks.join("/")
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(DIGITS[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// the board as an inline image in the terminal's protocol, as large as
+// the block board; `None` if it cannot be drawn
+fn inline_image(pieces: &[Vec<String>], opts: &TerminalOptions) -> Option<String> {
+    let pos = Position::from_fen(&format!("{} w - - 0 1", placement(pieces))).ok()?;
+    let opts_diagram = DiagramOptions {
+        layout: Layout {
+            square: 60.0,
+            ..Layout::default()
+        },
+        palette: opts.palette,
+        index: opts.index,
+        ..DiagramOptions::default()
+    };
+    let image = png::render(
+        &diagram::board(&pos, &opts_diagram),
+        &PieceFont::default(),
+        diagram::Quality::Low,
+    );
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image
+        .write_to(&mut bytes, image::ImageOutputFormat::Png)
+        .ok()?;
+    let data = base64(bytes.get_ref());
+    // 32 columns by 16 rows, the size of the block board's squares
+    Some(match opts.images {
+        ImageProtocol::Kitty => {
+            // the protocol takes the data in chunks of at most 4096 bytes
+            let chunks: Vec<&str> = data
+                .as_bytes()
+                .chunks(4096)
+                .map(|c| std::str::from_utf8(c).unwrap_or_default())
+                .collect();
+            let mut out = String::from("\n");
+            for (i, chunk) in chunks.iter().enumerate() {
+                let more = if i + 1 < chunks.len() { 1 } else { 0 };
+                let keys = if i == 0 {
+                    format!("a=T,f=100,c=32,r=16,m={}", more)
+                } else {
+                    format!("m={}", more)
+                };
+                out.push_str(&format!("\x1b_G{};{}\x1b\\", keys, chunk));
+            }
+            out
+        }
+        ImageProtocol::Iterm => format!(
+            "\n\x1b]1337;File=inline=1;width=32;height=16;preserveAspectRatio=1:{}\x07",
+            data
+        ),
+        ImageProtocol::None => return None,
+    })
+}
+
+// a solid (or lettered) piece coloured for its side, for the big board
+fn big_cell(glyph: &str, charset: Charset) -> String {
+    let index = WHITE_GLYPHS
+        .iter()
+        .chain(BLACK_GLYPHS.iter())
+        .position(|&g| g == glyph);
+    match index {
+        Some(i) => {
+            let text = match charset {
+                Charset::Ascii => "PNBRQKpnbrqk"[i..i + 1].to_string(),
+                _ => BLACK_GLYPHS[i % 6].to_string(),
+            };
+            let fg = if i < 6 { "1;97" } else { "30" };
+            format!("\x1b[{}m{}\x1b[22;39m", fg, text)
+        }
+        None => cell(glyph, charset),
+    }
+}
+
+/// Columns taken by `text` on screen, skipping ANSI escape sequences.
+pub fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI sequences end with a letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            width += c.width().unwrap_or(0);
+        }
+    }
+    width
+}
+
+// a glyph from `board_glyphs` (or a marker such as `·`) in the given charset
+fn cell(glyph: &str, charset: Charset) -> String {
+    let index = WHITE_GLYPHS
+        .iter()
+        .chain(BLACK_GLYPHS.iter())
+        .position(|&g| g == glyph);
+    match (charset, index) {
+        (Charset::Ascii, Some(i)) => "PNBRQKpnbrqk"[i..i + 1].to_string(),
+        (Charset::Ascii, None) => match glyph {
+            "·" => ".",
+            "■" => "#",
+            other => other,
+        }
+        .to_string(),
+        (Charset::Figurine, Some(i)) if i < 6 => format!("\x1b[1m{}\x1b[0m", BLACK_GLYPHS[i]),
+        _ => glyph.to_string(),
+    }
+}
+
+const WHITE_GLYPHS: [&str; 6] = ["♙", "♘", "♗", "♖", "♕", "♔"];
+const BLACK_GLYPHS: [&str; 6] = ["♟", "♞", "♝", "♜", "♛", "♚"];
+
+/// the chess glyph of `piece`, as in `board_glyphs`
+pub fn glyph(piece: Piece) -> String {
+    translate_piece(&piece.to_char().to_string()).to_string()
+}
+
+// sub symbols
+// White pieces are designated using uppercase letters ("PNBRQK"),
+// while black pieces use lowercase letters ("pnbrqk").
+fn translate_piece(x: &str) -> &str {
+    let newsym: &str = match x {
+        "p" => "♟",
+        "n" => "♞",
+        "b" => "♝",
+        "r" => "♜",
+        "q" => "♛",
+        "k" => "♚",
+        "P" => "♙",
+        "N" => "♘",
+        "B" => "♗",
+        "R" => "♖",
+        "Q" => "♕",
+        "K" => "♔",
+        "1" => "1",
+        "2" => "2",
+        "3" => "3",
+        "4" => "4",
+        "5" => "5",
+        "6" => "6",
+        "7" => "7",
+        "8" => "8",
+        _ => "",
+    };
+    newsym
+}
+
+/// Ask on the terminal which of `promotions` (the same pawn move promoting
+/// to different pieces) to play. `None` when there is nothing to choose
+/// from, stdin is not a terminal or it is closed.
+pub fn ask_promotion(promotions: &[Move]) -> Option<Move> {
+    if promotions.is_empty() || !std::io::stdin().is_terminal() {
+        return None;
+    }
+    loop {
+        print!("Promote to (q)ueen, (r)ook, (b)ishop or k(n)ight? [q] ");
+        let _ = std::io::stdout().flush();
+        let mut line = String::new();
+        if std::io::stdin().lock().read_line(&mut line).unwrap_or(0) == 0 {
+            return None;
+        }
+        let kind = match line.trim().to_ascii_lowercase().as_str() {
+            "" | "q" | "queen" => PieceKind::Queen,
+            "r" | "rook" => PieceKind::Rook,
+            "b" | "bishop" => PieceKind::Bishop,
+            "n" | "knight" => PieceKind::Knight,
+            _ => continue,
+        };
+        if let Some(&mv) = promotions.iter().find(|mv| mv.promotion == Some(kind)) {
+            return Some(mv);
+        }
+    }
+}
+
+/// Up to `shown` rows of a move list, numbered, scrolled to keep the move
+/// leading to `ply` near the middle and marked: in reverse video on a
+/// terminal, in brackets otherwise.
+pub fn move_list(rows: &[MoveRow], ply: usize, shown: usize) -> String {
+    let current = row_of(rows, ply).unwrap_or(0);
+    let first = current
+        .saturating_sub(shown / 2)
+        .min(rows.len().saturating_sub(shown));
+    let reverse = std::io::stdout().is_terminal();
+    let lines: Vec<String> = rows
+        .iter()
+        .skip(first)
+        .take(shown)
+        .map(|row| {
+            let mut line = format!("{:>4}.", row.number);
+            for entry in &row.moves {
+                let cell = match entry {
+                    Some((p, san)) if *p == ply && reverse => {
+                        format!(
+                            " \x1b[7m{}\x1b[27m{} ",
+                            san,
+                            " ".repeat(8 - san.len().min(8))
+                        )
+                    }
+                    Some((p, san)) if *p == ply => format!("[{:<8}]", san),
+                    Some((_, san)) => format!(" {:<8} ", san),
+                    None => format!(" {:<8} ", "..."),
+                };
+                line.push_str(&cell);
+            }
+            line.trim_end().to_string()
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// a key read from the terminal in raw mode
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    Left,
+    Right,
+    Home,
+    End,
+    Char(char),
+}
+
+/// The terminal switched to reading single keys without echo, restored
+/// when dropped. `stty` does the switching, as it does on every Unix.
+pub struct RawMode {
+    saved: String,
+}
+
+fn stty(args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new("stty")
+        .args(args)
+        .stdin(std::process::Stdio::inherit())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+impl RawMode {
+    /// `None` unless both standard input and output are terminals
+    pub fn enable() -> Option<RawMode> {
+        if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+            return None;
+        }
+        let saved = stty(&["-g"])?;
+        // keep output processing, so newlines still return the cursor
+        stty(&["-icanon", "-echo", "-isig", "min", "1"])?;
+        Some(RawMode { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+/// Read keys from standard input on a thread, decoding the arrow, Home
+/// and End escape sequences. Ctrl+C arrives as `q`, since raw mode has
+/// turned off the signal it would send.
+pub fn keys() -> std::sync::mpsc::Receiver<Key> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut bytes = std::io::Read::bytes(std::io::stdin().lock()).map_while(Result::ok);
+        while let Some(byte) = bytes.next() {
+            let key = match byte {
+                0x1b => {
+                    if bytes.next() != Some(b'[') {
+                        continue;
+                    }
+                    match bytes.next() {
+                        Some(b'D') => Key::Left,
+                        Some(b'C') => Key::Right,
+                        Some(b'H') => Key::Home,
+                        Some(b'F') => Key::End,
+                        // Home and End as ESC [ 1 ~ and ESC [ 4 ~
+                        Some(d @ (b'1' | b'4')) if bytes.next() == Some(b'~') => {
+                            if d == b'1' {
+                                Key::Home
+                            } else {
+                                Key::End
+                            }
+                        }
+                        _ => continue,
+                    }
+                }
+                0x03 => Key::Char('q'),
+                b if b.is_ascii() => Key::Char(b as char),
+                _ => continue,
+            };
+            if sender.send(key).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// the glyph grid seen from Black's side
+pub fn flipped(pieces: &[Vec<String>]) -> Vec<Vec<String>> {
+    pieces
+        .iter()
+        .rev()
+        .map(|rank| rank.iter().rev().cloned().collect())
+        .collect()
+}
@@ -0,0 +1,103 @@
+//! Square textures for the window
+//!
+//! Each style is a pair of tiles, one for the light squares and one for the
+//! dark. `assets/<style>-light.png` and `assets/<style>-dark.png` are used
+//! when present; otherwise the tiles are generated here.
+
+use clap::ValueEnum;
+use image::{Rgba, RgbaImage};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum BoardStyle {
+    /// plain squares in the palette's colours
+    #[default]
+    Flat,
+    /// light and dark wood grain
+    Wood,
+    /// white and green marble veins
+    Marble,
+}
+
+/// edge length of a generated tile in pixels
+const TILE: u32 = 128;
+
+// pseudo-random value in 0..1 for a lattice point
+fn lattice(x: i32, y: i32, seed: u32) -> f64 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ seed.wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    (h & 0xffff) as f64 / 65_535.0
+}
+
+// smoothly interpolated value noise
+fn noise(x: f64, y: f64, seed: u32) -> f64 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (sx, sy) = (fx * fx * (3.0 - 2.0 * fx), fy * fy * (3.0 - 2.0 * fy));
+    let (x0, y0) = (x0 as i32, y0 as i32);
+    let top = lattice(x0, y0, seed) * (1.0 - sx) + lattice(x0 + 1, y0, seed) * sx;
+    let bottom = lattice(x0, y0 + 1, seed) * (1.0 - sx) + lattice(x0 + 1, y0 + 1, seed) * sx;
+    top * (1.0 - sy) + bottom * sy
+}
+
+// several octaves of noise, in 0..1
+fn turbulence(x: f64, y: f64, seed: u32) -> f64 {
+    let mut total = 0.0;
+    let mut scale = 1.0;
+    for octave in 0..4 {
+        total += noise(x / scale, y / scale, seed + octave) * scale;
+        scale /= 2.0;
+    }
+    total / 1.875
+}
+
+fn mix(a: [u8; 3], b: [u8; 3], t: f64) -> Rgba<u8> {
+    let t = t.clamp(0.0, 1.0);
+    let channel = |i: usize| (a[i] as f64 * (1.0 - t) + b[i] as f64 * t).round() as u8;
+    Rgba([channel(0), channel(1), channel(2), 255])
+}
+
+// rings of grain running across the tile, stretched along it
+fn wood(base: [u8; 3], grain: [u8; 3], seed: u32) -> RgbaImage {
+    RgbaImage::from_fn(TILE, TILE, |x, y| {
+        let (x, y) = (x as f64, y as f64);
+        let bend = 8.0 * turbulence(x / 48.0, y / 12.0, seed);
+        let rings = ((y + bend) * 0.45).sin() * 0.5 + 0.5;
+        let fibre = noise(x / 24.0, y * 1.5, seed + 7);
+        mix(base, grain, 0.65 * rings.powi(3) + 0.25 * fibre)
+    })
+}
+
+// a few sharp veins through a cloudy ground
+fn marble(base: [u8; 3], vein: [u8; 3], seed: u32) -> RgbaImage {
+    RgbaImage::from_fn(TILE, TILE, |x, y| {
+        let (x, y) = (x as f64, y as f64);
+        let swirl = 6.0 * turbulence(x / 32.0, y / 32.0, seed);
+        let veins = 1.0 - ((x + y) / 28.0 + swirl).sin().abs();
+        let cloud = turbulence(x / 20.0, y / 20.0, seed + 11);
+        mix(base, vein, veins.powi(6) * 0.85 + 0.15 * cloud)
+    })
+}
+
+fn load(style: &str, shade: &str) -> Option<RgbaImage> {
+    let path = format!("assets/{}-{}.png", style, shade);
+    image::open(&path).ok().map(|tile| tile.to_rgba8())
+}
+
+/// The light and dark tiles of `style`, or `None` for flat squares.
+pub fn tiles(style: BoardStyle) -> Option<(RgbaImage, RgbaImage)> {
+    Some(match style {
+        BoardStyle::Flat => return None,
+        BoardStyle::Wood => (
+            load("wood", "light").unwrap_or_else(|| wood([226, 190, 142], [196, 152, 100], 1)),
+            load("wood", "dark").unwrap_or_else(|| wood([150, 98, 54], [104, 62, 30], 2)),
+        ),
+        BoardStyle::Marble => (
+            load("marble", "light").unwrap_or_else(|| marble([236, 234, 226], [150, 150, 146], 3)),
+            load("marble", "dark").unwrap_or_else(|| marble([64, 110, 90], [20, 48, 36], 4)),
+        ),
+    })
+}
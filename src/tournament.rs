@@ -0,0 +1,247 @@
+//! Engine tournaments from a book of openings
+//!
+//! Each pairing plays every opening twice, once with each engine as White,
+//! and the openings are cycled through until the requested number of games
+//! is reached. In a gauntlet the first engine meets all the others, which
+//! do not play each other. Games left unfinished at the move limit count as
+//! draws.
+
+use crate::epd;
+use crate::position::Position;
+use crate::selfplay;
+use crate::uci::Engine;
+use std::io::{BufRead, Write};
+
+/// one finished game, by index into the tournament's engines
+pub struct Outcome {
+    pub white: usize,
+    pub black: usize,
+    /// as in PGN: `1-0`, `0-1`, `1/2-1/2` or `*`
+    pub result: String,
+    /// index of the opening in the book
+    pub opening: usize,
+}
+
+impl Outcome {
+    /// points for White and Black
+    pub fn points(&self) -> (f64, f64) {
+        match self.result.as_str() {
+            "1-0" => (1.0, 0.0),
+            "0-1" => (0.0, 1.0),
+            _ => (0.5, 0.5),
+        }
+    }
+}
+
+pub struct Tournament {
+    pub names: Vec<String>,
+    pub games: Vec<Outcome>,
+}
+
+fn format_points(points: f64) -> String {
+    if points.fract() == 0.0 {
+        format!("{}", points)
+    } else {
+        format!("{:.1}", points)
+    }
+}
+
+impl Tournament {
+    /// the points `a` scored against `b` and the number of games they played
+    pub fn score(&self, a: usize, b: usize) -> (f64, usize) {
+        let mut points = 0.0;
+        let mut games = 0;
+        for game in &self.games {
+            let (white, black) = game.points();
+            if game.white == a && game.black == b {
+                points += white;
+            } else if game.black == a && game.white == b {
+                points += black;
+            } else {
+                continue;
+            }
+            games += 1;
+        }
+        (points, games)
+    }
+
+    /// the points and games of engine `a` against everyone
+    pub fn total(&self, a: usize) -> (f64, usize) {
+        (0..self.names.len())
+            .filter(|&b| b != a)
+            .map(|b| self.score(a, b))
+            .fold((0.0, 0), |(p, g), (points, games)| (p + points, g + games))
+    }
+
+    /// Format the crosstable, engines ordered by points, each row giving
+    /// the points scored against each column's engine.
+    pub fn crosstable(&self) -> String {
+        let mut order: Vec<usize> = (0..self.names.len()).collect();
+        order.sort_by(|&a, &b| self.total(b).0.total_cmp(&self.total(a).0));
+        let width = self
+            .names
+            .iter()
+            .map(|n| n.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut out = format!("{:>3}  {:<width$}", "", "Engine", width = width);
+        for rank in 1..=order.len() {
+            out.push_str(&format!(" {:>5}", rank));
+        }
+        out.push_str("   Score\n");
+        for (rank, &a) in order.iter().enumerate() {
+            out.push_str(&format!(
+                "{:>3}  {:<width$}",
+                rank + 1,
+                self.names[a],
+                width = width
+            ));
+            for &b in &order {
+                let cell = match self.score(a, b) {
+                    _ if a == b => "·".to_string(),
+                    (_, 0) => "-".to_string(),
+                    (points, _) => format_points(points),
+                };
+                out.push_str(&format!(" {:>5}", cell));
+            }
+            let (points, games) = self.total(a);
+            out.push_str(&format!("   {}/{}\n", format_points(points), games));
+        }
+        out
+    }
+}
+
+/// the positions of the book at `path`, an EPD or FEN file
+fn openings(path: &str) -> Result<Vec<Position>, String> {
+    let mut out = Vec::new();
+    for (n, line) in crate::batch::open(path)?.lines().enumerate() {
+        let line = line.map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match epd::parse_line(line) {
+            Ok(record) => out.push(record.pos),
+            Err(e) => eprintln!("line {}: Error: {}", n + 1, e),
+        }
+    }
+    if out.is_empty() {
+        return Err(format!("No openings in {}", path));
+    }
+    Ok(out)
+}
+
+/// the pairs of engines that meet, each once
+fn pairings(count: usize, gauntlet: bool) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for a in 0..count {
+        for b in a + 1..count {
+            if !gauntlet || a == 0 {
+                pairs.push((a, b));
+            }
+        }
+    }
+    pairs
+}
+
+// mutable references to two different engines
+fn two(engines: &mut [Engine], white: usize, black: usize) -> (&mut Engine, &mut Engine) {
+    if white < black {
+        let (left, right) = engines.split_at_mut(black);
+        (&mut left[white], &mut right[0])
+    } else {
+        let (left, right) = engines.split_at_mut(white);
+        (&mut right[0], &mut left[black])
+    }
+}
+
+/// how a tournament is played
+pub struct TournamentOptions {
+    /// number of games
+    pub games: usize,
+    /// milliseconds of search per move
+    pub movetime: u64,
+    /// full moves after which a game is stopped (as a draw)
+    pub max_moves: u32,
+    /// first engine against the others only
+    pub gauntlet: bool,
+}
+
+/// Play a tournament between the engines at `paths` from the openings in
+/// `book`. The games are appended to `save_pgn` as they finish, and
+/// `after_game` sees the tournament after each one and may end it early by
+/// returning `false`. The finished tournament is returned for reporting.
+pub fn run(
+    paths: &[String],
+    book: &str,
+    opts: &TournamentOptions,
+    save_pgn: Option<&str>,
+    mut after_game: impl FnMut(&Tournament) -> bool,
+) -> Result<Tournament, String> {
+    if paths.len() < 2 {
+        return Err("A tournament needs at least two engines".to_string());
+    }
+    let openings = openings(book)?;
+    let mut engines = paths
+        .iter()
+        .map(|path| Engine::spawn(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    // tell apart engines reporting the same name
+    let mut names: Vec<String> = Vec::new();
+    for engine in &engines {
+        let mut name = engine.name.clone();
+        let mut n = 1;
+        while names.contains(&name) {
+            n += 1;
+            name = format!("{} {}", engine.name, n);
+        }
+        names.push(name);
+    }
+    let mut pgn = match save_pgn {
+        Some(path) => Some(
+            std::fs::File::create(path).map_err(|e| format!("Could not write {}: {}", path, e))?,
+        ),
+        None => None,
+    };
+
+    let pairs = pairings(engines.len(), opts.gauntlet);
+    let mut tournament = Tournament {
+        names,
+        games: Vec::new(),
+    };
+    let schedule = openings.iter().enumerate().cycle().flat_map(|(i, pos)| {
+        pairs
+            .iter()
+            .flat_map(move |&(a, b)| [(i, pos, a, b), (i, pos, b, a)])
+    });
+    for (n, (opening, start, white, black)) in schedule.take(opts.games).enumerate() {
+        let (w, b) = two(&mut engines, white, black);
+        let mut game = selfplay::play(
+            start.clone(),
+            w,
+            b,
+            opts.movetime,
+            opts.max_moves,
+            |_, _| {},
+        )?;
+        let names = &tournament.names;
+        game.set_tag("Event", "fen tournament");
+        game.set_tag("Round", &(n + 1).to_string());
+        game.set_tag("White", &names[white]);
+        game.set_tag("Black", &names[black]);
+        if let (Some(file), Some(path)) = (pgn.as_mut(), save_pgn) {
+            writeln!(file, "{}", game.to_pgn())
+                .map_err(|e| format!("Could not write {}: {}", path, e))?;
+        }
+        tournament.games.push(Outcome {
+            white,
+            black,
+            result: game.result,
+            opening,
+        });
+        if !after_game(&tournament) {
+            break;
+        }
+    }
+    Ok(tournament)
+}
@@ -0,0 +1,120 @@
+//! Coordinate training: name the highlighted square
+
+use crate::diagram::Palette;
+use crate::position::{parse_square, square_name, Square};
+use crate::rng::Rng;
+use crate::terminal::{print_board, TerminalOptions};
+use crate::window::{self, WindowOptions};
+use piston::input::{Event, Input};
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct Tally {
+    correct: u32,
+    total: u32,
+    elapsed: Duration,
+}
+
+impl Tally {
+    fn record(&mut self, target: Square, answer: &str, took: Duration) {
+        self.total += 1;
+        self.elapsed += took;
+        if parse_square(&answer.trim().to_lowercase()) == Some(target) {
+            self.correct += 1;
+            println!("Correct ({:.1}s)", took.as_secs_f64());
+        } else {
+            println!(
+                "Wrong, that was {} ({:.1}s)",
+                square_name(target),
+                took.as_secs_f64()
+            );
+        }
+    }
+
+    fn report(&self) {
+        if self.total == 0 {
+            return;
+        }
+        println!(
+            "\nScore: {}/{} ({:.0}%), average {:.2}s per square",
+            self.correct,
+            self.total,
+            100.0 * self.correct as f64 / self.total as f64,
+            self.elapsed.as_secs_f64() / self.total as f64
+        );
+    }
+}
+
+/// an empty board with `target` marked, as terminal glyphs
+fn marked_board(target: Square) -> Vec<Vec<String>> {
+    (0..8u8)
+        .rev()
+        .map(|rank| {
+            (0..8u8)
+                .map(|file| {
+                    if rank * 8 + file == target {
+                        "■".to_string()
+                    } else {
+                        "·".to_string()
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Run `rounds` questions, in the window when `graphical`, otherwise in the
+/// terminal. Typing `q` in the terminal stops early.
+pub fn coordinates(rounds: u32, graphical: bool, term: &TerminalOptions, rng: &mut Rng) {
+    let mut tally = Tally::default();
+    if graphical {
+        in_window(rounds, rng, &mut tally, term.palette);
+    } else {
+        let stdin = io::stdin();
+        for _ in 0..rounds {
+            let target = rng.below(64) as Square;
+            print_board(&marked_board(target), term);
+            print!("Square? ");
+            let _ = io::stdout().flush();
+            let start = Instant::now();
+            let mut answer = String::new();
+            if stdin.lock().read_line(&mut answer).unwrap_or(0) == 0 || answer.trim() == "q" {
+                break;
+            }
+            tally.record(target, &answer, start.elapsed());
+        }
+    }
+    tally.report();
+}
+
+// squares flash in the window without coordinates; answers are typed into it
+fn in_window(rounds: u32, rng: &mut Rng, tally: &mut Tally, palette: Palette) {
+    let empty = vec![vec![String::new(); 8]; 8];
+    let mut target = rng.below(64) as Square;
+    let mut typed = String::new();
+    let mut start = Instant::now();
+    println!("Type the name of each highlighted square into the window");
+    let opts = WindowOptions {
+        palette,
+        ..WindowOptions::default()
+    };
+    let marker = window::gl_colour(palette.blunder);
+    window::run(empty, opts, |board, event| {
+        board.labels = false;
+        board.highlights = vec![(target, marker)];
+        if let Event::Input(Input::Text(text), _) = event {
+            typed.push_str(text);
+            if typed.chars().count() >= 2 {
+                tally.record(target, &typed, start.elapsed());
+                typed.clear();
+                if tally.total >= rounds {
+                    return false;
+                }
+                target = rng.below(64) as Square;
+                start = Instant::now();
+            }
+        }
+        true
+    });
+}
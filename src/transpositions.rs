@@ -0,0 +1,58 @@
+//! Positions shared between two games
+
+use crate::pgn::{first_game, Game};
+use crate::position::Color;
+use crate::zobrist;
+use std::collections::HashMap;
+
+/// Each position of `game` with its Zobrist key and a label naming the
+/// move that reached it, such as `after 5... Nf6`.
+fn positions(game: &Game) -> Vec<(u64, String)> {
+    let mut pos = game.start.clone();
+    let mut out = vec![(zobrist::hash(&pos), "at the start".to_string())];
+    for (&mv, san) in game.moves.iter().zip(game.san_moves()) {
+        let label = match pos.side {
+            Color::White => format!("after {}. {}", pos.fullmove, san),
+            Color::Black => format!("after {}... {}", pos.fullmove, san),
+        };
+        pos = pos.make_move(mv);
+        out.push((zobrist::hash(&pos), label));
+    }
+    out
+}
+
+/// Print every position reached in both games (ignoring move clocks),
+/// with the move after which it arose in each.
+pub fn run(pgn1: &str, pgn2: &str) -> Result<(), String> {
+    let (first, second) = (first_game(pgn1)?, first_game(pgn2)?);
+    let mut seen: HashMap<u64, (usize, String)> = HashMap::new();
+    for (ply, (key, label)) in positions(&second).into_iter().enumerate() {
+        seen.entry(key).or_insert((ply, label));
+    }
+
+    let mut shared = 0;
+    let mut pos = first.start.clone();
+    let mut reported = Vec::new();
+    for (i, (key, label)) in positions(&first).into_iter().enumerate() {
+        if i > 0 {
+            pos = pos.make_move(first.moves[i - 1]);
+        }
+        let (ply, other) = match seen.get(&key) {
+            Some(found) => found,
+            None => continue,
+        };
+        // a common starting position is not a transposition
+        if (i == 0 && *ply == 0) || reported.contains(&key) {
+            continue;
+        }
+        reported.push(key);
+        shared += 1;
+        println!("{:016x}  {}", key, pos.to_fen());
+        println!("    {}: {}", pgn1, label);
+        println!("    {}: {}", pgn2, other);
+    }
+    if shared == 0 {
+        println!("No positions in common");
+    }
+    Ok(())
+}
@@ -0,0 +1,133 @@
+//! Opening tree built from a PGN database
+//!
+//! Positions are keyed by Zobrist hash, so move orders that transpose
+//! into the same position share their statistics.
+
+use crate::movegen::Move;
+use crate::pgn::Game;
+use crate::position::{Color, Position, STARTING_FEN};
+use crate::san::to_san;
+use crate::zobrist;
+use std::collections::{HashMap, HashSet};
+
+/// how often a move was played and how those games ended
+#[derive(Clone, Copy, Default)]
+pub struct MoveStats {
+    pub games: usize,
+    pub white_wins: usize,
+    pub draws: usize,
+    pub black_wins: usize,
+}
+
+impl MoveStats {
+    /// percentage score for `side`, counting draws as half
+    pub fn score(&self, side: Color) -> f64 {
+        let wins = match side {
+            Color::White => self.white_wins,
+            Color::Black => self.black_wins,
+        };
+        100.0 * (wins as f64 + self.draws as f64 / 2.0) / self.games.max(1) as f64
+    }
+}
+
+pub struct Tree {
+    start: Position,
+    // moves from each position, most played first once built
+    nodes: HashMap<u64, Vec<(Move, MoveStats)>>,
+}
+
+impl Tree {
+    /// Index the first `depth` plies of each game.
+    pub fn build(games: &[Game], depth: usize) -> Tree {
+        let mut nodes: HashMap<u64, Vec<(Move, MoveStats)>> = HashMap::new();
+        for game in games {
+            let mut pos = game.start.clone();
+            for &mv in game.moves.iter().take(depth) {
+                let moves = nodes.entry(zobrist::hash(&pos)).or_default();
+                let stats = match moves.iter_mut().find(|(m, _)| *m == mv) {
+                    Some((_, stats)) => stats,
+                    None => {
+                        moves.push((mv, MoveStats::default()));
+                        &mut moves.last_mut().expect("just pushed").1
+                    }
+                };
+                stats.games += 1;
+                match game.result.as_str() {
+                    "1-0" => stats.white_wins += 1,
+                    "0-1" => stats.black_wins += 1,
+                    "1/2-1/2" => stats.draws += 1,
+                    _ => {}
+                }
+                pos = pos.make_move(mv);
+            }
+        }
+        for moves in nodes.values_mut() {
+            moves.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.games));
+        }
+        let start = games.first().map(|g| g.start.clone()).unwrap_or_else(|| {
+            Position::from_fen(STARTING_FEN).expect("starting position is valid")
+        });
+        Tree { start, nodes }
+    }
+
+    /// the moves played from `pos`, most popular first
+    pub fn moves(&self, pos: &Position) -> &[(Move, MoveStats)] {
+        self.nodes
+            .get(&zobrist::hash(pos))
+            .map_or(&[], |moves| moves.as_slice())
+    }
+
+    /// Print the tree from the first game's starting position, one move
+    /// per line indented by depth. Positions reached again by another
+    /// move order are marked as transpositions rather than repeated.
+    pub fn print(&self) {
+        let mut seen = HashSet::from([zobrist::hash(&self.start)]);
+        self.print_from(&self.start, 0, &mut seen);
+    }
+
+    fn print_from(&self, pos: &Position, indent: usize, seen: &mut HashSet<u64>) {
+        for &(mv, stats) in self.moves(pos) {
+            let number = match pos.side {
+                Color::White => format!("{}.", pos.fullmove),
+                Color::Black => format!("{}...", pos.fullmove),
+            };
+            let next = pos.make_move(mv);
+            let transposed = !seen.insert(zobrist::hash(&next));
+            println!(
+                "{:indent$}{} {:<7} {:>5} game{} {:>5.1}%{}",
+                "",
+                number,
+                to_san(pos, mv),
+                stats.games,
+                if stats.games == 1 { " " } else { "s" },
+                stats.score(pos.side),
+                if transposed { "  (transposition)" } else { "" },
+                indent = indent
+            );
+            if !transposed {
+                self.print_from(&next, indent + 2, seen);
+            }
+        }
+    }
+}
+
+/// one-line summary of the moves played from `pos`, for `--info`
+pub fn summary(tree: &Tree, pos: &Position) -> String {
+    let moves = tree.moves(pos);
+    if moves.is_empty() {
+        return "Position not found in the games".to_string();
+    }
+    let total: usize = moves.iter().map(|(_, s)| s.games).sum();
+    let listed: Vec<String> = moves
+        .iter()
+        .map(|(mv, stats)| {
+            format!(
+                "{} {} ({:.0}%)",
+                to_san(pos, *mv),
+                stats.games,
+                stats.score(pos.side)
+            )
+        })
+        .collect();
+    format!("Games from here: {}; {}", total, listed.join(", "))
+}
@@ -0,0 +1,270 @@
+//! Universal Chess Interface (UCI) protocol support
+
+use crate::position::STARTING_FEN;
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{self, Child, ChildStdin, ChildStdout, Stdio};
+
+/// Split a UCI `position` command, as found in engine logs, into the FEN
+/// it starts from and the list of moves played from there, e.g.
+/// `position fen <fen> moves e2e4 e7e5` or `position startpos moves d2d4`.
+/// The leading `position` keyword is optional.
+pub fn parse_position_command(line: &str) -> Result<(String, Vec<String>), String> {
+    let mut tokens = line.split_whitespace().peekable();
+    if tokens.peek() == Some(&"position") {
+        tokens.next();
+    }
+    let fen = match tokens.next() {
+        Some("startpos") => STARTING_FEN.to_string(),
+        Some("fen") => {
+            let fields: Vec<&str> = tokens.by_ref().take_while(|&t| t != "moves").collect();
+            if fields.is_empty() {
+                return Err("Expected a FEN after 'position fen'".to_string());
+            }
+            let fen = fields.join(" ");
+            // the moves keyword was consumed by take_while, so the rest are moves
+            return Ok((fen, tokens.map(str::to_string).collect()));
+        }
+        Some(other) => {
+            return Err(format!(
+                "Expected 'startpos' or 'fen' in UCI position command, found '{}'",
+                other
+            ))
+        }
+        None => return Err("Empty UCI position command".to_string()),
+    };
+    match tokens.next() {
+        Some("moves") | None => Ok((fen, tokens.map(str::to_string).collect())),
+        Some(other) => Err(format!(
+            "Expected 'moves' in UCI position command, found '{}'",
+            other
+        )),
+    }
+}
+
+/// An engine score, from the point of view of the side to move
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Score {
+    Centipawns(i32),
+    /// moves to mate; negative when the side to move is being mated
+    Mate(i32),
+}
+
+impl Score {
+    /// Centipawns for comparing scores, with mates beyond any material
+    /// score and shorter mates ranked higher.
+    pub fn centipawns(self) -> i32 {
+        match self {
+            Score::Centipawns(cp) => cp,
+            Score::Mate(n) if n > 0 => 100_000 - n,
+            Score::Mate(n) => -100_000 - n,
+        }
+    }
+
+    /// Share of an evaluation bar belonging to the side to move, in 0..=1,
+    /// using the logistic curve popularised by Lichess.
+    pub fn bar_share(self) -> f64 {
+        match self {
+            Score::Centipawns(cp) => 1.0 / (1.0 + (-0.00368208 * cp as f64).exp()),
+            Score::Mate(n) if n > 0 => 1.0,
+            Score::Mate(_) => 0.0,
+        }
+    }
+
+    pub fn negate(self) -> Score {
+        match self {
+            Score::Centipawns(cp) => Score::Centipawns(-cp),
+            Score::Mate(n) => Score::Mate(-n),
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Score::Centipawns(cp) => write!(f, "{:+.2}", *cp as f64 / 100.0),
+            Score::Mate(n) => write!(f, "#{}", n),
+        }
+    }
+}
+
+/// One principal variation reported by the engine through `info`
+#[derive(Clone, Debug)]
+pub struct Line {
+    pub multipv: usize,
+    pub depth: u32,
+    pub score: Score,
+    pub pv: Vec<String>,
+}
+
+impl Line {
+    /// parse an `info` line carrying a score; other info lines give `None`
+    fn parse(text: &str) -> Option<Line> {
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        let mut line = Line {
+            multipv: 1,
+            depth: 0,
+            score: Score::Centipawns(0),
+            pv: Vec::new(),
+        };
+        let mut scored = false;
+        let mut i = 1;
+        while i < tokens.len() {
+            match tokens[i] {
+                "multipv" => line.multipv = tokens.get(i + 1)?.parse().ok()?,
+                "depth" => line.depth = tokens.get(i + 1)?.parse().ok()?,
+                "score" => {
+                    let value = tokens.get(i + 2)?.parse().ok()?;
+                    line.score = match *tokens.get(i + 1)? {
+                        "cp" => Score::Centipawns(value),
+                        "mate" => Score::Mate(value),
+                        _ => return None,
+                    };
+                    scored = true;
+                    i += 1;
+                }
+                "pv" => {
+                    line.pv = tokens[i + 1..].iter().map(|t| t.to_string()).collect();
+                    break;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        if scored {
+            Some(line)
+        } else {
+            None
+        }
+    }
+}
+
+/// The result of a search: the best move and the lines seen on the way
+#[derive(Clone, Debug, Default)]
+pub struct Analysis {
+    pub best: Option<String>,
+    pub lines: Vec<Line>,
+}
+
+/// A UCI engine running as a child process
+pub struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    pub name: String,
+}
+
+impl Engine {
+    /// Start the engine at `path` and complete the `uci`/`uciok` handshake.
+    pub fn spawn(path: &str) -> Result<Engine, String> {
+        let mut child = process::Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Could not start engine {}: {}", path, e))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut engine = Engine {
+            child,
+            stdin,
+            stdout,
+            name: path.to_string(),
+        };
+        engine.send("uci")?;
+        loop {
+            let line = engine.read_line()?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        engine.sync()?;
+        Ok(engine)
+    }
+
+    pub fn send(&mut self, command: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("Could not write to engine {}: {}", self.name, e))
+    }
+
+    fn read_line(&mut self) -> Result<String, String> {
+        let mut line = String::new();
+        match self.stdout.read_line(&mut line) {
+            Ok(0) => Err(format!("Engine {} exited unexpectedly", self.name)),
+            Ok(_) => Ok(line),
+            Err(e) => Err(format!("Could not read from engine {}: {}", self.name, e)),
+        }
+    }
+
+    /// wait until the engine has processed all previous commands
+    pub fn sync(&mut self) -> Result<(), String> {
+        self.send("isready")?;
+        while self.read_line()?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.send(&format!("setoption name {} value {}", name, value))?;
+        self.sync()
+    }
+
+    pub fn new_game(&mut self) -> Result<(), String> {
+        self.send("ucinewgame")?;
+        self.sync()
+    }
+
+    /// Search from `fen` after `moves` with the given `go` arguments
+    /// (e.g. `movetime 100`), collecting the engine's reported lines.
+    pub fn analyse(&mut self, fen: &str, moves: &[String], go: &str) -> Result<Analysis, String> {
+        let mut command = format!("position fen {}", fen);
+        if !moves.is_empty() {
+            command.push_str(" moves ");
+            command.push_str(&moves.join(" "));
+        }
+        self.send(&command)?;
+        self.send(&format!("go {}", go))?;
+        let mut analysis = Analysis::default();
+        loop {
+            let line = self.read_line()?;
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("bestmove") => {
+                    analysis.best = tokens
+                        .next()
+                        .filter(|&m| m != "(none)" && m != "0000")
+                        .map(str::to_string);
+                    return Ok(analysis);
+                }
+                Some("info") => {
+                    if let Some(info) = Line::parse(&line) {
+                        // keep only the latest report for each multipv slot
+                        analysis.lines.retain(|l| l.multipv != info.multipv);
+                        analysis.lines.push(info);
+                        analysis.lines.sort_by_key(|l| l.multipv);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// the engine's best move in UCI form, or `None` if it reports no legal move
+    pub fn best_move(
+        &mut self,
+        fen: &str,
+        moves: &[String],
+        go: &str,
+    ) -> Result<Option<String>, String> {
+        Ok(self.analyse(fen, moves, go)?.best)
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.wait();
+    }
+}
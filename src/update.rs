@@ -0,0 +1,357 @@
+//! Updating the binary from the project's GitHub releases
+//!
+//! The latest release is looked up with the GitHub API and, when it is
+//! newer than this build, the asset built for this platform (its name
+//! holding the CPU architecture and the operating system, such as
+//! `fen-x86_64-linux` or `fen-aarch64-macos.tar.gz`) is downloaded and put
+//! in place of the running binary once it matches the release's
+//! `<asset>.sha256` checksum. Downloads go through `curl`, and archives
+//! are unpacked with `tar`, as neither is worth a dependency here; nor is
+//! SHA-256, which is short enough to write out.
+
+use crate::json::Json;
+use crate::version::VERSION;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const RELEASES: &str = "https://api.github.com/repos/jonocarroll/FEN.rs/releases/latest";
+
+fn curl(args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("curl")
+        .args(["-sSfL", "-H", "User-Agent: fen"])
+        .args(args)
+        .output()
+        .map_err(|e| format!("Could not run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Could not fetch {}: {}",
+            args.last().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+// the numbers of a version such as `v0.2.1`, for comparing
+fn numbers(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+// the words naming this platform in release asset names
+fn platform() -> (&'static str, &'static [&'static str]) {
+    let os: &[&str] = match std::env::consts::OS {
+        "macos" => &["macos", "darwin", "apple"],
+        _ => &[std::env::consts::OS],
+    };
+    (std::env::consts::ARCH, os)
+}
+
+/// the release asset built for this platform and its checksum
+#[derive(Debug, PartialEq)]
+struct Asset {
+    name: String,
+    url: String,
+    /// download link of `<name>.sha256`, when the release has one
+    checksum: Option<String>,
+}
+
+// the build for this platform among a release's assets
+fn asset(release: &Json) -> Option<Asset> {
+    let (arch, os) = platform();
+    let assets: Vec<(&str, &str)> = release
+        .get("assets")?
+        .as_array()?
+        .iter()
+        .filter_map(|a| {
+            Some((
+                a.get("name")?.as_str()?,
+                a.get("browser_download_url")?.as_str()?,
+            ))
+        })
+        .collect();
+    let (name, url) = assets.iter().find(|(name, _)| {
+        let lower = name.to_ascii_lowercase();
+        // checksums and signatures are listed alongside the builds
+        let build = ![".sha256", ".asc", ".sig"]
+            .iter()
+            .any(|s| lower.ends_with(s));
+        build && lower.contains(arch) && os.iter().any(|o| lower.contains(o))
+    })?;
+    let checksum = assets
+        .iter()
+        .find(|(other, _)| *other == format!("{}.sha256", name))
+        .map(|(_, url)| url.to_string());
+    Some(Asset {
+        name: name.to_string(),
+        url: url.to_string(),
+        checksum,
+    })
+}
+
+const ROUND: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+// SHA-256 of `data` (FIPS 180-4), as lowercase hex
+fn sha256(data: &[u8]) -> String {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64 * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(ROUND[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+    state.iter().map(|s| format!("{:08x}", s)).collect()
+}
+
+// Check `data` against a `.sha256` file, which holds the hex digest,
+// optionally followed by the file name as `sha256sum` writes it.
+fn verify(data: &[u8], checksum: &str, name: &str) -> Result<(), String> {
+    let expected = checksum
+        .split_whitespace()
+        .next()
+        .filter(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(|| format!("{}.sha256 does not hold a SHA-256 checksum", name))?
+        .to_ascii_lowercase();
+    let actual = sha256(data);
+    if actual != expected {
+        return Err(format!(
+            "The download of {} does not match its checksum (expected {}, got {}), so it was not installed",
+            name, expected, actual
+        ));
+    }
+    Ok(())
+}
+
+// the `fen` binary unpacked from the archive `archive` into `dir`
+fn unpack(archive: &Path, dir: &Path) -> Result<PathBuf, String> {
+    let status = Command::new("tar")
+        .arg("-xf")
+        .arg(archive)
+        .arg("-C")
+        .arg(dir)
+        .status()
+        .map_err(|e| format!("Could not run tar: {}", e))?;
+    if !status.success() {
+        return Err(format!("Could not unpack {}", archive.display()));
+    }
+    let name = if cfg!(windows) { "fen.exe" } else { "fen" };
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)
+            .map_err(|e| e.to_string())?
+            .flatten()
+        {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.file_name().is_some_and(|n| n == name) {
+                return Ok(path);
+            }
+        }
+    }
+    Err(format!("The release archive has no {}", name))
+}
+
+// put the binary at `new` in place of the one at `exe`
+fn replace(exe: &Path, new: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(new, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Could not make {} executable: {}", new.display(), e))?;
+    }
+    // a running binary cannot be overwritten on Windows, but it can be renamed
+    if cfg!(windows) {
+        let old = exe.with_extension("old.exe");
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(exe, &old)
+            .map_err(|e| format!("Could not move {} aside: {}", exe.display(), e))?;
+    }
+    std::fs::rename(new, exe).map_err(|e| format!("Could not replace {}: {}", exe.display(), e))
+}
+
+/// Update the running binary to the latest release, or with `check_only`
+/// only say whether there is one.
+pub fn run(check_only: bool) -> Result<(), String> {
+    let text = curl(&["-H", "Accept: application/vnd.github+json", RELEASES])?;
+    let release = Json::parse(&String::from_utf8_lossy(&text))
+        .map_err(|e| format!("Unexpected reply from GitHub: {}", e))?;
+    let tag = release
+        .get("tag_name")
+        .and_then(Json::as_str)
+        .ok_or("The latest release has no version")?;
+    if numbers(tag) <= numbers(VERSION) {
+        println!("fen {} is up to date", VERSION);
+        return Ok(());
+    }
+    if check_only {
+        println!("fen {} is available (this is {})", tag, VERSION);
+        return Ok(());
+    }
+    let Asset {
+        name,
+        url,
+        checksum,
+    } = asset(&release).ok_or_else(|| {
+        let (arch, os) = platform();
+        format!("Release {} has no build for {}-{}", tag, arch, os[0])
+    })?;
+    let checksum = checksum.ok_or_else(|| {
+        format!(
+            "Release {} has no {}.sha256 to check the download against, so fen was not updated",
+            tag, name
+        )
+    })?;
+
+    let exe = std::env::current_exe()
+        .and_then(|p| p.canonicalize())
+        .map_err(|e| format!("Could not find the running binary: {}", e))?;
+    let dir = exe.parent().ok_or("The running binary has no directory")?;
+    let work = dir.join(format!(".fen-update-{}", std::process::id()));
+    std::fs::create_dir_all(&work)
+        .map_err(|e| format!("Could not create {}: {}", work.display(), e))?;
+    let result = (|| {
+        let download = work.join(&name);
+        let data = curl(&[&url])?;
+        verify(&data, &String::from_utf8_lossy(&curl(&[&checksum])?), &name)?;
+        std::fs::write(&download, data)
+            .map_err(|e| format!("Could not write {}: {}", download.display(), e))?;
+        let archive = [".tar.gz", ".tgz", ".tar.xz", ".zip"]
+            .iter()
+            .any(|s| name.ends_with(s));
+        let new = if archive {
+            unpack(&download, &work)?
+        } else {
+            download
+        };
+        replace(&exe, &new)
+    })();
+    let _ = std::fs::remove_dir_all(&work);
+    result?;
+    println!("Updated fen {} to {}", VERSION, tag);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_test_vectors() {
+        assert_eq!(
+            sha256(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // two blocks once padded
+        assert_eq!(
+            sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn checksums_are_checked() {
+        let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert!(verify(b"abc", digest, "fen").is_ok());
+        assert!(verify(b"abc", &format!("{}  fen\n", digest.to_uppercase()), "fen").is_ok());
+        assert!(verify(b"abd", digest, "fen").is_err());
+        assert!(verify(b"abc", "not a checksum", "fen").is_err());
+    }
+
+    #[test]
+    fn asset_finds_the_build_and_its_checksum() {
+        let (arch, os) = platform();
+        let build = format!("fen-{}-{}.tar.gz", arch, os[0]);
+        let other = if arch == "x86_64" {
+            "aarch64"
+        } else {
+            "x86_64"
+        };
+        let listing = |names: &[String]| {
+            let assets: Vec<String> = names
+                .iter()
+                .map(|n| {
+                    format!(
+                        r#"{{"name": "{}", "browser_download_url": "https://example.com/{}"}}"#,
+                        n, n
+                    )
+                })
+                .collect();
+            Json::parse(&format!(r#"{{"assets": [{}]}}"#, assets.join(", "))).unwrap()
+        };
+        let release = listing(&[
+            format!("fen-{}-{}.tar.gz", other, os[0]),
+            format!("{}.sha256", build),
+            format!("{}.asc", build),
+            build.clone(),
+        ]);
+        assert_eq!(
+            asset(&release),
+            Some(Asset {
+                name: build.clone(),
+                url: format!("https://example.com/{}", build),
+                checksum: Some(format!("https://example.com/{}.sha256", build)),
+            })
+        );
+        let unchecked = listing(std::slice::from_ref(&build));
+        assert_eq!(asset(&unchecked).unwrap().checksum, None);
+        assert_eq!(asset(&listing(&[format!("{}.sha256", build)])), None);
+    }
+}
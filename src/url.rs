@@ -0,0 +1,204 @@
+//! Links to online analysis boards
+
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Site {
+    /// lichess.org analysis board
+    Lichess,
+    /// chess.com analysis board
+    Chesscom,
+}
+
+// percent-encode everything outside the URL-safe characters and `keep`
+fn percent_encode(text: &str, keep: &[char]) -> String {
+    let mut out = String::new();
+    for b in text.bytes() {
+        let c = b as char;
+        if c.is_ascii_alphanumeric() || "-._~".contains(c) || keep.contains(&c) {
+            out.push(c);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// analysis board URL for `fen` on `site`
+pub fn analysis_url(site: Site, fen: &str) -> String {
+    match site {
+        // lichess takes the FEN as the path, with underscores for spaces
+        Site::Lichess => format!(
+            "https://lichess.org/analysis/{}",
+            percent_encode(&fen.replace(' ', "_"), &['/'])
+        ),
+        Site::Chesscom => format!(
+            "https://www.chess.com/analysis?fen={}",
+            percent_encode(fen, &[])
+        ),
+    }
+}
+
+fn percent_decode(text: &str) -> Result<String, String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = text
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| format!("Bad percent-encoding in {}", text))?;
+            out.push(hex);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| format!("Bad percent-encoding in {}", text))
+}
+
+// fill in the fields lichess allows a link to leave out
+fn complete(fen: &str) -> String {
+    let mut fields: Vec<&str> = fen.split_whitespace().collect();
+    let defaults = ["", "w", "-", "-", "0", "1"];
+    if fields.len() < defaults.len() {
+        fields.extend(&defaults[fields.len().max(1)..]);
+    }
+    fields.join(" ")
+}
+
+/// The FEN in a lichess or chess.com analysis (or editor) link, or `None`
+/// when `input` is not a link at all.
+pub fn fen_from_url(input: &str) -> Option<Result<String, String>> {
+    let rest = input
+        .strip_prefix("https://")
+        .or_else(|| input.strip_prefix("http://"))?;
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = host.trim_start_matches("www.");
+    let path = path.split('#').next().unwrap_or("");
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let fen = match host {
+        "lichess.org" => ["analysis/", "editor/"]
+            .iter()
+            .find_map(|prefix| path.strip_prefix(prefix))
+            // variant-qualified links, e.g. analysis/standard/<fen>
+            .map(|fen| {
+                fen.strip_prefix("standard/")
+                    .unwrap_or(fen)
+                    .replace('_', " ")
+            }),
+        "chess.com" => query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("fen="))
+            .map(|fen| fen.replace('+', " ")),
+        _ => return Some(Err(format!("Links to {} are not supported", host))),
+    };
+    Some(match fen.filter(|f| !f.is_empty()) {
+        Some(fen) => percent_decode(&fen).map(|fen| complete(&fen)),
+        None => Err(format!("No FEN found in {}", input)),
+    })
+}
+
+/// Open `url` in the default browser.
+pub fn open(url: &str) -> Result<(), String> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut c = std::process::Command::new("cmd");
+        // the empty argument is the window title taken by `start`
+        c.args(["/C", "start", ""]);
+        c
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    let status = command
+        .arg(url)
+        .status()
+        .map_err(|e| format!("Could not open a browser: {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("Could not open {} in a browser", url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::STARTING_FEN;
+
+    const FEN: &str = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+
+    #[test]
+    fn lichess_links_are_read() {
+        assert_eq!(
+            fen_from_url(
+                "https://lichess.org/analysis/r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R_w_KQkq_-_2_3"
+            ),
+            Some(Ok(FEN.to_string()))
+        );
+        assert_eq!(
+            fen_from_url(
+                "https://lichess.org/analysis/standard/r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R%20w%20KQkq%20-%202%203#4"
+            ),
+            Some(Ok(FEN.to_string()))
+        );
+        // lichess allows the fields after the placement to be left out
+        assert_eq!(
+            fen_from_url("http://lichess.org/editor/rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR"),
+            Some(Ok(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn chesscom_links_are_read() {
+        assert_eq!(
+            fen_from_url(
+                "https://www.chess.com/analysis?tab=analysis&fen=r1bqkbnr%2Fpppp1ppp%2F2n5%2F4p3%2F4P3%2F5N2%2FPPPP1PPP%2FRNBQKB1R+w+KQkq+-+2+3"
+            ),
+            Some(Ok(FEN.to_string()))
+        );
+        assert_eq!(
+            fen_from_url(
+                "https://chess.com/analysis?fen=r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R%20w%20KQkq%20-%202%203"
+            ),
+            Some(Ok(FEN.to_string()))
+        );
+    }
+
+    #[test]
+    fn other_input_is_not_taken_for_a_link() {
+        assert_eq!(fen_from_url(STARTING_FEN), None);
+        assert!(matches!(
+            fen_from_url("https://example.com/analysis/8/8/8/8/8/8/8/8"),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            fen_from_url("https://lichess.org/analysis/"),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            fen_from_url("https://lichess.org/analysis/8/8/8/8/8/8/8/8_w_-_-_0_1%2"),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn analysis_links_round_trip() {
+        for fen in [STARTING_FEN, FEN] {
+            for site in [Site::Lichess, Site::Chesscom] {
+                let url = analysis_url(site, fen);
+                assert!(!url.contains(' '), "{}", url);
+                assert_eq!(fen_from_url(&url), Some(Ok(fen.to_string())), "{}", url);
+            }
+        }
+        assert_eq!(
+            analysis_url(Site::Chesscom, STARTING_FEN),
+            "https://www.chess.com/analysis?fen=rnbqkbnr%2Fpppppppp%2F8%2F8%2F8%2F8%2FPPPPPPPP%2FRNBQKBNR%20w%20KQkq%20-%200%201"
+        );
+    }
+}
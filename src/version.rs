@@ -0,0 +1,47 @@
+//! The version of the build and the optional parts compiled into it
+//!
+//! `version --json` prints these for scripts to check what a given binary
+//! can do before relying on it:
+//!
+//! ```text
+//! {"name":"fen","version":"0.1.0","features":{"gui":true,"engine":false,"self-update":false,"wasm":false}}
+//! ```
+
+use crate::json::Json;
+
+/// each optional capability and whether this build has it: the window,
+/// the built-in engine, `self-update`, and a WebAssembly target
+pub const FEATURES: [(&str, bool); 4] = [
+    ("gui", cfg!(not(target_arch = "wasm32"))),
+    ("engine", cfg!(feature = "engine")),
+    ("self-update", cfg!(feature = "self-update")),
+    ("wasm", cfg!(target_arch = "wasm32")),
+];
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// the version and features as one line, such as `fen 0.1.0 (gui, engine)`
+pub fn text() -> String {
+    let features: Vec<&str> = FEATURES
+        .iter()
+        .filter(|(_, on)| *on)
+        .map(|(name, _)| *name)
+        .collect();
+    format!("fen {} ({})", VERSION, features.join(", "))
+}
+
+pub fn to_json() -> Json {
+    Json::obj(vec![
+        ("name", Json::Str("fen".to_string())),
+        ("version", Json::Str(VERSION.to_string())),
+        (
+            "features",
+            Json::Obj(
+                FEATURES
+                    .iter()
+                    .map(|&(name, on)| (name.to_string(), Json::Bool(on)))
+                    .collect(),
+            ),
+        ),
+    ])
+}
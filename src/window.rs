@@ -0,0 +1,901 @@
+//! Graphical window showing the board
+
+use crate::atlas;
+use crate::diagram::{self, Layout, Palette, Quality, Rgba, SquareIndex};
+use crate::fonts::{self, PieceFont};
+use crate::position::{file_of, rank_of, square, Color, Position, Square};
+use crate::san::{row_of, MoveRow};
+use crate::stats;
+use crate::terminal::board_glyphs;
+use crate::texture::{self, BoardStyle};
+use glutin_window::GlutinWindow as Window;
+use opengl_graphics::{GlGraphics, GlyphCache, OpenGL, Texture, TextureSettings};
+use piston::event_loop::{EventLoop, EventSettings, Events};
+use piston::input::{
+    Button, Event, Key, PressEvent, ReleaseEvent, RenderArgs, RenderEvent, UpdateArgs, UpdateEvent,
+};
+use piston::window::{AdvancedWindow, Window as _, WindowSettings};
+use std::sync::mpsc::Receiver;
+
+#[derive(Clone, PartialEq)]
+pub struct Board {
+    pub pieces: Vec<Vec<String>>,            // glyphs, rank 8 first
+    pub eval: Option<f64>,                   // White's share of the evaluation bar
+    pub highlights: Vec<(Square, [f32; 4])>, // squares tinted with a colour
+    pub labels: bool,                        // annotate files and ranks
+    pub hidden: bool,                        // blindfold: draw the squares only
+    pub arrows: Vec<(Square, Square, Rgba)>, // arrows drawn over the pieces
+    pub flipped: bool,                       // seen from Black's side
+    pub palette: Palette,                    // square, highlight and arrow colours
+    pub shadows: bool,                       // soft shadows under the pieces
+    pub hints: Vec<(Square, bool)>,          // legal destinations, true for captures
+    pub index: Option<SquareIndex>,          // square indices or names in the corners
+    pub layout: Layout,                      // frame and margin around the board
+    pub focus: Option<Square>,               // keyboard focus, outlined
+    pub title: String,                       // window title, kept when empty
+    pub slider: Option<(usize, usize)>,      // ply shown of a game's plies, on a strip below
+    pub moves: Option<MoveList>,             // the game's moves, in a panel at the right
+}
+
+/// the moves of a game as listed beside the board
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MoveList {
+    pub rows: Vec<MoveRow>,
+    /// the ply shown, whose move is highlighted
+    pub ply: usize,
+    /// the first row in view
+    pub scroll: usize,
+}
+
+/// a change pushed to an open window
+pub enum Update {
+    /// a new position, whose pieces are shown and summed up in the title
+    Position(Position),
+    /// turn the board around
+    Flip,
+    /// tint these squares, replacing any earlier highlights
+    Highlight(Vec<Square>),
+}
+
+/// colour of the legal-move hints
+const HINT: [f32; 4] = [0.08, 0.08, 0.08, 0.35];
+
+impl MoveList {
+    /// Scroll as little as keeps the highlighted move among the `shown`
+    /// rows in view.
+    pub fn follow(&mut self, shown: usize) {
+        let row = row_of(&self.rows, self.ply).unwrap_or(0);
+        if row < self.scroll {
+            self.scroll = row;
+        } else if shown > 0 && row >= self.scroll + shown {
+            self.scroll = row + 1 - shown;
+        }
+    }
+
+    /// Scroll by `rows`, up when negative, without leaving the list.
+    pub fn scroll_by(&mut self, rows: isize, shown: usize) {
+        let last = self.rows.len().saturating_sub(shown);
+        self.scroll = self.scroll.saturating_add_signed(rows).min(last);
+    }
+}
+
+/// height of the strip under the board holding a game's slider
+const SLIDER: f64 = 24.0;
+
+/// width of the move list panel, and height of each of its rows
+const PANEL: f64 = 180.0;
+const ROW: f64 = 20.0;
+
+/// outline of the square with the keyboard focus, amber to stand out on
+/// both square colours
+const FOCUS: [f32; 4] = [1.0, 0.7, 0.0, 1.0];
+
+/// A short title for a window showing `pos`, such as `White to move,
+/// move 11, +2`: the side to move, the move number and White's material
+/// lead, so several open windows can be told apart in the taskbar.
+pub fn summary(pos: &Position) -> String {
+    let side = match pos.side {
+        Color::White => "White",
+        Color::Black => "Black",
+    };
+    let lead = stats::material(pos, Color::White) - stats::material(pos, Color::Black);
+    let material = match lead {
+        0 => "material even".to_string(),
+        lead => format!("{:+}", lead),
+    };
+    format!("{} to move, move {}, {}", side, pos.fullmove, material)
+}
+
+/// a diagram colour as OpenGL wants it
+pub fn gl_colour(colour: Rgba) -> [f32; 4] {
+    colour.map(|c| c as f32 / 255.0)
+}
+
+#[derive(Default)]
+pub struct WindowOptions {
+    /// White's share of the evaluation bar; no bar when `None`
+    pub eval: Option<f64>,
+    /// hide the pieces except while space is held down
+    pub blindfold: bool,
+    pub piece_font: PieceFont,
+    pub arrows: Vec<(Square, Square, Rgba)>,
+    pub palette: Palette,
+    /// textured squares instead of the palette's flat colours
+    pub style: BoardStyle,
+    /// multisampling and piece shadows when high
+    pub quality: Quality,
+    /// keep the window above all others
+    pub on_top: bool,
+    /// borderless fullscreen without a cursor, for a display board
+    pub kiosk: bool,
+    /// label each square with its index or name
+    pub index: Option<SquareIndex>,
+    /// frames (and updates) a second, 60 when `None`
+    pub fps: Option<u64>,
+    /// wait for vertical sync before showing a frame
+    pub vsync: bool,
+    /// draw only after input, for a window nothing else changes; there
+    /// are no update events then
+    pub lazy: bool,
+    /// the starting square size, and the frame and margin around each board
+    pub layout: Layout,
+    /// the window's title, such as a `summary` of the position; `Chess
+    /// Board` when empty
+    pub title: String,
+    /// leave room under the board for a game's slider
+    pub slider: bool,
+    /// leave room at the right of the board for a game's move list
+    pub move_list: bool,
+}
+
+/// The top-left corner and side of board `i` of `count` in a window of
+/// `size`: the boards fill a grid, each kept square and centred with its
+/// evaluation bar (a strip at the right) in its cell, under a title strip
+/// when `titled`, leaving `room` pixels free at its right and below (for a
+/// move list and a slider), and `inset` pixels inside its frame and margin.
+fn place(
+    size: [f64; 2],
+    count: usize,
+    i: usize,
+    bar: bool,
+    titled: bool,
+    room: [f64; 2],
+    inset: f64,
+) -> (f64, f64, f64) {
+    let count = count.max(1);
+    let cols = (count as f64).sqrt().ceil() as usize;
+    let rows = count.div_ceil(cols);
+    let (cell_w, cell_h) = (size[0] / cols as f64, size[1] / rows as f64);
+    let title = if titled { 24.0 } else { 0.0 };
+    let [right, below] = room;
+    let margin = if count > 1 { 6.0 } else { 0.0 };
+    let with_bar = if bar { 1.0 + 0.3 / 8.0 } else { 1.0 };
+    let side = ((cell_w - right - 2.0 * (margin + inset)) / with_bar)
+        .min(cell_h - title - below - 2.0 * (margin + inset))
+        .max(0.0);
+    let left = (i % cols) as f64 * cell_w + (cell_w - right - side * with_bar) / 2.0;
+    let top = (i / cols) as f64 * cell_h + title + (cell_h - title - below - side) / 2.0;
+    (left, top, side)
+}
+
+/// The rectangle of the window cell holding board `i` of `count`, as
+/// `place` divides a window of `size`.
+fn cell(size: [f64; 2], count: usize, i: usize) -> [f64; 4] {
+    let count = count.max(1);
+    let cols = (count as f64).sqrt().ceil() as usize;
+    let rows = count.div_ceil(cols);
+    let (cell_w, cell_h) = (size[0] / cols as f64, size[1] / rows as f64);
+    [
+        (i % cols) as f64 * cell_w,
+        (i / cols) as f64 * cell_h,
+        cell_w,
+        cell_h,
+    ]
+}
+
+/// frames a change is drawn for, so that every buffer the window swaps
+/// between (up to triple buffering) holds it
+const REDRAWS: u8 = 3;
+
+/// what a window draws with
+struct Canvas {
+    gl: GlGraphics,
+    /// coordinates and titles
+    text: GlyphCache<'static>,
+    /// the piece sprites
+    atlas: Texture,
+    /// light and dark square tiles
+    textures: Option<(Texture, Texture)>,
+    /// the boards, titles and window size last drawn
+    drawn: (Vec<Board>, Vec<String>, [f64; 2]),
+    /// frames left to draw each board for, and the whole window for
+    stale: (Vec<u8>, u8),
+}
+
+impl Canvas {
+    fn new(opengl: OpenGL, opts: &WindowOptions) -> Result<Canvas, String> {
+        let settings = TextureSettings::new();
+        // the text font is built in, so windows open from any directory
+        let text = GlyphCache::from_bytes(fonts::TEXT_FONT, (), settings)
+            .map_err(|_| "Could not load the window's text font".to_string())?;
+        Ok(Canvas {
+            gl: GlGraphics::new(opengl),
+            text,
+            atlas: Texture::from_image(
+                &atlas::build(&opts.piece_font),
+                &settings.generate_mipmap(true),
+            ),
+            textures: texture::tiles(opts.style).map(|(light, dark)| {
+                (
+                    Texture::from_image(&light, &settings),
+                    Texture::from_image(&dark, &settings),
+                )
+            }),
+            drawn: (Vec::new(), Vec::new(), [0.0, 0.0]),
+            stale: (Vec::new(), 0),
+        })
+    }
+
+    /// Draw `boards` in a grid filling the window, each under its title
+    /// when there are titles. Only the boards that changed in the last few
+    /// frames are drawn again, and nothing at all while none has, so a
+    /// board followed at several moves a second costs little.
+    fn render(&mut self, args: &RenderArgs, boards: &[Board], titles: &[String]) {
+        use graphics::*;
+
+        let count = boards.len();
+        let titled = !titles.is_empty();
+        let Canvas {
+            gl,
+            text: text_cache,
+            atlas,
+            textures,
+            drawn,
+            stale,
+        } = self;
+        if drawn.0.len() != count || drawn.1 != titles || drawn.2 != args.window_size {
+            *drawn = (boards.to_vec(), titles.to_vec(), args.window_size);
+            *stale = (vec![REDRAWS; count], REDRAWS);
+        }
+        for (i, board) in boards.iter().enumerate() {
+            if drawn.0[i] != *board {
+                drawn.0[i] = board.clone();
+                stale.0[i] = REDRAWS;
+            }
+        }
+        let whole = stale.1 > 0;
+        if !whole && stale.0.iter().all(|&n| n == 0) {
+            return;
+        }
+        gl.draw(args.viewport(), |c, gl| {
+            if whole {
+                clear([0.0, 0.0, 0.0, 1.0], gl);
+            }
+            for (i, board) in boards.iter().enumerate() {
+                if stale.0[i] == 0 {
+                    continue;
+                }
+                if !whole {
+                    rectangle(
+                        [0.0, 0.0, 0.0, 1.0],
+                        cell(args.window_size, count, i),
+                        c.transform,
+                        gl,
+                    );
+                }
+                let inset = board.layout.inset();
+                let (left, top, side) = place(
+                    args.window_size,
+                    count,
+                    i,
+                    board.eval.is_some(),
+                    titled,
+                    board.room(),
+                    inset,
+                );
+                if let Some(name) = titles.get(i) {
+                    text(
+                        [1.0, 1.0, 1.0, 1.0],
+                        16_u32,
+                        name,
+                        text_cache,
+                        c.transform.trans(left - inset, top - inset - 6.0),
+                        gl,
+                    )
+                    .unwrap();
+                }
+                let border = board.layout.border;
+                if border > 0.0 && side > 0.0 {
+                    let bar = if board.eval.is_some() {
+                        0.3 * side / 8.0
+                    } else {
+                        0.0
+                    };
+                    rectangle(
+                        gl_colour(diagram::FRAME),
+                        [
+                            left - border,
+                            top - border,
+                            side + bar + 2.0 * border,
+                            side + 2.0 * border,
+                        ],
+                        c.transform,
+                        gl,
+                    );
+                }
+                board.draw(c.trans(left, top), gl, side, text_cache, atlas, textures);
+                // the slider: a track filled up to the ply shown, and a knob there
+                if let Some((ply, plies)) = board.slider {
+                    let y = top + side + inset + SLIDER / 2.0;
+                    let at = left + side * ply as f64 / plies.max(1) as f64;
+                    let to_knob = at - left;
+                    rectangle(
+                        [0.35, 0.35, 0.35, 1.0],
+                        [left, y - 2.0, side, 4.0],
+                        c.transform,
+                        gl,
+                    );
+                    rectangle(
+                        gl_colour(board.palette.light),
+                        [left, y - 2.0, to_knob, 4.0],
+                        c.transform,
+                        gl,
+                    );
+                    ellipse(
+                        gl_colour(board.palette.dark),
+                        ellipse::circle(at, y, 7.0),
+                        c.transform,
+                        gl,
+                    );
+                }
+                if let Some(list) = &board.moves {
+                    let panel = board.panel(left, top, side);
+                    let [x, y, _, h] = panel;
+                    rectangle([0.12, 0.12, 0.12, 1.0], panel, c.transform, gl);
+                    let shown = (h / ROW).floor() as usize;
+                    let rows = list.rows.iter().skip(list.scroll).take(shown);
+                    for (n, row) in rows.enumerate() {
+                        let line = y + n as f64 * ROW;
+                        let number = format!("{}.", row.number);
+                        let mut cells = vec![(x + 6.0, number.as_str(), false)];
+                        for (i, entry) in row.moves.iter().enumerate() {
+                            if let Some((ply, san)) = entry {
+                                cells.push((x + 40.0 + 60.0 * i as f64, san, *ply == list.ply));
+                            }
+                        }
+                        for (at, label, current) in cells {
+                            if current {
+                                rectangle(
+                                    gl_colour(board.palette.highlight),
+                                    [at - 4.0, line + 2.0, 58.0, ROW - 2.0],
+                                    c.transform,
+                                    gl,
+                                );
+                            }
+                            text(
+                                [1.0, 1.0, 1.0, 1.0],
+                                14_u32,
+                                label,
+                                text_cache,
+                                c.transform.trans(at, line + ROW - 5.0),
+                                gl,
+                            )
+                            .unwrap();
+                        }
+                    }
+                }
+            }
+        });
+        stale.1 = stale.1.saturating_sub(1);
+        for n in &mut stale.0 {
+            *n = n.saturating_sub(1);
+        }
+    }
+}
+
+impl Board {
+    /// Draw the board with its top-left corner at the origin of `c`, each
+    /// side `side` pixels long, with any evaluation bar to its right.
+    fn draw(
+        &self,
+        c: graphics::Context,
+        gl: &mut GlGraphics,
+        side: f64,
+        glyph_cache: &mut GlyphCache<'static>,
+        atlas: &Texture,
+        textures: &Option<(Texture, Texture)>,
+    ) {
+        use graphics::*;
+
+        let dark = gl_colour(self.palette.dark);
+        let light = gl_colour(self.palette.light);
+        let bar = if self.eval.is_some() {
+            0.3 * (side / 8.0)
+        } else {
+            0.0
+        };
+        let eval = self.eval;
+        let pieces = &self.pieces;
+        let highlights = &self.highlights;
+        let labels = self.labels;
+        let hidden = self.hidden;
+        let arrows = &self.arrows;
+        let flipped = self.flipped;
+        let shadows = self.shadows;
+        let shadow = gl_colour(diagram::SHADOW);
+        let (x, y) = (side, side);
+        let square = rectangle::square(0.0, 0.0, x / 8.0);
+        let rank_corner = (0.05 * (x / 8.0), 0.25 * (y / 8.0));
+        let file_corner = (0.8 * (x / 8.0), 0.9 * (y / 8.0));
+        let index_corner = (0.05 * (x / 8.0), 0.95 * (y / 8.0));
+        let files = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+
+        {
+            let mut offset_x = 0.0;
+            let mut offset_y = 0.0;
+            let mut sq_col = dark;
+            let mut other_col;
+            for row in (1..=8).rev() {
+                for col in 1..=8 {
+                    // the square shown here; colours are unchanged by turning the board
+                    let (_r, _f) = if flipped {
+                        (9 - row, 9 - col)
+                    } else {
+                        (row, col)
+                    };
+                    sq_col = if sq_col == light { dark } else { light };
+                    let at = c.transform.trans(offset_x, offset_y);
+                    match textures {
+                        Some((light_tile, dark_tile)) => {
+                            let tile = if sq_col == light {
+                                light_tile
+                            } else {
+                                dark_tile
+                            };
+                            Image::new().rect(square).draw(tile, &c.draw_state, at, gl);
+                        }
+                        None => rectangle(sq_col, square, at, gl),
+                    }
+                    other_col = if sq_col == light { dark } else { light };
+                    // tint highlighted squares
+                    for (sq, colour) in highlights {
+                        if file_of(*sq) as usize == _f - 1 && rank_of(*sq) as usize == _r - 1 {
+                            rectangle(*colour, square, c.transform.trans(offset_x, offset_y), gl);
+                        }
+                    }
+                    // a dot on a quiet move's square, a ring round a capture
+                    for &(sq, capture) in &self.hints {
+                        if file_of(sq) as usize == _f - 1 && rank_of(sq) as usize == _r - 1 {
+                            let cell = x / 8.0;
+                            if capture {
+                                let ring = Ellipse::new_border(HINT, 0.05 * cell);
+                                ring.draw(
+                                    ellipse::circle(
+                                        offset_x + cell / 2.0,
+                                        offset_y + cell / 2.0,
+                                        0.45 * cell,
+                                    ),
+                                    &c.draw_state,
+                                    c.transform,
+                                    gl,
+                                );
+                            } else {
+                                ellipse(
+                                    HINT,
+                                    ellipse::circle(
+                                        offset_x + cell / 2.0,
+                                        offset_y + cell / 2.0,
+                                        0.15 * cell,
+                                    ),
+                                    c.transform,
+                                    gl,
+                                );
+                            }
+                        }
+                    }
+                    // annotate files
+                    if row == 1 && labels {
+                        text(
+                            other_col,
+                            16_u32,
+                            &files[_f - 1].to_string()[..],
+                            glyph_cache,
+                            c.transform
+                                .trans(offset_x + file_corner.0, offset_y + file_corner.1),
+                            gl,
+                        )
+                        .unwrap();
+                    }
+                    // annotate ranks
+                    if col == 1 && labels {
+                        text(
+                            other_col,
+                            16_u32,
+                            &_r.to_string()[..],
+                            glyph_cache,
+                            c.transform
+                                .trans(offset_x + rank_corner.0, offset_y + rank_corner.1),
+                            gl,
+                        )
+                        .unwrap();
+                    }
+                    // label the square with its index or name
+                    if let Some(index) = self.index {
+                        text(
+                            other_col,
+                            12_u32,
+                            &index.label(crate::position::square(_f as u8 - 1, _r as u8 - 1)),
+                            glyph_cache,
+                            c.transform
+                                .trans(offset_x + index_corner.0, offset_y + index_corner.1),
+                            gl,
+                        )
+                        .unwrap();
+                    }
+                    // add the pieces, each with its shadow underneath
+                    let sprite = if hidden {
+                        None
+                    } else {
+                        atlas::sprite(&pieces[8 - _r][_f - 1])
+                    };
+                    if let Some(sprite) = sprite {
+                        let mut layers = vec![(sprite, [1.0; 4], 0.0)];
+                        if shadows {
+                            layers.insert(0, (atlas::mask(sprite), shadow, 0.032 * (x / 8.0)));
+                        }
+                        for (sprite, colour, offset) in layers {
+                            Image::new_color(colour)
+                                .src_rect(atlas::source(sprite))
+                                .rect(square)
+                                .draw(
+                                    atlas,
+                                    &c.draw_state,
+                                    c.transform.trans(offset_x + offset, offset_y + offset),
+                                    gl,
+                                );
+                        }
+                    }
+                    offset_x += x / 8.0;
+                }
+                offset_x = 0.0;
+                offset_y += y / 8.0;
+                sq_col = if sq_col == light { dark } else { light };
+            }
+
+            for &(from, to, colour) in arrows {
+                let centre = |s: Square| {
+                    let (file, rank) = if flipped {
+                        (7 - file_of(s), 7 - rank_of(s))
+                    } else {
+                        (file_of(s), rank_of(s))
+                    };
+                    (
+                        (file as f64 + 0.5) * (x / 8.0),
+                        (7.0 - rank as f64 + 0.5) * (y / 8.0),
+                    )
+                };
+                let colour = gl_colour(colour);
+                for part in diagram::arrow(centre(from), centre(to), x / 8.0) {
+                    let points: Vec<[f64; 2]> = part.iter().map(|&(px, py)| [px, py]).collect();
+                    polygon(colour, &points, c.transform, gl);
+                }
+            }
+
+            if let Some(sq) = self.focus {
+                let (file, rank) = if flipped {
+                    (7 - file_of(sq), 7 - rank_of(sq))
+                } else {
+                    (file_of(sq), rank_of(sq))
+                };
+                let cell = x / 8.0;
+                let width = 0.06 * cell;
+                Rectangle::new_border(FOCUS, width).draw(
+                    [
+                        file as f64 * cell + width,
+                        (7 - rank) as f64 * cell + width,
+                        cell - 2.0 * width,
+                        cell - 2.0 * width,
+                    ],
+                    &c.draw_state,
+                    c.transform,
+                    gl,
+                );
+            }
+
+            // evaluation bar, White's share filling from the bottom
+            if let Some(share) = eval {
+                let white = y * share.clamp(0.0, 1.0);
+                rectangle(
+                    [40.0 / 255.0, 40.0 / 255.0, 40.0 / 255.0, 1.0],
+                    [x, 0.0, bar, y - white],
+                    c.transform,
+                    gl,
+                );
+                rectangle(
+                    [240.0 / 255.0, 240.0 / 255.0, 240.0 / 255.0, 1.0],
+                    [x, y - white, bar, white],
+                    c.transform,
+                    gl,
+                );
+            }
+        }
+    }
+
+    /// The square under the point `at` of a window of `size` showing only
+    /// this board.
+    pub fn square_at(&self, size: [f64; 2], at: [f64; 2]) -> Option<Square> {
+        let inset = self.layout.inset();
+        let (left, top, side) = place(size, 1, 0, self.eval.is_some(), false, self.room(), inset);
+        let (x, y) = ((at[0] - left) / side * 8.0, (at[1] - top) / side * 8.0);
+        if side <= 0.0 || !(0.0..8.0).contains(&x) || !(0.0..8.0).contains(&y) {
+            return None;
+        }
+        let (file, rank) = (x as u8, 7 - y as u8);
+        Some(if self.flipped {
+            square(7 - file, 7 - rank)
+        } else {
+            square(file, rank)
+        })
+    }
+
+    /// The ply of the slider under the point `at` of a window of `size`
+    /// showing only this board; once the knob is `held`, the ply across
+    /// from `at` wherever it is.
+    pub fn slider_at(&self, size: [f64; 2], at: [f64; 2], held: bool) -> Option<usize> {
+        let (_, plies) = self.slider?;
+        let inset = self.layout.inset();
+        let (left, top, side) = place(size, 1, 0, self.eval.is_some(), false, self.room(), inset);
+        let strip = top + side + inset;
+        let on = (left - 8.0..=left + side + 8.0).contains(&at[0])
+            && (strip..strip + SLIDER).contains(&at[1]);
+        if side <= 0.0 || !(held || on) {
+            return None;
+        }
+        let share = ((at[0] - left) / side).clamp(0.0, 1.0);
+        Some((share * plies as f64).round() as usize)
+    }
+
+    // the room kept free at the right of and below the board
+    fn room(&self) -> [f64; 2] {
+        [
+            if self.moves.is_some() { PANEL } else { 0.0 },
+            if self.slider.is_some() { SLIDER } else { 0.0 },
+        ]
+    }
+
+    // the move list's rectangle beside a board placed at `left`, `top`
+    fn panel(&self, left: f64, top: f64, side: f64) -> [f64; 4] {
+        let inset = self.layout.inset();
+        let bar = if self.eval.is_some() {
+            0.3 * side / 8.0
+        } else {
+            0.0
+        };
+        [
+            left + side + bar + inset + 8.0,
+            top - inset,
+            PANEL - 16.0,
+            side + 2.0 * inset,
+        ]
+    }
+
+    /// How many rows of the move list fit in a window of `size` showing
+    /// only this board.
+    pub fn rows_shown(&self, size: [f64; 2]) -> usize {
+        let inset = self.layout.inset();
+        let (left, top, side) = place(size, 1, 0, self.eval.is_some(), false, self.room(), inset);
+        (self.panel(left, top, side)[3] / ROW).floor() as usize
+    }
+
+    /// The ply of the move under the point `at` of the move list, in a
+    /// window of `size` showing only this board.
+    pub fn move_at(&self, size: [f64; 2], at: [f64; 2]) -> Option<usize> {
+        let list = self.moves.as_ref()?;
+        let inset = self.layout.inset();
+        let (left, top, side) = place(size, 1, 0, self.eval.is_some(), false, self.room(), inset);
+        let [x, y, w, h] = self.panel(left, top, side);
+        if !(x..x + w).contains(&at[0]) || !(y..y + h).contains(&at[1]) {
+            return None;
+        }
+        let row = list.rows.get(list.scroll + ((at[1] - y) / ROW) as usize)?;
+        let column = ((at[0] - x - 36.0) / 60.0).floor();
+        if !(0.0..2.0).contains(&column) {
+            return None;
+        }
+        row.moves[column as usize].as_ref().map(|(ply, _)| *ply)
+    }
+
+    #[allow(unused)]
+    fn update(&mut self, args: &UpdateArgs) {
+        // Rotate 2 radians per second.
+        // self.rotation += 2.0 * args.dt;
+    }
+}
+
+impl Board {
+    fn new(pieces: Vec<Vec<String>>, opts: &WindowOptions) -> Board {
+        Board {
+            pieces,
+            eval: opts.eval,
+            highlights: Vec::new(),
+            labels: true,
+            hidden: opts.blindfold,
+            arrows: opts.arrows.clone(),
+            flipped: false,
+            palette: opts.palette,
+            shadows: opts.quality == Quality::High,
+            hints: Vec::new(),
+            index: opts.index,
+            layout: opts.layout,
+            focus: None,
+            title: opts.title.clone(),
+            slider: None,
+            moves: None,
+        }
+    }
+}
+
+// Create the Glutin window, `size` pixels unless it fills the screen
+fn open(opts: &WindowOptions, size: [u32; 2]) -> (Window, Canvas) {
+    // Change this to OpenGL::V2_1 if not working.
+    let opengl = OpenGL::V3_2;
+
+    let samples = if opts.quality == Quality::High { 4 } else { 0 };
+    let title = if opts.title.is_empty() {
+        "Chess Board"
+    } else {
+        &opts.title
+    };
+    let window: Window = WindowSettings::new(title, size)
+        .graphics_api(opengl)
+        .exit_on_esc(true)
+        .samples(samples)
+        .fullscreen(opts.kiosk)
+        .decorated(!opts.kiosk)
+        .vsync(opts.vsync)
+        .build()
+        .unwrap();
+    window.ctx.window().set_always_on_top(opts.on_top);
+    if opts.kiosk {
+        window.ctx.window().set_cursor_visible(false);
+    }
+    let canvas = Canvas::new(opengl, opts).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1)
+    });
+    (window, canvas)
+}
+
+// the event loop's pace, and whether it waits for input between frames
+fn event_settings(opts: &WindowOptions) -> EventSettings {
+    let settings = EventSettings::new().lazy(opts.lazy);
+    match opts.fps {
+        Some(fps) => settings.max_fps(fps).ups(fps),
+        None => settings,
+    }
+}
+
+/// Open the window and run its event loop. `on_event` sees each event
+/// before it is drawn and may change the board; returning `false` closes
+/// the window.
+pub fn run(
+    pieces: Vec<Vec<String>>,
+    opts: WindowOptions,
+    mut on_event: impl FnMut(&mut Board, &Event) -> bool,
+) {
+    let side = opts.layout.side();
+    let bar = if opts.eval.is_some() {
+        0.3 * opts.layout.square
+    } else {
+        0.0
+    };
+    let right = if opts.move_list { PANEL } else { 0.0 };
+    let below = if opts.slider { SLIDER } else { 0.0 };
+    let size = [
+        (side + bar + right).round() as u32,
+        (side + below).round() as u32,
+    ];
+    let (mut window, mut canvas) = open(&opts, size);
+
+    // Create a new game and run it.
+    let mut board = Board::new(pieces, &opts);
+    let mut title = board.title.clone();
+
+    let mut events = Events::new(event_settings(&opts));
+    while let Some(e) = events.next(&mut window) {
+        if !on_event(&mut board, &e) {
+            window.set_should_close(true);
+        }
+        if board.title != title && !board.title.is_empty() {
+            title = board.title.clone();
+            window.set_title(title.clone());
+        }
+
+        if let Some(args) = e.render_args() {
+            canvas.render(&args, std::slice::from_ref(&board), &[]);
+        }
+
+        if let Some(args) = e.update_args() {
+            board.update(&args);
+        }
+    }
+}
+
+/// Show several titled boards in a grid in one window. Each `(title,
+/// layout)` received on `updates` replaces the pieces of the board with
+/// that title, or adds a board for a new one.
+pub fn show_grid(
+    boards: Vec<(String, Vec<Vec<String>>)>,
+    updates: Option<Receiver<(String, String)>>,
+    opts: WindowOptions,
+) {
+    let count = boards.len().max(1);
+    let cols = (count as f64).sqrt().ceil() as u32;
+    let rows = count.div_ceil(cols as usize) as u32;
+    let (mut window, mut canvas) = open(&opts, [cols * 320, rows * 344]);
+
+    let (mut titles, mut grid): (Vec<String>, Vec<Board>) = boards
+        .into_iter()
+        .map(|(title, pieces)| (title, Board::new(pieces, &opts)))
+        .unzip();
+    let mut revealed = false;
+    let mut events = Events::new(event_settings(&opts));
+    while let Some(e) = events.next(&mut window) {
+        if let Some(updates) = &updates {
+            while let Ok((title, layout)) = updates.try_recv() {
+                match titles.iter().position(|t| *t == title) {
+                    Some(i) => grid[i].pieces = board_glyphs(&layout),
+                    None => {
+                        titles.push(title);
+                        grid.push(Board::new(board_glyphs(&layout), &opts));
+                    }
+                }
+            }
+        }
+        if e.press_args() == Some(Button::Keyboard(Key::Space)) {
+            revealed = true;
+        }
+        if e.release_args() == Some(Button::Keyboard(Key::Space)) {
+            revealed = false;
+        }
+        for board in &mut grid {
+            board.hidden = opts.blindfold && !revealed;
+        }
+        if let Some(args) = e.render_args() {
+            canvas.render(&args, &grid, &titles);
+        }
+    }
+}
+
+/// Show the board. If `updates` is given, changes received on it are
+/// applied as they arrive. In blindfold mode the pieces are hidden
+/// except while space is held down.
+pub fn show(pieces: Vec<Vec<String>>, updates: Option<Receiver<Update>>, opts: WindowOptions) {
+    let blindfold = opts.blindfold;
+    let mut revealed = false;
+    run(pieces, opts, |board, event| {
+        if let Some(updates) = &updates {
+            while let Ok(update) = updates.try_recv() {
+                match update {
+                    Update::Position(pos) => {
+                        board.pieces = board_glyphs(&pos.placement());
+                        board.title = summary(&pos);
+                    }
+                    Update::Flip => board.flipped = !board.flipped,
+                    Update::Highlight(squares) => {
+                        let colour = gl_colour(board.palette.highlight);
+                        board.highlights = squares.into_iter().map(|sq| (sq, colour)).collect()
+                    }
+                }
+            }
+        }
+        if event.press_args() == Some(Button::Keyboard(Key::Space)) {
+            revealed = true;
+        }
+        if event.release_args() == Some(Button::Keyboard(Key::Space)) {
+            revealed = false;
+        }
+        board.hidden = blindfold && !revealed;
+        true
+    });
+}
@@ -0,0 +1,121 @@
+//! Build a FEN by answering a question per field
+//!
+//! Each answer is checked as `--echo` checks the field, and an empty answer
+//! takes the default shown in brackets. Castling rights default to those
+//! the placement still allows, so most positions need only the pieces.
+
+use crate::echo::field_error;
+use crate::position::{Color, Position, STARTING_FEN};
+use crate::terminal::{board_glyphs, print_board, TerminalOptions};
+use std::io::{self, BufRead, Write};
+
+/// Ask `question` until the answer (or `default` for an empty one) is
+/// accepted by `accept`, which may also rewrite it. `None` once stdin is
+/// closed.
+fn ask(
+    question: &str,
+    default: &str,
+    accept: impl Fn(&str) -> Result<String, String>,
+) -> Option<String> {
+    let stdin = io::stdin();
+    loop {
+        print!("{} [{}]: ", question, default);
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            println!();
+            return None;
+        }
+        let answer = match line.trim() {
+            "" => default,
+            answer => answer,
+        };
+        match accept(answer) {
+            Ok(field) => return Some(field),
+            Err(e) => println!("  {}, try again", e),
+        }
+    }
+}
+
+// accept a field that `--echo` would not complain about
+fn field(n: usize) -> impl Fn(&str) -> Result<String, String> {
+    move |text| match field_error(n, text) {
+        Some(e) => Err(e),
+        None => Ok(text.to_string()),
+    }
+}
+
+/// the pieces part of a FEN, with some shorthands for common boards
+fn placement(text: &str) -> Result<String, String> {
+    let text = match text {
+        "start" => STARTING_FEN.split(' ').next().unwrap_or_default(),
+        "empty" => "8/8/8/8/8/8/8/8",
+        text => text,
+    };
+    field(0)(text)
+}
+
+fn side(text: &str) -> Result<String, String> {
+    match text.to_ascii_lowercase().as_str() {
+        "w" | "white" => Ok("w".to_string()),
+        "b" | "black" => Ok("b".to_string()),
+        _ => Err("expected w or b".to_string()),
+    }
+}
+
+fn castling(text: &str) -> Result<String, String> {
+    let text = if text == "none" { "-" } else { text };
+    field(2)(text)?;
+    // written in the conventional order, whatever order they were typed in
+    let rights: String = "KQkq".chars().filter(|&c| text.contains(c)).collect();
+    Ok(if rights.is_empty() {
+        "-".to_string()
+    } else {
+        rights
+    })
+}
+
+/// Prompt for each field of a FEN, then show the position and print the
+/// FEN it makes.
+pub fn run(term: &TerminalOptions) -> Result<(), String> {
+    let closed = || "No answer: standard input was closed".to_string();
+    println!("Enter each field of the FEN; an empty answer takes the [default].");
+    println!("Pieces are given rank by rank from the 8th, separated by /, with");
+    println!("uppercase for White, lowercase for Black and digits for empty squares.");
+    let pieces =
+        ask("Piece placement, or start or empty", "start", placement).ok_or_else(closed)?;
+    let side = ask("Side to move, w or b", "w", side).ok_or_else(closed)?;
+
+    // the rights a king and rook on their home squares would still have
+    let color = if side == "w" {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let board = Position::from_fen(&format!("{} {} - -", pieces, side))?.board;
+    let possible = Position::from_placement(board, color).castling.to_string();
+    println!("Castling: K and Q are White's kingside and queenside, k and q Black's; - for none.");
+    let rights = ask("Castling rights", &possible, castling).ok_or_else(closed)?;
+    let ep = ask(
+        "En-passant target square (behind a pawn that just moved two squares), or -",
+        "-",
+        field(3),
+    )
+    .ok_or_else(closed)?;
+    let halfmove = ask(
+        "Halfmove clock (plies since the last capture or pawn move)",
+        "0",
+        field(4),
+    )
+    .ok_or_else(closed)?;
+    let fullmove = ask("Fullmove number", "1", field(5)).ok_or_else(closed)?;
+
+    let fen = format!(
+        "{} {} {} {} {} {}",
+        pieces, side, rights, ep, halfmove, fullmove
+    );
+    let pos = Position::from_fen(&fen)?;
+    print_board(&board_glyphs(&pos.placement()), term);
+    println!("{}", fen);
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! X-rays and batteries along the lines of the sliding pieces
+//!
+//! A slider x-rays an enemy piece (not a pawn) when exactly one piece stands
+//! between them on a line the slider moves along. A battery is two or more pieces
+//! of one side lined up with nothing between them, each able to move along
+//! that line, such as doubled rooks or a queen behind a bishop.
+
+use crate::movegen::offset;
+use crate::position::{file_of, rank_of, square_name, Color, Piece, PieceKind, Position, Square};
+
+const DIRS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 0),
+    (1, 1),
+    (1, -1),
+    (0, -1),
+    (-1, 0),
+    (-1, -1),
+    (-1, 1),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    XRay,
+    Battery,
+}
+
+pub struct Alignment {
+    pub kind: Kind,
+    /// for an x-ray the slider, the blocker and the target; for a battery
+    /// its pieces in order along the line
+    pub squares: Vec<Square>,
+    pub description: String,
+}
+
+fn slides_along(piece: Piece, (df, dr): (i8, i8)) -> bool {
+    let straight = df == 0 || dr == 0;
+    match piece.kind {
+        PieceKind::Queen => true,
+        PieceKind::Rook => straight,
+        PieceKind::Bishop => !straight,
+        _ => false,
+    }
+}
+
+fn side(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
+
+// e.g. Rb1, or b4 for a pawn
+fn piece_on(pos: &Position, sq: Square) -> String {
+    match pos.piece_at(sq) {
+        Some(p) if p.kind != PieceKind::Pawn => format!("{}{}", p.kind.letter(), square_name(sq)),
+        _ => square_name(sq),
+    }
+}
+
+/// the first piece from `from` in direction `dir`
+fn first_piece(pos: &Position, from: Square, (df, dr): (i8, i8)) -> Option<Square> {
+    let mut cur = offset(from, df, dr);
+    while let Some(sq) = cur {
+        if pos.piece_at(sq).is_some() {
+            return Some(sq);
+        }
+        cur = offset(sq, df, dr);
+    }
+    None
+}
+
+/// the file, rank or diagonal through `sq` in direction `dir`
+fn line_name(sq: Square, (df, dr): (i8, i8)) -> String {
+    if df == 0 {
+        return format!("{}-file", (b'a' + file_of(sq)) as char);
+    }
+    if dr == 0 {
+        let rank = rank_of(sq) + 1;
+        let suffix = match rank {
+            1 => "st",
+            2 => "nd",
+            3 => "rd",
+            _ => "th",
+        };
+        return format!("{}{} rank", rank, suffix);
+    }
+    // the two ends of the diagonal
+    let end = |df: i8, dr: i8| {
+        let mut at = sq;
+        while let Some(next) = offset(at, df, dr) {
+            at = next;
+        }
+        at
+    };
+    let (a, b) = (end(-df, -dr), end(df, dr));
+    let (low, high) = if file_of(a) < file_of(b) {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    format!("{}-{} diagonal", square_name(low), square_name(high))
+}
+
+/// Every x-ray and battery on the board, White's first.
+pub fn find(pos: &Position) -> Vec<Alignment> {
+    let mut found = Vec::new();
+    for color in [Color::White, Color::Black] {
+        for from in 0..64u8 {
+            let piece = match pos.piece_at(from) {
+                Some(p) if p.color == color => p,
+                _ => continue,
+            };
+            for dir in DIRS.into_iter().filter(|&dir| slides_along(piece, dir)) {
+                let partner = |sq: Square| {
+                    pos.piece_at(sq)
+                        .is_some_and(|p| p.color == color && slides_along(p, dir))
+                };
+                let Some(first) = first_piece(pos, from, dir) else {
+                    continue;
+                };
+
+                if partner(first) {
+                    // a battery is reported once, from its piece nearest the
+                    // first rank (or the a-file along a rank)
+                    let back = first_piece(pos, from, (-dir.0, -dir.1));
+                    if dir.1 < 0 || (dir.1 == 0 && dir.0 < 0) || back.is_some_and(partner) {
+                        continue;
+                    }
+                    let mut squares = vec![from, first];
+                    while let Some(next) =
+                        first_piece(pos, squares[squares.len() - 1], dir).filter(|&sq| partner(sq))
+                    {
+                        squares.push(next);
+                    }
+                    let mut names: Vec<String> =
+                        squares.iter().map(|&sq| piece_on(pos, sq)).collect();
+                    let last = names.pop().unwrap_or_default();
+                    found.push(Alignment {
+                        kind: Kind::Battery,
+                        description: format!(
+                            "{}'s {} and {} form a battery on the {}",
+                            side(color),
+                            names.join(", "),
+                            last,
+                            line_name(from, dir)
+                        ),
+                        squares,
+                    });
+                    continue;
+                }
+
+                let behind = first_piece(pos, first, dir);
+                if let Some(target) = behind.filter(|&sq| {
+                    pos.piece_at(sq)
+                        .is_some_and(|p| p.color != color && p.kind != PieceKind::Pawn)
+                }) {
+                    found.push(Alignment {
+                        kind: Kind::XRay,
+                        squares: vec![from, first, target],
+                        description: format!(
+                            "{}'s {} x-rays {}'s {} through {}",
+                            side(color),
+                            piece_on(pos, from),
+                            side(color.opponent()),
+                            piece_on(pos, target),
+                            piece_on(pos, first)
+                        ),
+                    });
+                }
+            }
+        }
+    }
+    found
+}
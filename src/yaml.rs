@@ -0,0 +1,294 @@
+//! Enough YAML to read study files, read into `Json`
+//!
+//! Block mappings and sequences nested by indentation, `[a, b]` and
+//! `{key: value}` flow collections, plain, single- and double-quoted
+//! scalars, `|` and `>` block scalars and `#` comments. Anchors, tags,
+//! multiple documents and multi-line flow collections are not read.
+
+use crate::json::Json;
+
+struct Line {
+    number: usize,
+    indent: usize,
+    text: String,
+}
+
+fn error(line: &Line, message: &str) -> String {
+    format!("line {}: {}", line.number, message)
+}
+
+// `text` without a trailing comment: a `#` at the start or after a space,
+// outside quotes
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' && previous.is_whitespace() => return &text[..i],
+            None => {}
+        }
+        previous = c;
+    }
+    text
+}
+
+// where the `:` ending a mapping key is, outside quotes and brackets
+fn key_end(text: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut depth = 0;
+    let bytes = text.as_bytes();
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' if i == 0 => quote = Some(c),
+                '[' | '{' => depth += 1,
+                ']' | '}' => depth -= 1,
+                ':' if depth == 0 && bytes.get(i + 1).is_none_or(|&b| b == b' ') => return Some(i),
+                _ => {}
+            },
+        }
+    }
+    None
+}
+
+// the rest of a sequence item's line after its dash
+fn item(text: &str) -> Option<&str> {
+    match text {
+        "-" => Some(""),
+        _ => text.strip_prefix("- "),
+    }
+}
+
+fn plain(text: &str) -> Json {
+    match text {
+        "" | "~" | "null" => Json::Null,
+        "true" => Json::Bool(true),
+        "false" => Json::Bool(false),
+        _ => match text.parse::<f64>() {
+            Ok(n) if text.starts_with(|c: char| c.is_ascii_digit() || c == '-') => Json::Num(n),
+            _ => Json::Str(text.to_string()),
+        },
+    }
+}
+
+// a quoted scalar at the start of `text`, and the rest of `text`
+fn quoted(text: &str) -> Result<(String, &str), String> {
+    let quote = text.chars().next().unwrap_or('"');
+    let mut out = String::new();
+    let mut chars = text.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' if quote == '\'' => {
+                if text[i + 1..].starts_with('\'') {
+                    chars.next();
+                    out.push('\'');
+                } else {
+                    return Ok((out, &text[i + 1..]));
+                }
+            }
+            '"' if quote == '"' => return Ok((out, &text[i + 1..])),
+            '\\' if quote == '"' => match chars.next().map(|(_, c)| c) {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some(c) => out.push(c),
+                None => break,
+            },
+            c => out.push(c),
+        }
+    }
+    Err(format!("{} is never closed", quote))
+}
+
+// a scalar or flow collection at the start of `text`, ending at one of
+// `stops` outside quotes and brackets
+fn flow<'a>(text: &'a str, stops: &[char]) -> Result<(Json, &'a str), String> {
+    let text = text.trim_start();
+    if text.starts_with('"') || text.starts_with('\'') {
+        let (s, rest) = quoted(text)?;
+        return Ok((Json::Str(s), rest.trim_start()));
+    }
+    if let Some(mut rest) = text.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Json::Arr(items), after.trim_start()));
+            }
+            let (item, after) = flow(rest, &[',', ']'])?;
+            items.push(item);
+            rest = after.strip_prefix(',').unwrap_or(after);
+            if rest.is_empty() {
+                return Err("[ is never closed".to_string());
+            }
+        }
+    }
+    if let Some(mut rest) = text.strip_prefix('{') {
+        let mut fields = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix('}') {
+                return Ok((Json::Obj(fields), after.trim_start()));
+            }
+            let colon = rest.find(':').ok_or("{ is never closed")?;
+            let key = rest[..colon].trim().trim_matches(|c| c == '"' || c == '\'');
+            let (value, after) = flow(&rest[colon + 1..], &[',', '}'])?;
+            fields.push((key.to_string(), value));
+            rest = after.strip_prefix(',').unwrap_or(after);
+            if rest.is_empty() {
+                return Err("{ is never closed".to_string());
+            }
+        }
+    }
+    let end = text.find(stops).unwrap_or(text.len());
+    Ok((plain(text[..end].trim()), &text[end..]))
+}
+
+fn scalar(text: &str) -> Result<Json, String> {
+    let (value, rest) = flow(text, &[])?;
+    if !rest.trim().is_empty() {
+        return Err(format!("Unexpected {} after a value", rest.trim()));
+    }
+    Ok(value)
+}
+
+struct Parser {
+    lines: Vec<Line>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Line> {
+        self.lines.get(self.next)
+    }
+
+    // a `|` or `>` scalar from the lines indented past `indent`
+    fn block_scalar(&mut self, indent: usize, folded: bool) -> Json {
+        let mut parts = Vec::new();
+        let deeper = self.peek().map_or(indent + 1, |l| l.indent);
+        while let Some(line) = self.peek().filter(|l| l.indent > indent) {
+            parts.push(format!("{}{}", " ".repeat(line.indent - deeper), line.text));
+            self.next += 1;
+        }
+        Json::Str(if folded {
+            parts.join(" ")
+        } else {
+            parts.join("\n") + "\n"
+        })
+    }
+
+    // the value after a key or dash: inline, or the block under it
+    fn value(&mut self, inline: &str, indent: usize, number: usize) -> Result<Json, String> {
+        match inline {
+            "|" | ">" => Ok(self.block_scalar(indent, inline == ">")),
+            "" => match self.peek() {
+                Some(line) if line.indent > indent => self.block(line.indent),
+                // a sequence may sit at its key's indentation
+                Some(line) if line.indent == indent && item(&line.text).is_some() => {
+                    self.sequence(indent)
+                }
+                _ => Ok(Json::Null),
+            },
+            text => scalar(text).map_err(|e| format!("line {}: {}", number, e)),
+        }
+    }
+
+    fn block(&mut self, indent: usize) -> Result<Json, String> {
+        match self.peek() {
+            Some(line) if item(&line.text).is_some() => self.sequence(indent),
+            _ => self.mapping(indent),
+        }
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Json, String> {
+        let mut items = Vec::new();
+        while let Some(line) = self.peek().filter(|l| l.indent == indent) {
+            let Some(rest) = item(&line.text) else {
+                break;
+            };
+            let (number, rest) = (line.number, rest.trim_start().to_string());
+            if key_end(&rest).is_some() && !rest.starts_with(['[', '{']) {
+                // a mapping starting on the dash's line, at the indentation of its first key
+                let inner = indent + line.text.len() - rest.len();
+                self.lines[self.next] = Line {
+                    number,
+                    indent: inner,
+                    text: rest,
+                };
+                items.push(self.mapping(inner)?);
+            } else {
+                self.next += 1;
+                let value = match self.peek() {
+                    // the next item is not a sequence under an empty one
+                    Some(line) if rest.is_empty() && line.indent == indent => Json::Null,
+                    _ => self.value(&rest, indent, number)?,
+                };
+                items.push(value);
+            }
+        }
+        Ok(Json::Arr(items))
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Json, String> {
+        let mut fields: Vec<(String, Json)> = Vec::new();
+        while let Some(line) = self.peek() {
+            if line.indent < indent || (line.indent == indent && item(&line.text).is_some()) {
+                break;
+            }
+            if line.indent > indent {
+                return Err(error(line, "Unexpected indentation"));
+            }
+            let end = key_end(&line.text).ok_or_else(|| error(line, "Expected key: value"))?;
+            let key = line.text[..end].trim();
+            let key = match key.chars().next() {
+                Some('"' | '\'') => quoted(key).map_err(|e| error(line, &e))?.0,
+                _ => key.to_string(),
+            };
+            let (number, inline) = (line.number, line.text[end + 1..].trim().to_string());
+            self.next += 1;
+            let value = self.value(&inline, indent, number)?;
+            fields.retain(|(k, _)| *k != key);
+            fields.push((key, value));
+        }
+        Ok(Json::Obj(fields))
+    }
+}
+
+/// Read a YAML document.
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut lines = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        let content = strip_comment(raw).trim_end();
+        let text = content.trim_start();
+        if text.is_empty() || text == "---" {
+            continue;
+        }
+        if content.starts_with('\t') {
+            return Err(format!("line {}: Tabs cannot indent YAML", i + 1));
+        }
+        lines.push(Line {
+            number: i + 1,
+            indent: content.len() - text.len(),
+            text: text.to_string(),
+        });
+    }
+    let mut parser = Parser { lines, next: 0 };
+    let indent = parser.peek().map_or(0, |l| l.indent);
+    let doc = match parser.peek() {
+        None => Json::Null,
+        Some(line) if key_end(&line.text).is_none() && item(&line.text).is_none() => {
+            let value = scalar(&line.text).map_err(|e| error(line, &e))?;
+            parser.next += 1;
+            value
+        }
+        Some(_) => parser.block(indent)?,
+    };
+    match parser.peek() {
+        Some(line) => Err(error(line, "Unexpected indentation")),
+        None => Ok(doc),
+    }
+}